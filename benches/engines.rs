@@ -0,0 +1,21 @@
+// Compare engines on a small corpus
+// Run with `cargo bench`
+
+use regexps::bench::{report, run, BenchCase};
+
+fn main() {
+    let prose = "the quick brown fox jumps over the lazy dog ".repeat(50);
+    let digits = "0123456789 ".repeat(100);
+    let cases = [
+        BenchCase::new("fox", &prose),
+        BenchCase::new("(the|a) (lazy|quick)", &prose),
+        BenchCase::new("o.e", &prose),
+        BenchCase::new("(0|1|2|3|4|5|6|7|8|9)+", &digits),
+        BenchCase::new("5*6", &digits),
+        BenchCase::new("z", &prose),
+    ];
+    match run(&cases, 20) {
+        Ok(timings) => print!("{}", report(&timings)),
+        Err(error) => eprintln!("{error}"),
+    }
+}
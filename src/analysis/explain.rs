@@ -0,0 +1,349 @@
+// Describe patterns in plain English
+// Meant for people who do not read regular expressions, for instance
+// pattern `(ab|c)+.?` is described as:
+// one or more repetitions of (either the text "ab" or the character 'c'), then an optional character
+
+use std::sync::{Arc, RwLock};
+
+use crate::parser::{syntax_tree::*, Parser};
+
+// Describe `pattern` in plain English
+pub fn explain(pattern: &str) -> Result<String, String> {
+    Ok(explanation(&Parser::parse(pattern)?))
+}
+
+// Describe an already parsed pattern in plain English
+pub fn explanation(regexp: &Arc<RwLock<ParsedRegexp>>) -> String {
+    describe(&regexp.read().unwrap())
+}
+
+fn describe(expr: &ParsedRegexp) -> String {
+    let children = expr.children.read().unwrap();
+    match expr.expression_type {
+        ExpressionType::EmptyExpression => String::from("nothing (the empty string)"),
+
+        ExpressionType::Assertion { kind } => String::from(match kind {
+            Assertion::Start | Assertion::StartOfInput => "the start of the text",
+            Assertion::End | Assertion::EndOfInput => "the end of the text",
+            Assertion::WordBoundary => "a word boundary",
+            Assertion::NonWordBoundary => "a place which is not a word boundary",
+            Assertion::StartOfLine(_) => "the start of a line",
+            Assertion::EndOfLine(_) => "the end of a line",
+        }),
+
+        ExpressionType::Lookbehind { negative } => format!(
+            "a place {}right after {}",
+            if negative { "not " } else { "" },
+            describe_nested(&children[0].read().unwrap())
+        ),
+
+        ExpressionType::CharacterExpression {
+            value,
+            quantifier,
+            line_terminator,
+        } => match value {
+            Some(ch) => describe_character(ch, quantifier),
+            None => describe_dot(quantifier, line_terminator),
+        },
+
+        ExpressionType::CharacterClass { quantifier } => describe_class(&children, quantifier),
+
+        ExpressionType::ShorthandClass { class, quantifier } => {
+            describe_shorthand(class, quantifier)
+        }
+
+        ExpressionType::CharacterRange { first, last } => format!(
+            "a character from {} to {}",
+            quote_character(first),
+            quote_character(last)
+        ),
+
+        ExpressionType::Backreference {
+            group,
+            quantifier,
+            case_insensitive,
+        } => {
+            let description = describe_backreference(group, quantifier);
+            if case_insensitive {
+                format!("{description}, in any case")
+            } else {
+                description
+            }
+        }
+
+        ExpressionType::Group {
+            quantifier, atomic, ..
+        } => {
+            let description = describe_group(&children[0].read().unwrap(), quantifier);
+            if atomic {
+                format!("{description}, never backtracking into it")
+            } else {
+                description
+            }
+        }
+
+        ExpressionType::Alternation => {
+            let branches = children
+                .iter()
+                .map(|child| describe_nested(&child.read().unwrap()))
+                .collect::<Vec<_>>();
+            match branches.len() {
+                2 => format!("either {} or {}", branches[0], branches[1]),
+                _ => format!("either {}", branches.join(", or ")),
+            }
+        }
+
+        ExpressionType::Concatenation => {
+            let mut parts = vec![];
+            // Consecutive characters without quantifiers are described together
+            // as a piece of text, `abc` is the text "abc" rather than three characters
+            let mut text = String::new();
+            for child in children.iter() {
+                let child = child.read().unwrap();
+                if let ExpressionType::CharacterExpression {
+                    value: Some(ch),
+                    quantifier: Quantifier::None,
+                    ..
+                } = child.expression_type
+                {
+                    text.push(ch);
+                    continue;
+                }
+                if !text.is_empty() {
+                    parts.push(describe_text(&text));
+                    text.clear();
+                }
+                // `a|b` inside `x(a|b)y` is parenthesized
+                // otherwise `then` and `or` would be mixed up
+                parts.push(describe_nested(&child));
+            }
+            if !text.is_empty() {
+                parts.push(describe_text(&text));
+            }
+            parts.join(", then ")
+        }
+    }
+}
+
+// Descriptions of concatenations and alternations are put in parentheses
+// when used inside other expressions to keep them apart from their surroundings
+fn describe_nested(expr: &ParsedRegexp) -> String {
+    let description = describe(expr);
+    let mut inner_type = expr.expression_type;
+    if let ExpressionType::Group {
+        quantifier: Quantifier::None,
+        atomic: false,
+        ..
+    } = inner_type
+    {
+        // Parentheses change nothing in plain English, look at what they enclose
+        inner_type = expr.children.read().unwrap()[0]
+            .read()
+            .unwrap()
+            .expression_type;
+    }
+    let needs_parentheses = match inner_type {
+        // Keep what an atomic group encloses apart from what comes after it
+        ExpressionType::Alternation | ExpressionType::Group { atomic: true, .. } => true,
+        // A concatenation of characters only is a single piece of text
+        ExpressionType::Concatenation => description.contains(", then "),
+        _ => false,
+    };
+    if needs_parentheses {
+        format!("({description})")
+    } else {
+        description
+    }
+}
+
+// `(ab)+` is one or more repetitions of the text "ab"
+fn describe_group(inner: &ParsedRegexp, quantifier: Quantifier) -> String {
+    match quantifier {
+        // Parentheses change nothing in plain English
+        Quantifier::None => describe(inner),
+        Quantifier::ZeroOrOne => format!("optionally {}", describe_nested(inner)),
+        Quantifier::ZeroOrMore => {
+            format!("zero or more repetitions of {}", describe_nested(inner))
+        }
+        Quantifier::OneOrMore => {
+            format!("one or more repetitions of {}", describe_nested(inner))
+        }
+        Quantifier::Counted { min, max } => {
+            let (count, plural) = describe_count(min, max);
+            let repetitions = if plural { "repetitions" } else { "repetition" };
+            format!("{count} {repetitions} of {}", describe_nested(inner))
+        }
+        Quantifier::Lazy { .. } => as_few_as_possible(describe_group(inner, quantifier.greedy())),
+    }
+}
+
+// `\1+` is one or more repetitions of the text captured by group 1
+fn describe_backreference(group: usize, quantifier: Quantifier) -> String {
+    let text = format!("the text captured by group {group}");
+    match quantifier {
+        Quantifier::None => text,
+        Quantifier::ZeroOrOne => format!("optionally {text}"),
+        Quantifier::ZeroOrMore => format!("zero or more repetitions of {text}"),
+        Quantifier::OneOrMore => format!("one or more repetitions of {text}"),
+        Quantifier::Counted { min, max } => {
+            let (count, plural) = describe_count(min, max);
+            let repetitions = if plural { "repetitions" } else { "repetition" };
+            format!("{count} {repetitions} of {text}")
+        }
+        Quantifier::Lazy { .. } => {
+            as_few_as_possible(describe_backreference(group, quantifier.greedy()))
+        }
+    }
+}
+
+fn describe_text(text: &str) -> String {
+    if text.chars().count() == 1 {
+        format!(
+            "the character {}",
+            quote_character(text.chars().next().unwrap())
+        )
+    } else {
+        format!("the text \"{}\"", text.escape_debug())
+    }
+}
+
+fn quote_character(ch: char) -> String {
+    format!("'{}'", ch.escape_debug())
+}
+
+// `[abc]` is one of the characters 'a', 'b' or 'c'
+// and `[a-z\d]` is one of the characters 'a' to 'z' or any digit
+fn describe_class(members: &[Arc<RwLock<ParsedRegexp>>], quantifier: Quantifier) -> String {
+    let mut characters = vec![];
+    describe_members(members, &mut characters);
+    if characters.len() == 1 {
+        // All members are the same, describe that member alone
+        match members[0].read().unwrap().expression_type {
+            ExpressionType::CharacterExpression {
+                value: Some(ch), ..
+            } => {
+                return describe_character(ch, quantifier);
+            }
+            ExpressionType::ShorthandClass { class, .. } => {
+                return describe_shorthand(class, quantifier);
+            }
+            _ => {}
+        }
+    }
+    let last = characters.pop().unwrap();
+    let characters = if characters.is_empty() {
+        last
+    } else {
+        format!("{} or {last}", characters.join(", "))
+    };
+    match quantifier {
+        Quantifier::None => format!("one of the characters {characters}"),
+        Quantifier::ZeroOrOne => format!("an optional character among {characters}"),
+        Quantifier::ZeroOrMore => format!("zero or more characters among {characters}"),
+        Quantifier::OneOrMore => format!("one or more characters among {characters}"),
+        Quantifier::Counted { min, max } => {
+            let (count, plural) = describe_count(min, max);
+            let s = if plural { "s" } else { "" };
+            format!("{count} character{s} among {characters}")
+        }
+        Quantifier::Lazy { .. } => as_few_as_possible(describe_class(members, quantifier.greedy())),
+    }
+}
+
+// Add descriptions of class `members` missing from `descriptions`
+// Members of a nested class are members of the class enclosing it
+fn describe_members(members: &[Arc<RwLock<ParsedRegexp>>], descriptions: &mut Vec<String>) {
+    for member in members {
+        let member = member.read().unwrap();
+        let description = match member.expression_type {
+            ExpressionType::CharacterExpression {
+                value: Some(ch), ..
+            } => quote_character(ch),
+            ExpressionType::CharacterRange { first, last } => {
+                format!("{} to {}", quote_character(first), quote_character(last))
+            }
+            ExpressionType::ShorthandClass { class, .. } => format!("any {}", class.name()),
+            ExpressionType::CharacterClass { .. } => {
+                describe_members(&member.children.read().unwrap(), descriptions);
+                continue;
+            }
+            _ => continue,
+        };
+        if !descriptions.contains(&description) {
+            descriptions.push(description);
+        }
+    }
+}
+
+// `\d+` is one or more digits
+fn describe_shorthand(class: Shorthand, quantifier: Quantifier) -> String {
+    let name = class.name();
+    match quantifier {
+        Quantifier::None => format!("a {name}"),
+        Quantifier::ZeroOrOne => format!("an optional {name}"),
+        Quantifier::ZeroOrMore => format!("zero or more {name}s"),
+        Quantifier::OneOrMore => format!("one or more {name}s"),
+        Quantifier::Counted { min, max } => {
+            let (count, plural) = describe_count(min, max);
+            let s = if plural { "s" } else { "" };
+            format!("{count} {name}{s}")
+        }
+        Quantifier::Lazy { .. } => {
+            as_few_as_possible(describe_shorthand(class, quantifier.greedy()))
+        }
+    }
+}
+
+fn describe_character(ch: char, quantifier: Quantifier) -> String {
+    let quoted = quote_character(ch);
+    match quantifier {
+        Quantifier::None => format!("the character {quoted}"),
+        Quantifier::ZeroOrOne => format!("an optional {quoted}"),
+        Quantifier::ZeroOrMore => format!("zero or more {quoted}"),
+        Quantifier::OneOrMore => format!("one or more {quoted}"),
+        Quantifier::Counted { min, max } => format!("{} {quoted}", describe_count(min, max).0),
+        Quantifier::Lazy { .. } => as_few_as_possible(describe_character(ch, quantifier.greedy())),
+    }
+}
+
+// A dot expression, which never matches a line terminator
+fn describe_dot(quantifier: Quantifier, line_terminator: LineTerminator) -> String {
+    let terminator = match line_terminator {
+        LineTerminator::Char('\n') => String::from("a newline"),
+        LineTerminator::Char(ch) => quote_character(ch),
+        LineTerminator::Crlf => String::from("a line break"),
+    };
+    match quantifier {
+        Quantifier::None => format!("any character but {terminator}"),
+        Quantifier::ZeroOrOne => format!("an optional character other than {terminator}"),
+        Quantifier::ZeroOrMore => format!("zero or more characters other than {terminator}"),
+        Quantifier::OneOrMore => format!("one or more characters other than {terminator}"),
+        Quantifier::Counted { min, max } => {
+            let (count, plural) = describe_count(min, max);
+            let s = if plural { "s" } else { "" };
+            format!("{count} character{s} other than {terminator}")
+        }
+        Quantifier::Lazy { .. } => {
+            as_few_as_possible(describe_dot(quantifier.greedy(), line_terminator))
+        }
+    }
+}
+
+// `x*?` is zero or more x, as few as possible
+fn as_few_as_possible(description: String) -> String {
+    format!("{description}, as few as possible")
+}
+
+// `{3}` is exactly 3 of something and `{2,5}` between 2 and 5 of it
+// along with whether that something is plural, `{1}` is exactly 1 character not characters
+fn describe_count(min: usize, max: Option<usize>) -> (String, bool) {
+    let count = match max {
+        None if min == 0 => String::from("any number of"),
+        None => format!("at least {min}"),
+        Some(max) if min == max => format!("exactly {min}"),
+        Some(max) if min == 0 => format!("at most {max}"),
+        Some(max) => format!("between {min} and {max}"),
+    };
+    let singular = max == Some(1) || (min == 1 && max.is_none());
+    (count, !singular)
+}
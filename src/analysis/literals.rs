@@ -0,0 +1,337 @@
+// Literals required by patterns
+// Every match of `ab+c(de|xdey)` contains both `ab` and `de`
+// Users can look these literals up in an index of their corpus (trigrams, suffix arrays...)
+// and run the matcher only on documents containing all of them
+
+use std::collections::BTreeSet;
+
+use crate::parser::syntax_tree::*;
+
+// Largest number of strings tracked for an expression matching finitely many strings
+const EXACT_LIMIT: usize = 64;
+
+// What is known about strings matched by an expression
+struct Literals {
+    // All strings matched by the expression
+    // None when there are too many of them (or infinitely many)
+    exact: Option<BTreeSet<String>>,
+    // Strings contained in every match of the expression
+    required: BTreeSet<String>,
+    // All strings matched by one iteration, if the expression is a repetition `E+`
+    // of an expression E matching finitely many strings
+    // Matches of the repetition begin and end with one of these strings
+    repeated: Option<BTreeSet<String>>,
+}
+
+impl Literals {
+    fn exactly(strings: &[&str]) -> Literals {
+        Literals {
+            exact: Some(strings.iter().map(|string| string.to_string()).collect()),
+            required: BTreeSet::new(),
+            repeated: None,
+        }
+    }
+
+    fn unknown() -> Literals {
+        Literals {
+            exact: None,
+            required: BTreeSet::new(),
+            repeated: None,
+        }
+    }
+
+    // Required strings including those implied by exact strings
+    fn all_required(&self) -> BTreeSet<String> {
+        let mut required = self.required.clone();
+        if let Some(common) = self.exact.as_ref().and_then(longest_common_substring) {
+            required.insert(common);
+        }
+        required
+    }
+}
+
+impl ParsedRegexp {
+    // Literals which every match of this expression contains
+    // Literals contained in other returned literals are left out
+    // longest literals come first
+    pub fn required_literals(&self) -> Vec<String> {
+        let required = self.literals().all_required();
+        let mut literals = required
+            .iter()
+            .filter(|literal| {
+                !literal.is_empty()
+                    && !required
+                        .iter()
+                        .any(|other| other != *literal && other.contains(literal.as_str()))
+            })
+            .cloned()
+            .collect::<Vec<_>>();
+        literals.sort_by_key(|literal| std::cmp::Reverse(literal.chars().count()));
+        literals
+    }
+
+    fn literals(&self) -> Literals {
+        let children = self.children.read().unwrap();
+        match self.expression_type {
+            // Where an assertion or a lookbehind holds does not matter
+            // every match contains what surrounds it
+            ExpressionType::EmptyExpression
+            | ExpressionType::Assertion { .. }
+            | ExpressionType::Lookbehind { .. } => Literals::exactly(&[""]),
+
+            // A range on its own matches a single character, like an unquantified class
+            ExpressionType::CharacterRange { .. } => match self.characters() {
+                Some(characters) => Literals {
+                    exact: Some(characters),
+                    required: BTreeSet::new(),
+                    repeated: None,
+                },
+                None => Literals::unknown(),
+            },
+
+            ExpressionType::CharacterExpression { quantifier, .. }
+            | ExpressionType::ShorthandClass { quantifier, .. }
+            | ExpressionType::CharacterClass { quantifier } => {
+                let Some(characters) = self.characters() else {
+                    // The dot and shorthand classes match too many strings
+                    return Literals::unknown();
+                };
+                match quantifier {
+                    Quantifier::None => Literals {
+                        exact: Some(characters),
+                        required: BTreeSet::new(),
+                        repeated: None,
+                    },
+                    Quantifier::ZeroOrOne => Literals {
+                        exact: Some(characters.into_iter().chain([String::new()]).collect()),
+                        required: BTreeSet::new(),
+                        repeated: None,
+                    },
+                    Quantifier::OneOrMore => Literals {
+                        exact: None,
+                        // Every match contains the character, if there is only one
+                        required: if characters.len() == 1 {
+                            characters.clone()
+                        } else {
+                            BTreeSet::new()
+                        },
+                        repeated: Some(characters),
+                    },
+                    Quantifier::ZeroOrMore => Literals::unknown(),
+                    // Lazy repetitions try fewer repetitions first but match the same strings
+                    Quantifier::Counted { min, max } | Quantifier::Lazy { min, max } => repetition(
+                        Literals {
+                            exact: Some(characters),
+                            required: BTreeSet::new(),
+                            repeated: None,
+                        },
+                        min,
+                        max,
+                    ),
+                }
+            }
+
+            // Captured text is not known before matching
+            ExpressionType::Backreference { .. } => Literals::unknown(),
+
+            ExpressionType::Group { quantifier, .. } => {
+                let inner = children[0].read().unwrap().literals();
+                match quantifier {
+                    Quantifier::None => inner,
+                    Quantifier::ZeroOrOne => Literals {
+                        exact: inner.exact.map(|mut exact| {
+                            exact.insert(String::new());
+                            exact
+                        }),
+                        required: BTreeSet::new(),
+                        repeated: None,
+                    },
+                    // Every match contains at least one iteration
+                    Quantifier::OneOrMore => Literals {
+                        required: inner.all_required(),
+                        exact: None,
+                        repeated: inner.exact,
+                    },
+                    Quantifier::ZeroOrMore => Literals::unknown(),
+                    Quantifier::Counted { min, max } | Quantifier::Lazy { min, max } => {
+                        repetition(inner, min, max)
+                    }
+                }
+            }
+
+            ExpressionType::Concatenation => {
+                let mut required = BTreeSet::new();
+                // Strings matched by the most recent items whose strings are all known
+                let mut run = Some(BTreeSet::from([String::new()]));
+                let mut exact_throughout = true;
+                for child in children.iter() {
+                    let child = child.read().unwrap().literals();
+                    // Whatever an item requires, the concatenation requires too
+                    // even if the run it belongs to requires something else
+                    required.extend(child.all_required());
+                    if let Some(product) = product(&run, &child.exact) {
+                        run = Some(product);
+                        continue;
+                    }
+                    // Run ends here, keep what it requires
+                    exact_throughout = false;
+                    // A repetition `E+` begins with an iteration of E, thus the run
+                    // continues through that first iteration
+                    // and a new run begins with its last iteration, like `ab` and `bc` in `ab+c`
+                    let run_end = match product(&run, &child.repeated) {
+                        Some(product) => Some(product),
+                        None => run,
+                    };
+                    if let Some(common) = run_end.as_ref().and_then(longest_common_substring) {
+                        required.insert(common);
+                    }
+                    run = child.exact.or(child.repeated);
+                }
+                match run {
+                    Some(run) if exact_throughout => Literals {
+                        exact: Some(run),
+                        required,
+                        repeated: None,
+                    },
+                    run => {
+                        if let Some(common) = run.as_ref().and_then(longest_common_substring) {
+                            required.insert(common);
+                        }
+                        Literals {
+                            exact: None,
+                            required,
+                            repeated: None,
+                        }
+                    }
+                }
+            }
+
+            ExpressionType::Alternation => {
+                let alternatives = children
+                    .iter()
+                    .map(|child| child.read().unwrap().literals())
+                    .collect::<Vec<_>>();
+
+                let mut exact = Some(BTreeSet::new());
+                for alternative in &alternatives {
+                    exact = match (exact, &alternative.exact) {
+                        (Some(mut union), Some(strings)) => {
+                            union.extend(strings.iter().cloned());
+                            Some(union).filter(|union| union.len() <= EXACT_LIMIT)
+                        }
+                        _ => None,
+                    };
+                }
+
+                // Whatever one alternative does not require is not required
+                let mut required = alternatives[0].all_required();
+                for alternative in &alternatives[1..] {
+                    let other = alternative.all_required();
+                    required.retain(|literal| {
+                        other.iter().any(|string| string.contains(literal.as_str()))
+                    });
+                }
+                Literals {
+                    exact,
+                    required,
+                    repeated: None,
+                }
+            }
+        }
+    }
+}
+
+impl ParsedRegexp {
+    // Characters a character, character class or range expression matches, as strings
+    // None for a dot or a class or range with too many characters
+    fn characters(&self) -> Option<BTreeSet<String>> {
+        match self.expression_type {
+            ExpressionType::CharacterExpression { value, .. } => {
+                Some(BTreeSet::from([value?.to_string()]))
+            }
+            ExpressionType::CharacterClass { .. } => {
+                let mut characters = BTreeSet::new();
+                for member in self.children.read().unwrap().iter() {
+                    characters.extend(member.read().unwrap().characters()?);
+                }
+                Some(characters).filter(|characters| characters.len() <= EXACT_LIMIT)
+            }
+            ExpressionType::CharacterRange { first, last } => {
+                let characters = (first..=last)
+                    .take(EXACT_LIMIT + 1)
+                    .map(String::from)
+                    .collect::<BTreeSet<_>>();
+                Some(characters).filter(|characters| characters.len() <= EXACT_LIMIT)
+            }
+            _ => None,
+        }
+    }
+}
+
+// Literals of `min` to `max` repetitions of an expression whose literals are `inner`
+// like `E{2,3}`, `max` is None if there is no upper bound
+fn repetition(inner: Literals, min: usize, max: Option<usize>) -> Literals {
+    let exact = max.and_then(|max| {
+        let mut exact = BTreeSet::new();
+        // Strings of `count` repetitions
+        let mut repeated = Some(BTreeSet::from([String::new()]));
+        for count in 0..=max {
+            if count >= min {
+                exact.extend(repeated.clone()?);
+                if exact.len() > EXACT_LIMIT {
+                    return None;
+                }
+            }
+            repeated = product(&repeated, &inner.exact);
+        }
+        Some(exact)
+    });
+    if min == 0 {
+        // Matches may be empty, nothing is required
+        return Literals {
+            exact,
+            required: BTreeSet::new(),
+            repeated: None,
+        };
+    }
+    // Every match begins and ends with an iteration, like matches of `E+`
+    Literals {
+        exact,
+        required: inner.all_required(),
+        repeated: inner.exact,
+    }
+}
+
+// Each string of `left` followed by each string of `right`
+// None if either is unknown or there would be too many strings
+fn product(
+    left: &Option<BTreeSet<String>>,
+    right: &Option<BTreeSet<String>>,
+) -> Option<BTreeSet<String>> {
+    match (left, right) {
+        (Some(left), Some(right)) if left.len() * right.len() <= EXACT_LIMIT => Some(
+            left.iter()
+                .flat_map(|first| right.iter().map(move |second| first.clone() + second))
+                .collect(),
+        ),
+        _ => None,
+    }
+}
+
+// Longest string contained in all `strings`, None if it is empty
+fn longest_common_substring(strings: &BTreeSet<String>) -> Option<String> {
+    let shortest = strings
+        .iter()
+        .min_by_key(|string| string.chars().count())?
+        .chars()
+        .collect::<Vec<_>>();
+    for length in (1..=shortest.len()).rev() {
+        for window in shortest.windows(length) {
+            let candidate = window.iter().collect::<String>();
+            if strings.iter().all(|string| string.contains(&candidate)) {
+                return Some(candidate);
+            }
+        }
+    }
+    None
+}
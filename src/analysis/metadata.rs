@@ -0,0 +1,283 @@
+// Facts about patterns useful before matching
+// like the shortest and longest strings they can match
+// Callers can use them to skip targets which can not possibly match
+
+use crate::parser::syntax_tree::*;
+
+impl ParsedRegexp {
+    // Can a match of this expression only begin at start of target?
+    // True if every match must begin with `^` or `\A`
+    // otherwise matches may begin anywhere in target
+    pub fn is_anchored_start(&self) -> bool {
+        let children = self.children.read().unwrap();
+        match self.expression_type {
+            ExpressionType::Assertion { kind } => kind.is_start(),
+            ExpressionType::EmptyExpression
+            | ExpressionType::CharacterExpression { .. }
+            | ExpressionType::ShorthandClass { .. }
+            | ExpressionType::CharacterClass { .. }
+            | ExpressionType::CharacterRange { .. }
+            | ExpressionType::Backreference { .. }
+            | ExpressionType::Lookbehind { .. } => false,
+            // Anchored when repeated at least once
+            ExpressionType::Group { quantifier, .. } => {
+                quantifier.bounds().0 > 0 && children[0].read().unwrap().is_anchored_start()
+            }
+            // Anchored when its first item is anchored
+            ExpressionType::Concatenation => children[0].read().unwrap().is_anchored_start(),
+            // Anchored when all alternatives are anchored
+            ExpressionType::Alternation => children
+                .iter()
+                .all(|child| child.read().unwrap().is_anchored_start()),
+        }
+    }
+
+    // Does this expression contain an assertion, like `^`, or a lookbehind?
+    // Where its matches are depends on more than the characters matched
+    pub fn has_assertions(&self) -> bool {
+        matches!(
+            self.expression_type,
+            ExpressionType::Assertion { .. } | ExpressionType::Lookbehind { .. }
+        ) || self
+            .children
+            .read()
+            .unwrap()
+            .iter()
+            .any(|child| child.read().unwrap().has_assertions())
+    }
+
+    // Inline flags a pattern written for this expression must begin with, if any
+    // `i` if it has a backreference ignoring case, characters ignoring case are written
+    // as classes of all their cases instead, like `[kKK]`
+    // `m` if it has `^` or `$` matching at start and end of each line
+    // and `R` if lines end with \r\n, \r or \n (CRLF mode)
+    // Other line terminators have no inline flag, see `Parser::parse_with_line_terminator`
+    pub fn inline_flags(&self) -> String {
+        let case_insensitive = if self.has_case_insensitive_backreferences() {
+            "i"
+        } else {
+            ""
+        };
+        let multiline = if self.has_line_assertions() { "m" } else { "" };
+        let crlf = if self.line_terminator() == Some(LineTerminator::Crlf) {
+            "R"
+        } else {
+            ""
+        };
+        if case_insensitive.is_empty() && multiline.is_empty() && crlf.is_empty() {
+            String::new()
+        } else {
+            format!("(?{case_insensitive}{multiline}{crlf})")
+        }
+    }
+
+    // Does this expression contain a backreference matching captured text in any case?
+    pub fn has_case_insensitive_backreferences(&self) -> bool {
+        matches!(
+            self.expression_type,
+            ExpressionType::Backreference {
+                case_insensitive: true,
+                ..
+            }
+        ) || self
+            .children
+            .read()
+            .unwrap()
+            .iter()
+            .any(|child| child.read().unwrap().has_case_insensitive_backreferences())
+    }
+
+    // Does this expression contain `^` or `$` in multiline mode?
+    pub fn has_line_assertions(&self) -> bool {
+        matches!(
+            self.expression_type,
+            ExpressionType::Assertion {
+                kind: Assertion::StartOfLine(_) | Assertion::EndOfLine(_)
+            }
+        ) || self
+            .children
+            .read()
+            .unwrap()
+            .iter()
+            .any(|child| child.read().unwrap().has_line_assertions())
+    }
+
+    // What ends lines for the first dot, `^` or `$` in multiline mode of this expression
+    // None if it has none of them, all of them end lines the same way in a parsed pattern
+    pub fn line_terminator(&self) -> Option<LineTerminator> {
+        match self.expression_type {
+            ExpressionType::CharacterExpression {
+                value: None,
+                line_terminator,
+                ..
+            }
+            | ExpressionType::Assertion {
+                kind:
+                    Assertion::StartOfLine(line_terminator) | Assertion::EndOfLine(line_terminator),
+            } => Some(line_terminator),
+            _ => self
+                .children
+                .read()
+                .unwrap()
+                .iter()
+                .find_map(|child| child.read().unwrap().line_terminator()),
+        }
+    }
+
+    // Number of capturing groups in this expression, like 2 in `(a)(?:b|(c))`
+    pub fn capture_count(&self) -> usize {
+        usize::from(self.expression_type.is_capturing_group())
+            + self
+                .children
+                .read()
+                .unwrap()
+                .iter()
+                .map(|child| child.read().unwrap().capture_count())
+                .sum::<usize>()
+    }
+
+    // Text every match of this expression begins with
+    // For instance, literal prefix of `ab(cd|ce)` is `abc`
+    pub fn literal_prefix(&self) -> String {
+        self.prefix().0
+    }
+
+    // (literal prefix, whether this expression matches nothing but its literal prefix)
+    fn prefix(&self) -> (String, bool) {
+        let children = self.children.read().unwrap();
+        match self.expression_type {
+            // Assertions and lookbehinds add no characters to a match
+            ExpressionType::EmptyExpression
+            | ExpressionType::Assertion { .. }
+            | ExpressionType::Lookbehind { .. } => (String::new(), true),
+
+            // At least `min` repetitions of the character, no more when `max` is the same
+            ExpressionType::CharacterExpression {
+                value, quantifier, ..
+            } => {
+                let (min, max) = quantifier.bounds();
+                match value {
+                    Some(ch) => (ch.to_string().repeat(min), max == Some(min)),
+                    None => (String::new(), max == Some(0)),
+                }
+            }
+
+            // Matches begin with one of several characters
+            ExpressionType::ShorthandClass { .. }
+            | ExpressionType::CharacterClass { .. }
+            | ExpressionType::CharacterRange { .. } => (String::new(), false),
+
+            // Captured text is not known before matching
+            ExpressionType::Backreference { .. } => (String::new(), false),
+
+            ExpressionType::Group { quantifier, .. } => {
+                let (prefix, complete) = children[0].read().unwrap().prefix();
+                match quantifier.bounds() {
+                    (0, max) => (String::new(), max == Some(0)),
+                    // Each repetition is the prefix alone, so they follow one another
+                    (min, max) if complete => (prefix.repeat(min), max == Some(min)),
+                    _ => (prefix, false),
+                }
+            }
+
+            ExpressionType::Concatenation => {
+                let mut prefix = String::new();
+                for child in children.iter() {
+                    let (child_prefix, complete) = child.read().unwrap().prefix();
+                    prefix.push_str(&child_prefix);
+                    if !complete {
+                        // Whatever follows is not known to come right after `prefix`
+                        return (prefix, false);
+                    }
+                }
+                (prefix, true)
+            }
+
+            ExpressionType::Alternation => {
+                let prefixes = children
+                    .iter()
+                    .map(|child| child.read().unwrap().prefix())
+                    .collect::<Vec<_>>();
+                // Longest prefix shared by all alternatives
+                let mut common = prefixes[0].0.chars().collect::<Vec<_>>();
+                for (prefix, _) in &prefixes[1..] {
+                    let shared = common
+                        .iter()
+                        .zip(prefix.chars())
+                        .take_while(|(ch, other)| **ch == *other)
+                        .count();
+                    common.truncate(shared);
+                }
+                let common = common.into_iter().collect::<String>();
+                let complete = prefixes
+                    .iter()
+                    .all(|(prefix, complete)| *complete && *prefix == common);
+                (common, complete)
+            }
+        }
+    }
+
+    // Number of characters in the shortest string this expression matches
+    pub fn min_match_len(&self) -> usize {
+        let children = self.children.read().unwrap();
+        match self.expression_type {
+            ExpressionType::EmptyExpression
+            | ExpressionType::Assertion { .. }
+            | ExpressionType::Lookbehind { .. } => 0,
+            ExpressionType::CharacterRange { .. } => 1,
+            ExpressionType::CharacterExpression { quantifier, .. }
+            | ExpressionType::ShorthandClass { quantifier, .. }
+            | ExpressionType::CharacterClass { quantifier } => quantifier.bounds().0,
+            // Captured text may be empty
+            ExpressionType::Backreference { .. } => 0,
+            ExpressionType::Group { quantifier, .. } => children[0]
+                .read()
+                .unwrap()
+                .min_match_len()
+                .saturating_mul(quantifier.bounds().0),
+            ExpressionType::Concatenation => children
+                .iter()
+                .map(|child| child.read().unwrap().min_match_len())
+                .sum(),
+            ExpressionType::Alternation => children
+                .iter()
+                .map(|child| child.read().unwrap().min_match_len())
+                .min()
+                .unwrap(),
+        }
+    }
+
+    // Number of characters in the longest string this expression matches
+    // None if matched strings can be arbitrarily long, like those of `a+`
+    pub fn max_match_len(&self) -> Option<usize> {
+        let children = self.children.read().unwrap();
+        match self.expression_type {
+            ExpressionType::EmptyExpression
+            | ExpressionType::Assertion { .. }
+            | ExpressionType::Lookbehind { .. } => Some(0),
+            ExpressionType::CharacterRange { .. } => Some(1),
+            ExpressionType::CharacterExpression { quantifier, .. }
+            | ExpressionType::ShorthandClass { quantifier, .. }
+            | ExpressionType::CharacterClass { quantifier } => quantifier.bounds().1,
+            // Captured text may be as long as the target
+            ExpressionType::Backreference { .. } => None,
+            ExpressionType::Group { quantifier, .. } => {
+                let inner = children[0].read().unwrap().max_match_len();
+                match quantifier.bounds().1 {
+                    Some(0) => Some(0),
+                    Some(max) => inner.and_then(|inner| inner.checked_mul(max)),
+                    // Repeating the empty string gives nothing longer
+                    None => inner.filter(|max| *max == 0),
+                }
+            }
+            ExpressionType::Concatenation => children
+                .iter()
+                .map(|child| child.read().unwrap().max_match_len())
+                .try_fold(0_usize, |total, max| total.checked_add(max?)),
+            ExpressionType::Alternation => children
+                .iter()
+                .map(|child| child.read().unwrap().max_match_len())
+                .try_fold(0_usize, |longest, max| Some(longest.max(max?))),
+        }
+    }
+}
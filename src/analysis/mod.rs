@@ -0,0 +1,21 @@
+// Analysis module
+// Inspect and transform syntax trees without matching them against any string
+
+// Plain English descriptions of patterns
+mod explain;
+
+// Canonical form of patterns
+mod normalize;
+
+// Railroad diagrams of patterns as SVG images
+mod railroad;
+
+// Anchoring, literal prefixes and match lengths of patterns
+mod metadata;
+
+// Literals every match of a pattern contains
+mod literals;
+
+pub use explain::{explain, explanation};
+pub use normalize::{normalize, normalized};
+pub use railroad::{railroad_diagram, railroad_svg};
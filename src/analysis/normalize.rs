@@ -0,0 +1,322 @@
+// Canonical form of patterns
+// Patterns written differently but built the same way normalize to the same string
+// so they can be deduplicated and compared. Normalization:
+// - drops redundant parentheses, `(a)b` => `ab` and `(a)*` => `a*`
+// - flattens nested concatenations and alternations, `(ab|cd)|ef` => `ab|cd|ef`
+// - writes adjacent single character alternatives as a class, `c|a|x|a` => `[acx]`
+// - sorts and deduplicates characters of character classes, `[cab]` => `[abc]`
+//   merges their ranges, `[a-cb-fx]` => `[a-fx]`
+//   and writes classes of a single character or shorthand class as that alone,
+//   `[*]` => `\*` and `[\d]` => `\d`
+// - escapes metacharacters with exactly one slash and nothing else
+// - writes counted repetitions with one of the other quantifiers when it means the same,
+//   `a{0,1}` => `a?`, `a{1,}` => `a+` and `a{1}` => `a`
+//   and lazy ones allowing a single number of repetitions without ?, `a{3}?` => `a{3}`
+// - drops atomic grouping around what matches a single way anyway, `(?>a)+` => `a+`
+// - keeps capturing groups of patterns with backreferences, which refer to groups by number,
+//   there `(a)\1` stays as it is and other groups are written non-capturing, `(a)(b)+\1` => `(a)(?:b)+\1`
+// Normalized patterns match exactly the same strings, in the same order, as the original
+
+use std::sync::{Arc, RwLock};
+
+use crate::automata::charset::CharSet;
+use crate::parser::{syntax_tree::*, Parser};
+use crate::scanner::{escape_char, is_class_metacharacter, is_metacharacter};
+
+// Normalize `pattern`
+pub fn normalize(pattern: &str) -> Result<String, String> {
+    Ok(normalized(&Parser::parse(pattern)?))
+}
+
+// Normalize an already parsed pattern
+pub fn normalized(regexp: &Arc<RwLock<ParsedRegexp>>) -> String {
+    let keep_captures = ParsedRegexp::iter(regexp).any(|visited| {
+        matches!(
+            visited.expression.read().unwrap().expression_type,
+            ExpressionType::Backreference { .. }
+        )
+    });
+    let regexp = regexp.read().unwrap();
+    let flags = regexp.inline_flags();
+    flags + &render(&simplify(&regexp, keep_captures), keep_captures)
+}
+
+// Syntax tree stripped from everything normalization does not care about
+#[derive(PartialEq)]
+enum Node {
+    Empty,
+    Assertion(Assertion),
+    // None is the dot
+    Character(Option<char>, Quantifier),
+    Shorthand(Shorthand, Quantifier),
+    // Sorted ranges of a character class, neither overlapping nor adjacent
+    // and its sorted shorthand classes, matching more than one character or shorthand
+    Class(Vec<(char, char)>, Vec<Shorthand>, Quantifier),
+    Concatenation(Vec<Node>),
+    Alternation(Vec<Node>),
+    // A quantified group, never Quantifier::None
+    Repetition(Box<Node>, Quantifier),
+    // An atomic group, possibly without a quantifier
+    Atomic(Box<Node>, Quantifier),
+    // A capturing group kept for backreferences, possibly without a quantifier
+    // Its node id keeps it apart from other groups enclosing the same
+    Capture(NodeId, Box<Node>, Quantifier),
+    // Number of the group referred to
+    Backreference(usize, Quantifier),
+    // Whether it's negative, and what it looks for
+    Lookbehind(bool, Box<Node>),
+}
+
+impl Node {
+    // Can this node match a string in only one way at a given position?
+    // Atomic grouping changes nothing for such nodes
+    fn is_single_way(&self) -> bool {
+        matches!(
+            self,
+            Node::Empty
+                | Node::Assertion(_)
+                | Node::Lookbehind(..)
+                | Node::Character(_, Quantifier::None)
+                | Node::Shorthand(_, Quantifier::None)
+                | Node::Class(_, _, Quantifier::None)
+                | Node::Backreference(_, Quantifier::None)
+        )
+    }
+}
+
+// `quantifier` written as `?`, `*` or `+`, or left out, when one of them means the same
+// Lazy quantifiers stay lazy unless they allow a single number of repetitions
+fn canonical(quantifier: Quantifier) -> Quantifier {
+    let (min, max) = quantifier.bounds();
+    if quantifier.is_lazy() && max != Some(min) {
+        // Written with `?`, `*` or `+` already when possible, see Quantifier::greedy
+        return quantifier;
+    }
+    match (min, max) {
+        (1, Some(1)) => Quantifier::None,
+        (0, Some(1)) => Quantifier::ZeroOrOne,
+        (0, None) => Quantifier::ZeroOrMore,
+        (1, None) => Quantifier::OneOrMore,
+        _ => quantifier.greedy(),
+    }
+}
+
+// `keep_captures` tells whether capturing groups must stay, see `Node::Capture`
+fn simplify(expr: &ParsedRegexp, keep_captures: bool) -> Node {
+    let children = expr
+        .children
+        .read()
+        .unwrap()
+        .iter()
+        .map(|child| simplify(&child.read().unwrap(), keep_captures))
+        .collect::<Vec<_>>();
+    match expr.expression_type {
+        ExpressionType::EmptyExpression => Node::Empty,
+
+        ExpressionType::Assertion { kind } => Node::Assertion(kind),
+
+        ExpressionType::Lookbehind { negative } => {
+            Node::Lookbehind(negative, Box::new(children.into_iter().next().unwrap()))
+        }
+
+        ExpressionType::CharacterExpression {
+            value, quantifier, ..
+        } => {
+            // Dots of a pattern all end lines the same way, see `inline_flags`
+            Node::Character(value, canonical(quantifier))
+        }
+
+        ExpressionType::ShorthandClass { class, quantifier } => {
+            Node::Shorthand(class, canonical(quantifier))
+        }
+
+        ExpressionType::CharacterClass { quantifier } => {
+            let mut ranges = vec![];
+            let mut shorthands = vec![];
+            for member in children {
+                match member {
+                    Node::Character(Some(ch), _) => ranges.push((ch, ch)),
+                    Node::Shorthand(class, _) => shorthands.push(class),
+                    // A nested class adds its members
+                    Node::Class(member_ranges, member_shorthands, _) => {
+                        ranges.extend(member_ranges);
+                        shorthands.extend(member_shorthands);
+                    }
+                    _ => {}
+                }
+            }
+            class(ranges, shorthands, canonical(quantifier))
+        }
+
+        ExpressionType::CharacterRange { first, last } => {
+            class(vec![(first, last)], vec![], Quantifier::None)
+        }
+
+        ExpressionType::Backreference {
+            group, quantifier, ..
+        } => Node::Backreference(group, canonical(quantifier)),
+
+        ExpressionType::Group {
+            quantifier, atomic, ..
+        } => {
+            let inner = children.into_iter().next().unwrap();
+            match (inner, canonical(quantifier)) {
+                (inner, quantifier)
+                    if keep_captures && expr.expression_type.is_capturing_group() =>
+                {
+                    Node::Capture(expr.id, Box::new(inner), quantifier)
+                }
+                (inner, quantifier) if atomic && !inner.is_single_way() => {
+                    Node::Atomic(Box::new(inner), quantifier)
+                }
+                (inner, Quantifier::None) => inner,
+                // Repeating the empty string matches only the empty string
+                (Node::Empty, _) => Node::Empty,
+                (Node::Character(value, Quantifier::None), quantifier) => {
+                    Node::Character(value, quantifier)
+                }
+                (Node::Shorthand(class, Quantifier::None), quantifier) => {
+                    Node::Shorthand(class, quantifier)
+                }
+                (Node::Class(ranges, shorthands, Quantifier::None), quantifier) => {
+                    Node::Class(ranges, shorthands, quantifier)
+                }
+                (inner, quantifier) => Node::Repetition(Box::new(inner), quantifier),
+            }
+        }
+
+        ExpressionType::Concatenation => {
+            let mut items = vec![];
+            for child in children {
+                match child {
+                    Node::Concatenation(grandchildren) => items.extend(grandchildren),
+                    // The empty string changes nothing in a concatenation
+                    Node::Empty => {}
+                    child => items.push(child),
+                }
+            }
+            match items.len() {
+                0 => Node::Empty,
+                1 => items.pop().unwrap(),
+                _ => Node::Concatenation(items),
+            }
+        }
+
+        ExpressionType::Alternation => {
+            let mut branches: Vec<Node> = vec![];
+            for child in children {
+                let flattened = match child {
+                    Node::Alternation(grandchildren) => grandchildren,
+                    child => vec![child],
+                };
+                for branch in flattened {
+                    // A repeated alternative is never chosen
+                    // because the first one with the same content always is
+                    if !branches.contains(&branch) {
+                        branches.push(branch);
+                    }
+                }
+            }
+
+            // Each run of adjacent single characters becomes a class, where they are sorted
+            // Order of other alternatives is kept because it decides which one matches first
+            let mut merged = vec![];
+            let mut run = vec![];
+            for branch in branches {
+                match branch {
+                    Node::Character(Some(ch), Quantifier::None) => run.push((ch, ch)),
+                    branch => {
+                        merged.extend(characters_class(std::mem::take(&mut run)));
+                        merged.push(branch);
+                    }
+                }
+            }
+            merged.extend(characters_class(run));
+            let mut branches = merged;
+
+            match branches.len() {
+                1 => branches.pop().unwrap(),
+                _ => Node::Alternation(branches),
+            }
+        }
+    }
+}
+
+// Class matching characters of `ranges` and `shorthands`
+// or a single character or shorthand class if that is all there is
+fn class(
+    ranges: Vec<(char, char)>,
+    mut shorthands: Vec<Shorthand>,
+    quantifier: Quantifier,
+) -> Node {
+    let ranges = CharSet::from_ranges(ranges).ranges().to_vec();
+    shorthands.sort_unstable();
+    shorthands.dedup();
+    match (&ranges[..], &shorthands[..]) {
+        ([(first, last)], []) if first == last => Node::Character(Some(*first), quantifier),
+        ([], [class]) => Node::Shorthand(*class, quantifier),
+        _ => Node::Class(ranges, shorthands, quantifier),
+    }
+}
+
+// Class matching characters of a run of single character alternatives, None if there are none
+// Two different characters never match the same string, thus their order does not matter
+fn characters_class(run: Vec<(char, char)>) -> Option<Node> {
+    (!run.is_empty()).then(|| class(run, vec![], Quantifier::None))
+}
+
+// Groups other than `Node::Capture` are written non-capturing if `keep_captures`
+fn render(node: &Node, keep_captures: bool) -> String {
+    let opening = if keep_captures { "(?:" } else { "(" };
+    let render = |node: &Node| render(node, keep_captures);
+    match node {
+        Node::Empty => String::new(),
+
+        Node::Assertion(kind) => kind.to_string(),
+
+        Node::Character(value, quantifier) => match value {
+            Some(ch) => format!("{}{quantifier}", escape_char(*ch, is_metacharacter)),
+            None => format!(".{quantifier}"),
+        },
+
+        Node::Shorthand(class, quantifier) => format!("{class}{quantifier}"),
+
+        Node::Class(ranges, shorthands, quantifier) => {
+            let escape = |ch: char| escape_char(ch, is_class_metacharacter);
+            let members = ranges
+                .iter()
+                .map(|(first, last)| match (*first..=*last).nth(2) {
+                    // A range of one or two characters is written as those characters
+                    None => (*first..=*last).map(escape).collect(),
+                    Some(_) => format!("{}-{}", escape(*first), escape(*last)),
+                })
+                .chain(shorthands.iter().map(Shorthand::to_string))
+                .collect::<String>();
+            format!("[{members}]{quantifier}")
+        }
+
+        Node::Concatenation(items) => items
+            .iter()
+            .map(|item| match item {
+                // Alternation has lower precedence than concatenation
+                Node::Alternation(_) => format!("{opening}{})", render(item)),
+                _ => render(item),
+            })
+            .collect(),
+
+        Node::Alternation(branches) => branches.iter().map(render).collect::<Vec<_>>().join("|"),
+
+        Node::Repetition(inner, quantifier) => format!("{opening}{}){quantifier}", render(inner)),
+
+        Node::Atomic(inner, quantifier) => format!("(?>{}){quantifier}", render(inner)),
+
+        Node::Capture(_, inner, quantifier) => format!("({}){quantifier}", render(inner)),
+
+        Node::Backreference(group, quantifier) => format!("\\{group}{quantifier}"),
+
+        Node::Lookbehind(negative, inner) => {
+            let opening = if *negative { "(?<!" } else { "(?<=" };
+            format!("{opening}{})", render(inner))
+        }
+    }
+}
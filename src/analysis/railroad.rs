@@ -0,0 +1,426 @@
+// Railroad diagrams
+// Draw patterns as SVG railroad (syntax) diagrams: each string matched by a pattern
+// is a path from the left end of the diagram to its right end
+// - Characters are rounded boxes, consecutive characters share one box
+// - The dot is a square box labeled `any character but newline`
+//   or `any character but line terminator` if lines end otherwise
+// - Alternatives are stacked tracks
+// - Optional expressions have a track going over them
+// - Repeated expressions have a track going back under them
+//   labeled with the number of repetitions if it is counted, like `2-5 times` for `{2,5}`
+
+use std::sync::{Arc, RwLock};
+
+use crate::parser::{syntax_tree::*, Parser};
+
+// Width of one character of box labels
+const CHAR_WIDTH: f64 = 8.0;
+// Height of boxes
+const BOX_HEIGHT: f64 = 22.0;
+// Horizontal room taken by curves connecting tracks
+const CURVE: f64 = 20.0;
+// Space between adjacent elements and between stacked tracks
+const GAP: f64 = 10.0;
+// Space around the whole diagram
+const MARGIN: f64 = 20.0;
+
+// Draw `pattern` as an SVG railroad diagram
+pub fn railroad_diagram(pattern: &str) -> Result<String, String> {
+    Ok(railroad_svg(&Parser::parse(pattern)?))
+}
+
+// Draw an already parsed pattern as an SVG railroad diagram
+pub fn railroad_svg(regexp: &Arc<RwLock<ParsedRegexp>>) -> String {
+    let diagram = Element::from(&regexp.read().unwrap());
+    let width = diagram.width + 2.0 * MARGIN;
+    let height = diagram.up + diagram.down + 2.0 * MARGIN;
+    let baseline = MARGIN + diagram.up;
+
+    let mut svg = format!(
+        "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{width}\" height=\"{height}\" \
+        viewBox=\"0 0 {width} {height}\">\n\
+        <style>\
+        path {{ fill: none; stroke: black; stroke-width: 2; }} \
+        rect {{ fill: #eef; stroke: black; stroke-width: 2; }} \
+        text {{ font-family: monospace; font-size: 14px; text-anchor: middle; }} \
+        circle {{ fill: black; }}\
+        </style>\n"
+    );
+    // Start and end of the diagram
+    svg.push_str(&format!(
+        "<circle cx=\"{}\" cy=\"{baseline}\" r=\"4\"/>\n",
+        MARGIN / 2.0
+    ));
+    svg.push_str(&format!(
+        "<circle cx=\"{}\" cy=\"{baseline}\" r=\"4\"/>\n",
+        width - MARGIN / 2.0
+    ));
+    line(&mut svg, (MARGIN / 2.0, baseline), (MARGIN, baseline));
+    diagram.draw(MARGIN, baseline, &mut svg);
+    line(
+        &mut svg,
+        (MARGIN + diagram.width, baseline),
+        (width - MARGIN / 2.0, baseline),
+    );
+    svg.push_str("</svg>\n");
+    svg
+}
+
+enum Shape {
+    // Nothing, the empty string
+    Skip,
+    // A box, `rounded` for characters
+    Label {
+        text: String,
+        rounded: bool,
+    },
+    // Elements one after another
+    Sequence(Vec<Element>),
+    // Stacked alternatives, `main` is on the baseline
+    // alternatives before it are above and those after it are below
+    Choice {
+        main: usize,
+        tracks: Vec<Element>,
+    },
+    // Element followed by a track back to its start, labeled with `note` if any
+    Loop {
+        element: Box<Element>,
+        note: Option<String>,
+    },
+}
+
+// A laid out piece of a diagram
+// Each element is entered from its left end and left from its right end, both on its baseline
+struct Element {
+    shape: Shape,
+    width: f64,
+    // Room needed above the baseline
+    up: f64,
+    // Room needed below the baseline
+    down: f64,
+}
+
+impl Element {
+    fn skip() -> Element {
+        Element {
+            shape: Shape::Skip,
+            width: 0.0,
+            up: 0.0,
+            down: 0.0,
+        }
+    }
+
+    fn label(text: String, rounded: bool) -> Element {
+        Element {
+            width: text.chars().count() as f64 * CHAR_WIDTH + 2.0 * GAP,
+            up: BOX_HEIGHT / 2.0,
+            down: BOX_HEIGHT / 2.0,
+            shape: Shape::Label { text, rounded },
+        }
+    }
+
+    fn sequence(items: Vec<Element>) -> Element {
+        if items.len() == 1 {
+            return items.into_iter().next().unwrap();
+        }
+        Element {
+            width: items.iter().map(|item| item.width).sum::<f64>()
+                + GAP * items.len().saturating_sub(1) as f64,
+            up: items.iter().map(|item| item.up).fold(0.0, f64::max),
+            down: items.iter().map(|item| item.down).fold(0.0, f64::max),
+            shape: Shape::Sequence(items),
+        }
+    }
+
+    fn choice(main: usize, tracks: Vec<Element>) -> Element {
+        let width = tracks.iter().map(|track| track.width).fold(0.0, f64::max) + 2.0 * CURVE;
+        let mut up = tracks[main].up;
+        for track in &tracks[..main] {
+            up += GAP + track.down + track.up;
+        }
+        let mut down = tracks[main].down;
+        for track in &tracks[main + 1..] {
+            down += GAP + track.up + track.down;
+        }
+        Element {
+            shape: Shape::Choice { main, tracks },
+            width,
+            up,
+            down,
+        }
+    }
+
+    fn repeated(element: Element, note: Option<String>) -> Element {
+        let note_width = note
+            .as_ref()
+            .map_or(0.0, |note| note.chars().count() as f64 * CHAR_WIDTH);
+        Element {
+            width: element.width.max(note_width) + 2.0 * CURVE,
+            up: element.up,
+            // Room for the note under the track going back
+            down: element.down + GAP + if note.is_some() { BOX_HEIGHT } else { 0.0 },
+            shape: Shape::Loop {
+                element: Box::new(element),
+                note,
+            },
+        }
+    }
+
+    // Apply `quantifier` to `element`
+    fn quantified(element: Element, quantifier: Quantifier) -> Element {
+        match quantifier {
+            Quantifier::None => element,
+            Quantifier::ZeroOrOne => Element::choice(1, vec![Element::skip(), element]),
+            Quantifier::OneOrMore => Element::repeated(element, None),
+            Quantifier::ZeroOrMore => {
+                Element::choice(1, vec![Element::skip(), Element::repeated(element, None)])
+            }
+            Quantifier::Counted { min, max } => {
+                let note = match max {
+                    // Going back any number of times says it all
+                    None if min <= 1 => None,
+                    None => Some(format!("{min}+ times")),
+                    Some(max) if min == max => Some(format!("{min} times")),
+                    Some(max) if min == 0 => Some(format!("at most {max} times")),
+                    Some(max) => Some(format!("{min}-{max} times")),
+                };
+                match (min, max) {
+                    (_, Some(0)) => Element::skip(),
+                    (1, Some(1)) => element,
+                    (0, Some(1)) => Element::choice(1, vec![Element::skip(), element]),
+                    (0, _) => {
+                        Element::choice(1, vec![Element::skip(), Element::repeated(element, note)])
+                    }
+                    _ => Element::repeated(element, note),
+                }
+            }
+            // Diagrams show which strings are matched, not which ones are tried first
+            Quantifier::Lazy { .. } => Element::quantified(element, quantifier.greedy()),
+        }
+    }
+
+    fn from(expr: &ParsedRegexp) -> Element {
+        let children = expr.children.read().unwrap();
+        match expr.expression_type {
+            ExpressionType::EmptyExpression => Element::skip(),
+
+            ExpressionType::Assertion { kind } => Element::label(
+                String::from(match kind {
+                    Assertion::Start | Assertion::StartOfInput => "start of text",
+                    Assertion::End | Assertion::EndOfInput => "end of text",
+                    Assertion::WordBoundary => "word boundary",
+                    Assertion::NonWordBoundary => "not a word boundary",
+                    Assertion::StartOfLine(_) => "start of line",
+                    Assertion::EndOfLine(_) => "end of line",
+                }),
+                false,
+            ),
+
+            // What a lookbehind looks for is drawn as a pattern, it's not part of the path
+            ExpressionType::Lookbehind { negative } => {
+                let inner = children[0].read().unwrap();
+                let preceded = if negative { "not after" } else { "after" };
+                Element::label(
+                    format!("{preceded} {}", inner.pattern.escape_debug()),
+                    false,
+                )
+            }
+
+            ExpressionType::CharacterExpression {
+                value,
+                quantifier,
+                line_terminator,
+            } => {
+                let label = match (value, line_terminator) {
+                    (Some(ch), _) => Element::label(ch.escape_debug().to_string(), true),
+                    (None, LineTerminator::Char('\n')) => {
+                        Element::label(String::from("any character but newline"), false)
+                    }
+                    (None, _) => {
+                        Element::label(String::from("any character but line terminator"), false)
+                    }
+                };
+                Element::quantified(label, quantifier)
+            }
+
+            ExpressionType::CharacterClass { quantifier } => {
+                let members = children
+                    .iter()
+                    .map(|member| member.read().unwrap().pattern.to_string())
+                    .collect::<String>();
+                let label = Element::label(format!("one of {}", members.escape_debug()), false);
+                Element::quantified(label, quantifier)
+            }
+
+            ExpressionType::ShorthandClass { class, quantifier } => Element::quantified(
+                Element::label(String::from(class.name()), false),
+                quantifier,
+            ),
+
+            ExpressionType::Backreference {
+                group,
+                quantifier,
+                case_insensitive,
+            } => {
+                let case = if case_insensitive { " in any case" } else { "" };
+                Element::quantified(
+                    Element::label(format!("text of group {group}{case}"), false),
+                    quantifier,
+                )
+            }
+
+            // Ranges are drawn as part of their classes
+            ExpressionType::CharacterRange { .. } => {
+                Element::label(expr.pattern.escape_debug().to_string(), false)
+            }
+
+            // Atomic groups are drawn like any other group
+            ExpressionType::Group { quantifier, .. } => {
+                Element::quantified(Element::from(&children[0].read().unwrap()), quantifier)
+            }
+
+            ExpressionType::Alternation => Element::choice(
+                0,
+                children
+                    .iter()
+                    .map(|child| Element::from(&child.read().unwrap()))
+                    .collect(),
+            ),
+
+            ExpressionType::Concatenation => {
+                let mut items = vec![];
+                // Consecutive characters without quantifiers share one box
+                let mut text = String::new();
+                for child in children.iter() {
+                    let child = child.read().unwrap();
+                    if let ExpressionType::CharacterExpression {
+                        value: Some(ch),
+                        quantifier: Quantifier::None,
+                        ..
+                    } = child.expression_type
+                    {
+                        text.extend(ch.escape_debug());
+                        continue;
+                    }
+                    if !text.is_empty() {
+                        items.push(Element::label(std::mem::take(&mut text), true));
+                    }
+                    items.push(Element::from(&child));
+                }
+                if !text.is_empty() {
+                    items.push(Element::label(text, true));
+                }
+                Element::sequence(items)
+            }
+        }
+    }
+
+    // Draw this element with its left end at (x, y)
+    fn draw(&self, x: f64, y: f64, svg: &mut String) {
+        match &self.shape {
+            Shape::Skip => {}
+
+            Shape::Label { text, rounded } => {
+                let radius = if *rounded { BOX_HEIGHT / 2.0 } else { 0.0 };
+                svg.push_str(&format!(
+                    "<rect x=\"{x}\" y=\"{}\" width=\"{}\" height=\"{BOX_HEIGHT}\" rx=\"{radius}\"/>\n",
+                    y - BOX_HEIGHT / 2.0,
+                    self.width
+                ));
+                svg.push_str(&format!(
+                    "<text x=\"{}\" y=\"{}\">{}</text>\n",
+                    x + self.width / 2.0,
+                    y + 5.0,
+                    xml_escape(text)
+                ));
+            }
+
+            Shape::Sequence(items) => {
+                let mut x = x;
+                for (index, item) in items.iter().enumerate() {
+                    if index > 0 {
+                        line(svg, (x, y), (x + GAP, y));
+                        x += GAP;
+                    }
+                    item.draw(x, y, svg);
+                    x += item.width;
+                }
+            }
+
+            Shape::Choice { main, tracks } => {
+                let inner_width = self.width - 2.0 * CURVE;
+                let mut track_ys = vec![y; tracks.len()];
+                for index in (0..*main).rev() {
+                    let below = &tracks[index + 1];
+                    track_ys[index] = track_ys[index + 1] - below.up - GAP - tracks[index].down;
+                }
+                for index in main + 1..tracks.len() {
+                    let above = &tracks[index - 1];
+                    track_ys[index] = track_ys[index - 1] + above.down + GAP + tracks[index].up;
+                }
+                for (track, track_y) in tracks.iter().zip(track_ys) {
+                    let start = x + CURVE;
+                    let end = start + inner_width;
+                    curve(svg, (x, y), (start, track_y));
+                    track.draw(start, track_y, svg);
+                    line(svg, (start + track.width, track_y), (end, track_y));
+                    curve(svg, (end, track_y), (x + self.width, y));
+                }
+            }
+
+            Shape::Loop { element, note } => {
+                let start = x + CURVE;
+                let end = start + element.width;
+                let back_y = y + element.down + GAP;
+                line(svg, (x, y), (start, y));
+                element.draw(start, y, svg);
+                line(svg, (end, y), (x + self.width, y));
+                // Track going back from the end of the element to its start
+                svg.push_str(&format!(
+                    "<path d=\"M {end} {y} C {} {y} {} {back_y} {end} {back_y} \
+                    L {start} {back_y} C {} {back_y} {} {y} {start} {y}\"/>\n",
+                    end + CURVE,
+                    end + CURVE,
+                    start - CURVE,
+                    start - CURVE,
+                ));
+                if let Some(note) = note {
+                    svg.push_str(&format!(
+                        "<text x=\"{}\" y=\"{}\">{}</text>\n",
+                        x + self.width / 2.0,
+                        back_y + BOX_HEIGHT / 2.0 + 5.0,
+                        xml_escape(note)
+                    ));
+                }
+            }
+        }
+    }
+}
+
+fn line(svg: &mut String, from: (f64, f64), to: (f64, f64)) {
+    if from != to {
+        svg.push_str(&format!(
+            "<path d=\"M {} {} L {} {}\"/>\n",
+            from.0, from.1, to.0, to.1
+        ));
+    }
+}
+
+// Smooth curve leaving `from` and reaching `to` horizontally
+fn curve(svg: &mut String, from: (f64, f64), to: (f64, f64)) {
+    if from.1 == to.1 {
+        return line(svg, from, to);
+    }
+    let middle = (from.0 + to.0) / 2.0;
+    svg.push_str(&format!(
+        "<path d=\"M {} {} C {middle} {} {middle} {} {} {}\"/>\n",
+        from.0, from.1, from.1, to.1, to.0, to.1
+    ));
+}
+
+fn xml_escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
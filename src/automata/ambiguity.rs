@@ -0,0 +1,153 @@
+// Ambiguity detection
+// A pattern is ambiguous when some string can be matched by it in more than one way
+// For instance, `(a|ab)(c|bc)` matches `abc` either as `a` + `bc` or as `ab` + `c`
+// This matters when building lexers: which subexpression "owns" a character
+// of an ambiguous match is not well defined
+
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::fmt::Display;
+use std::ops::Range;
+use std::sync::{Arc, RwLock};
+
+use super::{check_supported, GlushkovBuilder};
+use crate::parser::{syntax_tree::ParsedRegexp, Parser};
+
+// Why a pattern is ambiguous
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum AmbiguityKind {
+    // Two different character (or dot) expressions can match the same character
+    // in two different ways of matching the same string
+    // like the two `b`'s in `(a|ab)(c|bc)` or the two `a*`'s in `a*a*`
+    OverlappingSubexpressions,
+
+    // A quantified subexpression matches the empty string, thus the empty string
+    // can be matched by any number of its iterations, like `(a?)*`
+    QuantifiedEmptyMatch,
+
+    // Two or more alternation branches match the empty string, like `a?|b*`
+    EmptyAlternatives,
+
+    // A repetition of a repetition, the same string can be split into
+    // iterations in more than one way, like `(a+)+`
+    NestedRepetition,
+}
+
+// An ambiguity found in a pattern
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Ambiguity {
+    pub kind: AmbiguityKind,
+    // Spans (ranges of character indices in source pattern) of the subexpressions involved
+    pub spans: Vec<Range<usize>>,
+}
+
+impl Display for Ambiguity {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let spans = self
+            .spans
+            .iter()
+            .map(|span| format!("{}..{}", span.start, span.end))
+            .collect::<Vec<_>>()
+            .join(" and ");
+        match self.kind {
+            AmbiguityKind::OverlappingSubexpressions => write!(
+                f,
+                "Subexpressions at {spans} can match the same character of the same string"
+            ),
+            AmbiguityKind::QuantifiedEmptyMatch => write!(
+                f,
+                "Quantified subexpression at {spans} can match the empty string in more than one way"
+            ),
+            AmbiguityKind::EmptyAlternatives => {
+                write!(f, "Alternatives at {spans} all match the empty string")
+            }
+            AmbiguityKind::NestedRepetition => write!(
+                f,
+                "Repetition at {spans} can split the same string into iterations in more than one way"
+            ),
+        }
+    }
+}
+
+// Find all ambiguities of `pattern`
+pub fn find_ambiguities(pattern: &str) -> Result<Vec<Ambiguity>, String> {
+    ambiguities(&Parser::parse(pattern)?)
+}
+
+// Find all ambiguities of an already parsed pattern
+// Fails on patterns automata can not be built from, like `Nfa::from_regexp` does
+pub fn ambiguities(regexp: &Arc<RwLock<ParsedRegexp>>) -> Result<Vec<Ambiguity>, String> {
+    let regexp = regexp.read().unwrap();
+    check_supported(&regexp.pattern, &regexp)?;
+    let mut builder = GlushkovBuilder::new(&regexp);
+    let root = builder.fragment(&regexp);
+    let states = builder.states(root);
+
+    // Glushkov automaton of an unambiguous pattern is unambiguous, that's
+    // each accepted string has exactly one accepting path
+    // Run the automaton against itself (product automaton) and look for
+    // pairs of different states which both read the same string (reachable)
+    // and can both still reach accepting states reading the same string (co-reachable)
+
+    // Reachable pairs, and pairs reached right after both copies were in the same state
+    let mut edges: HashMap<(usize, usize), Vec<(usize, usize)>> = HashMap::new();
+    let mut divergences = vec![];
+    let mut reachable = HashSet::from([(0_usize, 0_usize)]);
+    let mut queue = VecDeque::from([(0_usize, 0_usize)]);
+    while let Some((state, other_state)) = queue.pop_front() {
+        for (label, next) in &states[state].transitions {
+            for (other_label, other_next) in &states[other_state].transitions {
+                if label.intersection(other_label).is_empty() {
+                    // No character is read by both transitions
+                    continue;
+                }
+                let next_pair = (*next, *other_next);
+                edges
+                    .entry(next_pair)
+                    .or_default()
+                    .push((state, other_state));
+                if state == other_state && next != other_next && next < other_next {
+                    // Paths split here
+                    divergences.push(next_pair);
+                }
+                if reachable.insert(next_pair) {
+                    queue.push_back(next_pair);
+                }
+            }
+        }
+    }
+
+    // Walk edges backwards from pairs of accepting states
+    let mut co_reachable = reachable
+        .iter()
+        .filter(|(state, other_state)| states[*state].accepting && states[*other_state].accepting)
+        .copied()
+        .collect::<HashSet<_>>();
+    let mut queue = co_reachable.iter().copied().collect::<VecDeque<_>>();
+    while let Some(pair) = queue.pop_front() {
+        for previous in edges.get(&pair).into_iter().flatten() {
+            if co_reachable.insert(*previous) {
+                queue.push_back(*previous);
+            }
+        }
+    }
+
+    let mut found = builder.ambiguities;
+    divergences.sort_unstable();
+    divergences.dedup();
+    for (position, other_position) in divergences {
+        if co_reachable.contains(&(position, other_position)) {
+            found.push(Ambiguity {
+                kind: AmbiguityKind::OverlappingSubexpressions,
+                spans: vec![
+                    builder.spans[position].clone(),
+                    builder.spans[other_position].clone(),
+                ],
+            });
+        }
+    }
+
+    // The same subexpressions may be found ambiguous more than once
+    let mut seen = HashSet::new();
+    found.retain(|ambiguity| seen.insert(ambiguity.clone()));
+    Ok(found)
+}
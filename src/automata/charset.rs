@@ -0,0 +1,236 @@
+// Sets of characters labeling automata transitions
+
+use std::fmt::Display;
+use std::sync::OnceLock;
+
+use crate::parser::syntax_tree::Shorthand;
+
+// A set of characters stored as a sorted list of inclusive ranges
+// Ranges never overlap and are never adjacent, thus two equal sets
+// always have equal lists of ranges
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct CharSet {
+    ranges: Vec<(char, char)>,
+}
+
+impl CharSet {
+    // The set containing no characters at all
+    pub fn empty() -> CharSet {
+        CharSet { ranges: vec![] }
+    }
+
+    // The set containing only `ch`
+    pub fn single(ch: char) -> CharSet {
+        CharSet {
+            ranges: vec![(ch, ch)],
+        }
+    }
+
+    // The set containing every character, like what a dot `.` matches
+    pub fn any() -> CharSet {
+        CharSet {
+            ranges: vec![('\0', char::MAX)],
+        }
+    }
+
+    // Build a set from arbitrary (possibly overlapping or unordered) inclusive ranges
+    // Ranges whose start is greater than their end are ignored
+    pub fn from_ranges<I: IntoIterator<Item = (char, char)>>(ranges: I) -> CharSet {
+        let mut ranges = ranges
+            .into_iter()
+            .filter(|(start, end)| start <= end)
+            .collect::<Vec<_>>();
+        ranges.sort_unstable();
+
+        // Merge overlapping and adjacent ranges
+        let mut merged: Vec<(char, char)> = Vec::with_capacity(ranges.len());
+        for (start, end) in ranges {
+            match merged.last_mut() {
+                Some((_, last_end)) if next_char(*last_end).is_none_or(|next| start <= next) => {
+                    *last_end = std::cmp::max(*last_end, end);
+                }
+                _ => merged.push((start, end)),
+            }
+        }
+
+        CharSet { ranges: merged }
+    }
+
+    // Set of all characters satisfying `predicate`, found by trying every character
+    pub fn matching(predicate: impl Fn(char) -> bool) -> CharSet {
+        let mut ranges: Vec<(char, char)> = vec![];
+        for ch in ('\0'..=char::MAX).filter(|ch| predicate(*ch)) {
+            match ranges.last_mut() {
+                Some((_, end)) if next_char(*end) == Some(ch) => *end = ch,
+                _ => ranges.push((ch, ch)),
+            }
+        }
+        CharSet { ranges }
+    }
+
+    // Characters matched by shorthand class `class`, like `\d`
+    // Trying every character is slow, thus sets are built once and reused
+    pub fn shorthand(class: Shorthand) -> CharSet {
+        static DIGIT: OnceLock<CharSet> = OnceLock::new();
+        static WORD: OnceLock<CharSet> = OnceLock::new();
+        static SPACE: OnceLock<CharSet> = OnceLock::new();
+        static HORIZONTAL_SPACE: OnceLock<CharSet> = OnceLock::new();
+        static VERTICAL_SPACE: OnceLock<CharSet> = OnceLock::new();
+        if class.is_negated() {
+            return CharSet::shorthand(class.negated()).complement();
+        }
+        let set = match class {
+            Shorthand::Digit => &DIGIT,
+            Shorthand::Word => &WORD,
+            Shorthand::HorizontalSpace => &HORIZONTAL_SPACE,
+            Shorthand::VerticalSpace => &VERTICAL_SPACE,
+            _ => &SPACE,
+        };
+        set.get_or_init(|| CharSet::matching(|ch| class.matches(ch)))
+            .clone()
+    }
+
+    // Sorted inclusive ranges of this set
+    pub fn ranges(&self) -> &[(char, char)] {
+        &self.ranges
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.ranges.is_empty()
+    }
+
+    // Is this the set of all characters?
+    pub fn is_any(&self) -> bool {
+        self.ranges == [('\0', char::MAX)]
+    }
+
+    pub fn contains(&self, ch: char) -> bool {
+        // Ranges are sorted, find the last range starting at or before `ch`
+        let index = self.ranges.partition_point(|(start, _)| *start <= ch);
+        index > 0 && ch <= self.ranges[index - 1].1
+    }
+
+    // Number of characters in this set
+    pub fn len(&self) -> u32 {
+        self.ranges
+            .iter()
+            .map(|(start, end)| chars_between(*start, *end))
+            .sum()
+    }
+
+    pub fn union(&self, other: &CharSet) -> CharSet {
+        CharSet::from_ranges(self.ranges.iter().chain(other.ranges.iter()).copied())
+    }
+
+    // All characters NOT in this set
+    pub fn complement(&self) -> CharSet {
+        let mut ranges = vec![];
+        let mut start = Some('\0');
+        for (range_start, range_end) in &self.ranges {
+            if let Some(gap_start) = start {
+                if gap_start < *range_start {
+                    ranges.push((gap_start, prev_char(*range_start).unwrap()));
+                }
+            }
+            start = next_char(*range_end);
+        }
+        if let Some(gap_start) = start {
+            ranges.push((gap_start, char::MAX));
+        }
+        CharSet { ranges }
+    }
+
+    pub fn intersection(&self, other: &CharSet) -> CharSet {
+        // A ∩ B = ~(~A ∪ ~B)
+        self.complement().union(&other.complement()).complement()
+    }
+}
+
+impl Display for CharSet {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if self.is_any() {
+            return write!(f, "any");
+        }
+        let items = self
+            .ranges
+            .iter()
+            .map(|(start, end)| {
+                if start == end {
+                    format!("{}", start.escape_debug())
+                } else {
+                    format!("{}-{}", start.escape_debug(), end.escape_debug())
+                }
+            })
+            .collect::<Vec<_>>();
+        write!(f, "{}", items.join(", "))
+    }
+}
+
+// Character right after `ch`, skipping surrogates which are not characters
+pub(crate) fn next_char(ch: char) -> Option<char> {
+    match ch {
+        '\u{D7FF}' => Some('\u{E000}'),
+        char::MAX => None,
+        _ => char::from_u32(ch as u32 + 1),
+    }
+}
+
+// Character right before `ch`, skipping surrogates which are not characters
+pub(crate) fn prev_char(ch: char) -> Option<char> {
+    match ch {
+        '\u{E000}' => Some('\u{D7FF}'),
+        '\0' => None,
+        _ => char::from_u32(ch as u32 - 1),
+    }
+}
+
+// Number of characters in inclusive range `start..=end`
+fn chars_between(start: char, end: char) -> u32 {
+    let surrogates = 0xE000 - 0xD800;
+    let count = end as u32 - start as u32 + 1;
+    if start <= '\u{D7FF}' && end >= '\u{E000}' {
+        count - surrogates
+    } else {
+        count
+    }
+}
+
+// A character of inclusive range `start..=end` suitable for showing to people
+// Prefer letters, then digits, then other printable ASCII characters
+pub(crate) fn representative(start: char, end: char) -> char {
+    for (preferred_start, preferred_end) in [('a', 'z'), ('A', 'Z'), ('0', '9'), ('!', '~')] {
+        if start <= preferred_end && preferred_start <= end {
+            return std::cmp::max(start, preferred_start);
+        }
+    }
+    start
+}
+
+// Split characters covered by `sets` into the coarsest list of disjoint ranges
+// such that each range is either entirely inside or entirely outside of every set
+// Characters covered by none of `sets` are not included
+pub(crate) fn partition(sets: &[&CharSet]) -> Vec<(char, char)> {
+    // Each range begins at a set range start or right after a set range end
+    let mut boundaries = sets
+        .iter()
+        .flat_map(|set| set.ranges.iter())
+        .flat_map(|(start, end)| [Some(*start), next_char(*end)])
+        .flatten()
+        .collect::<Vec<_>>();
+    boundaries.sort_unstable();
+    boundaries.dedup();
+
+    let mut ranges = vec![];
+    for (index, start) in boundaries.iter().enumerate() {
+        let end = match boundaries.get(index + 1) {
+            Some(next_boundary) => prev_char(*next_boundary).unwrap(),
+            None => char::MAX,
+        };
+        // A range is either inside or outside of each set
+        // checking its start is enough
+        if sets.iter().any(|set| set.contains(*start)) {
+            ranges.push((*start, end));
+        }
+    }
+    ranges
+}
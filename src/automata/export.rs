@@ -0,0 +1,106 @@
+// Serialize automata so other tools can inspect them
+// Both NFAs and DFAs are given as a list of states, each state is given as
+// (accepting, list of (characters read, destination state)) pair
+// State 0 is always the start state
+
+use super::charset::CharSet;
+
+// JSON document of the form:
+// {
+//   "kind": "nfa",
+//   "start": 0,
+//   "states": [
+//     {"id": 0, "accepting": false, "transitions": [{"to": 1, "ranges": [["a", "z"]]}]},
+//     {"id": 1, "accepting": true, "transitions": []}
+//   ]
+// }
+// Each item in "ranges" is an inclusive range of characters
+pub(super) fn to_json(kind: &str, states: &[(bool, Vec<(CharSet, usize)>)]) -> String {
+    let mut json = String::new();
+    json.push_str("{\n");
+    json.push_str(&format!("  \"kind\": {},\n", json_string(kind)));
+    json.push_str("  \"start\": 0,\n");
+    json.push_str("  \"states\": [");
+    for (id, (accepting, transitions)) in states.iter().enumerate() {
+        if id > 0 {
+            json.push(',');
+        }
+        let transitions = transitions
+            .iter()
+            .map(|(label, destination)| {
+                let ranges = label
+                    .ranges()
+                    .iter()
+                    .map(|(start, end)| {
+                        format!(
+                            "[{}, {}]",
+                            json_string(&start.to_string()),
+                            json_string(&end.to_string())
+                        )
+                    })
+                    .collect::<Vec<_>>();
+                format!(
+                    "{{\"to\": {destination}, \"ranges\": [{}]}}",
+                    ranges.join(", ")
+                )
+            })
+            .collect::<Vec<_>>();
+        json.push_str(&format!(
+            "\n    {{\"id\": {id}, \"accepting\": {accepting}, \"transitions\": [{}]}}",
+            transitions.join(", ")
+        ));
+    }
+    json.push_str("\n  ]\n}");
+    json
+}
+
+// Graphviz digraph, render it with something like `dot -Tsvg`
+// Accepting states are drawn as double circles
+pub(super) fn to_dot(kind: &str, states: &[(bool, Vec<(CharSet, usize)>)]) -> String {
+    let mut dot = String::new();
+    dot.push_str(&format!("digraph {kind} {{\n"));
+    dot.push_str("  rankdir=LR;\n");
+    dot.push_str("  node [shape=circle];\n");
+    // Invisible point with an arrow pointing to start state
+    dot.push_str("  start [shape=point];\n");
+    dot.push_str("  start -> 0;\n");
+    for (id, (accepting, _)) in states.iter().enumerate() {
+        if *accepting {
+            dot.push_str(&format!("  {id} [shape=doublecircle];\n"));
+        } else {
+            dot.push_str(&format!("  {id};\n"));
+        }
+    }
+    for (id, (_, transitions)) in states.iter().enumerate() {
+        for (label, destination) in transitions {
+            dot.push_str(&format!(
+                "  {id} -> {destination} [label=\"{}\"];\n",
+                label.to_string().replace('\\', "\\\\").replace('"', "\\\"")
+            ));
+        }
+    }
+    dot.push('}');
+    dot
+}
+
+// Quote and escape `value` as a JSON string
+pub(super) fn json_string(value: &str) -> String {
+    let mut quoted = String::with_capacity(value.len() + 2);
+    quoted.push('"');
+    for ch in value.chars() {
+        match ch {
+            '"' => quoted.push_str("\\\""),
+            '\\' => quoted.push_str("\\\\"),
+            '\n' => quoted.push_str("\\n"),
+            '\r' => quoted.push_str("\\r"),
+            '\t' => quoted.push_str("\\t"),
+            // Other control characters have no short escape
+            ch if ch.is_control() && (ch as u32) < 0x20 => {
+                quoted.push_str(&format!("\\u{:04x}", ch as u32))
+            }
+            ch => quoted.push(ch),
+        }
+    }
+    quoted.push('"');
+    quoted
+}
@@ -0,0 +1,188 @@
+// Approximate matching: finding substrings which match a pattern after a few edits
+// An edit is an extra character in text (an insertion), a character of pattern missing from
+// text (a deletion) or a different character (a substitution)
+// For instance, pattern `color` matches `colour` after one edit (inserted u)
+// The NFA is simulated while tracking the fewest edits needed to reach each state
+// Hamming mode allows substitutions only, thus matches have as many characters
+// as the strings the pattern matches exactly, which suits fixed-width identifiers
+// and is cheaper because no state is reached without reading a character
+
+use std::ops::Range;
+
+use super::Nfa;
+
+// A substring matching a pattern approximately
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct FuzzyMatch {
+    // Range of character indices in text
+    pub range: Range<usize>,
+    // Fewest edits making this substring match the pattern exactly
+    pub edits: usize,
+}
+
+// State cannot be reached within edits budget
+const UNREACHED: usize = usize::MAX;
+
+// Edits allowed while matching
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Edits {
+    // Insertions, deletions and substitutions (Levenshtein distance)
+    Any,
+    // Substitutions only (Hamming distance)
+    Substitutions,
+}
+
+impl Nfa {
+    // Fewest edits among accepting states, if any is reached
+    fn accepting_edits(&self, edits: &[usize]) -> Option<usize> {
+        self.states
+            .iter()
+            .zip(edits)
+            .filter(|(state, edits)| state.accepting && **edits != UNREACHED)
+            .map(|(_, edits)| *edits)
+            .min()
+    }
+
+    // Skipping a character the pattern expects (a deletion) costs one edit
+    // and moves along a transition without reading text
+    fn skip_pattern_characters(&self, edits: &mut [usize], max_edits: usize) {
+        let mut changed = true;
+        while changed {
+            changed = false;
+            for state in 0..self.states.len() {
+                if edits[state] >= max_edits {
+                    continue;
+                }
+                for (_, destination) in &self.states[state].transitions {
+                    if edits[state] + 1 < edits[*destination] {
+                        edits[*destination] = edits[state] + 1;
+                        changed = true;
+                    }
+                }
+            }
+        }
+    }
+
+    // Best approximate match starting at `start` in `text`
+    // Fewest edits first, then the longest one
+    fn fuzzy_match_at(
+        &self,
+        text: &[char],
+        start: usize,
+        max_edits: usize,
+        allowed: Edits,
+    ) -> Option<FuzzyMatch> {
+        // Fewest edits needed to reach each state after reading text so far
+        let mut edits = vec![UNREACHED; self.states.len()];
+        edits[0] = 0;
+        if allowed == Edits::Any {
+            self.skip_pattern_characters(&mut edits, max_edits);
+        }
+        let mut best = self.accepting_edits(&edits).map(|edits| FuzzyMatch {
+            range: start..start,
+            edits,
+        });
+
+        for (index, ch) in text[start..].iter().enumerate() {
+            let mut next = vec![UNREACHED; self.states.len()];
+            for (state, state_edits) in edits.iter().enumerate() {
+                if *state_edits == UNREACHED {
+                    continue;
+                }
+                for (label, destination) in &self.states[state].transitions {
+                    // Reading a character the pattern does not expect is a substitution
+                    let cost = state_edits + usize::from(!label.contains(*ch));
+                    next[*destination] = next[*destination].min(cost);
+                }
+                if allowed == Edits::Any {
+                    // Reading a character without moving is an insertion
+                    next[state] = next[state].min(state_edits + 1);
+                }
+            }
+            for state_edits in next.iter_mut().filter(|edits| **edits > max_edits) {
+                *state_edits = UNREACHED;
+            }
+            if allowed == Edits::Any {
+                self.skip_pattern_characters(&mut next, max_edits);
+            }
+            if next.iter().all(|edits| *edits == UNREACHED) {
+                break;
+            }
+            if let Some(found) = self.accepting_edits(&next) {
+                if best.as_ref().is_none_or(|best| found <= best.edits) {
+                    best = Some(FuzzyMatch {
+                        range: start..start + index + 1,
+                        edits: found,
+                    });
+                }
+            }
+            edits = next;
+        }
+        best
+    }
+
+    // First substring of `text` (from character index `from`) matching this automaton
+    // within `max_edits` edits
+    // The leftmost match wins unless a match starting inside it needs fewer edits
+    // like `abc` in `xabc` with one edit: 1..4 (no edits) rather than 0..4 (inserted x)
+    fn fuzzy_find_from(
+        &self,
+        text: &[char],
+        from: usize,
+        max_edits: usize,
+        allowed: Edits,
+    ) -> Option<FuzzyMatch> {
+        let mut best = (from..=text.len())
+            .find_map(|start| self.fuzzy_match_at(text, start, max_edits, allowed))?;
+        let mut start = best.range.start + 1;
+        while start < best.range.end && best.edits > 0 {
+            if let Some(found) = self.fuzzy_match_at(text, start, max_edits, allowed) {
+                if found.edits < best.edits {
+                    best = found;
+                }
+            }
+            start += 1;
+        }
+        Some(best)
+    }
+
+    fn fuzzy_find_all(&self, text: &str, max_edits: usize, allowed: Edits) -> Vec<FuzzyMatch> {
+        let text = text.chars().collect::<Vec<_>>();
+        let mut found = vec![];
+        let mut from = 0;
+        while from <= text.len() {
+            let Some(next) = self.fuzzy_find_from(&text, from, max_edits, allowed) else {
+                break;
+            };
+            // Move past an empty match or it is found again and again
+            from = next.range.end.max(next.range.start + 1);
+            found.push(next);
+        }
+        found
+    }
+
+    // First substring of `text` matching this automaton within `max_edits` edits
+    // Assertions see each substring as the whole text, check `searchable` first
+    pub fn find_fuzzy(&self, text: &str, max_edits: usize) -> Option<FuzzyMatch> {
+        let text = text.chars().collect::<Vec<_>>();
+        self.fuzzy_find_from(&text, 0, max_edits, Edits::Any)
+    }
+
+    // All non-overlapping substrings of `text` matching this automaton within `max_edits` edits
+    pub fn find_all_fuzzy(&self, text: &str, max_edits: usize) -> Vec<FuzzyMatch> {
+        self.fuzzy_find_all(text, max_edits, Edits::Any)
+    }
+
+    // First substring of `text` matching this automaton after at most `max_substitutions`
+    // character substitutions (Hamming distance), no insertions nor deletions
+    pub fn find_hamming(&self, text: &str, max_substitutions: usize) -> Option<FuzzyMatch> {
+        let text = text.chars().collect::<Vec<_>>();
+        self.fuzzy_find_from(&text, 0, max_substitutions, Edits::Substitutions)
+    }
+
+    // All non-overlapping substrings of `text` matching this automaton
+    // after at most `max_substitutions` character substitutions
+    pub fn find_all_hamming(&self, text: &str, max_substitutions: usize) -> Vec<FuzzyMatch> {
+        self.fuzzy_find_all(text, max_substitutions, Edits::Substitutions)
+    }
+}
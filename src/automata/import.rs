@@ -0,0 +1,418 @@
+// Read automata produced by other tools
+// Accepted documents have the same shape as those produced by `to_json`:
+// {
+//   "kind": "nfa",
+//   "start": 0,
+//   "states": [
+//     {"id": 0, "accepting": false, "transitions": [{"to": 1, "ranges": [["a", "z"], "_"]}]},
+//     {"id": 1, "accepting": true, "transitions": []}
+//   ]
+// }
+// - "kind" is optional, if present it must be either "nfa" or "dfa"
+// - "start" is optional and defaults to 0
+// - "id" of each state is optional and defaults to its index in "states"
+//   if present, ids must be exactly 0, 1, ..., N - 1 (in any order)
+// - An item in "ranges" is either an inclusive range ["first", "last"]
+//   or a single character "c"
+
+use super::charset::CharSet;
+use super::NfaState;
+
+// Minimal JSON document tree
+enum JsonValue {
+    Null,
+    Bool(bool),
+    Number(f64),
+    String(String),
+    Array(Vec<JsonValue>),
+    Object(Vec<(String, JsonValue)>),
+}
+
+impl JsonValue {
+    fn get(&self, key: &str) -> Option<&JsonValue> {
+        match self {
+            JsonValue::Object(members) => members
+                .iter()
+                .find(|(name, _)| name == key)
+                .map(|(_, value)| value),
+            _ => None,
+        }
+    }
+
+    fn type_name(&self) -> &'static str {
+        match self {
+            JsonValue::Null => "null",
+            JsonValue::Bool(_) => "boolean",
+            JsonValue::Number(_) => "number",
+            JsonValue::String(_) => "string",
+            JsonValue::Array(_) => "array",
+            JsonValue::Object(_) => "object",
+        }
+    }
+}
+
+struct JsonParser {
+    source: Vec<char>,
+    current: usize,
+}
+
+impl JsonParser {
+    fn parse(source: &str) -> Result<JsonValue, String> {
+        let mut parser = JsonParser {
+            source: source.chars().collect(),
+            current: 0,
+        };
+        let value = parser.value()?;
+        parser.skip_whitespace();
+        if parser.current < parser.source.len() {
+            return Err(parser.error("Unexpected characters after JSON document"));
+        }
+        Ok(value)
+    }
+
+    fn error(&self, message: &str) -> String {
+        format!("Invalid JSON in position {}: {message}", self.current)
+    }
+
+    fn peek(&self) -> Option<char> {
+        self.source.get(self.current).copied()
+    }
+
+    fn skip_whitespace(&mut self) {
+        while matches!(self.peek(), Some(' ' | '\n' | '\r' | '\t')) {
+            self.current += 1;
+        }
+    }
+
+    fn expect(&mut self, expected: char) -> Result<(), String> {
+        self.skip_whitespace();
+        if self.peek() != Some(expected) {
+            return Err(self.error(&format!("Expected {expected}")));
+        }
+        self.current += 1;
+        Ok(())
+    }
+
+    fn keyword(&mut self, keyword: &str, value: JsonValue) -> Result<JsonValue, String> {
+        for expected in keyword.chars() {
+            if self.peek() != Some(expected) {
+                return Err(self.error(&format!("Expected {keyword}")));
+            }
+            self.current += 1;
+        }
+        Ok(value)
+    }
+
+    fn value(&mut self) -> Result<JsonValue, String> {
+        self.skip_whitespace();
+        match self.peek() {
+            Some('{') => self.object(),
+            Some('[') => self.array(),
+            Some('"') => Ok(JsonValue::String(self.string()?)),
+            Some('t') => self.keyword("true", JsonValue::Bool(true)),
+            Some('f') => self.keyword("false", JsonValue::Bool(false)),
+            Some('n') => self.keyword("null", JsonValue::Null),
+            Some(ch) if ch == '-' || ch.is_ascii_digit() => self.number(),
+            Some(_) => Err(self.error("Expected a JSON value")),
+            None => Err(self.error("Unexpected end of JSON document")),
+        }
+    }
+
+    fn object(&mut self) -> Result<JsonValue, String> {
+        // Move past {
+        self.current += 1;
+        let mut members = vec![];
+        self.skip_whitespace();
+        if self.peek() == Some('}') {
+            self.current += 1;
+            return Ok(JsonValue::Object(members));
+        }
+        loop {
+            self.skip_whitespace();
+            if self.peek() != Some('"') {
+                return Err(self.error("Expected object key"));
+            }
+            let key = self.string()?;
+            self.expect(':')?;
+            let value = self.value()?;
+            members.push((key, value));
+            self.skip_whitespace();
+            match self.peek() {
+                Some(',') => self.current += 1,
+                Some('}') => {
+                    self.current += 1;
+                    return Ok(JsonValue::Object(members));
+                }
+                _ => return Err(self.error("Expected , or }")),
+            }
+        }
+    }
+
+    fn array(&mut self) -> Result<JsonValue, String> {
+        // Move past [
+        self.current += 1;
+        let mut items = vec![];
+        self.skip_whitespace();
+        if self.peek() == Some(']') {
+            self.current += 1;
+            return Ok(JsonValue::Array(items));
+        }
+        loop {
+            items.push(self.value()?);
+            self.skip_whitespace();
+            match self.peek() {
+                Some(',') => self.current += 1,
+                Some(']') => {
+                    self.current += 1;
+                    return Ok(JsonValue::Array(items));
+                }
+                _ => return Err(self.error("Expected , or ]")),
+            }
+        }
+    }
+
+    fn number(&mut self) -> Result<JsonValue, String> {
+        let start = self.current;
+        while matches!(self.peek(), Some(ch) if ch.is_ascii_digit() || "+-.eE".contains(ch)) {
+            self.current += 1;
+        }
+        self.source[start..self.current]
+            .iter()
+            .collect::<String>()
+            .parse::<f64>()
+            .map(JsonValue::Number)
+            .map_err(|_| self.error("Invalid number"))
+    }
+
+    fn string(&mut self) -> Result<String, String> {
+        // Move past opening "
+        self.current += 1;
+        let mut string = String::new();
+        loop {
+            let ch = self
+                .peek()
+                .ok_or_else(|| self.error("Unterminated string"))?;
+            self.current += 1;
+            match ch {
+                '"' => return Ok(string),
+                '\\' => {
+                    let escaped = self
+                        .peek()
+                        .ok_or_else(|| self.error("Unterminated string"))?;
+                    self.current += 1;
+                    match escaped {
+                        '"' | '\\' | '/' => string.push(escaped),
+                        'n' => string.push('\n'),
+                        'r' => string.push('\r'),
+                        't' => string.push('\t'),
+                        'b' => string.push('\u{8}'),
+                        'f' => string.push('\u{c}'),
+                        'u' => {
+                            let mut code = self.hex4()?;
+                            if (0xD800..0xDC00).contains(&code) {
+                                // High surrogate, must be followed by an escaped low surrogate
+                                if self.peek() != Some('\\') {
+                                    return Err(self.error("Expected low surrogate"));
+                                }
+                                self.current += 1;
+                                if self.peek() != Some('u') {
+                                    return Err(self.error("Expected low surrogate"));
+                                }
+                                self.current += 1;
+                                let low = self.hex4()?;
+                                if !(0xDC00..0xE000).contains(&low) {
+                                    return Err(self.error("Invalid low surrogate"));
+                                }
+                                code = 0x10000 + ((code - 0xD800) << 10) + (low - 0xDC00);
+                            }
+                            string.push(
+                                char::from_u32(code)
+                                    .ok_or_else(|| self.error("Invalid unicode escape"))?,
+                            );
+                        }
+                        _ => return Err(self.error("Invalid escape sequence")),
+                    }
+                }
+                ch => string.push(ch),
+            }
+        }
+    }
+
+    fn hex4(&mut self) -> Result<u32, String> {
+        let mut code = 0;
+        for _ in 1..=4 {
+            let digit = self
+                .peek()
+                .and_then(|ch| ch.to_digit(16))
+                .ok_or_else(|| self.error("Expected 4 hexadecimal digits"))?;
+            self.current += 1;
+            code = code * 16 + digit;
+        }
+        Ok(code)
+    }
+}
+
+// Read states of an automaton described in JSON
+// Returned states are renumbered such that the start state is state 0
+pub(super) fn states_from_json(json: &str) -> Result<Vec<NfaState>, String> {
+    let document = JsonParser::parse(json)?;
+    if !matches!(document, JsonValue::Object(_)) {
+        return Err(String::from("Invalid automaton: Expected a JSON object"));
+    }
+
+    if let Some(kind) = document.get("kind") {
+        match kind {
+            JsonValue::String(kind) if kind == "nfa" || kind == "dfa" => {}
+            _ => {
+                return Err(String::from(
+                    "Invalid automaton: \"kind\" must be either \"nfa\" or \"dfa\"",
+                ))
+            }
+        }
+    }
+
+    let states = match document.get("states") {
+        Some(JsonValue::Array(states)) => states,
+        Some(other) => {
+            return Err(format!(
+                "Invalid automaton: \"states\" must be an array, found {}",
+                other.type_name()
+            ))
+        }
+        None => return Err(String::from("Invalid automaton: Missing \"states\"")),
+    };
+    if states.is_empty() {
+        return Err(String::from("Invalid automaton: No states"));
+    }
+
+    let state_index = |value: &JsonValue, what: &str| -> Result<usize, String> {
+        match value {
+            JsonValue::Number(number)
+                if number.fract() == 0.0 && *number >= 0.0 && (*number as usize) < states.len() =>
+            {
+                Ok(*number as usize)
+            }
+            _ => Err(format!(
+                "Invalid automaton: {what} must be a state id between 0 and {}",
+                states.len() - 1
+            )),
+        }
+    };
+
+    let start = match document.get("start") {
+        Some(start) => state_index(start, "\"start\"")?,
+        None => 0,
+    };
+
+    let mut parsed: Vec<Option<NfaState>> = vec![None; states.len()];
+    for (index, state) in states.iter().enumerate() {
+        let id = match state.get("id") {
+            Some(id) => state_index(id, "\"id\"")?,
+            None => index,
+        };
+        if parsed[id].is_some() {
+            return Err(format!("Invalid automaton: Duplicate state id {id}"));
+        }
+
+        let accepting = match state.get("accepting") {
+            Some(JsonValue::Bool(accepting)) => *accepting,
+            None => false,
+            Some(_) => {
+                return Err(format!(
+                    "Invalid automaton: \"accepting\" of state {id} must be a boolean"
+                ))
+            }
+        };
+
+        let mut transitions = vec![];
+        match state.get("transitions") {
+            Some(JsonValue::Array(items)) => {
+                for transition in items {
+                    let destination = match transition.get("to") {
+                        Some(to) => state_index(to, "\"to\"")?,
+                        None => {
+                            return Err(format!(
+                                "Invalid automaton: Transition of state {id} has no \"to\""
+                            ))
+                        }
+                    };
+                    let label = match transition.get("ranges") {
+                        Some(JsonValue::Array(ranges)) => ranges_from_json(ranges, id)?,
+                        _ => {
+                            return Err(format!(
+                            "Invalid automaton: Transition of state {id} has no \"ranges\" array"
+                        ))
+                        }
+                    };
+                    transitions.push((label, destination));
+                }
+            }
+            None => {}
+            Some(_) => {
+                return Err(format!(
+                    "Invalid automaton: \"transitions\" of state {id} must be an array"
+                ))
+            }
+        }
+
+        parsed[id] = Some(NfaState {
+            accepting,
+            transitions,
+        });
+    }
+
+    // Ids are distinct and all less than states count, hence all states are present
+    let mut parsed = parsed.into_iter().map(Option::unwrap).collect::<Vec<_>>();
+
+    // Make start state the state 0 by swapping it with state 0
+    if start != 0 {
+        parsed.swap(0, start);
+        let renumber = |state: usize| match state {
+            0 => start,
+            _ if state == start => 0,
+            _ => state,
+        };
+        for state in parsed.iter_mut() {
+            for (_, destination) in state.transitions.iter_mut() {
+                *destination = renumber(*destination);
+            }
+        }
+    }
+
+    Ok(parsed)
+}
+
+fn ranges_from_json(ranges: &[JsonValue], id: usize) -> Result<CharSet, String> {
+    let single_char = |value: &JsonValue| -> Option<char> {
+        match value {
+            JsonValue::String(string) => {
+                let mut chars = string.chars();
+                match (chars.next(), chars.next()) {
+                    (Some(ch), None) => Some(ch),
+                    _ => None,
+                }
+            }
+            _ => None,
+        }
+    };
+
+    let mut parsed = vec![];
+    for range in ranges {
+        let bounds = match range {
+            JsonValue::Array(bounds) if bounds.len() == 2 => {
+                single_char(&bounds[0]).zip(single_char(&bounds[1]))
+            }
+            _ => single_char(range).map(|ch| (ch, ch)),
+        };
+        match bounds {
+            Some((start, end)) if start <= end => parsed.push((start, end)),
+            _ => {
+                return Err(format!(
+                    "Invalid automaton: Transition of state {id} has an invalid range, \
+                    expected [\"first\", \"last\"] or \"c\""
+                ))
+            }
+        }
+    }
+    Ok(CharSet::from_ranges(parsed))
+}
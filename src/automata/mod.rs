@@ -0,0 +1,1032 @@
+// Automata module
+// Build finite automata equivalent to a parsed regular expression
+// An automaton built from a pattern accepts exactly those strings which
+// the pattern matches as a whole (from first character to last character)
+// Assertions like `^`, `$` and `\b` see nothing before or after the string the automaton reads
+// so searching with an automaton would treat each match as if it were the whole target
+// and find `a` in "ba" for `^a`, searches reject such patterns (see `check_searchable`)
+// Patterns with atomic groups are rejected (see `check_supported`), what they match depends
+// on which way of matching their contents is tried first, and automata try them all at once
+// Line breaks `\R` are not, though parsed as atomic groups users did not write them as such
+// So are backreferences, which match what their group matched, a finite automaton
+// can not remember it, `(a|b)\1` matches "aa" and "bb" but neither "ab" nor "ba"
+// and lookbehinds, which look at characters before the string the automaton reads
+
+// Sets of characters labeling transitions
+pub mod charset;
+
+// Serialization of automata to JSON and Graphviz DOT
+mod export;
+
+// Reading automata described in JSON
+mod import;
+
+// Searching strings using automata
+pub mod search;
+
+// Detecting patterns which can match a string in more than one way
+pub mod ambiguity;
+
+// Approximate matching within a number of edits
+pub mod fuzzy;
+
+use std::collections::{hash_map::Entry, BTreeMap, BTreeSet, HashMap, VecDeque};
+use std::ops::Range;
+use std::sync::{Arc, RwLock};
+
+use crate::diagnostics::DiagnosticCode;
+use crate::parser::{syntax_tree::*, Parser};
+use crate::{format_error, telemetry};
+use ambiguity::{Ambiguity, AmbiguityKind};
+use charset::CharSet;
+
+// A state of a nondeterministic finite automaton
+#[derive(Debug, Clone)]
+pub struct NfaState {
+    // Is this a final state?
+    pub accepting: bool,
+    // (characters read, destination state) pairs
+    // Labels of different transitions may overlap, hence the nondeterminism
+    pub transitions: Vec<(CharSet, usize)>,
+}
+
+// Nondeterministic finite automaton without empty (epsilon) transitions
+// State 0 is always the start state
+#[derive(Debug, Clone)]
+pub struct Nfa {
+    states: Vec<NfaState>,
+    // Why searching with it would find matches the pattern forbids, see `check_searchable`
+    search_error: Option<String>,
+}
+
+// A state of a deterministic finite automaton
+#[derive(Debug, Clone)]
+pub struct DfaState {
+    // Is this a final state?
+    pub accepting: bool,
+    // (first character, last character, destination state) triples
+    // Ranges are sorted and disjoint, characters in no range lead nowhere
+    pub transitions: Vec<(char, char, usize)>,
+}
+
+// Deterministic finite automaton
+// State 0 is always the start state
+#[derive(Debug, Clone)]
+pub struct Dfa {
+    states: Vec<DfaState>,
+    // Like `Nfa::search_error`, kept from the NFA it is built from
+    search_error: Option<String>,
+}
+
+// Is every string matched (as a whole) by `pattern` also matched by `other_pattern`?
+// For instance, `a(b|c)` is a subset of `a.` but not the other way around
+pub fn is_subset(pattern: &str, other_pattern: &str) -> Result<bool, String> {
+    Ok(Dfa::new(pattern)?.is_subset_of(&Dfa::new(other_pattern)?))
+}
+
+// A shortest string matched (as a whole) by `pattern`
+// Handy in messages like "expected something matching `a+b`, for instance `ab`"
+pub fn shortest_example(pattern: &str) -> Result<Option<String>, String> {
+    Ok(Dfa::new(pattern)?.shortest_example())
+}
+
+// First substring of `text` matching `pattern` after at most `max_edits` edits
+// (inserting, deleting or substituting one character), see `fuzzy`
+pub fn find_fuzzy(
+    pattern: &str,
+    text: &str,
+    max_edits: usize,
+) -> Result<Option<fuzzy::FuzzyMatch>, String> {
+    let nfa = Nfa::new(pattern)?;
+    nfa.searchable()?;
+    Ok(nfa.find_fuzzy(text, max_edits))
+}
+
+// First substring of `text` matching `pattern` after at most `max_substitutions`
+// character substitutions, see `fuzzy`
+pub fn find_hamming(
+    pattern: &str,
+    text: &str,
+    max_substitutions: usize,
+) -> Result<Option<fuzzy::FuzzyMatch>, String> {
+    let nfa = Nfa::new(pattern)?;
+    nfa.searchable()?;
+    Ok(nfa.find_hamming(text, max_substitutions))
+}
+
+// Number of distinct strings of exactly `length` characters matched (as a whole) by `pattern`
+// None if that number does not fit in a u128, which happens quickly with dots
+// because a dot matches any of 1,112,064 characters
+pub fn count_strings(pattern: &str, length: usize) -> Result<Option<u128>, String> {
+    Ok(Dfa::new(pattern)?.count_strings(length))
+}
+
+// Characters matched by a character, dot, shorthand class, character class
+// or character range expression
+fn label(expr: &ParsedRegexp) -> CharSet {
+    match expr.expression_type {
+        // A `None` value is a dot expression, matching anything but a line terminator
+        ExpressionType::CharacterExpression {
+            value,
+            line_terminator,
+            ..
+        } => value.map_or_else(
+            || match line_terminator {
+                LineTerminator::Char(ch) => CharSet::single(ch).complement(),
+                LineTerminator::Crlf => {
+                    CharSet::from_ranges([('\n', '\n'), ('\r', '\r')]).complement()
+                }
+            },
+            CharSet::single,
+        ),
+        ExpressionType::CharacterClass { .. } => expr
+            .children
+            .read()
+            .unwrap()
+            .iter()
+            .fold(CharSet::empty(), |set, member| {
+                set.union(&label(&member.read().unwrap()))
+            }),
+        ExpressionType::CharacterRange { first, last } => CharSet::from_ranges([(first, last)]),
+        ExpressionType::ShorthandClass { class, .. } => CharSet::shorthand(class),
+        _ => CharSet::empty(),
+    }
+}
+
+// Glushkov (position) automaton construction
+// Each character, dot or character class expression in the pattern is a `position`
+// and each position is a state of the constructed NFA entered by reading
+// a character matched by that position, the start state is not a position
+// Assertions are not positions, they decide which positions can follow one another
+// from what is on both sides of a place between characters (see `Side`)
+struct GlushkovBuilder {
+    // labels[i] is what position i matches, labels[0] is unused (start state)
+    labels: Vec<CharSet>,
+    // sides[i] is what a character read by position i is, sides[0] is unused
+    sides: Vec<Side>,
+    // follow[i] are positions which can come right after position i
+    follow: Vec<BTreeSet<usize>>,
+    // spans[i] is the span of character, dot or class expression of position i in source pattern
+    spans: Vec<Range<usize>>,
+    // Whether the pattern has word boundaries, then positions matching both
+    // word characters and other characters are split in two, one for each
+    // so that each position reads either word characters or other characters
+    split_words: bool,
+    // Like `split_words`, line terminators and other characters are split
+    // if there are `^` or `$` in multiline mode, set to what ends lines then
+    split_lines: Option<LineTerminator>,
+    // Subexpressions found to match some strings in more than one way
+    // they are detected while building because they are hidden in the built automaton
+    ambiguities: Vec<Ambiguity>,
+}
+
+// What is on one side of a place between characters of the string read by an automaton
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Side {
+    // Nothing, the place is the start (or the end) of the string read
+    Edge,
+    // A word character, see `is_word_character`
+    Word,
+    // A line terminator, seen by `^` and `$` in multiline mode
+    // \n in CRLF mode (see `LineTerminator::Crlf`)
+    Newline,
+    // A carriage return \r in CRLF mode
+    Return,
+    // Any other character
+    Other,
+}
+
+const SIDES: [Side; 5] = [
+    Side::Edge,
+    Side::Word,
+    Side::Newline,
+    Side::Return,
+    Side::Other,
+];
+
+// Set of (before, after) pairs of sides of places between characters, one bit per pair
+type Places = u32;
+
+// Every place, where the empty string always matches
+const ANYWHERE: Places = Places::MAX >> (Places::BITS as usize - SIDES.len() * SIDES.len());
+
+// The place with `before` and `after` on its sides
+fn place(before: Side, after: Side) -> Places {
+    1 << (before as usize * SIDES.len() + after as usize)
+}
+
+// Places where `assertion` holds
+fn places(assertion: Assertion) -> Places {
+    let mut places = 0;
+    for before in SIDES {
+        for after in SIDES {
+            let holds = match assertion {
+                Assertion::Start | Assertion::StartOfInput => before == Side::Edge,
+                Assertion::End | Assertion::EndOfInput => after == Side::Edge,
+                Assertion::WordBoundary => (before == Side::Word) != (after == Side::Word),
+                Assertion::NonWordBoundary => (before == Side::Word) == (after == Side::Word),
+                // Never between \r and \n in CRLF mode
+                Assertion::StartOfLine(_) => {
+                    matches!(before, Side::Edge | Side::Newline)
+                        || (before == Side::Return && after != Side::Newline)
+                }
+                Assertion::EndOfLine(_) => {
+                    matches!(after, Side::Edge | Side::Return)
+                        || (after == Side::Newline && before != Side::Return)
+                }
+            };
+            if holds {
+                places |= place(before, after);
+            }
+        }
+    }
+    places
+}
+
+// Summary of a subexpression in Glushkov construction
+// The default one matches nothing at all
+#[derive(Default)]
+struct Fragment {
+    // Places where this subexpression matches the empty string, 0 if it never does
+    nullable: Places,
+    // Positions which can match the first character of a string
+    // along with places right before that character where it can
+    // like only at start of the string read for `a` in `^a`
+    first: BTreeMap<usize, Places>,
+    // Positions which can match the last character of a string
+    // along with places right after that character where it can
+    last: BTreeMap<usize, Places>,
+}
+
+impl Fragment {
+    // Fragment matching the empty string only, wherever it is in `places`
+    fn empty(places: Places) -> Fragment {
+        Fragment {
+            nullable: places,
+            ..Fragment::default()
+        }
+    }
+
+    // Does this subexpression match the empty string somewhere?
+    fn is_nullable(&self) -> bool {
+        self.nullable != 0
+    }
+}
+
+// Add `places` to those of `position` in `positions`, unless there are none
+fn add_places(positions: &mut BTreeMap<usize, Places>, position: usize, places: Places) {
+    if places != 0 {
+        *positions.entry(position).or_default() |= places;
+    }
+}
+
+// Fragment of `left` followed by `right`, except follow sets linking them
+// The empty string matched by one part is at the same place as what the other part matches
+fn concatenate(left: Fragment, right: Fragment) -> Fragment {
+    let mut first = left.first;
+    for (position, places) in right.first {
+        add_places(&mut first, position, left.nullable & places);
+    }
+    let mut last = right.last;
+    for (position, places) in left.last {
+        add_places(&mut last, position, right.nullable & places);
+    }
+    Fragment {
+        nullable: left.nullable & right.nullable,
+        first,
+        last,
+    }
+}
+
+impl GlushkovBuilder {
+    fn new(regexp: &ParsedRegexp) -> GlushkovBuilder {
+        GlushkovBuilder {
+            labels: vec![CharSet::empty()],
+            sides: vec![Side::Edge],
+            follow: vec![BTreeSet::new()],
+            // Start state is not a position, it has an empty span
+            spans: vec![Range::default()],
+            split_words: has_word_boundaries(regexp),
+            split_lines: regexp
+                .has_line_assertions()
+                .then(|| regexp.line_terminator().unwrap_or_default()),
+            ambiguities: vec![],
+        }
+    }
+
+    // Create a new position matching characters in `label`
+    // or more of them if it must be split, see fields `split_words` and `split_lines`
+    fn position(&mut self, label: CharSet, span: Range<usize>) -> Fragment {
+        // Without word boundaries and line assertions, which side a character is on never matters
+        let mut parts = vec![(label, Side::Other)];
+        if let Some(terminator) = self.split_lines {
+            let (newline, carriage_return) = match terminator {
+                LineTerminator::Char(ch) => (CharSet::single(ch), CharSet::empty()),
+                LineTerminator::Crlf => (CharSet::single('\n'), CharSet::single('\r')),
+            };
+            let (label, _) = parts.pop().unwrap();
+            let other = newline.union(&carriage_return).complement();
+            parts = vec![
+                (label.intersection(&newline), Side::Newline),
+                (label.intersection(&carriage_return), Side::Return),
+                (label.intersection(&other), Side::Other),
+            ];
+        }
+        if self.split_words {
+            // A newline is not a word character
+            let words = CharSet::shorthand(Shorthand::Word);
+            let (label, _) = parts.pop().unwrap();
+            parts.extend([
+                (label.intersection(&words), Side::Word),
+                (label.intersection(&words.complement()), Side::Other),
+            ]);
+        }
+        parts.retain(|(label, _)| !label.is_empty());
+        let mut fragment = Fragment::default();
+        for (label, side) in parts {
+            let position = self.labels.len();
+            self.labels.push(label);
+            self.sides.push(side);
+            self.follow.push(BTreeSet::new());
+            self.spans.push(span.clone());
+            fragment.first.insert(position, ANYWHERE);
+            fragment.last.insert(position, ANYWHERE);
+        }
+        fragment
+    }
+
+    // Let positions ending a string of `left` be followed by positions beginning a string of `right`
+    // where both allow the place between their characters
+    // Gives back true if some position could already be followed by a position it is linked to now
+    fn link(&mut self, left: &BTreeMap<usize, Places>, right: &BTreeMap<usize, Places>) -> bool {
+        let mut repeated_follow = false;
+        for (position, left_places) in left {
+            for (next, right_places) in right {
+                let between = place(self.sides[*position], self.sides[*next]);
+                if left_places & right_places & between != 0 {
+                    repeated_follow |= !self.follow[*position].insert(*next);
+                }
+            }
+        }
+        repeated_follow
+    }
+
+    fn fragment(&mut self, expr: &ParsedRegexp) -> Fragment {
+        match expr.expression_type {
+            ExpressionType::EmptyExpression => Fragment::empty(ANYWHERE),
+
+            ExpressionType::Assertion { kind } => Fragment::empty(places(kind)),
+
+            ExpressionType::Lookbehind { .. } => {
+                unreachable!("lookbehinds are rejected by check_supported")
+            }
+
+            ExpressionType::CharacterExpression { quantifier, .. }
+            | ExpressionType::ShorthandClass { quantifier, .. }
+            | ExpressionType::CharacterClass { quantifier } => {
+                self.repeat(quantifier, expr.span.clone(), |builder| {
+                    builder.position(label(expr), expr.span.clone())
+                })
+            }
+
+            // Ranges are labels of their classes, never positions on their own
+            ExpressionType::CharacterRange { .. } => {
+                let position = self.position(label(expr), expr.span.clone());
+                self.quantify(position, Quantifier::None, expr.span.clone())
+            }
+
+            ExpressionType::Group { quantifier, .. } => {
+                let inner = &expr.children.read().unwrap()[0];
+                self.repeat(quantifier, expr.span.clone(), |builder| {
+                    builder.fragment(&inner.read().unwrap())
+                })
+            }
+
+            ExpressionType::Backreference { .. } => {
+                unreachable!("backreferences are rejected by check_supported")
+            }
+
+            ExpressionType::Concatenation => {
+                let mut concatenation = Fragment::empty(ANYWHERE);
+                for child in expr.children.read().unwrap().iter() {
+                    let next = self.fragment(&child.read().unwrap());
+                    // What ends the left part can be followed by what begins the right part
+                    // unless an assertion between them does not hold there, like in `a^b`
+                    self.link(&concatenation.last, &next.first);
+                    concatenation = concatenate(concatenation, next);
+                }
+                concatenation
+            }
+
+            ExpressionType::Alternation => {
+                let mut alternation = Fragment::default();
+                let mut nullable_branches = vec![];
+                for child in expr.children.read().unwrap().iter() {
+                    let child = child.read().unwrap();
+                    let branch = self.fragment(&child);
+                    if branch.is_nullable() {
+                        nullable_branches.push(child.span.clone());
+                    }
+                    alternation.nullable |= branch.nullable;
+                    for (position, places) in branch.first {
+                        add_places(&mut alternation.first, position, places);
+                    }
+                    for (position, places) in branch.last {
+                        add_places(&mut alternation.last, position, places);
+                    }
+                }
+                if nullable_branches.len() > 1 {
+                    // Each of these branches can match the empty string
+                    self.ambiguities.push(Ambiguity {
+                        kind: AmbiguityKind::EmptyAlternatives,
+                        spans: nullable_branches,
+                    });
+                }
+                alternation
+            }
+        }
+    }
+
+    // Subexpression spanning `span` (with its quantifier) built by `build` and quantified
+    // A counted repetition has its own copy of the subexpression for each repetition
+    // `a{2,4}` is built as `aa(aa?)?` and `a{2,}` as `aaa*`
+    fn repeat(
+        &mut self,
+        quantifier: Quantifier,
+        span: Range<usize>,
+        mut build: impl FnMut(&mut GlushkovBuilder) -> Fragment,
+    ) -> Fragment {
+        // Automata accept whole strings, preferring fewer repetitions changes nothing
+        let quantifier = quantifier.greedy();
+        let Quantifier::Counted { min, max } = quantifier else {
+            let fragment = build(self);
+            return self.quantify(fragment, quantifier, span);
+        };
+        let mut repeated = Fragment::empty(ANYWHERE);
+        let append = |builder: &mut GlushkovBuilder, repeated: Fragment, next: Fragment| {
+            builder.link(&repeated.last, &next.first);
+            concatenate(repeated, next)
+        };
+        for _ in 0..min {
+            let next = build(self);
+            repeated = append(self, repeated, next);
+        }
+        match max {
+            None => {
+                let next = build(self);
+                let next = self.quantify(next, Quantifier::ZeroOrMore, span);
+                repeated = append(self, repeated, next);
+            }
+            Some(max) => {
+                // Optional repetitions nest, so there is one way to match each number of them
+                let mut optional: Option<Fragment> = None;
+                for _ in min..max {
+                    let mut next = build(self);
+                    if let Some(rest) = optional.take() {
+                        next = append(self, next, rest);
+                    }
+                    next = self.quantify(next, Quantifier::ZeroOrOne, span.clone());
+                    optional = Some(next);
+                }
+                if let Some(optional) = optional {
+                    repeated = append(self, repeated, optional);
+                }
+            }
+        }
+        repeated
+    }
+
+    // Apply a quantifier to an already built subexpression spanning `span` (with its quantifier)
+    fn quantify(
+        &mut self,
+        mut fragment: Fragment,
+        quantifier: Quantifier,
+        span: Range<usize>,
+    ) -> Fragment {
+        if fragment.is_nullable() && !matches!(quantifier, Quantifier::None) {
+            // The empty string is matched by either zero or one (or more) iterations
+            self.ambiguities.push(Ambiguity {
+                kind: AmbiguityKind::QuantifiedEmptyMatch,
+                spans: vec![span.clone()],
+            });
+        }
+        match quantifier {
+            Quantifier::None => {}
+            Quantifier::ZeroOrOne => fragment.nullable = ANYWHERE,
+            // Built by `repeat` out of the other quantifiers
+            Quantifier::Counted { .. } | Quantifier::Lazy { .. } => {
+                unreachable!("counted repetitions are expanded, lazy ones made greedy")
+            }
+            Quantifier::ZeroOrMore | Quantifier::OneOrMore => {
+                // Repeating: what ends one iteration can be followed by what begins the next one
+                // Iterations matching the empty string in between only add assertions
+                // which must hold at the same place, they never link more positions
+                let repeated_follow = self.link(&fragment.last, &fragment.first);
+                if repeated_follow && !fragment.is_nullable() {
+                    // Going from a last position back to a first position was already
+                    // possible inside a single iteration, like in `(a+)+`
+                    self.ambiguities.push(Ambiguity {
+                        kind: AmbiguityKind::NestedRepetition,
+                        spans: vec![span],
+                    });
+                }
+                if matches!(quantifier, Quantifier::ZeroOrMore) {
+                    fragment.nullable = ANYWHERE;
+                }
+            }
+        }
+        fragment
+    }
+
+    // States of the built automaton, `root` is the fragment of the whole pattern
+    fn states(&self, root: Fragment) -> Vec<NfaState> {
+        let mut states = self
+            .follow
+            .iter()
+            .map(|follow| NfaState {
+                accepting: false,
+                // Entering a position means reading a character matched by that position
+                transitions: follow
+                    .iter()
+                    .map(|next| (self.labels[*next].clone(), *next))
+                    .collect(),
+            })
+            .collect::<Vec<_>>();
+        // The start state is at start of the string read
+        states[0].transitions = root
+            .first
+            .iter()
+            .filter(|(next, places)| *places & place(Side::Edge, self.sides[**next]) != 0)
+            .map(|(next, _)| (self.labels[*next].clone(), *next))
+            .collect();
+        // where the empty string is both started and ended
+        states[0].accepting = root.nullable & place(Side::Edge, Side::Edge) != 0;
+        for (position, places) in root.last {
+            states[position].accepting = places & place(self.sides[position], Side::Edge) != 0;
+        }
+
+        states
+    }
+}
+
+// Fail if `expr` or one of its descendants can not be matched exactly by an automaton
+// An automaton built from it anyway would accept strings the pattern never matches
+// thus subset checks, counts and examples given by it would be wrong
+fn check_supported(source: &str, expr: &ParsedRegexp) -> Result<(), String> {
+    let unsupported = match expr.expression_type {
+        // `\R` is parsed as an atomic group (see `LINEBREAK`), automata match it
+        // like `\r\n|[\n\r...]`, which is what users of automata expect from it
+        ExpressionType::Group { atomic: true, .. } if !expr.pattern.starts_with("\\R") => {
+            Some("Atomic group")
+        }
+        ExpressionType::Backreference { .. } => Some("Backreference"),
+        ExpressionType::Lookbehind { .. } => Some("Lookbehind"),
+        _ => None,
+    };
+    if let Some(construct) = unsupported {
+        return Err(unsupported_error(
+            source,
+            expr,
+            construct,
+            "Search with a Matcher instead, it matches every construct",
+        ));
+    }
+    for child in expr.children.read().unwrap().iter() {
+        check_supported(source, &child.read().unwrap())?;
+    }
+    Ok(())
+}
+
+// Searching tries substrings of a target as if each were the whole target
+// thus assertions would hold where the pattern forbids them, like `\bcat\b` in "concatenate"
+// Whole string questions, like `is_subset`, are not affected
+fn check_searchable(source: &str, expr: &ParsedRegexp) -> Result<(), String> {
+    if let ExpressionType::Assertion { .. } = expr.expression_type {
+        return Err(unsupported_error(
+            source,
+            expr,
+            "Assertion",
+            "Automata see each match as a whole target, search with a Matcher instead",
+        ));
+    }
+    for child in expr.children.read().unwrap().iter() {
+        check_searchable(source, &child.read().unwrap())?;
+    }
+    Ok(())
+}
+
+fn unsupported_error(source: &str, expr: &ParsedRegexp, construct: &str, hint: &str) -> String {
+    let length = expr.span.len().clamp(1, u8::MAX as usize) as u8;
+    format_error(
+        &format!(
+            "Unsupported pattern [{}] in position {}: {construct} `{}`",
+            DiagnosticCode::UnsupportedByAutomata,
+            expr.span.start,
+            expr.pattern
+        ),
+        source,
+        &[(expr.span.start, length)],
+        hint,
+    )
+}
+
+// Does `expr` contain `\b` or `\B`?
+fn has_word_boundaries(expr: &ParsedRegexp) -> bool {
+    matches!(
+        expr.expression_type,
+        ExpressionType::Assertion {
+            kind: Assertion::WordBoundary | Assertion::NonWordBoundary
+        }
+    ) || expr
+        .children
+        .read()
+        .unwrap()
+        .iter()
+        .any(|child| has_word_boundaries(&child.read().unwrap()))
+}
+
+impl Nfa {
+    // Build an NFA from `pattern`
+    pub fn new(pattern: &str) -> Result<Nfa, String> {
+        Nfa::from_regexp(&Parser::parse(pattern)?)
+    }
+
+    // Build an NFA from an already parsed pattern
+    // Fails if it has a construct automata can not match, see `check_supported`
+    pub fn from_regexp(regexp: &Arc<RwLock<ParsedRegexp>>) -> Result<Nfa, String> {
+        let regexp = regexp.read().unwrap();
+        check_supported(&regexp.pattern, &regexp)?;
+        let search_error = check_searchable(&regexp.pattern, &regexp).err();
+        Ok(telemetry::compile("nfa", Some(&regexp.pattern), || {
+            let mut builder = GlushkovBuilder::new(&regexp);
+            let root = builder.fragment(&regexp);
+            Nfa {
+                states: builder.states(root),
+                search_error,
+            }
+        }))
+    }
+
+    // Read an NFA described in JSON, see `to_json` for the expected format
+    pub fn from_json(json: &str) -> Result<Nfa, String> {
+        Ok(Nfa {
+            states: import::states_from_json(json)?,
+            search_error: None,
+        })
+    }
+
+    // Can this NFA search targets? Not if its pattern has assertions, see `check_searchable`
+    pub fn searchable(&self) -> Result<(), String> {
+        self.search_error.clone().map_or(Ok(()), Err)
+    }
+
+    pub fn states(&self) -> &[NfaState] {
+        &self.states
+    }
+
+    // Does this automaton accept the whole `input`?
+    pub fn accepts(&self, input: &str) -> bool {
+        // Track all states the automaton could be in simultaneously
+        let mut current = vec![0_usize];
+        for ch in input.chars() {
+            let mut next = current
+                .iter()
+                .flat_map(|state| self.states[*state].transitions.iter())
+                .filter(|(label, _)| label.contains(ch))
+                .map(|(_, destination)| *destination)
+                .collect::<Vec<_>>();
+            if next.is_empty() {
+                return false;
+            }
+            next.sort_unstable();
+            next.dedup();
+            current = next;
+        }
+        current.iter().any(|state| self.states[*state].accepting)
+    }
+
+    pub fn to_json(&self) -> String {
+        export::to_json("nfa", &self.labeled_transitions())
+    }
+
+    pub fn to_dot(&self) -> String {
+        export::to_dot("nfa", &self.labeled_transitions())
+    }
+
+    fn labeled_transitions(&self) -> Vec<(bool, Vec<(CharSet, usize)>)> {
+        self.states
+            .iter()
+            .map(|state| (state.accepting, state.transitions.clone()))
+            .collect()
+    }
+}
+
+impl Dfa {
+    // Build a DFA from `pattern`
+    pub fn new(pattern: &str) -> Result<Dfa, String> {
+        Ok(Dfa::from_nfa(&Nfa::new(pattern)?))
+    }
+
+    pub fn from_nfa(nfa: &Nfa) -> Dfa {
+        telemetry::compile("dfa", None, || Dfa::subset_construction(nfa))
+    }
+
+    // Subset construction
+    // Each DFA state is a set of NFA states the NFA could be in simultaneously
+    fn subset_construction(nfa: &Nfa) -> Dfa {
+        let mut ids = HashMap::from([(vec![0_usize], 0_usize)]);
+        let mut subsets = VecDeque::from([vec![0_usize]]);
+        let mut states = vec![];
+
+        while let Some(subset) = subsets.pop_front() {
+            let accepting = subset.iter().any(|state| nfa.states[*state].accepting);
+            let outgoing = subset
+                .iter()
+                .flat_map(|state| nfa.states[*state].transitions.iter())
+                .collect::<Vec<_>>();
+            let labels = outgoing.iter().map(|(label, _)| label).collect::<Vec<_>>();
+
+            let mut transitions: Vec<(char, char, usize)> = vec![];
+            for (start, end) in charset::partition(&labels) {
+                // Where can the NFA go after reading any character in `start..=end`?
+                let mut destination = outgoing
+                    .iter()
+                    .filter(|(label, _)| label.contains(start))
+                    .map(|(_, next)| *next)
+                    .collect::<Vec<_>>();
+                destination.sort_unstable();
+                destination.dedup();
+
+                let next_id = ids.len();
+                let destination = *ids.entry(destination.clone()).or_insert_with(|| {
+                    subsets.push_back(destination);
+                    next_id
+                });
+
+                match transitions.last_mut() {
+                    // Merge with previous range if they are adjacent and go to the same state
+                    Some((_, last_end, last_destination))
+                        if *last_destination == destination
+                            && charset::next_char(*last_end) == Some(start) =>
+                    {
+                        *last_end = end;
+                    }
+                    _ => transitions.push((start, end, destination)),
+                }
+            }
+
+            states.push(DfaState {
+                accepting,
+                transitions,
+            });
+        }
+
+        Dfa {
+            states,
+            search_error: nfa.search_error.clone(),
+        }
+    }
+
+    // Can this DFA search targets? Like `Nfa::searchable`
+    pub fn searchable(&self) -> Result<(), String> {
+        self.search_error.clone().map_or(Ok(()), Err)
+    }
+
+    // Read a DFA described in JSON, see `to_json` for the expected format
+    // Transitions leaving the same state must not share characters
+    pub fn from_json(json: &str) -> Result<Dfa, String> {
+        let states = import::states_from_json(json)?;
+        let mut dfa_states = Vec::with_capacity(states.len());
+        for (id, state) in states.into_iter().enumerate() {
+            let mut transitions = state
+                .transitions
+                .iter()
+                .flat_map(|(label, destination)| {
+                    label
+                        .ranges()
+                        .iter()
+                        .map(|(start, end)| (*start, *end, *destination))
+                })
+                .collect::<Vec<_>>();
+            transitions.sort_unstable();
+
+            let mut merged: Vec<(char, char, usize)> = vec![];
+            for (start, end, destination) in transitions {
+                match merged.last_mut() {
+                    Some((_, last_end, last_destination)) if start <= *last_end => {
+                        if *last_destination != destination {
+                            return Err(format!(
+                                "Invalid automaton: State {id} is not deterministic, \
+                                character {} leads to both states {last_destination} and {destination}",
+                                start.escape_debug()
+                            ));
+                        }
+                        *last_end = std::cmp::max(*last_end, end);
+                    }
+                    // Merge adjacent ranges going to the same state
+                    Some((_, last_end, last_destination))
+                        if *last_destination == destination
+                            && charset::next_char(*last_end) == Some(start) =>
+                    {
+                        *last_end = end;
+                    }
+                    _ => merged.push((start, end, destination)),
+                }
+            }
+
+            dfa_states.push(DfaState {
+                accepting: state.accepting,
+                transitions: merged,
+            });
+        }
+        Ok(Dfa {
+            states: dfa_states,
+            search_error: None,
+        })
+    }
+
+    pub fn states(&self) -> &[DfaState] {
+        &self.states
+    }
+
+    // State reached from `state` after reading `ch`, if any
+    pub fn next_state(&self, state: usize, ch: char) -> Option<usize> {
+        let transitions = &self.states[state].transitions;
+        // Ranges are sorted, find the last range starting at or before `ch`
+        let index = transitions.partition_point(|(start, _, _)| *start <= ch);
+        if index > 0 && ch <= transitions[index - 1].1 {
+            Some(transitions[index - 1].2)
+        } else {
+            None
+        }
+    }
+
+    // Does this automaton accept the whole `input`?
+    pub fn accepts(&self, input: &str) -> bool {
+        let mut state = 0;
+        for ch in input.chars() {
+            match self.next_state(state, ch) {
+                Some(next) => state = next,
+                None => return false,
+            }
+        }
+        self.states[state].accepting
+    }
+
+    // Is every string accepted by this automaton also accepted by `other`?
+    pub fn is_subset_of(&self, other: &Dfa) -> bool {
+        self.not_accepted_by(other).is_none()
+    }
+
+    // Shortest string accepted by this automaton but rejected by `other`, if any
+    // Run both automata side by side (product automaton) searching
+    // breadth first for a pair of states where only this automaton accepts
+    pub fn not_accepted_by(&self, other: &Dfa) -> Option<String> {
+        // State of `other` is None after it rejected a prefix (it has no transition)
+        // (state of self, state of other) => (previous pair, character read)
+        let start = (0_usize, Some(0_usize));
+        let mut previous = HashMap::from([(start, None)]);
+        let mut queue = VecDeque::from([start]);
+
+        while let Some(pair) = queue.pop_front() {
+            let (state, other_state) = pair;
+            let rejected_by_other =
+                other_state.is_none_or(|other_state| !other.states[other_state].accepting);
+            if self.states[state].accepting && rejected_by_other {
+                // Walk back to start state collecting characters read
+                let mut example = vec![];
+                let mut current = pair;
+                while let Some((before, ch)) = previous[&current] {
+                    example.push(ch);
+                    current = before;
+                }
+                return Some(example.into_iter().rev().collect());
+            }
+
+            let own_ranges = CharSet::from_ranges(
+                self.states[state]
+                    .transitions
+                    .iter()
+                    .map(|(start, end, _)| (*start, *end)),
+            );
+            let other_ranges =
+                CharSet::from_ranges(other_state.into_iter().flat_map(|other_state| {
+                    other.states[other_state]
+                        .transitions
+                        .iter()
+                        .map(|(start, end, _)| (*start, *end))
+                }));
+
+            for (start, end) in charset::partition(&[&own_ranges, &other_ranges]) {
+                // Characters in the same range lead to the same pair, so use any of them
+                let ch = charset::representative(start, end);
+                let Some(next) = self.next_state(state, ch) else {
+                    // This automaton rejects anything starting this way
+                    continue;
+                };
+                let next_pair = (
+                    next,
+                    other_state.and_then(|other_state| other.next_state(other_state, ch)),
+                );
+                if let Entry::Vacant(entry) = previous.entry(next_pair) {
+                    entry.insert(Some((pair, ch)));
+                    queue.push_back(next_pair);
+                }
+            }
+        }
+
+        None
+    }
+
+    // A shortest string accepted by this automaton, if it accepts anything
+    // Search breadth first from start state until an accepting state is found
+    pub fn shortest_example(&self) -> Option<String> {
+        // state => (previous state, character read)
+        let mut previous: Vec<Option<(usize, char)>> = vec![None; self.states.len()];
+        let mut visited = vec![false; self.states.len()];
+        visited[0] = true;
+        let mut queue = VecDeque::from([0_usize]);
+
+        while let Some(state) = queue.pop_front() {
+            if self.states[state].accepting {
+                // Walk back to start state collecting characters read
+                let mut example = vec![];
+                let mut current = state;
+                while let Some((before, ch)) = previous[current] {
+                    example.push(ch);
+                    current = before;
+                }
+                return Some(example.into_iter().rev().collect());
+            }
+
+            for (start, end, destination) in &self.states[state].transitions {
+                if !visited[*destination] {
+                    visited[*destination] = true;
+                    previous[*destination] = Some((state, charset::representative(*start, *end)));
+                    queue.push_back(*destination);
+                }
+            }
+        }
+
+        None
+    }
+
+    // Number of distinct strings of exactly `length` characters accepted by this automaton
+    // None if that number does not fit in a u128
+    // A DFA has exactly one path for each accepted string, hence counting strings
+    // is counting paths of `length` transitions from start state to an accepting state
+    // That's, raising the transfer matrix (how many characters lead from state i to state j)
+    // to the power `length`, done here one vector-matrix product at a time
+    pub fn count_strings(&self, length: usize) -> Option<u128> {
+        // paths[i] = number of strings leading from start state to state i
+        let mut paths = vec![0_u128; self.states.len()];
+        paths[0] = 1;
+        for _ in 1..=length {
+            let mut next_paths = vec![0_u128; self.states.len()];
+            for (state, count) in paths.iter().enumerate() {
+                if *count == 0 {
+                    continue;
+                }
+                for (start, end, destination) in &self.states[state].transitions {
+                    let characters = CharSet::from_ranges([(*start, *end)]).len() as u128;
+                    next_paths[*destination] = count
+                        .checked_mul(characters)?
+                        .checked_add(next_paths[*destination])?;
+                }
+            }
+            paths = next_paths;
+        }
+
+        paths
+            .iter()
+            .enumerate()
+            .filter(|(state, _)| self.states[*state].accepting)
+            .try_fold(0_u128, |total, (_, count)| total.checked_add(*count))
+    }
+
+    pub fn to_json(&self) -> String {
+        export::to_json("dfa", &self.labeled_transitions())
+    }
+
+    pub fn to_dot(&self) -> String {
+        export::to_dot("dfa", &self.labeled_transitions())
+    }
+
+    fn labeled_transitions(&self) -> Vec<(bool, Vec<(CharSet, usize)>)> {
+        self.states
+            .iter()
+            .map(|state| {
+                // Group ranges by destination state to get one labeled transition per destination
+                let mut grouped: Vec<(CharSet, usize)> = vec![];
+                for (start, end, destination) in &state.transitions {
+                    let range = CharSet::from_ranges([(*start, *end)]);
+                    match grouped.iter_mut().find(|(_, next)| next == destination) {
+                        Some((label, _)) => *label = label.union(&range),
+                        None => grouped.push((range, *destination)),
+                    }
+                }
+                (state.accepting, grouped)
+            })
+            .collect()
+    }
+}
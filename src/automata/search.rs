@@ -0,0 +1,171 @@
+// Search strings using a DFA
+// This lets automata built elsewhere (for instance read by `Dfa::from_json`)
+// be used like any pattern of this crate
+
+use std::ops::Range;
+
+use super::{Dfa, Nfa};
+use crate::matcher::{split_around_matches, substitute_matches, Match};
+use crate::telemetry;
+
+// Find non-overlapping matches of a DFA in a target string
+// At each position the longest match is reported (leftmost-longest)
+pub struct AutomatonMatcher {
+    // Automaton used for matching
+    dfa: Dfa,
+
+    // String on which the search is done
+    target: Vec<char>,
+
+    // Position in target where the next search begins
+    pos: usize,
+
+    // True when target is completely consumed
+    finished: bool,
+}
+
+impl AutomatonMatcher {
+    // Fails if `dfa` was built from a pattern with assertions, see `Dfa::searchable`
+    pub fn new(dfa: Dfa, target: &str) -> Result<AutomatonMatcher, String> {
+        dfa.searchable()?;
+        Ok(AutomatonMatcher {
+            dfa,
+            target: target.chars().collect(),
+            pos: 0,
+            finished: false,
+        })
+    }
+
+    // NFAs are converted to DFAs before searching
+    pub fn from_nfa(nfa: &Nfa, target: &str) -> Result<AutomatonMatcher, String> {
+        AutomatonMatcher::new(Dfa::from_nfa(nfa), target)
+    }
+
+    // Create a matcher from a DFA described in JSON (see `Dfa::to_json`)
+    pub fn from_json(json: &str, target: &str) -> Result<AutomatonMatcher, String> {
+        // Read it as an NFA so that nondeterministic descriptions are also accepted
+        AutomatonMatcher::from_nfa(&Nfa::from_json(json)?, target)
+    }
+
+    // Assign a new target to match on
+    pub fn assign_match_target(&mut self, target: &str) {
+        self.target = target.chars().collect();
+        self.reset();
+    }
+
+    // Start searching from the beginning of target again
+    pub fn reset(&mut self) {
+        self.pos = 0;
+        self.finished = false;
+    }
+
+    // End of the longest match starting at `start`, if any
+    fn longest_match_at(&self, start: usize) -> Option<usize> {
+        longest_match(&self.dfa, &self.target, start)
+    }
+
+    // Does some range within the target match?
+    pub fn is_matching(&mut self) -> bool {
+        self.reset();
+        self.next().is_some()
+    }
+
+    // Return true if the whole target is accepted by the automaton
+    pub fn fullmatch(&self) -> bool {
+        self.longest_match_at(0) == Some(self.target.len())
+    }
+
+    // Is the whole of `target` accepted by the automaton? `fullmatch` on `target`
+    pub fn full_match(&mut self, target: &str) -> bool {
+        self.match_full(target).is_some()
+    }
+
+    // Match the whole of `target`, from index 0 to its length, if it is accepted
+    // Target of this matcher is left as it is
+    pub fn match_full<'t>(&mut self, target: &'t str) -> Option<Match<'t>> {
+        let chars = target.chars().collect::<Vec<_>>();
+        (longest_match(&self.dfa, &chars, 0) == Some(chars.len()))
+            .then(|| Match::new(target, 0..chars.len()))
+    }
+
+    // Split target `splits_count` times
+    // A large splits_count splits the whole target
+    pub fn splitn(&mut self, splits_count: usize) -> Vec<String> {
+        if splits_count == 0 {
+            return vec![];
+        }
+
+        self.reset();
+        let target = self.target.clone();
+        split_around_matches(&target, self.by_ref(), splits_count)
+    }
+
+    // Split the whole target
+    pub fn split(&mut self) -> Vec<String> {
+        self.splitn(self.target.len() + 1)
+    }
+
+    // Return copy of target with `subs_count` substitutions replacing
+    // each match with `repl`
+    pub fn subn(&mut self, repl: &str, subs_count: usize) -> String {
+        self.reset();
+        let target = self.target.clone();
+        substitute_matches(&target, self.by_ref(), repl, subs_count)
+    }
+
+    // Return copy of target with each match replaced with `repl`
+    pub fn sub(&mut self, repl: &str) -> String {
+        self.subn(repl, self.target.len() + 1)
+    }
+}
+
+// End of the longest match of `dfa` in `target` beginning at `start`, if any
+fn longest_match(dfa: &Dfa, target: &[char], start: usize) -> Option<usize> {
+    let mut state = 0;
+    let mut longest = dfa.states[0].accepting.then_some(start);
+    for (index, ch) in target[start..].iter().enumerate() {
+        match dfa.next_state(state, *ch) {
+            Some(next) => state = next,
+            None => break,
+        }
+        if dfa.states[state].accepting {
+            longest = Some(start + index + 1);
+        }
+    }
+    longest
+}
+
+impl Iterator for AutomatonMatcher {
+    type Item = Range<usize>;
+
+    // Find the next match (non-overlapping with previous match)
+    fn next(&mut self) -> Option<Range<usize>> {
+        telemetry::search("dfa", None, self.target.len(), || self.next_match())
+    }
+}
+
+impl AutomatonMatcher {
+    fn next_match(&mut self) -> Option<Range<usize>> {
+        while !self.finished {
+            let start = self.pos;
+            let found = self.longest_match_at(start);
+            match found {
+                // Matched a non-empty range, continue right after it
+                Some(end) if end > start => self.pos = end,
+                // Matched the empty string or nothing at all
+                // Advance or we would match the empty string at the same position forever
+                _ => {
+                    if self.pos < self.target.len() {
+                        self.pos += 1;
+                    } else {
+                        self.finished = true;
+                    }
+                }
+            }
+            if let Some(end) = found {
+                return Some(Range { start, end });
+            }
+        }
+        None
+    }
+}
@@ -0,0 +1,184 @@
+// Timing engines of this crate against each other
+// Each case (a pattern and a haystack) is searched for all matches by every engine:
+// - Backtracker: Matcher, the default engine, leftmost-first semantics
+// - NFA: simulating an Nfa (all states it could be in at once), leftmost-longest semantics
+// - DFA: AutomatonMatcher, leftmost-longest semantics
+// Match counts are reported too, engines with different semantics may disagree
+
+use std::fmt::Display;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use crate::automata::{search::AutomatonMatcher, Dfa, Nfa};
+use crate::matcher::Matcher;
+use crate::parser::Parser;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Engine {
+    Backtracker,
+    Nfa,
+    Dfa,
+}
+
+impl Engine {
+    pub const ALL: [Engine; 3] = [Engine::Backtracker, Engine::Nfa, Engine::Dfa];
+}
+
+impl Display for Engine {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let name = match self {
+            Engine::Backtracker => "backtracker",
+            Engine::Nfa => "nfa",
+            Engine::Dfa => "dfa",
+        };
+        write!(f, "{name}")
+    }
+}
+
+// A pattern along with a haystack to search
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BenchCase {
+    pub pattern: String,
+    pub haystack: String,
+}
+
+impl BenchCase {
+    pub fn new(pattern: &str, haystack: &str) -> BenchCase {
+        BenchCase {
+            pattern: pattern.to_string(),
+            haystack: haystack.to_string(),
+        }
+    }
+}
+
+// Time one engine took on one case
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Timing {
+    pub engine: Engine,
+    pub pattern: String,
+    // Number of characters in haystack
+    pub haystack_len: usize,
+    // Time to build the engine from pattern, like building a DFA
+    pub compile: Duration,
+    // Average time of one search for all matches in haystack
+    pub search: Duration,
+    // Number of matches found
+    pub matches: usize,
+}
+
+// Run each case through each engine, searching its haystack `iterations` times
+// Fails if a pattern has a syntax error or automata can not search for it
+pub fn run(cases: &[BenchCase], iterations: usize) -> Result<Vec<Timing>, String> {
+    let iterations = iterations.max(1);
+    let mut timings = vec![];
+    for case in cases {
+        for engine in Engine::ALL {
+            timings.push(run_case(case, engine, iterations)?);
+        }
+    }
+    Ok(timings)
+}
+
+fn run_case(case: &BenchCase, engine: Engine, iterations: usize) -> Result<Timing, String> {
+    let haystack = case.haystack.as_str();
+    let compile_start = Instant::now();
+    // A search for all matches, giving back their number
+    let mut search: Box<dyn FnMut() -> usize> = match engine {
+        Engine::Backtracker => {
+            let regexp = Parser::parse(&case.pattern)?;
+            let mut matcher = Matcher::from_regexp(Arc::clone(&regexp), haystack);
+            Box::new(move || {
+                matcher.assign_match_target(haystack);
+                matcher.by_ref().count()
+            })
+        }
+        Engine::Nfa => {
+            let nfa = Nfa::new(&case.pattern)?;
+            nfa.searchable()?;
+            let haystack = haystack.chars().collect::<Vec<_>>();
+            Box::new(move || nfa_matches(&nfa, &haystack))
+        }
+        Engine::Dfa => {
+            let mut matcher = AutomatonMatcher::new(Dfa::new(&case.pattern)?, haystack)?;
+            Box::new(move || {
+                matcher.reset();
+                matcher.by_ref().count()
+            })
+        }
+    };
+    let compile = compile_start.elapsed();
+
+    let search_start = Instant::now();
+    let mut matches = 0;
+    for _ in 0..iterations {
+        matches = search();
+    }
+    let search_time = search_start.elapsed() / iterations as u32;
+
+    Ok(Timing {
+        engine,
+        pattern: case.pattern.clone(),
+        haystack_len: case.haystack.chars().count(),
+        compile,
+        search: search_time,
+        matches,
+    })
+}
+
+// Number of non-overlapping leftmost-longest matches, found by simulating `nfa`
+// Empty matches are handled like AutomatonMatcher does
+fn nfa_matches(nfa: &Nfa, haystack: &[char]) -> usize {
+    let states = nfa.states();
+    let mut count = 0;
+    let mut start = 0;
+    loop {
+        // End of longest match starting at `start`
+        let mut longest = states[0].accepting.then_some(start);
+        let mut current = vec![0_usize];
+        for (index, ch) in haystack[start..].iter().enumerate() {
+            let mut next = current
+                .iter()
+                .flat_map(|state| states[*state].transitions.iter())
+                .filter(|(label, _)| label.contains(*ch))
+                .map(|(_, destination)| *destination)
+                .collect::<Vec<_>>();
+            if next.is_empty() {
+                break;
+            }
+            next.sort_unstable();
+            next.dedup();
+            if next.iter().any(|state| states[*state].accepting) {
+                longest = Some(start + index + 1);
+            }
+            current = next;
+        }
+        if longest.is_some() {
+            count += 1;
+        }
+        match longest {
+            Some(end) if end > start => start = end,
+            _ if start < haystack.len() => start += 1,
+            _ => return count,
+        }
+    }
+}
+
+// Timings as a table, one row for each (case, engine) pair
+pub fn report(timings: &[Timing]) -> String {
+    let mut table = format!(
+        "{:<24} {:<12} {:>10} {:>12} {:>12} {:>8}\n",
+        "pattern", "engine", "haystack", "compile", "search", "matches"
+    );
+    for timing in timings {
+        table.push_str(&format!(
+            "{:<24} {:<12} {:>10} {:>12} {:>12} {:>8}\n",
+            timing.pattern,
+            timing.engine.to_string(),
+            timing.haystack_len,
+            format!("{:.2?}", timing.compile),
+            format!("{:.2?}", timing.search),
+            timing.matches
+        ));
+    }
+    table
+}
@@ -0,0 +1,364 @@
+// re: search files for lines matching a pattern, or replace matches like sed
+// Usage: re [OPTIONS] PATTERN [FILE]...
+// With no FILE, or when FILE is -, read standard input
+
+use std::fs::File;
+use std::io::{self, BufReader, IsTerminal, Write};
+use std::path::PathBuf;
+use std::process::ExitCode;
+
+use regexps::grep::ansi::{highlight_matches, Style};
+use regexps::grep::{glob::Glob, parallel::OutputOrder, walk::Walker, LineMatch, Searcher};
+use regexps::regex::RegexBuilder;
+
+const USAGE: &str = "\
+Usage: re [OPTIONS] PATTERN [FILE]...
+Search each FILE (or standard input) for lines matching PATTERN
+With no FILE, or when FILE is -, read standard input
+
+Options:
+  -r, --recursive          search files under each directory FILE, binary files are skipped
+                           with no FILE, search the current directory
+      --include GLOB       search only files whose names match GLOB (may be repeated)
+      --exclude GLOB       skip files and directories whose names match GLOB (may be repeated)
+                           in GLOB, * is any sequence of characters, ? is any character
+                           and {a,b} is either a or b
+      --skip-hidden        with -r, skip files and directories whose names start with .
+      --ignore-files       with -r, skip files and directories matched by .gitignore
+                           and .ignore files
+  -j, --threads N          search N files at once, 0 means as many as the machine runs in parallel
+                           results are still printed in order of files
+  -S, --smart-case         ignore case unless PATTERN has an uppercase letter
+  -v, --invert-match       select lines NOT matching PATTERN
+  -c, --count              print only the number of selected lines per file
+  -N, --no-line-number     do not prefix lines with their line numbers
+      --color WHEN         highlight matches and pattern errors: never, always or auto (default)
+                           auto highlights only when printing to a terminal
+      --replace TEMPLATE   print input with each match replaced by TEMPLATE
+                           in TEMPLATE, $0 is the matched text and $$ is a literal $
+      --in-place           with --replace, write replaced text back to each FILE
+      --backup SUFFIX      with --in-place, first copy each changed FILE to FILE followed by SUFFIX
+  -h, --help               print this help
+
+Exit status is 0 if a line is selected (or a match is replaced),
+1 if none is and 2 if an error occurred";
+
+#[derive(Default)]
+struct Options {
+    smart_case: bool,
+    invert_match: bool,
+    count: bool,
+    no_line_number: bool,
+    // Highlight matches in printed lines
+    color: bool,
+    // Color pattern errors printed to standard error
+    color_errors: bool,
+    replace: Option<String>,
+    in_place: bool,
+    backup: Option<String>,
+    recursive: bool,
+    // Search files using a pool of this many threads
+    threads: Option<usize>,
+    skip_hidden: bool,
+    ignore_files: bool,
+    include: Vec<String>,
+    exclude: Vec<String>,
+    pattern: Option<String>,
+    files: Vec<String>,
+}
+
+// Value of option `name` if `arg` is that option, given either as `name VALUE` or `name=VALUE`
+fn option_value(
+    name: &str,
+    arg: &str,
+    args: &mut impl Iterator<Item = String>,
+) -> Result<Option<String>, String> {
+    if arg == name {
+        match args.next() {
+            Some(value) => Ok(Some(value)),
+            None => Err(format!("Missing value after {name}\n\n{USAGE}")),
+        }
+    } else {
+        Ok(arg
+            .strip_prefix(name)
+            .and_then(|rest| rest.strip_prefix('='))
+            .map(String::from))
+    }
+}
+
+fn parse_args(mut args: impl Iterator<Item = String>) -> Result<Options, String> {
+    let mut options = Options {
+        color: io::stdout().is_terminal(),
+        color_errors: io::stderr().is_terminal(),
+        ..Options::default()
+    };
+    // After `--` everything is a pattern or a file, even if it starts with -
+    let mut only_operands = false;
+    while let Some(arg) = args.next() {
+        if !only_operands && arg.starts_with('-') && arg != "-" {
+            if let Some(template) = option_value("--replace", &arg, &mut args)? {
+                options.replace = Some(template);
+                continue;
+            }
+            if let Some(suffix) = option_value("--backup", &arg, &mut args)? {
+                options.backup = Some(suffix);
+                continue;
+            }
+            if let Some(when) = option_value("--color", &arg, &mut args)? {
+                (options.color, options.color_errors) = match when.as_str() {
+                    "never" => (false, false),
+                    "always" => (true, true),
+                    "auto" => (io::stdout().is_terminal(), io::stderr().is_terminal()),
+                    _ => return Err(format!("Invalid --color value {when}\n\n{USAGE}")),
+                };
+                continue;
+            }
+            let threads = match option_value("--threads", &arg, &mut args)? {
+                None if arg == "-j" => option_value("-j", &arg, &mut args)?,
+                threads => threads,
+            };
+            if let Some(threads) = threads {
+                match threads.parse() {
+                    Ok(threads) => options.threads = Some(threads),
+                    Err(_) => return Err(format!("Invalid thread count {threads}\n\n{USAGE}")),
+                }
+                continue;
+            }
+            if let Some(glob) = option_value("--include", &arg, &mut args)? {
+                options.include.push(glob);
+                continue;
+            }
+            if let Some(glob) = option_value("--exclude", &arg, &mut args)? {
+                options.exclude.push(glob);
+                continue;
+            }
+            match arg.as_str() {
+                "--in-place" => options.in_place = true,
+                "-r" | "--recursive" => options.recursive = true,
+                "--skip-hidden" => options.skip_hidden = true,
+                "--ignore-files" => options.ignore_files = true,
+                "--" => only_operands = true,
+                "-S" | "--smart-case" => options.smart_case = true,
+                "-v" | "--invert-match" => options.invert_match = true,
+                "-c" | "--count" => options.count = true,
+                "-N" | "--no-line-number" => options.no_line_number = true,
+                "-h" | "--help" => {
+                    println!("{USAGE}");
+                    std::process::exit(0);
+                }
+                _ => return Err(format!("Unknown option {arg}\n\n{USAGE}")),
+            }
+        } else if options.pattern.is_none() {
+            options.pattern = Some(arg);
+        } else {
+            options.files.push(arg);
+        }
+    }
+    if options.pattern.is_none() {
+        return Err(format!("Missing PATTERN\n\n{USAGE}"));
+    }
+    if options.replace.is_some() && (options.invert_match || options.count) {
+        return Err(String::from(
+            "--replace can not be used with --invert-match or --count",
+        ));
+    }
+    if options.backup.is_some() && !options.in_place {
+        return Err(String::from("--backup requires --in-place"));
+    }
+    if options.in_place {
+        if options.replace.is_none() {
+            return Err(String::from("--in-place requires --replace"));
+        }
+        if options.files.is_empty() || options.files.iter().any(|file| file == "-") {
+            return Err(String::from(
+                "--in-place requires files, not standard input",
+            ));
+        }
+    }
+    if options.files.is_empty() {
+        let default = if options.recursive { "." } else { "-" };
+        options.files.push(String::from(default));
+    }
+    Ok(options)
+}
+
+// Files to search: FILE operands, with directories expanded when searching recursively
+fn list_files(options: &Options) -> Result<Vec<String>, String> {
+    if !options.recursive {
+        return Ok(options.files.clone());
+    }
+    let mut walker = Walker::new();
+    walker
+        .skip_hidden(options.skip_hidden)
+        .ignore_files(options.ignore_files);
+    for glob in &options.include {
+        walker.include(Glob::new(glob)?);
+    }
+    for glob in &options.exclude {
+        walker.exclude(Glob::new(glob)?);
+    }
+    let mut files = vec![];
+    for file in &options.files {
+        if file == "-" {
+            files.push(file.clone());
+            continue;
+        }
+        let found = walker
+            .files(file)
+            .map_err(|error| format!("{file}: {error}"))?;
+        files.extend(found.iter().map(|path| path.to_string_lossy().into_owned()));
+    }
+    Ok(files)
+}
+
+// Print selected lines of one file, prefixed by file name when searching many files
+fn print_lines(
+    out: &mut impl Write,
+    options: &Options,
+    file_name: Option<&str>,
+    lines: &[LineMatch],
+) -> io::Result<()> {
+    let prefix = file_name.map_or(String::new(), |name| format!("{name}:"));
+    if options.count {
+        return writeln!(out, "{prefix}{}", lines.len());
+    }
+    for line in lines {
+        let text = if options.color {
+            highlight_matches(&line.line, &line.matches, &Style::default())
+        } else {
+            line.line.clone()
+        };
+        if options.no_line_number {
+            writeln!(out, "{prefix}{text}")?;
+        } else {
+            writeln!(out, "{prefix}{}:{text}", line.line_number)?;
+        }
+    }
+    Ok(())
+}
+
+// Replace matches in each file, printing the result or writing it back to the file
+// Gives back exit status
+fn replace(options: &Options, files: &[String], searcher: &Searcher, template: &str) -> ExitCode {
+    let stdout = io::stdout();
+    let mut out = stdout.lock();
+    let mut replaced_any = false;
+    let mut failed = false;
+    for file in files {
+        let written = if options.in_place {
+            searcher
+                .replace_in_file(file, template, options.backup.as_deref())
+                .map(|replacements| replaced_any |= replacements > 0)
+        } else {
+            let replaced = if file == "-" {
+                searcher.replace_reader(io::stdin().lock(), template)
+            } else {
+                File::open(file)
+                    .and_then(|opened| searcher.replace_reader(BufReader::new(opened), template))
+            };
+            replaced.and_then(|(text, replacements)| {
+                replaced_any |= replacements > 0;
+                out.write_all(text.as_bytes())
+            })
+        };
+        if let Err(error) = written {
+            if error.kind() == io::ErrorKind::BrokenPipe {
+                break;
+            }
+            eprintln!("re: {file}: {error}");
+            failed = true;
+        }
+    }
+
+    if failed {
+        ExitCode::from(2)
+    } else if replaced_any {
+        ExitCode::SUCCESS
+    } else {
+        ExitCode::from(1)
+    }
+}
+
+fn main() -> ExitCode {
+    let options = match parse_args(std::env::args().skip(1)) {
+        Ok(options) => options,
+        Err(error) => {
+            eprintln!("re: {error}");
+            return ExitCode::from(2);
+        }
+    };
+    let regex = RegexBuilder::new(options.pattern.as_deref().unwrap())
+        .smart_case(options.smart_case)
+        .colored_errors(options.color_errors)
+        .build();
+    let mut searcher = match regex {
+        Ok(regex) => Searcher::from_regex(regex),
+        Err(error) => {
+            eprintln!("{error}");
+            return ExitCode::from(2);
+        }
+    };
+    searcher.invert_match(options.invert_match);
+    let files = match list_files(&options) {
+        Ok(files) => files,
+        Err(error) => {
+            eprintln!("re: {error}");
+            return ExitCode::from(2);
+        }
+    };
+    if let Some(template) = &options.replace {
+        return replace(&options, &files, &searcher, template);
+    }
+
+    let stdout = io::stdout();
+    let mut out = stdout.lock();
+    let mut selected_any = false;
+    let mut failed = false;
+    // Output is closed, like when piped into `head`
+    let mut closed = false;
+    let many_files = options.recursive || files.len() > 1;
+    // Print result of searching one file, gives back whether output is closed
+    let mut report = |file: &str, lines: io::Result<Vec<LineMatch>>| {
+        match lines {
+            _ if closed => {}
+            Ok(lines) => {
+                selected_any |= !lines.is_empty();
+                let file_name = many_files.then_some(file);
+                closed = print_lines(&mut out, &options, file_name, &lines).is_err();
+            }
+            Err(error) => {
+                eprintln!("re: {file}: {error}");
+                failed = true;
+            }
+        }
+        closed
+    };
+    match options.threads {
+        Some(threads) if !files.iter().any(|file| file == "-") => {
+            let files = files.iter().map(PathBuf::from).collect();
+            searcher.search_files_parallel(files, threads, OutputOrder::Ordered, |result| {
+                report(&result.path.to_string_lossy(), result.lines);
+            });
+        }
+        _ => {
+            for file in &files {
+                let lines = if file == "-" {
+                    searcher.search_reader(io::stdin().lock())
+                } else {
+                    searcher.search_file(file)
+                };
+                if report(file, lines) {
+                    break;
+                }
+            }
+        }
+    }
+
+    if failed {
+        ExitCode::from(2)
+    } else if selected_any {
+        ExitCode::SUCCESS
+    } else {
+        ExitCode::from(1)
+    }
+}
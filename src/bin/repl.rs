@@ -0,0 +1,156 @@
+// repl: interactive pattern debugging
+// Set a pattern, then type target strings to see where the pattern matches them
+// Lines starting with `:` are commands, type :help to list them
+
+use std::io::{self, BufRead, IsTerminal, Write};
+use std::sync::{Arc, RwLock};
+
+use regexps::analysis::explanation;
+use regexps::grep::ansi::{highlight_matches_cycling, Style};
+use regexps::matcher::Matcher;
+use regexps::parser::{syntax_tree::*, Parser};
+
+const HELP: &str = "\
+:pattern PATTERN   use PATTERN (also :p PATTERN)
+:tree              print syntax tree of current pattern
+:explain           describe current pattern in plain English
+:trace on|off      print every step taken while matching
+:help              print this help
+:quit              exit (also end of input)
+Any other line is a target string to match current pattern against";
+
+struct Session {
+    regexp: Option<Arc<RwLock<ParsedRegexp>>>,
+    trace: bool,
+    // Errors are printed to standard output along with everything else
+    colored_errors: bool,
+}
+
+// Short name of an expression type
+fn kind(expression_type: ExpressionType) -> &'static str {
+    match expression_type {
+        ExpressionType::EmptyExpression => "empty",
+        ExpressionType::CharacterExpression { value: None, .. } => "dot",
+        ExpressionType::CharacterExpression { .. } => "character",
+        ExpressionType::Concatenation => "concatenation",
+        ExpressionType::Alternation => "alternation",
+        ExpressionType::Group { .. } => "group",
+        ExpressionType::ShorthandClass { .. } => "shorthand",
+        ExpressionType::CharacterClass { .. } => "class",
+        ExpressionType::CharacterRange { .. } => "range",
+        ExpressionType::Assertion { .. } => "assertion",
+        ExpressionType::Backreference { .. } => "backreference",
+        ExpressionType::Lookbehind { .. } => "lookbehind",
+    }
+}
+
+impl Session {
+    fn set_pattern(&mut self, pattern: &str) {
+        match Parser::parse_with_colored_errors(pattern, self.colored_errors) {
+            Ok((regexp, warnings)) => {
+                for warning in warnings {
+                    println!("{}", warning.render_with(pattern, self.colored_errors));
+                }
+                self.regexp = Some(regexp);
+            }
+            Err(error) => println!("{error}"),
+        }
+    }
+
+    fn print_tree(&self, regexp: &Arc<RwLock<ParsedRegexp>>) {
+        for visited in ParsedRegexp::iter(regexp) {
+            let expression = visited.expression.read().unwrap();
+            println!(
+                "{}{} {} {} ({}..{})",
+                "  ".repeat(visited.depth),
+                expression.id,
+                kind(expression.expression_type),
+                expression.pattern,
+                expression.span.start,
+                expression.span.end
+            );
+        }
+    }
+
+    fn match_target(&self, regexp: &Arc<RwLock<ParsedRegexp>>, target: &str) {
+        let mut matcher = Matcher::from_regexp(Arc::clone(regexp), target);
+        matcher.log_steps(self.trace);
+        let mut matches = vec![];
+        loop {
+            let found = matcher.next();
+            if let Some(log) = matcher.step_log() {
+                print!("{log}");
+            }
+            match found {
+                Some(m) => matches.push(m),
+                None => break,
+            }
+        }
+        println!(
+            "{}",
+            highlight_matches_cycling(target, &matches, &[Style::reverse(), Style::new("4")])
+        );
+        if matches.is_empty() {
+            println!("no match");
+        }
+        for m in &matches {
+            let text = target
+                .chars()
+                .skip(m.start)
+                .take(m.end - m.start)
+                .collect::<String>();
+            println!("{}..{} {text:?}", m.start, m.end);
+        }
+    }
+
+    // Handle one input line, return false to exit
+    fn handle(&mut self, line: &str) -> bool {
+        let (command, argument) = match line.strip_prefix(':') {
+            Some(command) => command.split_once(' ').unwrap_or((command, "")),
+            None => {
+                match &self.regexp {
+                    Some(regexp) => self.match_target(regexp, line),
+                    None => println!("No pattern yet, set one with :pattern PATTERN"),
+                }
+                return true;
+            }
+        };
+        match (command, &self.regexp) {
+            ("pattern" | "p", _) => self.set_pattern(argument),
+            ("tree", Some(regexp)) => self.print_tree(regexp),
+            ("explain", Some(regexp)) => println!("{}", explanation(regexp)),
+            ("tree" | "explain", None) => println!("No pattern yet, set one with :pattern PATTERN"),
+            ("trace", _) => match argument {
+                "on" => self.trace = true,
+                "off" => self.trace = false,
+                _ => println!("Usage: :trace on|off"),
+            },
+            ("help", _) => println!("{HELP}"),
+            ("quit" | "q", _) => return false,
+            _ => println!("Unknown command :{command}, type :help to list commands"),
+        }
+        true
+    }
+}
+
+fn main() -> io::Result<()> {
+    let mut session = Session {
+        regexp: None,
+        trace: false,
+        colored_errors: io::stdout().is_terminal(),
+    };
+    println!("Type :help to list commands");
+    let stdin = io::stdin();
+    let mut lines = stdin.lock().lines();
+    loop {
+        print!("> ");
+        io::stdout().flush()?;
+        let Some(line) = lines.next() else {
+            println!();
+            return Ok(());
+        };
+        if !session.handle(&line?) {
+            return Ok(());
+        }
+    }
+}
@@ -0,0 +1,259 @@
+// Stable codes of all diagnostics (errors and warnings) reported for patterns
+// A code never changes meaning once assigned, so tools can rely on it
+// instead of on message texts, which may be reworded
+// Rendered errors and warnings carry their code, like `Syntax error [E0001] in position 1`
+
+use std::collections::HashMap;
+use std::fmt::Display;
+
+use crate::parser::warning::WarningKind;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum DiagnosticCode {
+    // E0001 ) without a matching (
+    UnbalancedRightParen,
+    // E0002 ( not followed by an expression, like `a(`
+    ExpectedExpressionAfterLeftParen,
+    // E0003 Group not closed by ), like `(a`
+    ExpectedRightParen,
+    // E0004 A token which can not begin an expression, like `*` in `*a`
+    ExpectedExpression,
+    // E0005 Pattern ends with an escape character escaping nothing, like `a\`
+    TrailingEscape,
+    // E0006 Pattern library references a fragment not defined
+    UndefinedFragment,
+    // E0007 Pattern library fragment references itself
+    RecursiveFragment,
+    // E0008 Pattern longer than allowed, by parse limits or an untrusted profile
+    PatternTooLong,
+    // E0009 Pattern nests groups or classes deeper than allowed, by parse limits or an untrusted profile
+    NestingTooDeep,
+    // E0010 Untrusted pattern repeats a group containing a repeated subexpression, like `(a+)+`
+    NestedQuantifier,
+    // E0011 Character class not closed by ], like `[ab`
+    ExpectedRightBracket,
+    // E0012 Character class listing no characters, `[]`
+    EmptyCharacterClass,
+    // E0013 Range of a character class whose first character comes after its last, like `[z-a]`
+    ReversedRange,
+    // E0014 Quantifier after an assertion, which matches no characters to repeat, like `^*`
+    QuantifiedAssertion,
+    // E0015 Counted repetition whose minimum count is greater than its maximum count, like `a{3,1}`
+    ReversedRepetition,
+    // E0016 Counted repetition with a count greater than MAX_REPETITION, like `a{5000}`
+    RepetitionTooLarge,
+    // E0017 Backreference to a capturing group not opened before it, like `\2` in `(a)\2`
+    UndefinedBackreference,
+    // E0018 Untrusted pattern with a backreference, like `(a)\1`
+    UntrustedBackreference,
+    // E0019 Lookbehind matching strings of different lengths, like `(?<=a+)`
+    VariableLengthLookbehind,
+    // E0020 Comment without a closing ), like `a(?#b`
+    UnterminatedComment,
+    // E0021 Escape character before a letter which means nothing escaped, like `\q`
+    UnknownEscape,
+    // E0022 Pattern built of more syntax tree nodes than parse limits allow
+    TooManyNodes,
+    // E0023 Construct which automata can not match exactly, like the atomic group in `(?>a*)a`
+    UnsupportedByAutomata,
+    // W0001 A slash before a character which is neither a metacharacter nor a letter, like `\@`
+    LiteralEscape,
+    // W0002 An alternative matching only the empty string, like in `a|`
+    EmptyAlternative,
+    // W0003 A group enclosing nothing, `()`
+    EmptyGroup,
+}
+
+impl DiagnosticCode {
+    // The catalog: every diagnostic code, errors first
+    pub const ALL: [DiagnosticCode; 26] = [
+        DiagnosticCode::UnbalancedRightParen,
+        DiagnosticCode::ExpectedExpressionAfterLeftParen,
+        DiagnosticCode::ExpectedRightParen,
+        DiagnosticCode::ExpectedExpression,
+        DiagnosticCode::TrailingEscape,
+        DiagnosticCode::UndefinedFragment,
+        DiagnosticCode::RecursiveFragment,
+        DiagnosticCode::PatternTooLong,
+        DiagnosticCode::NestingTooDeep,
+        DiagnosticCode::NestedQuantifier,
+        DiagnosticCode::ExpectedRightBracket,
+        DiagnosticCode::EmptyCharacterClass,
+        DiagnosticCode::ReversedRange,
+        DiagnosticCode::QuantifiedAssertion,
+        DiagnosticCode::ReversedRepetition,
+        DiagnosticCode::RepetitionTooLarge,
+        DiagnosticCode::UndefinedBackreference,
+        DiagnosticCode::UntrustedBackreference,
+        DiagnosticCode::VariableLengthLookbehind,
+        DiagnosticCode::UnterminatedComment,
+        DiagnosticCode::UnknownEscape,
+        DiagnosticCode::TooManyNodes,
+        DiagnosticCode::UnsupportedByAutomata,
+        DiagnosticCode::LiteralEscape,
+        DiagnosticCode::EmptyAlternative,
+        DiagnosticCode::EmptyGroup,
+    ];
+
+    // Code as written in rendered diagnostics, like "E0001"
+    // Errors start with E and warnings with W
+    pub fn code(&self) -> &'static str {
+        match self {
+            DiagnosticCode::UnbalancedRightParen => "E0001",
+            DiagnosticCode::ExpectedExpressionAfterLeftParen => "E0002",
+            DiagnosticCode::ExpectedRightParen => "E0003",
+            DiagnosticCode::ExpectedExpression => "E0004",
+            DiagnosticCode::TrailingEscape => "E0005",
+            DiagnosticCode::UndefinedFragment => "E0006",
+            DiagnosticCode::RecursiveFragment => "E0007",
+            DiagnosticCode::PatternTooLong => "E0008",
+            DiagnosticCode::NestingTooDeep => "E0009",
+            DiagnosticCode::NestedQuantifier => "E0010",
+            DiagnosticCode::ExpectedRightBracket => "E0011",
+            DiagnosticCode::EmptyCharacterClass => "E0012",
+            DiagnosticCode::ReversedRange => "E0013",
+            DiagnosticCode::QuantifiedAssertion => "E0014",
+            DiagnosticCode::ReversedRepetition => "E0015",
+            DiagnosticCode::RepetitionTooLarge => "E0016",
+            DiagnosticCode::UndefinedBackreference => "E0017",
+            DiagnosticCode::UntrustedBackreference => "E0018",
+            DiagnosticCode::VariableLengthLookbehind => "E0019",
+            DiagnosticCode::UnterminatedComment => "E0020",
+            DiagnosticCode::UnknownEscape => "E0021",
+            DiagnosticCode::TooManyNodes => "E0022",
+            DiagnosticCode::UnsupportedByAutomata => "E0023",
+            DiagnosticCode::LiteralEscape => "W0001",
+            DiagnosticCode::EmptyAlternative => "W0002",
+            DiagnosticCode::EmptyGroup => "W0003",
+        }
+    }
+
+    // One line description of this diagnostic
+    pub fn summary(&self) -> &'static str {
+        match self {
+            DiagnosticCode::UnbalancedRightParen => "unbalanced )",
+            DiagnosticCode::ExpectedExpressionAfterLeftParen => "expected expression after (",
+            DiagnosticCode::ExpectedRightParen => "expected ) closing a group",
+            DiagnosticCode::ExpectedExpression => "expected expression",
+            DiagnosticCode::TrailingEscape => "trailing escape character",
+            DiagnosticCode::UndefinedFragment => "undefined pattern library fragment",
+            DiagnosticCode::RecursiveFragment => "pattern library fragment references itself",
+            DiagnosticCode::PatternTooLong => "pattern too long",
+            DiagnosticCode::NestingTooDeep => "pattern nests groups or classes too deep",
+            DiagnosticCode::NestedQuantifier => {
+                "untrusted pattern repeats a repeated subexpression"
+            }
+            DiagnosticCode::ExpectedRightBracket => "expected ] closing a character class",
+            DiagnosticCode::EmptyCharacterClass => "empty character class",
+            DiagnosticCode::ReversedRange => "reversed range in character class",
+            DiagnosticCode::QuantifiedAssertion => "quantifier after an assertion",
+            DiagnosticCode::ReversedRepetition => "reversed counts in repetition",
+            DiagnosticCode::RepetitionTooLarge => "repetition count too large",
+            DiagnosticCode::UndefinedBackreference => "backreference to an undefined group",
+            DiagnosticCode::UntrustedBackreference => "untrusted pattern has a backreference",
+            DiagnosticCode::VariableLengthLookbehind => "lookbehind of variable length",
+            DiagnosticCode::UnterminatedComment => "comment never closed",
+            DiagnosticCode::UnknownEscape => "unknown escape",
+            DiagnosticCode::TooManyNodes => "pattern has too many syntax tree nodes",
+            DiagnosticCode::UnsupportedByAutomata => "construct automata can not match",
+            DiagnosticCode::LiteralEscape => "escaped character is not a metacharacter",
+            DiagnosticCode::EmptyAlternative => "empty alternative",
+            DiagnosticCode::EmptyGroup => "empty group",
+        }
+    }
+
+    // Warnings can be allowed or denied (see `DiagnosticLevels`), errors are always fatal
+    pub fn is_warning(&self) -> bool {
+        self.code().starts_with('W')
+    }
+
+    // Diagnostic with code `code`, like "E0001"
+    pub fn from_code(code: &str) -> Option<DiagnosticCode> {
+        DiagnosticCode::ALL
+            .into_iter()
+            .find(|diagnostic| diagnostic.code() == code)
+    }
+
+    // Code of rendered error or warning `rendered`, from its first line
+    // None if it carries no code
+    pub fn of_rendered(rendered: &str) -> Option<DiagnosticCode> {
+        let first_line = rendered.lines().next()?;
+        let start = first_line.find('[')? + 1;
+        let end = start + first_line[start..].find(']')?;
+        DiagnosticCode::from_code(&first_line[start..end])
+    }
+}
+
+impl From<WarningKind> for DiagnosticCode {
+    fn from(kind: WarningKind) -> DiagnosticCode {
+        match kind {
+            WarningKind::LiteralEscape(_) => DiagnosticCode::LiteralEscape,
+            WarningKind::EmptyAlternative => DiagnosticCode::EmptyAlternative,
+            WarningKind::EmptyGroup => DiagnosticCode::EmptyGroup,
+        }
+    }
+}
+
+impl Display for DiagnosticCode {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.code())
+    }
+}
+
+// What to do about a warning
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum Level {
+    // Drop it silently
+    Allow,
+    // Report it along with the parsed pattern
+    #[default]
+    Warn,
+    // Reject the pattern, reporting the warning as an error
+    Deny,
+}
+
+// Level of each warning, those not set are at `Level::Warn`
+// see Parser::parse_with_levels
+#[derive(Debug, Clone, Default)]
+pub struct DiagnosticLevels {
+    levels: HashMap<DiagnosticCode, Level>,
+}
+
+impl DiagnosticLevels {
+    pub fn new() -> DiagnosticLevels {
+        DiagnosticLevels::default()
+    }
+
+    // All warnings at `level`, like `deny_all` for a strict parse
+    pub fn all(level: Level) -> DiagnosticLevels {
+        let mut levels = DiagnosticLevels::new();
+        for code in DiagnosticCode::ALL {
+            levels.set(code, level);
+        }
+        levels
+    }
+
+    // Set level of `code`, errors are always fatal thus they are left as they are
+    pub fn set(&mut self, code: DiagnosticCode, level: Level) -> &mut DiagnosticLevels {
+        if code.is_warning() {
+            self.levels.insert(code, level);
+        }
+        self
+    }
+
+    pub fn allow(&mut self, code: DiagnosticCode) -> &mut DiagnosticLevels {
+        self.set(code, Level::Allow)
+    }
+
+    pub fn deny(&mut self, code: DiagnosticCode) -> &mut DiagnosticLevels {
+        self.set(code, Level::Deny)
+    }
+
+    // Level of `code`, errors are always at `Level::Deny`
+    pub fn level(&self, code: DiagnosticCode) -> Level {
+        if !code.is_warning() {
+            return Level::Deny;
+        }
+        self.levels.get(&code).copied().unwrap_or_default()
+    }
+}
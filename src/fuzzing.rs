@@ -0,0 +1,161 @@
+// Structured inputs for fuzzing
+// Fuzz targets can take these as inputs (with the `arbitrary` crate) to always get
+// well-formed patterns, instead of random strings the parser rejects right away
+//
+//     fuzz_target!(|input: (ArbitraryPattern, String)| {
+//         let (pattern, target) = input;
+//         let _ = Matcher::new(&pattern.0, &target).unwrap().count();
+//     });
+
+use std::sync::{Arc, RwLock};
+
+use arbitrary::{Arbitrary, Result, Unstructured};
+
+use crate::matcher::escape;
+use crate::parser::{syntax_tree::*, Parser};
+use crate::scanner::is_class_metacharacter;
+
+// Deepest nesting of groups in generated patterns
+const MAX_DEPTH: usize = 4;
+// Most alternatives in one alternation, and most items in one concatenation
+const MAX_BRANCHES: u8 = 4;
+
+// A pattern the parser accepts
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct ArbitraryPattern(pub String);
+
+// Syntax tree of an ArbitraryPattern
+#[derive(Debug, Clone)]
+pub struct ArbitraryRegexp(pub Arc<RwLock<ParsedRegexp>>);
+
+impl<'a> Arbitrary<'a> for ArbitraryPattern {
+    fn arbitrary(u: &mut Unstructured<'a>) -> Result<Self> {
+        Ok(ArbitraryPattern(alternation(u, 0)?))
+    }
+}
+
+impl<'a> Arbitrary<'a> for ArbitraryRegexp {
+    fn arbitrary(u: &mut Unstructured<'a>) -> Result<Self> {
+        let ArbitraryPattern(pattern) = u.arbitrary()?;
+        // Generated patterns are always well-formed
+        let regexp = Parser::parse(&pattern).map_err(|_| arbitrary::Error::IncorrectFormat)?;
+        Ok(ArbitraryRegexp(regexp))
+    }
+}
+
+// Counts of counted repetitions are kept small, large ones only make searches slow
+impl<'a> Arbitrary<'a> for Quantifier {
+    fn arbitrary(u: &mut Unstructured<'a>) -> Result<Self> {
+        let quantifier = match u.int_in_range(0..=4)? {
+            0 => Quantifier::None,
+            1 => Quantifier::ZeroOrOne,
+            2 => Quantifier::ZeroOrMore,
+            3 => Quantifier::OneOrMore,
+            _ => {
+                let min = u.int_in_range(0..=3)?;
+                let max = match u.arbitrary::<Option<()>>()? {
+                    Some(()) => Some(min + u.int_in_range(0..=3)?),
+                    None => None,
+                };
+                Quantifier::Counted { min, max }
+            }
+        };
+        // Any quantifier but none at all can be lazy
+        if quantifier != Quantifier::None && u.ratio(1, 4)? {
+            return Ok(quantifier.lazy());
+        }
+        Ok(quantifier)
+    }
+}
+
+// Alternation => Concatenation ( "|" Concatenation )*
+// a single concatenation when it has one alternative
+fn alternation(u: &mut Unstructured, depth: usize) -> Result<String> {
+    let alternatives = u.int_in_range(1..=MAX_BRANCHES)?;
+    let mut pattern = concatenation(u, depth)?;
+    for _ in 1..alternatives {
+        pattern.push('|');
+        pattern.push_str(&concatenation(u, depth)?);
+    }
+    Ok(pattern)
+}
+
+// Concatenation => Primary*
+// it may be empty, giving an empty expression
+fn concatenation(u: &mut Unstructured, depth: usize) -> Result<String> {
+    let items = u.int_in_range(0..=MAX_BRANCHES)?;
+    let mut pattern = String::new();
+    for _ in 0..items {
+        pattern.push_str(&primary(u, depth)?);
+    }
+    Ok(pattern)
+}
+
+// Class => "[" ( Class | Shorthand | Character ( "-" Character )? )+ "]"
+fn class(u: &mut Unstructured, depth: usize) -> Result<String> {
+    let members = u.int_in_range(1..=MAX_BRANCHES)?;
+    let mut pattern = String::from("[");
+    // Character as written inside a class
+    let escape = |ch: char| {
+        let slash = if is_class_metacharacter(ch) { "\\" } else { "" };
+        format!("{slash}{ch}")
+    };
+    for _ in 0..members {
+        let member = if depth < MAX_DEPTH && u.ratio(1, 8)? {
+            class(u, depth + 1)?
+        } else if u.ratio(1, 4)? {
+            // A range, never reversed
+            let (first, last) = (u.arbitrary::<char>()?, u.arbitrary::<char>()?);
+            format!("{}-{}", escape(first.min(last)), escape(first.max(last)))
+        } else if u.ratio(1, 5)? {
+            u.arbitrary::<Shorthand>()?.to_string()
+        } else if u.ratio(1, 3)? {
+            escape(u.arbitrary::<char>()?)
+        } else {
+            escape(*u.choose(&['a', 'b', 'c'])?)
+        };
+        pattern.push_str(&member);
+    }
+    pattern.push(']');
+    Ok(pattern)
+}
+
+// Lookbehind => ( "(?<=" | "(?<!" ) ( Character | "." | Shorthand )+ ")"
+// each item matches a single character, thus lookbehinds look for a fixed number of them
+fn lookbehind(u: &mut Unstructured) -> Result<String> {
+    let mut pattern = String::from(*u.choose(&["(?<=", "(?<!"])?);
+    for _ in 0..u.int_in_range(1..=MAX_BRANCHES)? {
+        let item = match u.int_in_range(0..=3)? {
+            0 => String::from("."),
+            1 => u.arbitrary::<Shorthand>()?.to_string(),
+            _ => String::from(*u.choose(&['a', 'b', 'c'])?),
+        };
+        pattern.push_str(&item);
+    }
+    pattern.push(')');
+    Ok(pattern)
+}
+
+// Primary => ( Character | "." | Shorthand | Class | GroupOpening Alternation ")" ) Quantifier?
+//          | Assertion | Lookbehind
+// GroupOpening => "(" | "(?>" | "(?:"
+fn primary(u: &mut Unstructured, depth: usize) -> Result<String> {
+    let mut pattern = match u.int_in_range(0..=15)? {
+        // Mostly characters from a tiny alphabet, so patterns and targets often match
+        0..=5 => String::from(*u.choose(&['a', 'b', 'c'])?),
+        6 => escape(&u.arbitrary::<char>()?.to_string()),
+        7 => String::from("."),
+        8 => u.arbitrary::<Shorthand>()?.to_string(),
+        9 => class(u, depth)?,
+        // Assertions and lookbehinds are never quantified
+        10 => return Ok(u.arbitrary::<Assertion>()?.to_string()),
+        11 => return lookbehind(u),
+        12 if depth < MAX_DEPTH => format!("(?>{})", alternation(u, depth + 1)?),
+        13 if depth < MAX_DEPTH => format!("(?:{})", alternation(u, depth + 1)?),
+        _ if depth < MAX_DEPTH => format!("({})", alternation(u, depth + 1)?),
+        _ => String::from("a"),
+    };
+    let quantifier: Quantifier = u.arbitrary()?;
+    pattern.push_str(&quantifier.to_string());
+    Ok(pattern)
+}
@@ -0,0 +1,127 @@
+// ANSI escape sequences for highlighting matches in terminal output
+
+use std::ops::Range;
+
+use crate::scanner::highlight::{highlight, Highlight, HighlightCategory};
+
+// A terminal text style given as ANSI SGR (Select Graphic Rendition) parameters
+// like "1;31" for bold red text
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Style {
+    parameters: String,
+}
+
+impl Style {
+    // Style of SGR `parameters`, like "1;31"
+    pub fn new(parameters: &str) -> Style {
+        Style {
+            parameters: parameters.to_string(),
+        }
+    }
+
+    // Bold red, like grep colors matches
+    pub fn bold_red() -> Style {
+        Style::new("1;31")
+    }
+
+    // Swap foreground and background colors
+    pub fn reverse() -> Style {
+        Style::new("7")
+    }
+
+    // Style for pattern spans of `category`, None for ordinary characters
+    pub fn for_category(category: HighlightCategory) -> Option<Style> {
+        match category {
+            HighlightCategory::Literal => None,
+            HighlightCategory::Metacharacter => Some(Style::new("36")),
+            HighlightCategory::Quantifier => Some(Style::new("33")),
+            HighlightCategory::GroupDelimiter => Some(Style::new("35")),
+            HighlightCategory::Escape => Some(Style::new("32")),
+            HighlightCategory::Error => Some(Style::bold_red()),
+            // Bright black, comments fade into the background
+            HighlightCategory::Comment => Some(Style::new("90")),
+            HighlightCategory::Flags => Some(Style::new("34")),
+        }
+    }
+
+    // Escape sequence turning this style on
+    pub fn prefix(&self) -> String {
+        format!("\x1b[{}m", self.parameters)
+    }
+
+    // Escape sequence turning all styles off
+    pub fn suffix(&self) -> &'static str {
+        "\x1b[0m"
+    }
+
+    // `text` in this style
+    pub fn paint(&self, text: &str) -> String {
+        format!("{}{text}{}", self.prefix(), self.suffix())
+    }
+}
+
+impl Default for Style {
+    fn default() -> Style {
+        Style::bold_red()
+    }
+}
+
+// Copy of `haystack` with each of `matches` painted in `style`
+// `matches` are ranges of character indices, sorted and non-overlapping
+// like what a Matcher gives back, empty ones are left as they are
+pub fn highlight_matches(haystack: &str, matches: &[Range<usize>], style: &Style) -> String {
+    highlight_matches_cycling(haystack, matches, std::slice::from_ref(style))
+}
+
+// Like `highlight_matches` but matches are painted in `styles` one after another
+// starting over after the last one, so adjacent matches can be told apart
+pub fn highlight_matches_cycling(
+    haystack: &str,
+    matches: &[Range<usize>],
+    styles: &[Style],
+) -> String {
+    let haystack = haystack.chars().collect::<Vec<_>>();
+    let mut highlighted = String::with_capacity(haystack.len());
+    let mut end = 0;
+    let mut styles = styles.iter().cycle();
+    for m in matches.iter().filter(|m| !m.is_empty()) {
+        let Some(style) = styles.next() else {
+            // No styles at all
+            break;
+        };
+        highlighted.extend(&haystack[end..m.start]);
+        highlighted.push_str(&style.prefix());
+        highlighted.extend(&haystack[m.start..m.end]);
+        highlighted.push_str(style.suffix());
+        end = m.end;
+    }
+    highlighted.extend(&haystack[end..]);
+    highlighted
+}
+
+// Copy of `pattern` with each span colored by its category (see `Style::for_category`)
+// Characters in any of `errors`, ranges of character indices, are painted bold red instead
+pub fn highlight_pattern(pattern: &str, errors: &[Range<usize>]) -> String {
+    let chars = pattern.chars().collect::<Vec<_>>();
+    let mut highlighted = String::with_capacity(2 * pattern.len());
+    for Highlight { span, category } in highlight(pattern) {
+        let style = Style::for_category(category);
+        // Split span into runs of characters inside and outside of errors
+        let mut start = span.start;
+        while start < span.end {
+            let in_error = |index: usize| errors.iter().any(|error| error.contains(&index));
+            let error = in_error(start);
+            let end = (start..span.end)
+                .find(|index| in_error(*index) != error)
+                .unwrap_or(span.end);
+            let text = chars[start..end].iter().collect::<String>();
+            match (error, &style) {
+                (true, _) => highlighted.push_str(&Style::bold_red().paint(&text)),
+                (false, Some(style)) => highlighted.push_str(&style.paint(&text)),
+                (false, None) => highlighted.push_str(&text),
+            }
+            start = end;
+        }
+    }
+    highlighted
+}
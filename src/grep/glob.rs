@@ -0,0 +1,68 @@
+// Shell-style globs, like `*.rs`, for selecting files by name
+// A glob is converted to a pattern and matched against whole file names
+
+use crate::automata::Dfa;
+use crate::matcher::escape;
+
+// Convert `glob` into an equivalent pattern
+// *      any sequence of characters, even an empty one
+// ?      any single character
+// {a,b}  either a or b, alternatives may contain the other wildcards
+// Any other character (or a wildcard preceded by a slash) matches itself
+pub fn glob_to_pattern(glob: &str) -> String {
+    let mut pattern = String::with_capacity(2 * glob.len());
+    // Number of `{` not closed yet
+    let mut open_braces = 0_usize;
+    let mut chars = glob.chars();
+    while let Some(ch) = chars.next() {
+        match ch {
+            '*' => pattern.push_str(".*"),
+            '?' => pattern.push('.'),
+            '{' => {
+                open_braces += 1;
+                pattern.push('(');
+            }
+            ',' if open_braces > 0 => pattern.push('|'),
+            '}' if open_braces > 0 => {
+                open_braces -= 1;
+                pattern.push(')');
+            }
+            '\\' => match chars.next() {
+                Some(escaped) => pattern.push_str(&escape(&escaped.to_string())),
+                None => pattern.push_str(&escape("\\")),
+            },
+            _ => pattern.push_str(&escape(&ch.to_string())),
+        }
+    }
+    // A `{` never closed encloses everything after it
+    for _ in 0..open_braces {
+        pattern.push(')');
+    }
+    pattern
+}
+
+// A compiled glob
+#[derive(Debug, Clone)]
+pub struct Glob {
+    glob: String,
+    // Globs match whole names, which is exactly what a DFA accepts
+    dfa: Dfa,
+}
+
+impl Glob {
+    pub fn new(glob: &str) -> Result<Glob, String> {
+        Ok(Glob {
+            glob: glob.to_string(),
+            dfa: Dfa::new(&glob_to_pattern(glob))?,
+        })
+    }
+
+    // Does the whole `name` match this glob?
+    pub fn is_match(&self, name: &str) -> bool {
+        self.dfa.accepts(name)
+    }
+
+    pub fn as_str(&self) -> &str {
+        &self.glob
+    }
+}
@@ -0,0 +1,142 @@
+// `.gitignore`-style rules for skipping files while walking directories
+// Each line of an ignore file is a rule:
+// - Empty lines and lines starting with # are skipped, use \# for a leading #
+// - A leading ! negates the rule: a path it matches is NOT ignored, even if an earlier rule
+//   ignores it. Use \! for a leading !
+// - A trailing / makes the rule match directories only
+// - A rule without a slash (other than a trailing one) matches names at any depth,
+//   otherwise it matches paths relative to directory containing the ignore file
+// - ** as a whole path component matches any number of components, including none
+//   but a trailing /** matches only what is inside a directory
+// - Other wildcards are the same as in globs (see `glob`), they never match a slash
+// When many rules match a path the last one wins
+
+use std::fs;
+use std::io;
+use std::path::Path;
+
+use super::glob::Glob;
+
+// Names of files read from each directory by a walker honoring ignore files
+// Rules of a later file take precedence over those of an earlier one
+pub const IGNORE_FILE_NAMES: [&str; 2] = [".gitignore", ".ignore"];
+
+#[derive(Debug, Clone)]
+struct IgnoreRule {
+    // Globs of the rule path components, None stands for **
+    components: Vec<Option<Glob>>,
+    // Rule starts with !
+    negated: bool,
+    // Rule ends with /
+    directory_only: bool,
+}
+
+// Do path `components` match rule `pattern` components?
+fn components_match(pattern: &[Option<Glob>], components: &[&str]) -> bool {
+    match pattern.split_first() {
+        None => components.is_empty(),
+        // A trailing ** matches at least one component, everything inside a directory
+        Some((None, [])) => !components.is_empty(),
+        Some((None, rest)) => {
+            (0..=components.len()).any(|skipped| components_match(rest, &components[skipped..]))
+        }
+        Some((Some(glob), rest)) => match components.split_first() {
+            Some((first, others)) => glob.is_match(first) && components_match(rest, others),
+            None => false,
+        },
+    }
+}
+
+// Rules read from one ignore file
+#[derive(Debug, Clone, Default)]
+pub struct IgnoreRules {
+    rules: Vec<IgnoreRule>,
+}
+
+impl IgnoreRules {
+    pub fn new() -> IgnoreRules {
+        IgnoreRules::default()
+    }
+
+    // Rules in `text`, one for each line
+    pub fn parse(text: &str) -> IgnoreRules {
+        let mut rules = IgnoreRules::new();
+        for line in text.lines() {
+            rules.add(line);
+        }
+        rules
+    }
+
+    // Rules in file at `path`
+    pub fn from_file<P: AsRef<Path>>(path: P) -> io::Result<IgnoreRules> {
+        Ok(IgnoreRules::parse(&fs::read_to_string(path)?))
+    }
+
+    // Add rule in `line`, after existing rules
+    // Like git, lines which are not valid rules are skipped
+    pub fn add(&mut self, line: &str) -> &mut IgnoreRules {
+        let mut rule = line.strip_suffix('\r').unwrap_or(line);
+        // Trailing spaces are skipped unless escaped
+        while rule.ends_with(' ') && !rule.ends_with("\\ ") {
+            rule = &rule[..rule.len() - 1];
+        }
+        if rule.is_empty() || rule.starts_with('#') {
+            return self;
+        }
+        let negated = rule.starts_with('!');
+        if negated {
+            rule = &rule[1..];
+        }
+        let directory_only = rule.ends_with('/');
+        if directory_only {
+            rule = &rule[..rule.len() - 1];
+        }
+        if rule.is_empty() {
+            return self;
+        }
+
+        // A rule without a slash matches at any depth, just like `**/rule`
+        let anchored = rule.contains('/');
+        let rule = rule.strip_prefix('/').unwrap_or(rule);
+        let mut components = if anchored { vec![] } else { vec![None] };
+        for component in rule.split('/') {
+            if component == "**" {
+                components.push(None);
+            } else {
+                match Glob::new(component) {
+                    Ok(glob) => components.push(Some(glob)),
+                    Err(_) => return self,
+                }
+            }
+        }
+        self.rules.push(IgnoreRule {
+            components,
+            negated,
+            directory_only,
+        });
+        self
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.rules.is_empty()
+    }
+
+    // What the last rule matching `path` says about it
+    // Some(true) if it is ignored, Some(false) if it is explicitly not ignored (by a negated rule)
+    // or None if no rule matches
+    // `path` is relative to directory containing the ignore file, with components separated by /
+    pub fn decision(&self, path: &str, is_dir: bool) -> Option<bool> {
+        let components = path.split('/').collect::<Vec<_>>();
+        self.rules
+            .iter()
+            .rev()
+            .find(|rule| {
+                (is_dir || !rule.directory_only) && components_match(&rule.components, &components)
+            })
+            .map(|rule| !rule.negated)
+    }
+
+    pub fn is_ignored(&self, path: &str, is_dir: bool) -> bool {
+        self.decision(path, is_dir).unwrap_or(false)
+    }
+}
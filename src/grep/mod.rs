@@ -0,0 +1,293 @@
+// Grep module
+// Search text line by line for lines matching a pattern, like grep does
+
+// Shell-style globs for selecting files by name
+pub mod glob;
+
+// Listing files to search under directories
+pub mod walk;
+
+// `.gitignore`-style rules for skipping files
+pub mod ignore;
+
+// Searching many files at once using worker threads
+pub mod parallel;
+
+// Highlighting matches in terminal output
+pub mod ansi;
+
+use std::ffi::OsString;
+use std::fs::{self, File};
+use std::io::{self, BufRead, BufReader, Write};
+use std::ops::Range;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, RwLock};
+
+use crate::matcher::{expand_replacement, substitute_matches_with};
+use crate::parser::syntax_tree::ParsedRegexp;
+use crate::regex::Regex;
+use walk::Walker;
+
+// A line selected by a search
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LineMatch {
+    // Line number, first line is line 1
+    pub line_number: usize,
+    // Line text without its line terminator
+    pub line: String,
+    // Ranges of character indices in `line` matching the pattern
+    // Empty for lines selected by an inverted search
+    pub matches: Vec<Range<usize>>,
+}
+
+// A line selected by a search in a file, along with the file path
+// Unlike printed lines, it can be consumed by other tools as it is
+// or serialized (with feature `serde`), like into JSON
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct SearchResult {
+    pub path: PathBuf,
+    // Line number, first line is line 1
+    pub line_number: usize,
+    // Line text without its line terminator
+    pub line: String,
+    // Ranges of character indices in `line` matching the pattern
+    // Empty for lines selected by an inverted search
+    pub spans: Vec<Range<usize>>,
+}
+
+impl SearchResult {
+    pub fn new(path: PathBuf, line: LineMatch) -> SearchResult {
+        SearchResult {
+            path,
+            line_number: line.line_number,
+            line: line.line,
+            spans: line.matches,
+        }
+    }
+}
+
+// Outcome of searching one file among many
+#[derive(Debug)]
+pub struct FileMatches {
+    pub path: PathBuf,
+    // Selected lines, or the error which stopped reading this file
+    pub lines: io::Result<Vec<LineMatch>>,
+}
+
+impl FileMatches {
+    // Selected lines of this file as search results
+    pub fn into_results(self) -> io::Result<Vec<SearchResult>> {
+        let path = self.path;
+        Ok(self
+            .lines?
+            .into_iter()
+            .map(|line| SearchResult::new(path.clone(), line))
+            .collect())
+    }
+}
+
+// Searches text for lines matching a pattern
+// The pattern is compiled once, each line and each file reuses it
+pub struct Searcher {
+    regex: Regex,
+    // Select lines NOT matching the pattern instead
+    invert_match: bool,
+}
+
+impl Searcher {
+    pub fn new(pattern: &str) -> Result<Searcher, String> {
+        Ok(Searcher::from_regex(Regex::new(pattern)?))
+    }
+
+    pub fn from_regexp(regexp: Arc<RwLock<ParsedRegexp>>) -> Searcher {
+        let source = Arc::clone(&regexp.read().unwrap().pattern);
+        Searcher::from_regex(Regex::from_regexp(&source, regexp))
+    }
+
+    // Search with a pattern compiled with options, like `RegexBuilder::smart_case`
+    pub fn from_regex(regex: Regex) -> Searcher {
+        Searcher {
+            regex,
+            invert_match: false,
+        }
+    }
+
+    // Select lines not matching the pattern, like grep -v
+    pub fn invert_match(&mut self, enabled: bool) -> &mut Searcher {
+        self.invert_match = enabled;
+        self
+    }
+
+    // Matches of pattern in a single line
+    pub fn find_in_line(&self, line: &str) -> Vec<Range<usize>> {
+        self.regex.matcher(line).collect()
+    }
+
+    // Read lines from `reader` and give back selected ones, in order
+    pub fn search_reader<R: BufRead>(&self, reader: R) -> io::Result<Vec<LineMatch>> {
+        let mut selected = vec![];
+        let mut matcher = self.regex.matcher("");
+        for (index, line) in reader.lines().enumerate() {
+            let line = line?;
+            matcher.assign_match_target(&line);
+            let matches = matcher.by_ref().collect::<Vec<_>>();
+            if matches.is_empty() == self.invert_match {
+                selected.push(LineMatch {
+                    line_number: index + 1,
+                    matches: if self.invert_match { vec![] } else { matches },
+                    line,
+                });
+            }
+        }
+        Ok(selected)
+    }
+
+    // Copy of text read from `reader` with each match replaced by expanded `template`
+    // (see `expand_replacement`), along with number of replaced matches
+    // Like searching, text is matched line by line and line terminators are kept as they are
+    pub fn replace_reader<R: BufRead>(
+        &self,
+        mut reader: R,
+        template: &str,
+    ) -> io::Result<(String, usize)> {
+        let mut replaced = String::new();
+        let mut replacements = 0;
+        let mut matcher = self.regex.matcher("");
+        let mut buffer = String::new();
+        while reader.read_line(&mut buffer)? > 0 {
+            let line = buffer.strip_suffix('\n').unwrap_or(&buffer);
+            matcher.assign_match_target(line);
+            let matches = matcher.by_ref().collect::<Vec<_>>();
+            replacements += matches.len();
+            let line = line.chars().collect::<Vec<_>>();
+            replaced.push_str(&substitute_matches_with(
+                &line,
+                matches.into_iter(),
+                |m| expand_replacement(template, &line[m.clone()].iter().collect::<String>()),
+                usize::MAX,
+            ));
+            if buffer.ends_with('\n') {
+                replaced.push('\n');
+            }
+            buffer.clear();
+        }
+        Ok((replaced, replacements))
+    }
+
+    // Search lines of file at `path`
+    pub fn search_file<P: AsRef<Path>>(&self, path: P) -> io::Result<Vec<LineMatch>> {
+        self.search_reader(BufReader::new(File::open(path)?))
+    }
+
+    // Search lines of file at `path`, giving back search results instead of lines
+    pub fn search_file_results<P: AsRef<Path>>(&self, path: P) -> io::Result<Vec<SearchResult>> {
+        let path = path.as_ref();
+        FileMatches {
+            lines: self.search_file(path),
+            path: path.to_path_buf(),
+        }
+        .into_results()
+    }
+
+    // Search each file `walker` lists under `root` (see `Walker::files`), in order of their paths
+    // Files without selected lines are included too, with no lines
+    // An error reading a file is reported in its result and does not stop the search
+    pub fn search_files<P: AsRef<Path>>(
+        &self,
+        walker: &Walker,
+        root: P,
+    ) -> io::Result<Vec<FileMatches>> {
+        Ok(walker
+            .files(root)?
+            .into_iter()
+            .map(|path| FileMatches {
+                lines: self.search_file(&path),
+                path,
+            })
+            .collect())
+    }
+
+    // Replace each match in file at `path` with expanded `template` (see `replace_reader`)
+    // and give back number of replaced matches
+    // The file is replaced atomically: new contents are written to a temporary file
+    // in the same directory, flushed to disk and then renamed over the original file
+    // thus readers see either the old or the new contents, never a partially written file
+    // If `backup_suffix` is given, original contents are first copied to a file
+    // named like the original with that suffix appended, like `notes.txt.bak`
+    // A file without matches is left untouched
+    pub fn replace_in_file<P: AsRef<Path>>(
+        &self,
+        path: P,
+        template: &str,
+        backup_suffix: Option<&str>,
+    ) -> io::Result<usize> {
+        let path = path.as_ref();
+        let (replaced, replacements) =
+            self.replace_reader(BufReader::new(File::open(path)?), template)?;
+        if replacements == 0 {
+            return Ok(0);
+        }
+
+        if let Some(suffix) = backup_suffix {
+            let mut backup = path.as_os_str().to_owned();
+            backup.push(suffix);
+            fs::copy(path, backup)?;
+        }
+
+        let file_name = path
+            .file_name()
+            .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "not a file path"))?;
+        let mut temporary_name = OsString::from(".");
+        temporary_name.push(file_name);
+        temporary_name.push(format!(".{}.tmp", std::process::id()));
+        let temporary_path = path.with_file_name(temporary_name);
+        let written = (|| {
+            let mut temporary = File::create(&temporary_path)?;
+            temporary.write_all(replaced.as_bytes())?;
+            temporary.sync_all()?;
+            // Keep permissions of the original file
+            fs::set_permissions(&temporary_path, fs::metadata(path)?.permissions())?;
+            fs::rename(&temporary_path, path)
+        })();
+        if written.is_err() {
+            // Do not leave the temporary file behind
+            let _ = fs::remove_file(&temporary_path);
+        }
+        written.map(|_| replacements)
+    }
+}
+
+// Number of lines read from `reader` matching pattern of `searcher`
+// Lines are not kept, so this works for inputs of any size
+// Unlike searching, whether `searcher` inverts matches does not matter
+pub fn count_matching_lines<R: BufRead>(reader: R, searcher: &Searcher) -> io::Result<usize> {
+    let mut count = 0;
+    let mut matcher = searcher.regex.matcher("");
+    for line in reader.lines() {
+        matcher.assign_match_target(&line?);
+        count += usize::from(matcher.next().is_some());
+    }
+    Ok(count)
+}
+
+// Lines read from `reader` split into those matching pattern of `searcher`
+// and those not matching it, both in their original order
+// Like `count_matching_lines`, whether `searcher` inverts matches does not matter
+pub fn partition_lines<R: BufRead>(
+    reader: R,
+    searcher: &Searcher,
+) -> io::Result<(Vec<String>, Vec<String>)> {
+    let (mut matching, mut not_matching) = (vec![], vec![]);
+    let mut matcher = searcher.regex.matcher("");
+    for line in reader.lines() {
+        let line = line?;
+        matcher.assign_match_target(&line);
+        if matcher.next().is_some() {
+            matching.push(line);
+        } else {
+            not_matching.push(line);
+        }
+    }
+    Ok((matching, not_matching))
+}
@@ -0,0 +1,85 @@
+// Searching many files at once using a pool of worker threads
+// All workers share the same compiled pattern, each worker matches with its own Matcher
+
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::mpsc;
+use std::thread;
+
+use super::{FileMatches, Searcher};
+
+// Order in which results of a parallel search are reported
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum OutputOrder {
+    // In the same order files are given, a result is held back
+    // until results of all files before it are reported
+    #[default]
+    Ordered,
+    // As soon as each file is searched, whatever its position
+    Streaming,
+}
+
+// Number of worker threads to use when caller asks for 0
+fn default_threads() -> usize {
+    thread::available_parallelism().map_or(1, |threads| threads.get())
+}
+
+impl Searcher {
+    // Search `files` using `threads` worker threads and report result of each file
+    // to `report`, which is called on the calling thread in the given `order`
+    // With 0 threads, as many threads as the machine can run in parallel are used
+    // Like `search_files`, errors are reported in results of their files
+    pub fn search_files_parallel(
+        &self,
+        files: Vec<PathBuf>,
+        threads: usize,
+        order: OutputOrder,
+        mut report: impl FnMut(FileMatches),
+    ) {
+        let threads = match threads {
+            0 => default_threads(),
+            threads => threads,
+        }
+        .min(files.len());
+        // Index of next file to search, shared by all workers
+        let next = AtomicUsize::new(0);
+        let (sender, receiver) = mpsc::channel::<(usize, FileMatches)>();
+        thread::scope(|scope| {
+            for _ in 0..threads {
+                let sender = sender.clone();
+                let (next, files) = (&next, &files);
+                scope.spawn(move || loop {
+                    let index = next.fetch_add(1, Ordering::Relaxed);
+                    let Some(path) = files.get(index) else {
+                        break;
+                    };
+                    let result = FileMatches {
+                        lines: self.search_file(path),
+                        path: path.clone(),
+                    };
+                    if sender.send((index, result)).is_err() {
+                        break;
+                    }
+                });
+            }
+            // Receiving ends once all workers are done and their senders are dropped
+            drop(sender);
+
+            // Results which arrived before some result preceding them
+            let mut pending = BTreeMap::new();
+            let mut next_reported = 0;
+            for (index, result) in receiver {
+                if order == OutputOrder::Streaming {
+                    report(result);
+                    continue;
+                }
+                pending.insert(index, result);
+                while let Some(result) = pending.remove(&next_reported) {
+                    report(result);
+                    next_reported += 1;
+                }
+            }
+        });
+    }
+}
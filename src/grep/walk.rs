@@ -0,0 +1,172 @@
+// Finding files to search under directories
+
+use std::fs::{self, File};
+use std::io::{self, Read};
+use std::path::{Path, PathBuf};
+
+use super::glob::Glob;
+use super::ignore::{IgnoreRules, IGNORE_FILE_NAMES};
+
+// Number of leading bytes inspected by `is_binary_file`
+const BINARY_PROBE_LENGTH: usize = 8192;
+
+// Does `bytes` look like the start of a binary (not text) file?
+// Like grep, a file containing a NUL byte is considered binary
+pub fn is_binary(bytes: &[u8]) -> bool {
+    bytes.contains(&0)
+}
+
+// Does the file at `path` look binary? Only its first few kilobytes are read
+pub fn is_binary_file<P: AsRef<Path>>(path: P) -> io::Result<bool> {
+    let mut probe = Vec::with_capacity(BINARY_PROBE_LENGTH);
+    File::open(path)?
+        .take(BINARY_PROBE_LENGTH as u64)
+        .read_to_end(&mut probe)?;
+    Ok(is_binary(&probe))
+}
+
+// Lists files under directories, recursively
+// Files are selected by their names (not whole paths) using globs
+#[derive(Debug, Clone, Default)]
+pub struct Walker {
+    // When not empty, a file is selected only if its name matches one of these
+    include: Vec<Glob>,
+    // A file or directory whose name matches one of these is skipped
+    exclude: Vec<Glob>,
+    // Also select files which look binary
+    binary: bool,
+    // Skip files and directories whose names start with a dot
+    skip_hidden: bool,
+    // Skip files and directories matched by rules of ignore files (see `ignore`)
+    ignore_files: bool,
+}
+
+// Path of `path` relative to `base`, with components separated by /
+fn relative_path(base: &Path, path: &Path) -> String {
+    path.strip_prefix(base)
+        .unwrap_or(path)
+        .components()
+        .map(|component| component.as_os_str().to_string_lossy())
+        .collect::<Vec<_>>()
+        .join("/")
+}
+
+impl Walker {
+    pub fn new() -> Walker {
+        Walker::default()
+    }
+
+    pub fn include(&mut self, glob: Glob) -> &mut Walker {
+        self.include.push(glob);
+        self
+    }
+
+    pub fn exclude(&mut self, glob: Glob) -> &mut Walker {
+        self.exclude.push(glob);
+        self
+    }
+
+    // Select binary files too, they are skipped by default
+    pub fn binary(&mut self, enabled: bool) -> &mut Walker {
+        self.binary = enabled;
+        self
+    }
+
+    // Skip hidden files and directories, those whose names start with a dot
+    pub fn skip_hidden(&mut self, enabled: bool) -> &mut Walker {
+        self.skip_hidden = enabled;
+        self
+    }
+
+    // Honor `.gitignore` and `.ignore` files found in walked directories
+    // Rules of a file apply to its directory and everything under it,
+    // rules of a deeper file take precedence
+    pub fn ignore_files(&mut self, enabled: bool) -> &mut Walker {
+        self.ignore_files = enabled;
+        self
+    }
+
+    // Whether file named `name` is selected
+    pub fn is_selected(&self, name: &str) -> bool {
+        (self.include.is_empty() || self.include.iter().any(|glob| glob.is_match(name)))
+            && !self.exclude.iter().any(|glob| glob.is_match(name))
+    }
+
+    // Selected files under `root`, sorted by path
+    // If `root` is a file it is the only one listed, regardless of its name
+    // Symbolic links are not followed
+    pub fn files<P: AsRef<Path>>(&self, root: P) -> io::Result<Vec<PathBuf>> {
+        let root = root.as_ref();
+        let mut files = vec![];
+        if fs::metadata(root)?.is_dir() {
+            self.walk_directory(root, &mut vec![], &mut files)?;
+        } else {
+            files.push(root.to_path_buf());
+        }
+        Ok(files)
+    }
+
+    // Is `path` ignored by rules of ignore files in `ignored`?
+    // Deeper rules are checked first because they take precedence
+    fn is_ignored(ignored: &[(PathBuf, IgnoreRules)], path: &Path, is_dir: bool) -> bool {
+        ignored
+            .iter()
+            .rev()
+            .find_map(|(base, rules)| rules.decision(&relative_path(base, path), is_dir))
+            .unwrap_or(false)
+    }
+
+    // `ignored` holds rules of ignore files in `directory` and its ancestors
+    // along with directories containing them
+    fn walk_directory(
+        &self,
+        directory: &Path,
+        ignored: &mut Vec<(PathBuf, IgnoreRules)>,
+        files: &mut Vec<PathBuf>,
+    ) -> io::Result<()> {
+        let mut rules = IgnoreRules::new();
+        if self.ignore_files {
+            for name in IGNORE_FILE_NAMES {
+                let path = directory.join(name);
+                if path.is_file() {
+                    for line in fs::read_to_string(path)?.lines() {
+                        rules.add(line);
+                    }
+                }
+            }
+        }
+        let has_rules = !rules.is_empty();
+        if has_rules {
+            ignored.push((directory.to_path_buf(), rules));
+        }
+
+        let mut entries = fs::read_dir(directory)?.collect::<Result<Vec<_>, _>>()?;
+        entries.sort_by_key(|entry| entry.file_name());
+        for entry in entries {
+            let name = entry.file_name();
+            let name = name.to_string_lossy();
+            let path = entry.path();
+            let file_type = entry.file_type()?;
+            if (self.skip_hidden && name.starts_with('.'))
+                || Walker::is_ignored(ignored, &path, file_type.is_dir())
+            {
+                continue;
+            }
+            if file_type.is_dir() {
+                if !self.exclude.iter().any(|glob| glob.is_match(&name)) {
+                    self.walk_directory(&path, ignored, files)?;
+                }
+            } else if file_type.is_file()
+                && self.is_selected(&name)
+                && (self.binary || !is_binary_file(&path)?)
+            {
+                files.push(path);
+            }
+        }
+
+        if has_rules {
+            ignored.pop();
+        }
+        Ok(())
+    }
+}
@@ -48,7 +48,7 @@ Let's take an example, given string `(a|b|c)+`:
 `(` / `a` / `|` / `b` / `|` / `c` / `)` / `+`
 
 - step 2: <code>[Parser]</code> always tries to parse an alternation (like above expression),
-when it fails it returns parsed expression (if any) as a concatenation (such as `abc`)
+  when it fails it returns parsed expression (if any) as a concatenation (such as `abc`)
 
 [Parser]: parser::Parser
 
@@ -85,6 +85,8 @@ You can create a <code>[Matcher]</code> using its factory method [`Matcher::new`
 ------
 */
 
+use std::ops::Range;
+
 // Scanner module
 // Take the source pattern string and generate tokens as needed
 pub mod scanner;
@@ -98,6 +100,44 @@ pub mod parser;
 // Use a syntax tree to match against strings
 pub mod matcher;
 
+// Regex module
+// Compile a pattern once, with options, for matching many targets
+pub mod regex;
+
+// Automata module
+// Build finite automata (NFA/DFA) from a syntax tree
+pub mod automata;
+
+// Analysis module
+// Inspect syntax trees, for instance to describe them in plain English
+pub mod analysis;
+
+// Diagnostics module
+// Stable codes of errors and warnings, and levels for allowing or denying warnings
+pub mod diagnostics;
+
+// Grep module
+// Search text line by line for lines matching a pattern
+pub mod grep;
+
+// Bench module
+// Time engines (backtracker, NFA, DFA) against each other
+pub mod bench;
+
+// Telemetry module
+// Spans and events for the `tracing` crate, with feature `tracing`
+mod telemetry;
+
+// Python bindings
+// Expose pattern compilation and searching to Python scripts
+#[cfg(feature = "python")]
+mod python;
+
+// Fuzzing inputs
+// Generate well-formed patterns and syntax trees for fuzz targets
+#[cfg(feature = "fuzzing")]
+pub mod fuzzing;
+
 // Format error as follow:
 // First line prints error type, its position and the specific error name
 // Second line prints source string (string given to parser to process)
@@ -122,6 +162,19 @@ pub fn format_error(
     positions_and_markers_count: &[(usize, u8)],
     // Any helpful hints
     hints: &str,
+) -> String {
+    format_error_with(error_msg, source, positions_and_markers_count, hints, false)
+}
+
+// Format error like `format_error`, colored for printing it to a terminal when `colored`
+// Echoed source string is colored by token category (see `scanner::highlight`)
+// and items causing the error, along with their carets, are painted bold red
+pub fn format_error_with(
+    error_msg: &str,
+    source: &str,
+    positions_and_markers_count: &[(usize, u8)],
+    hints: &str,
+    colored: bool,
 ) -> String {
     // Allocate enough space to make less allocations during process
     let mut formatted_error = String::with_capacity(
@@ -139,19 +192,34 @@ pub fn format_error(
     formatted_error.push_str(error_msg);
     formatted_error.push('\n');
 
-    formatted_error.push_str(source);
+    if colored {
+        let errors = positions_and_markers_count
+            .iter()
+            .map(|(pos, count)| *pos..*pos + *count as usize)
+            .collect::<Vec<_>>();
+        formatted_error.push_str(&grep::ansi::highlight_pattern(source, &errors));
+    } else {
+        formatted_error.push_str(source);
+    }
     formatted_error.push('\n');
 
+    let mut markers = String::new();
     for (pos, count) in positions_and_markers_count {
         for _ in 1..=*pos {
             // Add spaces to align ^ with error position in source string
-            formatted_error.push(' ');
+            markers.push(' ');
         }
         for _ in 1..=*count {
             // Add as many carets as needed to pinpoint item causing error
-            formatted_error.push('^');
+            markers.push('^');
         }
     }
+    if colored {
+        let start = markers.len() - markers.trim_start().len();
+        let carets = grep::ansi::Style::bold_red().paint(&markers[start..]);
+        markers.replace_range(start.., &carets);
+    }
+    formatted_error.push_str(&markers);
 
     if !hints.is_empty() {
         // If there are no hints, do not add another newline character
@@ -163,6 +231,108 @@ pub fn format_error(
     formatted_error
 }
 
+// A span of source string an error points at, along with a short message about it
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ErrorLabel {
+    // Range of character indices in source string, an empty span points at its start
+    pub span: Range<usize>,
+    pub message: String,
+    // A primary label marks what caused the error with carets `^`
+    // a secondary one marks a related item with dashes `-`
+    pub primary: bool,
+}
+
+impl ErrorLabel {
+    pub fn primary(span: Range<usize>, message: &str) -> ErrorLabel {
+        ErrorLabel {
+            span,
+            message: message.to_string(),
+            primary: true,
+        }
+    }
+
+    pub fn secondary(span: Range<usize>, message: &str) -> ErrorLabel {
+        ErrorLabel {
+            span,
+            message: message.to_string(),
+            primary: false,
+        }
+    }
+}
+
+// Format error like `format_error` but pointing at many labeled spans
+// Each label gets its own line below source string, in the given order
+// followed by notes, each in its own line
+//
+// For instance:
+// Syntax error [E0003] at end of pattern: Expected ) after expression
+// a(b
+//  - group opened here
+//    ^ expected ) here
+// note: each ( needs a matching )
+pub fn format_error_labeled(
+    error_msg: &str,
+    source: &str,
+    labels: &[ErrorLabel],
+    notes: &[&str],
+) -> String {
+    format_error_labeled_with(error_msg, source, labels, notes, false)
+}
+
+// Format error like `format_error_labeled`, colored like `format_error_with` when `colored`
+// Secondary labels are painted bold blue
+pub fn format_error_labeled_with(
+    error_msg: &str,
+    source: &str,
+    labels: &[ErrorLabel],
+    notes: &[&str],
+    colored: bool,
+) -> String {
+    let mut formatted_error = String::new();
+    formatted_error.push_str(error_msg);
+    formatted_error.push('\n');
+
+    if colored {
+        let errors = labels
+            .iter()
+            .filter(|label| label.primary)
+            .map(|label| label.span.clone())
+            .collect::<Vec<_>>();
+        formatted_error.push_str(&grep::ansi::highlight_pattern(source, &errors));
+    } else {
+        formatted_error.push_str(source);
+    }
+
+    for label in labels {
+        let (marker, style) = if label.primary {
+            ('^', grep::ansi::Style::bold_red())
+        } else {
+            ('-', grep::ansi::Style::new("1;34"))
+        };
+        let markers = marker.to_string().repeat(label.span.len().max(1));
+        let mut line = markers;
+        if !label.message.is_empty() {
+            line.push(' ');
+            line.push_str(&label.message);
+        }
+        formatted_error.push('\n');
+        // Add spaces to align markers with labeled span in source string
+        formatted_error.push_str(&" ".repeat(label.span.start));
+        if colored {
+            formatted_error.push_str(&style.paint(&line));
+        } else {
+            formatted_error.push_str(&line);
+        }
+    }
+
+    for note in notes {
+        formatted_error.push_str("\nnote: ");
+        formatted_error.push_str(note);
+    }
+
+    formatted_error
+}
+
 fn report_fatal_error(msg: &str) -> ! {
     eprintln!("FATAL ERROR:");
     eprintln!("{msg}");
@@ -0,0 +1,53 @@
+// Byte offsets of matches
+// Matches are ranges of character indices, but a `&str` is sliced by byte offsets
+// where characters outside ASCII take two to four bytes instead of one
+// Byte ranges of matches always slice the target on character boundaries
+
+use std::ops::Range;
+
+// Converts character indices of a haystack to byte offsets and back
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ByteMap {
+    // offsets[i] is byte offset of character i
+    // there is one more offset than characters, for the end of haystack
+    offsets: Vec<usize>,
+}
+
+impl ByteMap {
+    pub fn new(haystack: &str) -> ByteMap {
+        ByteMap::from_chars(haystack.chars())
+    }
+
+    pub fn from_chars(chars: impl Iterator<Item = char>) -> ByteMap {
+        let mut offsets = vec![0];
+        let mut offset = 0;
+        for ch in chars {
+            offset += ch.len_utf8();
+            offsets.push(offset);
+        }
+        ByteMap { offsets }
+    }
+
+    // Byte offset of character at `char_index`
+    // Indices past the end of haystack are clamped to its end
+    pub fn offset(&self, char_index: usize) -> usize {
+        self.offsets[char_index.min(self.offsets.len() - 1)]
+    }
+
+    // Range of bytes covered by `m`
+    pub fn range(&self, m: &Range<usize>) -> Range<usize> {
+        self.offset(m.start)..self.offset(m.end)
+    }
+
+    // Index of character beginning at `byte_offset`
+    // None if it is inside a character or past the end of haystack
+    pub fn char_index(&self, byte_offset: usize) -> Option<usize> {
+        self.offsets.binary_search(&byte_offset).ok()
+    }
+}
+
+// Byte ranges of `matches` found in `haystack`
+pub fn byte_ranges(haystack: &str, matches: &[Range<usize>]) -> Vec<Range<usize>> {
+    let map = ByteMap::new(haystack);
+    matches.iter().map(|m| map.range(m)).collect()
+}
@@ -0,0 +1,106 @@
+// Capture slots
+// While matching, Matcher records the range each capturing group matched
+// Groups are numbered from 1 in order of their opening parentheses, in `(a(b))(c)`
+// group 1 is `(a(b))`, group 2 is `(b)` and group 3 is `(c)`
+// A repeated group holds the range of its last iteration, `(a|b)+` matching "ab" holds 1..2
+// and a group holds nothing if it did not take part in the match, like `(b)` in `(a)|(b)` matching "a"
+// Non-capturing groups `(?:...)` and atomic groups `(?>...)` are not numbered
+
+use std::ops::Range;
+use std::sync::{Arc, RwLock};
+
+use crate::parser::syntax_tree::{NodeId, ParsedRegexp};
+
+// Ranges matched so far by capturing groups of ongoing match
+#[derive(Debug, Clone, Default)]
+pub(super) struct CaptureSlots {
+    // Identifiers of capturing groups, sorted
+    // Groups are numbered in pre-order, thus in order of their opening parentheses
+    groups: Vec<NodeId>,
+    // Range matched by each group, None if it did not match
+    slots: Vec<Option<Range<usize>>>,
+}
+
+// Slots of capturing groups within a subexpression taken before it attempts to match
+pub(super) struct CaptureSnapshot {
+    // Indices of those groups in `CaptureSlots::groups`
+    indices: Range<usize>,
+    slots: Vec<Option<Range<usize>>>,
+}
+
+impl CaptureSlots {
+    // Slots of capturing groups of `regexp`, whose node ids are already assigned
+    pub(super) fn new(regexp: &Arc<RwLock<ParsedRegexp>>) -> CaptureSlots {
+        let groups = ParsedRegexp::iter(regexp)
+            .filter_map(|visited| {
+                let expr = visited.expression.read().unwrap();
+                expr.expression_type.is_capturing_group().then_some(expr.id)
+            })
+            .collect::<Vec<_>>();
+        CaptureSlots {
+            slots: vec![None; groups.len()],
+            groups,
+        }
+    }
+
+    pub(super) fn is_empty(&self) -> bool {
+        self.groups.is_empty()
+    }
+
+    pub(super) fn clear(&mut self) {
+        self.slots.fill(None);
+    }
+
+    // Ranges matched by groups, group 1 first
+    pub(super) fn slots(&self) -> &[Option<Range<usize>>] {
+        &self.slots
+    }
+
+    // Indices of capturing groups among `expr` and its descendants
+    // Descendants are numbered right after their ancestor (see NodeId)
+    fn indices(&self, expr: &ParsedRegexp) -> Range<usize> {
+        let first = self.groups.partition_point(|id| *id < expr.id);
+        let last_descendant = expr.last_descendant_id();
+        let end = first + self.groups[first..].partition_point(|id| *id <= last_descendant);
+        first..end
+    }
+
+    // None if `expr` has no capturing groups, then there is nothing to restore
+    pub(super) fn snapshot(&self, expr: &ParsedRegexp) -> Option<CaptureSnapshot> {
+        if self.is_empty() {
+            return None;
+        }
+        let indices = self.indices(expr);
+        if indices.is_empty() {
+            return None;
+        }
+        Some(CaptureSnapshot {
+            slots: self.slots[indices.clone()].to_vec(),
+            indices,
+        })
+    }
+
+    // Forget whatever groups of `snapshot` recorded after it was taken
+    // like when their subexpression failed to match
+    pub(super) fn restore(&mut self, snapshot: &CaptureSnapshot) {
+        self.slots[snapshot.indices.clone()].clone_from_slice(&snapshot.slots);
+    }
+
+    // Like `restore`, but only for groups whose identifiers are `first` or greater
+    // A concatenation backtracking to one of its items restores groups of that item
+    // and of all items after it, all of them will match again
+    pub(super) fn restore_from(&mut self, snapshot: &CaptureSnapshot, first: NodeId) {
+        for (index, slot) in snapshot.indices.clone().zip(&snapshot.slots) {
+            if self.groups[index] >= first {
+                self.slots[index] = slot.clone();
+            }
+        }
+    }
+
+    // Record the range matched by group `id`, if it's a capturing group
+    pub(super) fn record(&mut self, id: NodeId, matched: Range<usize>) {
+        if let Ok(index) = self.groups.binary_search(&id) {
+            self.slots[index] = Some(matched);
+        }
+    }
+}
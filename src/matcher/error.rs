@@ -0,0 +1,27 @@
+// Why a search could not be completed
+// A search which completes but finds nothing is not an error, see Matcher::try_find
+
+use std::fmt::Display;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum MatchError {
+    // Search attempted to match subexpressions more than `limit` times
+    // see Matcher::set_step_limit
+    StepLimitExceeded { limit: usize },
+    // Search was still running when its deadline passed
+    // see Matcher::find_with_deadline
+    DeadlineExceeded,
+}
+
+impl Display for MatchError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            MatchError::StepLimitExceeded { limit } => {
+                write!(f, "Search exceeded its limit of {limit} steps")
+            }
+            MatchError::DeadlineExceeded => write!(f, "Search did not finish before its deadline"),
+        }
+    }
+}
+
+impl std::error::Error for MatchError {}
@@ -0,0 +1,155 @@
+// Searching patterns which match only a few fixed strings, like `foo` or `get|set|put`
+// such patterns are searched for as plain substrings, skipping the backtracker entirely
+// Strings are tried in pattern order, thus the first alternative matching
+// at the leftmost position wins, just like with the backtracker
+// Patterns the backtracker matches differently are left to it, see `literals_of`
+
+use std::ops::Range;
+use std::sync::{Arc, RwLock};
+
+use crate::parser::syntax_tree::*;
+
+// Largest number of strings searched for by a LiteralSearcher
+const MAX_LITERALS: usize = 64;
+
+#[derive(Debug, Clone)]
+pub(crate) struct LiteralSearcher {
+    // All strings matched by the pattern, in the order the backtracker tries them
+    literals: Vec<Vec<char>>,
+    // First character of each literal, sorted
+    // positions starting with any other character are skipped quickly
+    first_chars: Vec<char>,
+}
+
+impl LiteralSearcher {
+    // Searcher for `regexp` if it matches only a few non-empty fixed strings
+    pub(crate) fn new(regexp: &Arc<RwLock<ParsedRegexp>>) -> Option<LiteralSearcher> {
+        let literals = literals_of(&regexp.read().unwrap())?;
+        // Empty matches advance the search in their own way, leave them to the backtracker
+        if literals.iter().any(|literal| literal.is_empty()) {
+            return None;
+        }
+        let mut first_chars = literals
+            .iter()
+            .map(|literal| literal[0])
+            .collect::<Vec<_>>();
+        first_chars.sort_unstable();
+        first_chars.dedup();
+        Some(LiteralSearcher {
+            literals,
+            first_chars,
+        })
+    }
+
+    // First literal in `target` starting at or after `from`
+    pub(crate) fn find(&self, target: &[char], from: usize) -> Option<Range<usize>> {
+        (from..target.len())
+            .filter(|start| self.first_chars.binary_search(&target[*start]).is_ok())
+            .find_map(|start| {
+                self.literals
+                    .iter()
+                    .find(|literal| target[start..].starts_with(literal))
+                    .map(|literal| start..start + literal.len())
+            })
+    }
+}
+
+// Strings matched by `expression` in the order they are tried
+// None if it matches a string which is not fixed or too many of them
+fn literals_of(expression: &ParsedRegexp) -> Option<Vec<Vec<char>>> {
+    let children = expression.children.read().unwrap();
+    match expression.expression_type {
+        ExpressionType::EmptyExpression => Some(vec![vec![]]),
+
+        // Substring search does not check where a string is found
+        ExpressionType::Assertion { .. } | ExpressionType::Lookbehind { .. } => None,
+
+        ExpressionType::CharacterExpression {
+            value: Some(ch),
+            quantifier: Quantifier::None,
+            ..
+        } => Some(vec![vec![ch]]),
+
+        // Captured text is not known before matching
+        ExpressionType::CharacterExpression { .. }
+        | ExpressionType::ShorthandClass { .. }
+        | ExpressionType::Backreference { .. } => None,
+
+        // Each member is a single character
+        ExpressionType::CharacterClass {
+            quantifier: Quantifier::None,
+        } => children
+            .iter()
+            .map(|member| literals_of(&member.read().unwrap()))
+            .try_fold(vec![], |mut literals, member| {
+                literals.extend(member?);
+                Some(literals).filter(|literals| literals.len() <= MAX_LITERALS)
+            }),
+
+        ExpressionType::CharacterClass { .. } => None,
+
+        ExpressionType::CharacterRange { first, last } => Some(
+            (first..=last)
+                .take(MAX_LITERALS + 1)
+                .map(|ch| vec![ch])
+                .collect::<Vec<_>>(),
+        )
+        .filter(|literals| literals.len() <= MAX_LITERALS),
+
+        ExpressionType::Group {
+            quantifier: Quantifier::None,
+            atomic: false,
+            ..
+        } => literals_of(&children[0].read().unwrap()),
+
+        // An atomic group does not try each of its strings with every choice of later items
+        ExpressionType::Group { .. } => None,
+
+        ExpressionType::Concatenation => {
+            let mut literals = vec![vec![]];
+            for (index, child) in children.iter().enumerate() {
+                let following = literals_of(&child.read().unwrap())?;
+                // Once an item matched, the backtracker does not try its other strings
+                // when later items fail, like `(?:a|ab)c` finds nothing in "abc"
+                // thus items followed by others must match at most one string in any position
+                if index + 1 < children.len() && has_prefix_pair(&following) {
+                    return None;
+                }
+                if literals.len() * following.len() > MAX_LITERALS {
+                    return None;
+                }
+                // Backtracker tries each choice of an item with every choice of later items
+                // before moving to next choice of that item
+                literals = literals
+                    .iter()
+                    .flat_map(|first: &Vec<char>| {
+                        following
+                            .iter()
+                            .map(move |second| [&first[..], second].concat())
+                    })
+                    .collect();
+            }
+            Some(literals)
+        }
+
+        ExpressionType::Alternation => {
+            let mut literals = vec![];
+            for child in children.iter() {
+                literals.extend(literals_of(&child.read().unwrap())?);
+                if literals.len() > MAX_LITERALS {
+                    return None;
+                }
+            }
+            Some(literals)
+        }
+    }
+}
+
+// Is one of `literals` a proper prefix of another? Like "a" of "ab"
+fn has_prefix_pair(literals: &[Vec<char>]) -> bool {
+    literals.iter().any(|short| {
+        literals
+            .iter()
+            .any(|long| long.len() > short.len() && long.starts_with(short))
+    })
+}
@@ -1,34 +1,323 @@
 // Use a parsed regular expression to match against strings
 
-use std::sync::{Arc, RwLock};
+// Recording what each subexpression matched
+pub mod submatch;
 
-use crate::parser::{syntax_tree::*, Parser};
+// Recording each step taken while searching
+pub mod steps;
 
-const METACHARACTERS: [char; 7] = ['(', ')', '\\', '|', '*', '.', '?'];
+// Callbacks told about matching events
+pub mod observer;
 
+// Statistics of a single search
+pub mod stats;
+
+// Match positions in UTF-16 code units
+pub mod utf16;
+
+// Match positions in bytes
+pub mod bytes;
+
+// Per-search policies applied to matches
+pub mod options;
+
+// Why a search could not be completed
+pub mod error;
+
+// Describing replacements without applying them
+pub mod preview;
+
+// Replacing matches in text too large to keep in memory
+pub mod stream;
+
+// Replacing matches of many patterns in a single pass
+pub mod multi;
+
+// Searching patterns matching only fixed strings
+mod literal;
+
+// Ranges matched by capturing groups
+mod capture;
+
+use std::cell::OnceCell;
+use std::ops::Range;
+use std::sync::{Arc, OnceLock, RwLock};
+use std::time::Instant;
+
+use crate::parser::{syntax_tree::*, untrusted::UntrustedProfile, Parser};
+use crate::scanner::is_metacharacter;
+use crate::{report_fatal_error, telemetry};
+use bytes::ByteMap;
+use capture::CaptureSlots;
+use error::MatchError;
+use literal::LiteralSearcher;
+use observer::MatchObserver;
+use options::{Matches, SearchOptions};
+use stats::SearchStats;
+use steps::{Step, StepLog};
+use submatch::{SubmatchRecorder, SubmatchTrace};
+
+// Escape `pattern` so that it matches itself literally wherever it is placed in a pattern
+// inside character classes and in free-spacing mode too, see `pattern!`
+// Characters meaning something only in some places, like `-` inside classes
+// or whitespace and `#` in free-spacing mode, can not be escaped with a slash
+// everywhere, they are enclosed by a class of their own instead, like `[-]`
+// which nests inside other classes and is never in free-spacing mode
 pub fn escape(pattern: &str) -> String {
-    // Escape all metacharacters in `pattern`
     let mut escaped = String::with_capacity(
-        // Possible each character is a metacharacter
-        // requiring two slashes
-        3 * pattern.len(),
+        // Possibly each character is enclosed by a class, like `[\]]`
+        4 * pattern.len(),
     );
     for ch in pattern.chars() {
-        if METACHARACTERS.contains(&ch) {
-            // Add a slash to escaped the metacharacter
-            // You need to write one slash BUT Rust needs you to escape this one slash
-            // so actually we need 2 slashes
-            escaped.push('\\'); // Rust escaping slash
-            escaped.push('\\'); // ParsedRegexp escaping slash
+        if ch == ']' {
+            escaped.push_str("[\\]]");
+        } else if ch == '-' || ch == '#' || ch.is_whitespace() {
+            escaped.push('[');
+            escaped.push(ch);
+            escaped.push(']');
+        } else {
+            if is_metacharacter(ch) {
+                // Add a slash to escape the metacharacter
+                escaped.push('\\');
+            }
+            escaped.push(ch);
         }
-        escaped.push(ch);
     }
     escaped.shrink_to_fit();
     escaped
 }
 
-// Match operation outcome
-pub type Match = std::ops::Range<usize>;
+// Build a pattern from a template and runtime values
+// Each `{}` in template is replaced by the next value escaped using `escape`
+// thus values are always matched literally, whatever characters they contain
+// and wherever placeholders are, inside classes like `[{}]` or in free-spacing mode
+// Like `format!`, `{{` and `}}` stand for a literal { and }, other braces are left as they are
+// For instance, pattern!("{}=(on|off)", "a.b") gives pattern `a\.b=(on|off)`
+// Number of values is checked against number of placeholders at compile time
+#[macro_export]
+macro_rules! pattern {
+    ($template:literal $(, $value:expr)* $(,)?) => {{
+        const _: () = assert!(
+            $crate::matcher::count_placeholders($template) == {
+                let values: &[&str] = &[$(stringify!($value)),*];
+                values.len()
+            },
+            "pattern!: number of values differs from number of {{}} placeholders"
+        );
+        $crate::matcher::interpolate(
+            $template,
+            &[$(::std::string::ToString::to_string(&$value)),*],
+        )
+    }};
+}
+
+// Number of `{}` placeholders in a `pattern!` template
+pub const fn count_placeholders(template: &str) -> usize {
+    let bytes = template.as_bytes();
+    let mut count = 0;
+    let mut index = 0;
+    while index < bytes.len() {
+        let pair = index + 1 < bytes.len();
+        if pair && bytes[index] == b'{' && bytes[index + 1] == b'}' {
+            count += 1;
+            index += 2;
+        } else if pair
+            && ((bytes[index] == b'{' && bytes[index + 1] == b'{')
+                || (bytes[index] == b'}' && bytes[index + 1] == b'}'))
+        {
+            index += 2;
+        } else {
+            index += 1;
+        }
+    }
+    count
+}
+
+// Replace `{}` placeholders in `template` with escaped `values` (see `pattern!`)
+pub fn interpolate(template: &str, values: &[String]) -> String {
+    let mut values = values.iter();
+    let mut interpolated = String::with_capacity(template.len());
+    let mut chars = template.chars().peekable();
+    while let Some(ch) = chars.next() {
+        match (ch, chars.peek()) {
+            ('{', Some('}')) => {
+                chars.next();
+                match values.next() {
+                    Some(value) => interpolated.push_str(&escape(value)),
+                    None => report_fatal_error("pattern!: more placeholders than values"),
+                }
+            }
+            ('{', Some('{')) | ('}', Some('}')) => {
+                chars.next();
+                interpolated.push(ch);
+            }
+            _ => interpolated.push(ch),
+        }
+    }
+    interpolated
+}
+
+// Expand replacement `template` for a match whose text is `matched`
+// `$0` is replaced by the matched text and `$$` by a single `$`
+// any other `$` is copied as is
+pub fn expand_replacement(template: &str, matched: &str) -> String {
+    let mut expanded = String::with_capacity(template.len() + matched.len());
+    let mut chars = template.chars().peekable();
+    while let Some(ch) = chars.next() {
+        match (ch, chars.peek()) {
+            ('$', Some('0')) => {
+                chars.next();
+                expanded.push_str(matched);
+            }
+            ('$', Some('$')) => {
+                chars.next();
+                expanded.push('$');
+            }
+            _ => expanded.push(ch),
+        }
+    }
+    expanded
+}
+
+// Steps taken between two readings of the clock when a search has a deadline
+const DEADLINE_CHECK_INTERVAL: usize = 1024;
+
+// Text matched in a haystack, with its position in bytes and in characters
+// Byte offsets slice the haystack, character indices are what searches report
+// and what `captures` holds
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Match<'t> {
+    haystack: &'t str,
+    // Byte offsets
+    start: usize,
+    end: usize,
+    // Character indices
+    char_start: usize,
+    char_end: usize,
+}
+
+impl<'t> Match<'t> {
+    // Match covering character indices `chars` of `haystack`
+    // Indices past the end of haystack are clamped to its end
+    pub fn new(haystack: &'t str, chars: Range<usize>) -> Match<'t> {
+        Match::with_map(haystack, chars, &ByteMap::new(haystack))
+    }
+
+    pub(crate) fn with_map(haystack: &'t str, chars: Range<usize>, map: &ByteMap) -> Match<'t> {
+        let Range { start, end } = map.range(&chars);
+        Match {
+            haystack,
+            start,
+            end,
+            char_start: map.char_index(start).unwrap_or(chars.start),
+            char_end: map.char_index(end).unwrap_or(chars.end),
+        }
+    }
+
+    // Text matched
+    pub fn as_str(&self) -> &'t str {
+        &self.haystack[self.start..self.end]
+    }
+
+    // Byte offset of first character matched
+    pub fn start(&self) -> usize {
+        self.start
+    }
+
+    // Byte offset just past last character matched
+    pub fn end(&self) -> usize {
+        self.end
+    }
+
+    // Byte offsets of match, `&haystack[m.range()]` is `m.as_str()`
+    pub fn range(&self) -> Range<usize> {
+        self.start..self.end
+    }
+
+    // Character indices of match, as reported by searches
+    pub fn char_range(&self) -> Range<usize> {
+        self.char_start..self.char_end
+    }
+
+    // Length of match in bytes
+    pub fn len(&self) -> usize {
+        self.end - self.start
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.start == self.end
+    }
+}
+
+impl std::fmt::Display for Match<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+// Outcome of matching text which may be continued later, see `Matcher::match_partial`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PartialMatch<'t> {
+    // Pattern matches text given so far
+    Complete(Match<'t>),
+    // No match yet, but this text, which extends to end of target,
+    // is a prefix of a possible match, so more input may complete it
+    Partial(Match<'t>),
+    // No match, whatever text comes next
+    NoMatch,
+}
+
+impl PartialMatch<'_> {
+    pub fn is_complete(&self) -> bool {
+        matches!(self, PartialMatch::Complete(_))
+    }
+
+    pub fn needs_more_input(&self) -> bool {
+        matches!(self, PartialMatch::Partial(_))
+    }
+}
+
+// A match found ahead of time and the ranges its groups captured
+type CachedMatch = (Range<usize>, Vec<Option<Range<usize>>>);
+
+// What a Matcher derives from its pattern alone, whatever target it matches
+// Computed once by `Regex` and copied into each Matcher it creates
+#[derive(Debug, Clone)]
+pub(crate) struct CompiledPattern {
+    pattern: Arc<RwLock<ParsedRegexp>>,
+    capture_slots: CaptureSlots,
+    literal_searcher: Option<LiteralSearcher>,
+    // Copies of pattern anchored at start of target, at its end and at both
+    // see `Matcher::anchored_matcher`
+    anchored: AnchoredPatterns,
+}
+
+// Anchored copies of a pattern, each compiled when first needed
+// Shared by all matchers of the same compiled pattern
+type AnchoredPatterns = Arc<[OnceLock<CompiledPattern>; 3]>;
+
+impl CompiledPattern {
+    pub(crate) fn new(pattern: Arc<RwLock<ParsedRegexp>>) -> CompiledPattern {
+        let source = pattern.read().unwrap().pattern.clone();
+        telemetry::compile("matcher", Some(&source), || {
+            // Backtracking info is keyed by node ids
+            // make sure they are assigned even for trees not built by the parser
+            ParsedRegexp::assign_ids(&pattern);
+            let capture_slots = CaptureSlots::new(&pattern);
+            let literal_searcher = LiteralSearcher::new(&pattern);
+            CompiledPattern {
+                pattern,
+                capture_slots,
+                literal_searcher,
+                anchored: AnchoredPatterns::default(),
+            }
+        })
+    }
+
+    pub(crate) fn pattern(&self) -> &Arc<RwLock<ParsedRegexp>> {
+        &self.pattern
+    }
+}
 
 #[allow(dead_code)]
 // If an expression E can backtrack (like a+)
@@ -37,11 +326,8 @@ pub type Match = std::ops::Range<usize>;
 // Matcher can use its last record range to force it
 // to match a smaller range
 struct ExpressionBacktrackInfo {
-    // The last item in this Vec represent the index of current pattern among its siblings
-    // within its level in parsed pattern syntax tree
-    // All other items represent the index of its parents among their siblings within
-    // the their respective parsed pattern syntax tree level
-    index_sequence: Vec<usize>,
+    // Identifier of the associated expression in parsed pattern syntax tree
+    node_id: NodeId,
 
     // Position of last successful match of the associated expression
     last_match_start: usize,
@@ -81,62 +367,172 @@ pub struct Matcher {
 
     next_match_phase: MatchPhase,
 
-    // The last item in this Vec represent the index of current pattern among its siblings in
-    // current syntax tree level
-    // All other items represent the index of its parents amongs their siblings with the same
-    // syntax tree level
-    pattern_index_sequence: Vec<usize>,
-    // Of course, root pattern (ParsedRegexp parsed in Matcher::new) will have Vec
-    // of one 0usize item, because root has no parent and its the zeroth (first) child in its level
-    // For instance, a value of X = vec![0, 3, 4] means that currently processed pattern (subexpression)
-    // is the fourth (X[2]) child within its level
-    // its parent is the third(X[1]) child within the level above
-    // its grandparent is the root (X[0])
-
     // Backtrack info of all subexpressions which can backtrack
+    // sorted by field `node_id`
     backtrack_table: Vec<ExpressionBacktrackInfo>,
 
     // Exclusive upper bound of ongoing match
     // Match fails if gives back a range whose end index >= `match_bound`
     match_bound: usize,
 
-    // Successful matches
-    match_cache: Vec<Match>,
+    // End of last match of a group `(E)` backtracking with a lazy E, like `(.*?)`
+    // E has no backtrack entry of its own, so its next match must end after this
+    match_floor: Option<usize>,
+
+    // Successful matches along with ranges matched by capturing groups
+    match_cache: Vec<CachedMatch>,
+
+    // Add matches to `match_cache`? Searches going once through a target never look
+    // matches up again, they are not cached so that each match allocates nothing
+    caches_matches: bool,
 
     // Target substring containing all matches start index
     matches_substring_start: Option<usize>,
 
     // Target substring containing all matches end index
     matches_substring_end: usize,
+
+    // Ranges matched by subexpressions during ongoing match
+    // None unless sub-match tracing is enabled
+    submatch_recorder: Option<SubmatchRecorder>,
+
+    // Sub-match trace of most recent match
+    last_submatch_trace: Option<SubmatchTrace>,
+
+    // Ranges matched by capturing groups during ongoing match
+    capture_slots: CaptureSlots,
+
+    // Captures of most recent match, see `captures`
+    last_captures: Vec<Option<Range<usize>>>,
+
+    // Steps taken by most recent search, None unless step logging is enabled
+    step_log: Option<StepLog>,
+
+    // Told about attempts and backtracking, if any
+    observer: Option<Box<dyn MatchObserver>>,
+
+    // Statistics of ongoing search, only collected by `find_with_stats`
+    stats: Option<SearchStats>,
+
+    // Most subexpression match attempts a single search may take, None for no limit
+    step_limit: Option<usize>,
+
+    // Subexpression match attempts taken by ongoing search
+    steps_taken: usize,
+
+    // Ongoing search is abandoned once this instant has passed, see `find_with_deadline`
+    deadline: Option<Instant>,
+
+    // Why most recent search was abandoned, if it was
+    // like exceeding `step_limit` or `deadline`
+    abandoned: Option<MatchError>,
+
+    // True if ongoing attempt needed characters past end of target, see `match_partial`
+    hit_end: bool,
+
+    // Substring searcher used instead of backtracking
+    // if the pattern matches only a few fixed strings
+    literal_searcher: Option<LiteralSearcher>,
+
+    // Copies of pattern anchored at start or end of target, see `anchored_matcher`
+    anchored: AnchoredPatterns,
+
+    // Byte offsets of target characters, built when first needed, see `byte_range`
+    byte_map: OnceCell<ByteMap>,
 }
 
 impl Matcher {
     // Create a new matcher from `pattern`
     // which is matched against `target`
     pub fn new(pattern: &str, target: &str) -> Result<Matcher, String> {
-        let pattern = Parser::parse(pattern)?;
-        let target = target.chars().collect::<Vec<_>>();
+        Ok(Matcher::from_regexp(Parser::parse(pattern)?, target))
+    }
+
+    // Create a new matcher from `pattern` supplied by an untrusted user
+    // Pattern must follow `profile` and searches are limited to its step budget
+    pub fn new_untrusted(
+        pattern: &str,
+        target: &str,
+        profile: &UntrustedProfile,
+    ) -> Result<Matcher, String> {
+        let mut matcher = Matcher::from_regexp(Parser::parse_untrusted(pattern, profile)?, target);
+        matcher.set_step_limit(Some(profile.step_limit));
+        Ok(matcher)
+    }
+
+    // Create a new matcher from an already parsed pattern
+    // which is matched against `target`
+    pub fn from_regexp(pattern: Arc<RwLock<ParsedRegexp>>, target: &str) -> Matcher {
+        Matcher::from_compiled(&CompiledPattern::new(pattern), target)
+    }
+
+    // Create a new matcher from a pattern compiled beforehand
+    // which is matched against `target`
+    pub(crate) fn from_compiled(compiled: &CompiledPattern, target: &str) -> Matcher {
+        Matcher::with_target(compiled, target.chars().collect())
+    }
+
+    fn with_target(compiled: &CompiledPattern, target: Vec<char>) -> Matcher {
+        let pattern = Arc::clone(&compiled.pattern);
         let pos = 0;
         let next_match_phase = MatchPhase::Normal;
-        let pattern_index_sequence = vec![];
         let backtrack_table = vec![];
         let match_bound = target.len() + 1;
+        let match_floor = None;
         let match_cache = vec![];
+        let caches_matches = true;
         let matches_substring_start = Option::<usize>::None;
         let matches_substring_end = 0;
-
-        Ok(Matcher {
+        let submatch_recorder = None;
+        let last_submatch_trace = None;
+        let capture_slots = compiled.capture_slots.clone();
+        let last_captures = vec![];
+        let step_log = None;
+        let observer = None;
+        let stats = None;
+        let step_limit = None;
+        let steps_taken = 0;
+        let deadline = None;
+        let abandoned = None;
+        let hit_end = false;
+        let literal_searcher = compiled.literal_searcher.clone();
+        let anchored = Arc::clone(&compiled.anchored);
+        let byte_map = OnceCell::new();
+
+        Matcher {
             pattern,
             target,
             pos,
             next_match_phase,
-            pattern_index_sequence,
             backtrack_table,
             match_bound,
+            match_floor,
             match_cache,
+            caches_matches,
             matches_substring_start,
             matches_substring_end,
-        })
+            submatch_recorder,
+            last_submatch_trace,
+            capture_slots,
+            last_captures,
+            step_log,
+            observer,
+            stats,
+            step_limit,
+            steps_taken,
+            deadline,
+            abandoned,
+            hit_end,
+            literal_searcher,
+            anchored,
+            byte_map,
+        }
+    }
+
+    // Matcher which does not cache its matches, for searches going once through target
+    pub(crate) fn without_match_cache(mut self) -> Matcher {
+        self.caches_matches = false;
+        self
     }
 
     // Current "normalized" position
@@ -164,6 +560,8 @@ impl Matcher {
     // Assign a new target to match on
     pub fn assign_match_target(&mut self, target: &str) {
         self.target = target.chars().collect();
+        self.byte_map = OnceCell::new();
+        self.match_bound = self.target.len() + 1;
         self.match_cache.clear();
         self.reset();
     }
@@ -171,6 +569,9 @@ impl Matcher {
     // Assign a new pattern to match against
     pub fn assign_pattern_string(&mut self, pattern: &str) -> Result<(), String> {
         self.pattern = Parser::parse(pattern)?;
+        self.literal_searcher = LiteralSearcher::new(&self.pattern);
+        self.anchored = AnchoredPatterns::default();
+        self.capture_slots = CaptureSlots::new(&self.pattern);
         self.match_cache.clear();
         self.reset();
         Ok(())
@@ -182,6 +583,9 @@ impl Matcher {
             let regexp = regexp.read().unwrap();
             regexp.deep_copy()
         };
+        self.literal_searcher = LiteralSearcher::new(&self.pattern);
+        self.anchored = AnchoredPatterns::default();
+        self.capture_slots = CaptureSlots::new(&self.pattern);
         self.match_cache.clear();
         self.reset();
     }
@@ -196,12 +600,137 @@ impl Matcher {
         self.set_position(position);
         // Back to normal matching mode (processing target)
         self.next_match_phase = MatchPhase::Normal;
-        // Stop tracking expressions
-        self.pattern_index_sequence.clear();
         // Do not use old backtrack info
         self.backtrack_table.clear();
     }
 
+    // Enable or disable sub-match tracing
+    // When enabled, each successful match also records which range of target
+    // each subexpression matched, see `submatch_trace`
+    pub fn trace_submatches(&mut self, enabled: bool) {
+        self.submatch_recorder = if enabled {
+            Some(SubmatchRecorder::default())
+        } else {
+            None
+        };
+        self.last_submatch_trace = None;
+    }
+
+    // Tree mirroring the pattern syntax tree where each subexpression holds
+    // the range it matched in the most recent match
+    // None if sub-match tracing is disabled or most recent search found no match
+    pub fn submatch_trace(&self) -> Option<&SubmatchTrace> {
+        self.last_submatch_trace.as_ref()
+    }
+
+    // Range matched by each capturing group in most recent match, see module `capture`
+    // index 0 holds the whole match and index i holds group i
+    // Empty if most recent search found no match
+    pub fn captures(&self) -> &[Option<Range<usize>>] {
+        &self.last_captures
+    }
+
+    // Byte offsets of `m`, a match in target, slicing the target string
+    // on character boundaries, `&target[matcher.byte_range(&m)]` is the text matched
+    pub fn byte_range(&self, m: &Range<usize>) -> Range<usize> {
+        self.byte_map().range(m)
+    }
+
+    // Byte offsets of ranges in `captures`, index 0 holds the whole match
+    pub fn byte_captures(&self) -> Vec<Option<Range<usize>>> {
+        let map = self.byte_map();
+        self.last_captures
+            .iter()
+            .map(|capture| capture.as_ref().map(|capture| map.range(capture)))
+            .collect()
+    }
+
+    pub(crate) fn byte_map(&self) -> &ByteMap {
+        self.byte_map
+            .get_or_init(|| ByteMap::from_chars(self.target.iter().copied()))
+    }
+
+    // Enable or disable step logging
+    // When enabled, each search records every step it takes, see `step_log`
+    pub fn log_steps(&mut self, enabled: bool) {
+        self.step_log = if enabled {
+            Some(StepLog::default())
+        } else {
+            None
+        };
+    }
+
+    // Steps taken by most recent search (call to `next`)
+    // None if step logging is disabled
+    pub fn step_log(&self) -> Option<&StepLog> {
+        self.step_log.as_ref()
+    }
+
+    // Register `observer` to be told about matching events, replacing previous one (if any)
+    pub fn set_observer(&mut self, observer: Box<dyn MatchObserver>) {
+        self.observer = Some(observer);
+    }
+
+    // Unregister current observer (if any) and give it back
+    pub fn take_observer(&mut self) -> Option<Box<dyn MatchObserver>> {
+        self.observer.take()
+    }
+
+    // Abandon a search once it has attempted to match subexpressions `limit` times
+    // An abandoned search finds nothing and ends iteration over matches
+    // use `try_find` and other `try_` methods to tell it apart from a search which found nothing
+    pub fn set_step_limit(&mut self, limit: Option<usize>) {
+        self.step_limit = limit;
+    }
+
+    pub fn step_limit(&self) -> Option<usize> {
+        self.step_limit
+    }
+
+    // Error if most recent search was abandoned
+    fn abandoned_error(&self) -> Result<(), MatchError> {
+        self.abandoned.map_or(Ok(()), Err)
+    }
+
+    // Record a step if step logging is enabled
+    #[inline(always)]
+    fn log_step(&mut self, step: impl FnOnce() -> Step) {
+        if let Some(log) = &mut self.step_log {
+            log.steps.push(step());
+        }
+    }
+
+    // Can matches only begin at start of target? see ParsedRegexp::is_anchored_start
+    pub fn is_anchored_start(&self) -> bool {
+        self.pattern.read().unwrap().is_anchored_start()
+    }
+
+    // Text every match begins with, see ParsedRegexp::literal_prefix
+    pub fn literal_prefix(&self) -> String {
+        self.pattern.read().unwrap().literal_prefix()
+    }
+
+    // Number of capturing groups, see ParsedRegexp::capture_count
+    pub fn capture_count(&self) -> usize {
+        self.pattern.read().unwrap().capture_count()
+    }
+
+    // Length of shortest possible match, in characters
+    pub fn min_match_len(&self) -> usize {
+        self.pattern.read().unwrap().min_match_len()
+    }
+
+    // Length of longest possible match, in characters
+    // None if matches can be arbitrarily long
+    pub fn max_match_len(&self) -> Option<usize> {
+        self.pattern.read().unwrap().max_match_len()
+    }
+
+    // Literals every match contains, see ParsedRegexp::required_literals
+    pub fn required_literals(&self) -> Vec<String> {
+        self.pattern.read().unwrap().required_literals()
+    }
+
     fn supports_backtracking(expr: &Arc<RwLock<ParsedRegexp>>) -> bool {
         // An arbitrary expression E supports backtracking if:
         // 1 - It's quantified, in other words it's succeeded by a quantifier, like `.*`
@@ -214,8 +743,15 @@ impl Matcher {
             // It doesn't need backtracking
             ExpressionType::EmptyExpression => false,
 
-            ExpressionType::CharacterExpression { quantifier, .. } => {
-                // . or x are quantified
+            // An assertion matches the empty string or nothing at all
+            // and so does a lookbehind, whatever its child matched
+            ExpressionType::Assertion { .. } | ExpressionType::Lookbehind { .. } => false,
+
+            ExpressionType::CharacterExpression { quantifier, .. }
+            | ExpressionType::ShorthandClass { quantifier, .. }
+            | ExpressionType::CharacterClass { quantifier }
+            | ExpressionType::Backreference { quantifier, .. } => {
+                // . or x or \d or [xy] or \1 are quantified
 
                 // It's not the case that this expression has no quantifier
                 // in other words, it's quantified with one of ? \ * \ +
@@ -223,14 +759,17 @@ impl Matcher {
                 // Variant Quantifier::None represent the idea of `no quantifier`
             }
 
-            ExpressionType::Group { quantifier } => {
+            ExpressionType::Group {
+                quantifier, atomic, ..
+            } => {
                 // The group itself is quantified or the grouped expression
-                // inside supports backtracking
+                // inside supports backtracking, unless the group is atomic
 
                 // It's not the case that this expression has no quantifier
                 // in other words, it's quantified with one of ? \ * \ +
                 !matches!(quantifier, Quantifier::None)
-                    || Self::supports_backtracking(&parsed_expr.children.read().unwrap()[0])
+                    || (!atomic
+                        && Self::supports_backtracking(&parsed_expr.children.read().unwrap()[0]))
                 // Variant Quantifier::None represent the idea of `no quantifier`
             }
 
@@ -248,19 +787,70 @@ impl Matcher {
     }
 
     // ALL EXPRESSIONS MUST RESTORE OLD POSITION WHEN FAILING TO MATCH
-    fn compute_match(&mut self) -> Option<Match> {
+    fn compute_match(&mut self) -> Option<Range<usize>> {
+        self.steps_taken += 1;
+        if self.abandoned.is_none() {
+            if let Some(limit) = self.step_limit.filter(|limit| self.steps_taken > *limit) {
+                self.abandoned = Some(MatchError::StepLimitExceeded { limit });
+            } else if self.steps_taken % DEADLINE_CHECK_INTERVAL == 1
+                && self
+                    .deadline
+                    .is_some_and(|deadline| Instant::now() >= deadline)
+            {
+                // Reading the clock is slow, so it is read only every so often
+                self.abandoned = Some(MatchError::DeadlineExceeded);
+            }
+        }
+        if self.abandoned.is_some() {
+            // Fail everything so that the search unwinds quickly
+            return None;
+        }
+
         let parsed_pattern = Arc::clone(&self.pattern);
         let parsed_pattern = parsed_pattern.read().unwrap();
         let pattern_type = parsed_pattern.expression_type;
 
+        // Ranges recorded by this expression and its descendants before this attempt
+        let submatch_snapshot = self
+            .submatch_recorder
+            .as_ref()
+            .map(|recorder| recorder.snapshot(&parsed_pattern));
+        let capture_snapshot = self.capture_slots.snapshot(&parsed_pattern);
+
+        let position = self.current();
+        self.log_step(|| Step::Enter {
+            id: parsed_pattern.id,
+            pattern: Arc::clone(&parsed_pattern.pattern),
+            position,
+        });
+
         let computed_match = match pattern_type {
             ExpressionType::EmptyExpression => self.empty_expression_match(),
 
-            ExpressionType::CharacterExpression { value, quantifier } => {
-                self.character_expression_match(value, quantifier)
+            ExpressionType::Assertion { kind } => self.assertion_match(kind),
+
+            ExpressionType::Lookbehind { negative } => self.lookbehind_match(negative),
+
+            ExpressionType::CharacterExpression { quantifier, .. }
+            | ExpressionType::ShorthandClass { quantifier, .. }
+            | ExpressionType::CharacterClass { quantifier } => {
+                self.character_expression_match(&parsed_pattern, quantifier)
             }
 
-            ExpressionType::Group { quantifier } => self.group_match(quantifier),
+            // Ranges are matched as part of their classes, never on their own
+            ExpressionType::CharacterRange { .. } => {
+                self.character_expression_match(&parsed_pattern, Quantifier::None)
+            }
+
+            ExpressionType::Group {
+                quantifier, atomic, ..
+            } => self.group_match(quantifier, atomic),
+
+            ExpressionType::Backreference {
+                group,
+                quantifier,
+                case_insensitive,
+            } => self.backreference_match(group, quantifier, case_insensitive),
 
             ExpressionType::Alternation => self.alternation_match(),
             ExpressionType::Concatenation => self.concatenation_match(),
@@ -288,24 +878,20 @@ impl Matcher {
         // If current expression successfully matched AND
         // It can backtrack (like .?) AND
         // It's not root expression (it makes no sense to have root expression request a backtrack, it has no siblings)
-        if computed_match.is_some()
-            && Self::supports_backtracking(&self.pattern)
+        if let Some(Range { start, end }) = computed_match.clone().filter(|_| {
+            Self::supports_backtracking(&self.pattern)
             // Root expression does not backtrack
             && parsed_pattern.parent.is_some()
             && expression_not_grouped
-        {
+        }) {
             // Record first match info for later use when backtracking
-
-            let (start, end) = {
-                let temp = computed_match.as_ref().unwrap();
-                (temp.start, temp.end)
-            };
+            let lazy = Self::is_lazy(&parsed_pattern);
 
             // Attempt to find current expression info entry
-            let search_index = self.backtrack_table.binary_search_by(|info_entry| {
-                info_entry.index_sequence.cmp(&self.pattern_index_sequence)
-            });
-            match search_index {
+            let search_index = self
+                .backtrack_table
+                .binary_search_by(|info_entry| info_entry.node_id.cmp(&parsed_pattern.id));
+            let table_entry_index = match search_index {
                 Ok(item_index) => {
                     // Found entry
                     let expr_info = &mut self.backtrack_table[item_index];
@@ -316,46 +902,119 @@ impl Matcher {
                     // When matching, expression `last_match_end - 1` is used as current bound match
                     // so if the expression made a match, variable `end` will have smaller value
                     // than field `last_match_end` because end it's at most (last_match_end - 1)
-                    expr_info.backtracked_to_last_match_start = start == end;
+                    // Lazy expressions grow instead, see `lazy_floor`
+                    expr_info.backtracked_to_last_match_start = start == end && !lazy;
+                    item_index
                 }
                 Err(insertion_index) => {
                     // This expression never matched before
                     // Insert a new info entry while maintaining order of all entries
                     // Insert at index found by binary search stored in `search_index`
-                    // Entries (ExpressionBacktrackInfo objects) are sorted by field 'index_sequence'
+                    // Entries (ExpressionBacktrackInfo objects) are sorted by field 'node_id'
 
                     self.backtrack_table.insert(
                         insertion_index,
                         ExpressionBacktrackInfo {
-                            index_sequence: self.pattern_index_sequence.clone(),
+                            node_id: parsed_pattern.id,
                             last_match_start: start,
                             last_match_end: end,
-                            backtracked_to_last_match_start: start == end,
+                            backtracked_to_last_match_start: start == end && !lazy,
                         },
-                    )
+                    );
+                    insertion_index
                 }
+            };
+            self.log_table_update(table_entry_index);
+        }
+
+        self.log_step(|| Step::Exit {
+            id: parsed_pattern.id,
+            matched: computed_match.clone(),
+        });
+
+        if let (Some(recorder), Some(snapshot)) = (&mut self.submatch_recorder, submatch_snapshot) {
+            match &computed_match {
+                Some(matched) => recorder.record(snapshot, matched.clone()),
+                // A failed attempt must not leave any traces
+                None => recorder.restore(snapshot),
             }
         }
+        // Groups record what they matched themselves, see `grouped_expression_match`
+        if let (None, Some(snapshot)) = (&computed_match, capture_snapshot) {
+            self.capture_slots.restore(&snapshot);
+        }
 
         computed_match
     }
 
-    #[inline(always)]
-    fn dive(&mut self) {
-        // Begin matching a child of current patttern
-        self.pattern_index_sequence.push(0);
+    // Record current state of backtrack table entry at `index`
+    fn log_table_update(&mut self, index: usize) {
+        if let Some(log) = &mut self.step_log {
+            let entry = &self.backtrack_table[index];
+            log.steps.push(Step::TableUpdate {
+                id: entry.node_id,
+                last_match_start: entry.last_match_start,
+                last_match_end: entry.last_match_end,
+                backtracked_to_last_match_start: entry.backtracked_to_last_match_start,
+            });
+        }
     }
 
     #[inline(always)]
-    fn bubble_up(&mut self) {
-        // Done matching current pattern, go up back to its parent
-        self.pattern_index_sequence.pop();
+    fn current_node_id(&self) -> NodeId {
+        // Identifier of currently processed pattern
+        self.pattern.read().unwrap().id
     }
 
-    #[inline(always)]
-    fn appoint_next_child(&mut self) {
-        // Begin matching a sibling of current pattern
-        *self.pattern_index_sequence.last_mut().unwrap() += 1;
+    // Index of backtrack entry (in `self.backtrack_table`) of currently processed pattern, if any
+    fn table_entry_index(&self) -> Option<usize> {
+        let node_id = self.current_node_id();
+        self.backtrack_table
+            .binary_search_by(|info_entry| info_entry.node_id.cmp(&node_id))
+            .ok()
+    }
+
+    // LAZY EXPRESSIONS:
+    // x*? \ .+? \ [xy]?? \ (E){2,5}? and so on
+    // They match the shortest range they can, and each time they backtrack
+    // the shortest range ending after their previous match
+    // Once no such range exists they are marked as backtracked to their last match start
+    // and their next match starts over from the shortest range
+    // A group backtracks like what it encloses if that is lazy, `(.*?)` like `.*?`
+    // but lazy expressions deeper inside groups, like `.*?` in `(x.*?)`, never grow
+    // when something after the group fails
+
+    // Is `expr` lazy? Groups without quantifiers are as lazy as what they enclose
+    // so `(.*?)` backtracks just like `.*?`
+    fn is_lazy(expr: &ParsedRegexp) -> bool {
+        match expr.expression_type {
+            ExpressionType::Group {
+                quantifier: Quantifier::None,
+                atomic: false,
+                ..
+            } => Self::is_lazy(&expr.children.read().unwrap()[0].read().unwrap()),
+            expr_type => expr_type.quantifier().is_lazy(),
+        }
+    }
+
+    // End of last match of a lazy expression which its next match must go past
+    // None if the expression is not backtracking from current position, then it starts over
+    // Lazy expressions inside groups have no backtrack entries, they use `match_floor` instead
+    fn lazy_floor(&mut self, table_entry_index: Option<usize>) -> Option<usize> {
+        let Some(index) = table_entry_index else {
+            return self.match_floor.take();
+        };
+        let entry = &self.backtrack_table[index];
+        (!entry.backtracked_to_last_match_start && entry.last_match_start == self.current())
+            .then_some(entry.last_match_end)
+    }
+
+    // Mark a lazy expression which failed to match as having no longer match left to try
+    fn exhaust_lazy(&mut self, table_entry_index: Option<usize>) {
+        if let Some(index) = table_entry_index {
+            self.backtrack_table[index].backtracked_to_last_match_start = true;
+            self.log_table_update(index);
+        }
     }
 
     // EMPTY EXPRESSIONS:
@@ -369,30 +1028,103 @@ impl Matcher {
 
     // Always match
     #[inline(always)]
-    fn empty_expression_match(&mut self) -> Option<Match> {
+    fn empty_expression_match(&mut self) -> Option<Range<usize>> {
         // BUT ensure Matcher advances (call self.advance) later
         // otherwise Matcher would loop endlessly matching the empty string
         // at the same position because empty expression match NEVER fails
         let current = self.current();
-        Some(Match {
+        Some(Range {
+            start: current,
+            end: current,
+        })
+    }
+
+    // ASSERTIONS:
+    // ^ \ $ \ \A \ \Z
+
+    // Match the empty string at current position if the assertion holds there
+    // consuming no characters, see Assertion::holds
+    #[inline(always)]
+    fn assertion_match(&mut self, kind: Assertion) -> Option<Range<usize>> {
+        let current = self.current();
+        let holds = kind.holds(&self.target, current);
+        // Whether a word boundary holds at end of target depends on what comes next
+        if !holds
+            && current == self.target.len()
+            && matches!(kind, Assertion::WordBoundary | Assertion::NonWordBoundary)
+        {
+            self.hit_end = true;
+        }
+        holds.then_some(Range {
+            start: current,
+            end: current,
+        })
+    }
+
+    // LOOKBEHINDS:
+    // (?<=E) \ (?<!E), where E is an expression matching strings of a single length
+    // for instance, (?<=\$)\d+ matches digits right after a dollar sign
+
+    // HOW TO MATCH A LOOKBEHIND:
+    // Match E starting as many characters before current position as E matches
+    // and ending no later than current position, like an atomic group
+    // A lookbehind holds if E matched, or if it's negative, if E did not
+    // Either way it matches the empty string at current position
+    // Capturing groups in E keep what they captured only if a positive lookbehind holds
+    // a failing lookbehind forgets it like any expression failing to match, see `compute_match`
+    fn lookbehind_match(&mut self, negative: bool) -> Option<Range<usize>> {
+        let current = self.current();
+        let old_pattern = Arc::clone(&self.pattern);
+        self.pattern = Arc::clone(&old_pattern.read().unwrap().children.read().unwrap()[0]);
+        let length = self.pattern.read().unwrap().min_match_len();
+
+        let inner_expression_match = if length <= current {
+            self.set_position(current - length);
+            let old_match_bound = std::mem::replace(&mut self.match_bound, current);
+            let old_match_floor = self.match_floor.take();
+            let inner_expression_match = self.compute_match();
+            self.match_bound = old_match_bound;
+            self.match_floor = old_match_floor;
+            self.set_position(current);
+
+            // E never matches again, whatever follows this lookbehind
+            let descendants = {
+                let inner_expression = self.pattern.read().unwrap();
+                inner_expression.id..=inner_expression.last_descendant_id()
+            };
+            self.backtrack_table
+                .retain(|info_entry| !descendants.contains(&info_entry.node_id));
+            inner_expression_match
+        } else {
+            // Too few characters before current position
+            None
+        };
+
+        // Restore parent pattern to process remaining siblings of current pattern
+        self.pattern = old_pattern;
+
+        (inner_expression_match.is_some() != negative).then_some(Range {
             start: current,
             end: current,
         })
     }
 
-    // CHARACTER & DOT EXPRESSIONS:
+    // CHARACTER & DOT & CHARACTER CLASS EXPRESSIONS:
     // x \ x? \ x* \ x+
     // . \ .? \ .* \ .+
+    // [xyz] \ [xyz]? \ [xyz]* \ [xyz]+
     // x is a single character
     // Also, x is not a metacharacter or it's an escaped metacharacter
     // metacharacters are defined in file `grammar`
     // for instance, k+ is a character expression
 
-    // HOW TO MATCH CHARACTER & DOT EXPRESSIONS?
+    // HOW TO MATCH CHARACTER & DOT & CHARACTER CLASS EXPRESSIONS?
     // If field `value`, found in field `tag` of this expression, is Option::<char>::None
     // then this character expression is actually a dot expression
     // If this expression is `.`, consume a single character
     // If this expression `x`, consume a single `x` only if current character is `x`
+    // If this expression is `[xyz]`, consume a single character only if it is one of x, y, z
+    // see ParsedRegexp::matches_char
 
     // For any other backtracking character (or dot) expression (a . or an arbitrary character followed by one of ? \ * \ +)
     // if the expression NEVER matched before
@@ -406,34 +1138,34 @@ impl Matcher {
     // Return Option::<std::ops::Range>::None on failure
     fn character_expression_match(
         &mut self,
-        value: Option<char>,
+        expression: &ParsedRegexp,
         quantifier: Quantifier,
-    ) -> Option<Match> {
+    ) -> Option<Range<usize>> {
+        let is_dot = matches!(
+            expression.expression_type,
+            ExpressionType::CharacterExpression { value: None, .. }
+        );
+        // Find backtrack entry (in self.backtrack_table) of this character/dot expression
+        let table_entry_index = self.table_entry_index();
         let old_match_bound = self.match_bound;
-        self.match_bound = {
-            // Find backtrack entry (in self.backtrack_table) of this character/dot expression
-            let table_entry_index = self.backtrack_table.binary_search_by(|info_entry| {
-                info_entry.index_sequence.cmp(&self.pattern_index_sequence)
-            });
-            match table_entry_index {
-                // This expression matched/backtracked before
-                Ok(entry_index) => {
-                    // Subtract one, if possible, from last match end index
-                    // to force this expression to match a smaller range
-                    self.backtrack_table[entry_index]
-                        .last_match_end
-                        .saturating_sub(1)
-                }
-                // This expression NEVER matched/backtracked before
-                _ => old_match_bound,
-            }
+        self.match_bound = match table_entry_index {
+            // This expression matched/backtracked before
+            // Subtract one, if possible, from last match end index
+            // to force this expression to match a smaller range
+            // unless it's lazy, then it matches a longer range instead
+            Some(entry_index) if !quantifier.is_lazy() => self.backtrack_table[entry_index]
+                .last_match_end
+                .saturating_sub(1),
+            // This expression NEVER matched/backtracked before
+            _ => old_match_bound,
         };
 
         let expr_match = match quantifier {
             Quantifier::None | Quantifier::ZeroOrOne => {
-                // Match `x`\`x?` (value = Some('x')) or `.`\`.?` (value = None)
-                if self.has_next() && (value.is_none() || self.target[self.pos] == value.unwrap()) {
-                    Option::<Match>::Some(Match {
+                // Match `x`\`x?` (value = Some('x')) or `.`\`.?` (value = None) or `[xy]`\`[xy]?`
+                self.hit_end |= !self.has_next();
+                if self.has_next() && expression.matches_char(self.target[self.pos]) {
+                    Option::<Range<usize>>::Some(Range {
                         start: self.current(),
                         end: {
                             self.advance();
@@ -441,37 +1173,94 @@ impl Matcher {
                         },
                     })
                 } else if matches!(quantifier, Quantifier::None) {
-                    Option::<Match>::None
+                    Option::<Range<usize>>::None
                 } else {
                     self.empty_expression_match()
                 }
             }
 
-            _ => {
-                // Match `x*` \ `x+` (value = Some('x')) or `.*` \ `.+` (value = None)
+            Quantifier::ZeroOrMore | Quantifier::OneOrMore | Quantifier::Counted { .. } => {
+                // Match `x*` \ `x+` \ `x{2,5}` (value = Some('x')) or `.*` \ `.+` \ `.{2,5}` (value = None)
+                // or `[xy]*` \ `[xy]+` \ `[xy]{2,5}`
+                let (min, max) = quantifier.bounds();
                 let start = self.current();
-                if value.is_none() {
-                    // Matching `.*` or `.+`
-                    // Just move `self.pos`
-                    self.set_position(self.match_bound.saturating_sub(1));
-                } else {
-                    let value = value.unwrap();
+                // Never move past `limit`, the end of the longest allowed match
+                let limit = max.map_or(usize::MAX, |max| start.saturating_add(max));
+                if !is_dot {
                     while let Some(target_char) = self.target.get(self.pos) {
-                        if *target_char != value || self.pos >= self.match_bound {
+                        if !expression.matches_char(*target_char)
+                            || self.pos >= self.match_bound
+                            || self.pos >= limit
+                        {
                             break;
                         }
                         self.advance();
                     }
+                } else {
+                    // Matching `.*` or `.+`
+                    // Just move `self.pos` as far as the loop above would go
+                    // that is up to match bound, but not beyond target end or a line terminator
+                    let bound = start.max(self.match_bound.min(self.target.len()).min(limit));
+                    let line_end = self.target[start..bound]
+                        .iter()
+                        .position(|ch| !expression.matches_char(*ch))
+                        .map_or(bound, |offset| start + offset);
+                    self.set_position(line_end);
                 }
                 let end = self.current();
-
-                if start < end {
-                    Option::<Match>::Some(Match { start, end })
-                } else if matches!(quantifier, Quantifier::ZeroOrMore) {
+                // Stopped only because target ended
+                self.hit_end |= end == self.target.len() && end < self.match_bound && end < limit;
+
+                if end - start < min {
+                    // Too few repetitions, or match bound exceeded/reached, abort
+                    self.set_position(start);
+                    Option::<Range<usize>>::None
+                } else if start < end {
+                    Option::<Range<usize>>::Some(Range { start, end })
+                } else {
                     self.empty_expression_match()
+                }
+            }
+
+            Quantifier::Lazy { min, max } => {
+                // Match `x*?` \ `x+?` \ `x{2,5}?` (value = Some('x')) or `.*?` \ `.+?` \ `.{2,5}?` (value = None)
+                // or `[xy]*?` \ `[xy]+?` \ `[xy]{2,5}?`
+                // Consume as few characters as allowed, or when backtracking
+                // one character more than last time
+                let floor = self.lazy_floor(table_entry_index);
+                let start = self.current();
+                let limit = max.map_or(usize::MAX, |max| start.saturating_add(max));
+                let mut consumed = true;
+                while self.pos - start < min || floor.is_some_and(|floor| self.pos <= floor) {
+                    match self.target.get(self.pos) {
+                        Some(target_char)
+                            if expression.matches_char(*target_char)
+                                && self.pos < self.match_bound
+                                && self.pos < limit =>
+                        {
+                            self.advance()
+                        }
+                        None => {
+                            self.hit_end = true;
+                            consumed = false;
+                            break;
+                        }
+                        _ => {
+                            consumed = false;
+                            break;
+                        }
+                    }
+                }
+                if consumed {
+                    Option::<Range<usize>>::Some(Range {
+                        start,
+                        end: self.current(),
+                    })
                 } else {
-                    // Match bound exceeded/reached, abort
-                    Option::<Match>::None
+                    // Too few characters, or no longer match left to try
+                    self.set_position(start);
+                    self.exhaust_lazy(table_entry_index);
+                    Option::<Range<usize>>::None
                 }
             }
         };
@@ -481,6 +1270,167 @@ impl Matcher {
         expr_match
     }
 
+    // BACKREFERENCES:
+    // \1 \ \1? \ \1* \ \1+ and so on, up to \9
+    // for instance, (a|b)\1 matches "aa" and "bb" but not "ab"
+
+    // HOW TO MATCH A BACKREFERENCE:
+    // Find the text most recently captured by its group (see module `capture`)
+    // and fail if that group captured nothing
+    // Then match that text the way a character expression matches its character
+    // each repetition consumes the whole text, thus backtracking gives back whole repetitions
+
+    // Return Option::<std::ops::Range>::Some(...) on success
+    // Return Option::<std::ops::Range>::None on failure
+    fn backreference_match(
+        &mut self,
+        group: usize,
+        quantifier: Quantifier,
+        case_insensitive: bool,
+    ) -> Option<Range<usize>> {
+        // Find backtrack entry (in self.backtrack_table) of this backreference
+        let table_entry_index = self.table_entry_index();
+        let old_match_bound = self.match_bound;
+        self.match_bound = match table_entry_index {
+            // Match fewer repetitions than last time, unless lazy
+            Some(entry_index) if !quantifier.is_lazy() => self.backtrack_table[entry_index]
+                .last_match_end
+                .saturating_sub(1),
+            _ => old_match_bound,
+        };
+        let floor = if quantifier.is_lazy() {
+            self.lazy_floor(table_entry_index)
+        } else {
+            None
+        };
+
+        let start = self.current();
+        let captured = self.capture_slots.slots()[group - 1].clone();
+        let length = captured.as_ref().map_or(0, |captured| captured.len());
+        let same_text = |text: &[char], other: &[char]| {
+            if case_insensitive {
+                text.len() == other.len()
+                    && std::iter::zip(text, other).all(|(a, b)| equal_ignoring_case(*a, *b))
+            } else {
+                text == other
+            }
+        };
+        // Does captured text come next at `pos`, ending at or before `bound`?
+        let follows = |matcher: &mut Matcher, pos: usize, bound: usize| {
+            captured.as_ref().is_some_and(|captured| {
+                let len = matcher.target.len();
+                // Target ends in the middle of captured text
+                matcher.hit_end |= len < pos + length
+                    && pos + length <= bound
+                    && same_text(
+                        &matcher.target[pos..],
+                        &matcher.target[captured.start..][..len - pos],
+                    );
+                pos + length <= bound.min(len)
+                    && same_text(
+                        &matcher.target[pos..pos + length],
+                        &matcher.target[captured.clone()],
+                    )
+            })
+        };
+
+        let backreference_match = match quantifier {
+            _ if captured.is_none() && quantifier.bounds().0 > 0 => {
+                // Group captured nothing, thus none of its repetitions can match
+                // Quantifiers allowing zero repetitions match empty string below
+                if quantifier.is_lazy() {
+                    self.exhaust_lazy(table_entry_index);
+                }
+                Option::<Range<usize>>::None
+            }
+
+            Quantifier::None => {
+                if follows(self, start, usize::MAX) {
+                    self.set_position(start + length);
+                    Some(Range {
+                        start,
+                        end: self.current(),
+                    })
+                } else {
+                    Option::<Range<usize>>::None
+                }
+            }
+
+            Quantifier::ZeroOrOne => {
+                if length > 0 && follows(self, start, self.match_bound) {
+                    self.set_position(start + length);
+                    Some(Range {
+                        start,
+                        end: self.current(),
+                    })
+                } else {
+                    self.empty_expression_match()
+                }
+            }
+
+            Quantifier::ZeroOrMore | Quantifier::OneOrMore | Quantifier::Counted { .. } => {
+                let (min, max) = quantifier.bounds();
+                let mut repetitions = 0;
+                // Repeating empty text consumes nothing, it matches as many times as needed
+                while length > 0
+                    && max.is_none_or(|max| repetitions < max)
+                    && follows(self, self.pos, self.match_bound)
+                {
+                    self.set_position(self.pos + length);
+                    repetitions += 1;
+                }
+                let end = self.current();
+                if length > 0 && repetitions < min {
+                    // Too few repetitions, or match bound exceeded/reached, abort
+                    self.set_position(start);
+                    Option::<Range<usize>>::None
+                } else if start < end {
+                    Some(Range { start, end })
+                } else {
+                    self.empty_expression_match()
+                }
+            }
+
+            Quantifier::Lazy { min, max } => {
+                // Match as few repetitions as allowed, or when backtracking
+                // one repetition more than last time
+                let mut repetitions = 0;
+                let mut consumed = true;
+                while repetitions < min || floor.is_some_and(|floor| self.pos <= floor) {
+                    if length == 0 {
+                        // Empty text stands for each missing repetition
+                        // but never moves past `floor`
+                        consumed = floor.is_none();
+                        break;
+                    }
+                    if max.is_some_and(|max| repetitions >= max)
+                        || !follows(self, self.pos, self.match_bound)
+                    {
+                        consumed = false;
+                        break;
+                    }
+                    self.set_position(self.pos + length);
+                    repetitions += 1;
+                }
+                if consumed {
+                    Some(Range {
+                        start,
+                        end: self.current(),
+                    })
+                } else {
+                    // Too few repetitions, or no longer match left to try
+                    self.set_position(start);
+                    self.exhaust_lazy(table_entry_index);
+                    Option::<Range<usize>>::None
+                }
+            }
+        };
+
+        self.match_bound = old_match_bound;
+
+        backreference_match
+    }
+
     // GROUP/GROUPED EXPRESSIONS:
     // (E) where E is also an expression
     // for instance, (a+|b) is group/grouped expression
@@ -491,51 +1441,88 @@ impl Matcher {
 
     // Return Option::<std::ops::Range>::Some(...) on success
     // Return Option::<std::ops::Range>::None on failure
-    fn group_match(&mut self, quantifier: Quantifier) -> Option<Match> {
+    fn group_match(&mut self, quantifier: Quantifier, atomic: bool) -> Option<Range<usize>> {
+        // Find backtrack entry (in self.backtrack_table) of this group expression
+        let table_entry_index = self.table_entry_index();
+        let lazy = Self::is_lazy(&self.pattern.read().unwrap());
         let old_match_bound = self.match_bound;
-        self.match_bound = {
-            // Find backtrack entry (in self.backtrack_table) of this group expression
-            let table_entry_index = self.backtrack_table.binary_search_by(|info_entry| {
-                info_entry.index_sequence.cmp(&self.pattern_index_sequence)
-            });
-            match table_entry_index {
-                // This expression matched/backtracked before
-                Ok(entry_index) => self.backtrack_table[entry_index]
-                    .last_match_end
-                    .saturating_sub(1),
-                // This expression NEVER matched/backtracked before
-                _ => old_match_bound,
-            }
+        self.match_bound = match table_entry_index {
+            // This expression matched/backtracked before
+            // Lazy groups match a longer range instead of a smaller one
+            Some(entry_index) if !lazy => self.backtrack_table[entry_index]
+                .last_match_end
+                .saturating_sub(1),
+            // This expression NEVER matched/backtracked before
+            _ => old_match_bound,
         };
 
         let old_pattern = Arc::clone(&self.pattern);
+        let group_id = old_pattern.read().unwrap().id;
         let pattern = Arc::clone(&old_pattern);
         let pattern = pattern.read().unwrap();
         let pattern = &pattern.children;
         self.pattern = Arc::clone(&pattern.read().unwrap()[0]);
 
         let grouped_expression_mactch = {
-            // Start tracking your child
-            self.dive();
-            // `self.dive()` MUST be called here because it mutates `self.pattern_index_sequence`
-            // which is used to find associated entry (in self.backtrack_table) of this group itself
-            // Thus calling `self.dive()` before computing `match_bound` makes the search
-            // in `self.backtrack_table` always fail
-
             match quantifier {
+                Quantifier::None if lazy => {
+                    // Matching `(E)` where E is lazy
+                    // E must go past last match of this group, if backtracking
+                    let floor = self.lazy_floor(table_entry_index);
+                    let old_match_floor = std::mem::replace(&mut self.match_floor, floor);
+                    let inner_expression_match = self.grouped_expression_match(group_id, atomic);
+                    self.match_floor = old_match_floor;
+                    if inner_expression_match.is_none() {
+                        self.exhaust_lazy(table_entry_index);
+                    }
+                    inner_expression_match
+                }
+
                 Quantifier::None => {
                     // Matching `(E)`
                     // return whatever expression `E` returns
-                    self.compute_match()
+                    let start = self.current();
+                    match self.grouped_expression_match(group_id, atomic) {
+                        // An atomic E ignores match bound, see `grouped_expression_match`
+                        Some(inner_expression_match)
+                            if inner_expression_match.end > self.match_bound =>
+                        {
+                            // Match bound exceeded, abort
+                            self.set_position(start);
+                            Option::<Range<usize>>::None
+                        }
+                        inner_expression_match => inner_expression_match,
+                    }
                 }
 
                 Quantifier::ZeroOrOne => {
                     // Matching `(E)?`
-                    match self.compute_match() {
+                    let start = self.current();
+                    let snapshot = self
+                        .submatch_recorder
+                        .as_ref()
+                        .map(|recorder| recorder.snapshot(&self.pattern.read().unwrap()));
+                    let captures = self.capture_slots.snapshot(&old_pattern.read().unwrap());
+                    match self.grouped_expression_match(group_id, atomic) {
                         Some(inner_expression_match) => {
                             if inner_expression_match.end >= self.match_bound {
-                                // Match bound exceeded/reached, abort
-                                Option::<Match>::None
+                                // Match bound exceeded/reached
+                                if atomic {
+                                    // E can not match a shorter range, match it zero times
+                                    self.set_position(start);
+                                    if let (Some(recorder), Some(snapshot)) =
+                                        (&mut self.submatch_recorder, snapshot)
+                                    {
+                                        recorder.restore(snapshot);
+                                    }
+                                    if let Some(captures) = captures {
+                                        self.capture_slots.restore(&captures);
+                                    }
+                                    self.empty_expression_match()
+                                } else {
+                                    // abort
+                                    Option::<Range<usize>>::None
+                                }
                             } else {
                                 Some(inner_expression_match)
                             }
@@ -544,8 +1531,9 @@ impl Matcher {
                     }
                 }
 
-                _ => {
-                    // Matching `(E)*` or `(E)+`
+                Quantifier::ZeroOrMore | Quantifier::OneOrMore | Quantifier::Counted { .. } => {
+                    // Matching `(E)*` or `(E)+` or `(E){2,5}`
+                    let (min, max) = quantifier.bounds();
 
                     // A guard to stop matching if inner expression matched the empty string at least once
                     // so that Matcher does not loop endlessly matching the empty string at current position
@@ -553,13 +1541,36 @@ impl Matcher {
 
                     let start = self.current();
                     let mut end = self.current();
+                    // Number of times inner expression matched
+                    let mut repetitions = 0;
                     // Keep matching inner expression unless match bound is exceeded
                     // or the inner expression matched the empty string at least once
-                    while let Some(new_match) = self.compute_match() {
+                    // or it matched as many times as allowed
+                    while max.is_none_or(|max| repetitions < max) {
+                        let iteration_snapshot = self
+                            .submatch_recorder
+                            .as_ref()
+                            .map(|recorder| recorder.snapshot(&self.pattern.read().unwrap()));
+                        // Including range of this group, recorded by previous iteration
+                        let iteration_captures =
+                            self.capture_slots.snapshot(&old_pattern.read().unwrap());
+                        let Some(new_match) = self.grouped_expression_match(group_id, atomic)
+                        else {
+                            break;
+                        };
                         if self.pos > self.match_bound {
                             // Match bound exceeded while matching inner expression
                             // Roll back to end of most recent successful match
                             self.set_position(end);
+                            // Forget what this rejected iteration recorded
+                            if let (Some(recorder), Some(snapshot)) =
+                                (&mut self.submatch_recorder, iteration_snapshot)
+                            {
+                                recorder.restore(snapshot);
+                            }
+                            if let Some(snapshot) = iteration_captures {
+                                self.capture_slots.restore(&snapshot);
+                            }
                             break;
                         }
                         if new_match.is_empty() && matched_empty_string {
@@ -575,20 +1586,64 @@ impl Matcher {
                         // Update match end index of this group expression
                         end = new_match.end;
                         matched_empty_string = new_match.is_empty();
+                        repetitions += 1;
                     }
 
-                    // Matched empty range BUT that empty range is NOT the empty string
-                    // In other words, failed to match even the empty string
-                    if start == end && !matched_empty_string {
-                        // Total failure
-                        if matches!(quantifier, Quantifier::OneOrMore) {
-                            Option::<Match>::None
-                        } else {
-                            self.empty_expression_match()
-                        }
+                    // Once inner expression matched the empty string
+                    // it can match it again for each missing repetition
+                    if repetitions < min && !matched_empty_string {
+                        // Too few repetitions, total failure
+                        self.set_position(start);
+                        Option::<Range<usize>>::None
+                    } else if start == end && !matched_empty_string {
+                        // Matched empty range BUT that empty range is NOT the empty string
+                        // In other words, matched no repetitions at all, which is allowed
+                        self.empty_expression_match()
                     } else {
                         // Matched some string, possibly the empty string
-                        Some(Match { start, end })
+                        Some(Range { start, end })
+                    }
+                }
+
+                Quantifier::Lazy { min, max } => {
+                    // Matching `(E)*?` or `(E)+?` or `(E){2,5}?`
+                    // Match inner expression as few times as allowed, or when backtracking
+                    // as few times as needed to end after last match of this group
+                    let floor = self.lazy_floor(table_entry_index);
+                    let start = self.current();
+                    let mut end = self.current();
+                    let mut repetitions = 0;
+                    let mut matched = true;
+                    while repetitions < min || floor.is_some_and(|floor| end <= floor) {
+                        if max.is_some_and(|max| repetitions >= max) {
+                            // Matched as many times as allowed
+                            matched = false;
+                            break;
+                        }
+                        match self.grouped_expression_match(group_id, atomic) {
+                            Some(new_match) if self.pos <= self.match_bound => {
+                                if new_match.is_empty() {
+                                    // Matching the empty string again never moves past `floor`
+                                    // but it stands for each missing repetition
+                                    matched = floor.is_none_or(|floor| end > floor);
+                                    break;
+                                }
+                                end = new_match.end;
+                                repetitions += 1;
+                            }
+                            // Inner expression failed or exceeded match bound
+                            _ => {
+                                matched = false;
+                                break;
+                            }
+                        }
+                    }
+                    if matched {
+                        Some(Range { start, end })
+                    } else {
+                        self.set_position(start);
+                        self.exhaust_lazy(table_entry_index);
+                        Option::<Range<usize>>::None
                     }
                 }
             }
@@ -597,12 +1652,48 @@ impl Matcher {
         self.match_bound = old_match_bound;
         // Restore parent pattern to process remaining siblings of current pattern
         self.pattern = old_pattern;
-        // Abandon your child
-        self.bubble_up();
 
         grouped_expression_mactch
     }
 
+    // ATOMIC GROUPS:
+    // (?>E) \ (?>E)? \ (?>E)* and so on, where E is also an expression
+    // for instance, (?>a+) matches all a's it finds and never gives any of them back
+    // so (?>a+)a matches nothing at all
+
+    // HOW TO MATCH AN ATOMIC GROUP:
+    // Match E as if nothing came after the group, ignoring match bound
+    // and then discard backtrack entries of E and all its descendants
+    // Thus no sibling of the group can make E match again a shorter (or longer) range
+    // and next time the group is matched E starts over
+    // An unquantified atomic group never backtracks, a quantified one backtracks
+    // by matching fewer (or more, if lazy) repetitions of E, each matched atomically
+
+    // Match expression enclosed by current group, currently processed pattern
+    // A capturing group `group_id` records the range matched, thus a repeated group
+    // ends up holding range of its last repetition (see module `capture`)
+    fn grouped_expression_match(&mut self, group_id: NodeId, atomic: bool) -> Option<Range<usize>> {
+        let inner_expression_match = if atomic {
+            let old_match_bound = std::mem::replace(&mut self.match_bound, self.target.len() + 1);
+            let inner_expression_match = self.compute_match();
+            self.match_bound = old_match_bound;
+
+            let descendants = {
+                let inner_expression = self.pattern.read().unwrap();
+                inner_expression.id..=inner_expression.last_descendant_id()
+            };
+            self.backtrack_table
+                .retain(|info_entry| !descendants.contains(&info_entry.node_id));
+            inner_expression_match
+        } else {
+            self.compute_match()
+        };
+        if let Some(matched) = &inner_expression_match {
+            self.capture_slots.record(group_id, matched.clone());
+        }
+        inner_expression_match
+    }
+
     // ALTERNATION EXPRESSIONS:
     // (E1|E2|...|E_n) where E1,E2,...,E_n are also expressions
     // for instance, a|b.c|x is an alternation expression
@@ -613,10 +1704,7 @@ impl Matcher {
 
     // Return Option::<std::ops::Range>::Some(...) on success
     // Return Option::<std::ops::Range>::None on failure
-    fn alternation_match(&mut self) -> Option<Match> {
-        // Start tracking your children
-        self.dive();
-
+    fn alternation_match(&mut self) -> Option<Range<usize>> {
         let old_position = self.current();
         let old_pattern = self.pattern.clone();
 
@@ -649,10 +1737,6 @@ impl Matcher {
                     // Return that child match
                     break;
                 }
-
-                // Current child failed to match
-                // Start tracking next child
-                self.appoint_next_child();
             }
 
             child_match
@@ -660,8 +1744,6 @@ impl Matcher {
 
         // Restore parent pattern to process remaining siblings of current pattern
         self.pattern = old_pattern;
-        // Abandon your children
-        self.bubble_up();
 
         alternation_match
     }
@@ -697,10 +1779,7 @@ impl Matcher {
 
     // Return Option::<std::ops::Range>::Some(...) on success
     // Return Option::<std::ops::Range>::None on failure
-    fn concatenation_match(&mut self) -> Option<Match> {
-        // Start tracking your children
-        self.dive();
-
+    fn concatenation_match(&mut self) -> Option<Range<usize>> {
         let old_position = self.current();
         let old_pattern = self.pattern.clone();
 
@@ -718,6 +1797,8 @@ impl Matcher {
 
             let mut match_region_end = self.current();
             let mut child_index = 0usize;
+            // Ranges of capturing groups before any item matched
+            let captures = self.capture_slots.snapshot(&old_pattern.read().unwrap());
 
             while child_index < children.len() {
                 let (child, table_info_pos) = {
@@ -746,15 +1827,20 @@ impl Matcher {
                     // Rust won't allow (self.current()) after (&mut self.backtrack_table)
                     let cur = self.current();
                     let table_entry = &mut self.backtrack_table[table_pos];
-                    if prev.is_some() && table_entry.backtracked_to_last_match_start {
+                    if prev.is_some()
+                        && table_entry.backtracked_to_last_match_start
+                        && !Self::is_lazy(&child.read().unwrap())
+                    {
                         // This expression backtracked all the way back to start
                         // of its last successful match and it has
                         // a preceeding sibling which can backtrack
                         // Reset its entry in `self.backtrack_table`
                         // to make it usable again
+                        // Lazy expressions start over on their own, see `lazy_floor`
                         table_entry.last_match_start = cur;
                         table_entry.last_match_end = self.target.len();
                         table_entry.backtracked_to_last_match_start = false;
+                        self.log_table_update(table_pos);
                     }
                 }
 
@@ -771,11 +1857,10 @@ impl Matcher {
                         if table_entry_index.is_none() && Self::supports_backtracking(&self.pattern)
                         {
                             // Store backtrack info entry index of this expression
+                            let node_id = self.current_node_id();
                             let table_pos = self
                                 .backtrack_table
-                                .binary_search_by(|item| {
-                                    item.index_sequence.cmp(&self.pattern_index_sequence)
-                                })
+                                .binary_search_by(|item| item.node_id.cmp(&node_id))
                                 .unwrap();
                             *table_entry_index = Some(table_pos);
                         }
@@ -795,9 +1880,28 @@ impl Matcher {
                                     &self.backtrack_table[table_entry_index]
                                 };
                                 // Resume matching from the last successful match start of that sibling
-                                self.set_position(table_entry.last_match_start);
-                                // Fix subexpressions tracker
-                                *self.pattern_index_sequence.last_mut().unwrap() = child_index;
+                                let position = table_entry.last_match_start;
+                                self.set_position(position);
+                                // That sibling and those after it match again
+                                // what they captured in previous attempt is gone
+                                if let Some(captures) = &captures {
+                                    let from = children[child_idx].0.read().unwrap().id;
+                                    self.capture_slots.restore_from(captures, from);
+                                }
+                                let failed = self.current_node_id();
+                                let to = children[child_idx].0.read().unwrap().id;
+                                if let Some(observer) = &mut self.observer {
+                                    observer.backtrack(to, position);
+                                }
+                                if let Some(stats) = &mut self.stats {
+                                    stats.backtracks += 1;
+                                }
+                                self.log_step(|| Step::Backtrack {
+                                    id: old_pattern.read().unwrap().id,
+                                    failed,
+                                    to,
+                                    position,
+                                });
                                 continue;
                             }
                             None => {
@@ -811,49 +1915,103 @@ impl Matcher {
                                 // Restore old position
                                 self.set_position(old_position);
 
-                                // Abandon your children
-                                self.bubble_up();
-
-                                return Option::<Match>::None;
+                                return Option::<Range<usize>>::None;
                             }
                         }
                     }
                 }
 
                 child_index += 1;
-                // Start tracking next child
-                self.appoint_next_child();
             }
 
-            Some(Match {
+            Some(Range {
                 start: old_position,
                 end: match_region_end,
             })
         };
 
         self.pattern = old_pattern.clone();
-        // Abandon your children
-        self.bubble_up();
 
         concatenation_match
     }
 }
 
 impl Iterator for Matcher {
-    type Item = Match;
+    type Item = Range<usize>;
 
     // Find the next match (non-overlapping with previous match)
-    fn next(&mut self) -> Option<Match> {
+    fn next(&mut self) -> Option<Range<usize>> {
+        let pattern = Arc::clone(&self.pattern.read().unwrap().pattern);
+        let haystack_length = self.target.len();
+        let engine = if self.uses_literal_search() {
+            "literal"
+        } else {
+            "backtracker"
+        };
+        telemetry::search(engine, Some(&pattern), haystack_length, || {
+            self.next_match()
+        })
+    }
+}
+
+impl Matcher {
+    // Search for fixed strings instead of backtracking?
+    // Searching substrings takes no steps to log, report or limit
+    // thus instrumented and limited searches still backtrack
+    // and substring search can not tell what capturing groups matched
+    fn uses_literal_search(&self) -> bool {
+        self.literal_searcher.is_some()
+            && self.capture_slots.is_empty()
+            && self.submatch_recorder.is_none()
+            && self.step_log.is_none()
+            && self.observer.is_none()
+            && self.stats.is_none()
+            && self.step_limit.is_none()
+    }
+
+    // First fixed string matched by pattern starting at or after current position
+    // Position moves to its end, or to end of target if none is found
+    fn literal_match(&mut self) -> Option<Range<usize>> {
+        let found = self
+            .literal_searcher
+            .as_ref()
+            .and_then(|searcher| searcher.find(&self.target, self.current()));
+        self.set_position(found.as_ref().map_or(self.target.len(), |found| found.end));
+        found
+    }
+
+    fn next_match(&mut self) -> Option<Range<usize>> {
         // Return Option::<std::ops::Range>::Some(...) on success
         // Return Option::<std::ops::Range>::None on failure
 
         if matches!(self.next_match_phase, MatchPhase::Finished) {
             // Target is completely consumed
             // No more matches to compute
-            return Option::<Match>::None;
+            return Option::<Range<usize>>::None;
         }
 
-        if let Some(cached_range) = { self.match_cache.iter().find(|m| self.pos <= m.start) } {
+        // Start over logging steps
+        if let Some(log) = &mut self.step_log {
+            log.steps.clear();
+        }
+        self.steps_taken = 0;
+        self.abandoned = None;
+
+        // Cached matches have no sub-match traces nor step logs
+        // compute them again when tracing
+        let cached_range = if self.submatch_recorder.is_some()
+            || self.step_log.is_some()
+            || self.observer.is_some()
+            || self.stats.is_some()
+        {
+            None
+        } else {
+            self.match_cache
+                .iter()
+                .find(|(m, _)| self.pos <= m.start)
+                .cloned()
+        };
+        if let Some((cached_range, cached_captures)) = cached_range {
             let accept_cache = match self.next_match_phase {
                 MatchPhase::Normal => true,
                 MatchPhase::TrailingEmptyString => cached_range.is_empty(),
@@ -862,25 +2020,35 @@ impl Iterator for Matcher {
 
             if accept_cache {
                 let old_pos = self.pos;
-                self.pos = cached_range.end;
+                // Like a computed empty match, move past it
+                // or the same empty match is found again and again
+                self.pos = if cached_range.is_empty() {
+                    cached_range.end + 1
+                } else {
+                    cached_range.end
+                };
 
                 self.next_match_phase = if self.pos < self.target.len() {
                     MatchPhase::Normal
-                } else if old_pos < self.target.len() {
+                } else if old_pos < self.target.len() && self.pos == self.target.len() {
                     MatchPhase::TrailingEmptyString
                 } else {
                     MatchPhase::Finished
                 };
 
-                return Some(cached_range.clone());
+                self.last_captures = cached_captures;
+                return Some(cached_range);
             }
 
             self.next_match_phase = MatchPhase::Finished;
-            return Option::<Match>::None;
+            self.last_captures.clear();
+            return Option::<Range<usize>>::None;
         }
 
-        // Track root expression
-        self.dive();
+        // Start over recording sub-matches
+        if let Some(recorder) = &mut self.submatch_recorder {
+            recorder.clear();
+        }
 
         // WHY WE NEED A LOOP?
         // Because first match in target string may not start at index 0
@@ -888,11 +2056,46 @@ impl Iterator for Matcher {
         // first successful match or reach end of target
         let mut match_attempt;
         loop {
-            match_attempt = self.compute_match();
+            let position = self.current();
+            #[cfg(feature = "tracing")]
+            tracing::trace!(position, "match attempt");
+            self.log_step(|| Step::Attempt { position });
+            if let Some(observer) = &mut self.observer {
+                observer.attempt_start(position);
+            }
+            if let Some(stats) = &mut self.stats {
+                stats.positions_scanned += 1;
+            }
+            // Groups capture nothing before each attempt
+            self.capture_slots.clear();
+            match_attempt = if self.uses_literal_search() {
+                self.literal_match()
+            } else {
+                self.compute_match()
+            };
             // Remove old backtrack info
             self.backtrack_table.clear();
+            if self.abandoned.is_some() {
+                // Abandon this search and any later one
+                self.next_match_phase = MatchPhase::Finished;
+                self.last_submatch_trace = None;
+                self.last_captures.clear();
+                return None;
+            }
+            if let Some(observer) = &mut self.observer {
+                match &match_attempt {
+                    Some(matched) => observer.attempt_success(matched),
+                    None => observer.attempt_failure(position),
+                }
+            }
             if match_attempt.is_none() {
                 // Last match failed
+                if position == 0 && self.is_anchored_start() {
+                    // Matches can not begin anywhere else
+                    // STOP
+                    self.set_position(self.target.len());
+                    break;
+                }
                 if self.has_next() {
                     // Move forward to retry
                     // ADVANCE
@@ -913,11 +2116,17 @@ impl Iterator for Matcher {
                     self.advance();
                 }
 
-                self.match_cache.insert(
-                    self.match_cache
-                        .partition_point(|m| match_attempt.start > m.start),
-                    match_attempt.clone(),
-                );
+                if self.caches_matches && !self.match_cache.iter().any(|(m, _)| *m == match_attempt)
+                {
+                    let captures = std::iter::once(Some(match_attempt.clone()))
+                        .chain(self.capture_slots.slots().iter().cloned())
+                        .collect();
+                    self.match_cache.insert(
+                        self.match_cache
+                            .partition_point(|(m, _)| match_attempt.start > m.start),
+                        (match_attempt.clone(), captures),
+                    );
+                }
 
                 if self.matches_substring_start.is_none() {
                     self.matches_substring_start = Some(match_attempt.start);
@@ -930,14 +2139,26 @@ impl Iterator for Matcher {
 
         self.next_match_phase = match self.pos.cmp(&self.target.len()) {
             std::cmp::Ordering::Less => MatchPhase::Normal,
-            _ => match self.next_match_phase {
+            std::cmp::Ordering::Equal => match self.next_match_phase {
                 MatchPhase::Normal => MatchPhase::TrailingEmptyString,
                 _ => MatchPhase::Finished,
             },
+            // Moved past an empty match at end of target, like that of `$`
+            // it must not be found again
+            std::cmp::Ordering::Greater => MatchPhase::Finished,
         };
 
-        // Abandon root expression
-        self.bubble_up();
+        self.last_submatch_trace = match (&self.submatch_recorder, &match_attempt) {
+            (Some(recorder), Some(_)) => Some(recorder.trace(&self.pattern)),
+            _ => None,
+        };
+        // Buffer is reused, only `match_cache` keeps a copy of captures for each match
+        self.last_captures.clear();
+        if let Some(matched) = &match_attempt {
+            self.last_captures.push(Some(matched.clone()));
+            self.last_captures
+                .extend_from_slice(self.capture_slots.slots());
+        }
 
         match_attempt
     }
@@ -958,17 +2179,346 @@ impl Matcher {
         }
     }
 
+    // Find the next match, like `next`, but tell a search abandoned because it
+    // exceeded its budget (see `set_step_limit`) apart from one which found nothing
+    pub fn try_find(&mut self) -> Result<Option<Range<usize>>, MatchError> {
+        let found = self.next();
+        self.abandoned_error()?;
+        Ok(found)
+    }
+
+    // `match_full` failing if search exceeds its budget
+    pub fn try_match_full<'t>(&mut self, target: &'t str) -> Result<Option<Match<'t>>, MatchError> {
+        let found = self.match_full(target);
+        self.abandoned_error()?;
+        Ok(found)
+    }
+
+    // `rfind` failing if search exceeds its budget
+    pub fn try_rfind<'t>(&mut self, target: &'t str) -> Result<Option<Match<'t>>, MatchError> {
+        let found = self.rfind(target);
+        self.abandoned_error()?;
+        Ok(found)
+    }
+
+    // `match_partial` failing if search exceeds its budget
+    pub fn try_match_partial<'t>(
+        &mut self,
+        target: &'t str,
+    ) -> Result<PartialMatch<'t>, MatchError> {
+        let found = self.match_partial(target);
+        self.abandoned_error()?;
+        Ok(found)
+    }
+
+    // Does some range within the target match pattern? like `is_matching`
+    // but a search exceeding its budget is an error
+    pub fn try_is_match(&mut self) -> Result<bool, MatchError> {
+        self.reset();
+        Ok(self.try_find()?.is_some())
+    }
+
+    // Find the next match, like `next`, but give up once `deadline` has passed
+    // The clock is read every so often while matching, so a search may run a little past it
+    // Services matching untrusted patterns use this as a safety valve
+    pub fn find_with_deadline(
+        &mut self,
+        deadline: Instant,
+    ) -> Result<Option<Range<usize>>, MatchError> {
+        self.deadline = Some(deadline);
+        let found = self.next();
+        self.deadline = None;
+        self.abandoned_error()?;
+        Ok(found)
+    }
+
+    // First match beginning at `start` (a character index) or after it, if any
+    // Unlike searching a slice of target beginning at `start`, assertions and lookbehinds
+    // see characters before it, `^` does not hold there unless it is 0
+    // Iteration goes on from the end of the match found, to resume after an empty match
+    // call this again with its end plus one
+    pub fn find_at(&mut self, start: usize) -> Option<Range<usize>> {
+        // Cached matches were found from other positions
+        // and one found from `start` would mislead later searches, like in overlapping mode
+        self.match_cache.clear();
+        self.seek(start.min(self.target.len()));
+        let found = self.next();
+        self.match_cache.clear();
+        found
+    }
+
+    // First match within `window` of target, beginning at its start or after it, if any
+    // Target is searched as if it ended at end of `window`, where `$` and `\Z` hold
+    // while characters before its start are seen like with `find_at`
+    // Both ends are character indices, clamped to target length
+    pub fn find_in(&mut self, window: Range<usize>) -> Option<Range<usize>> {
+        let end = window.end.min(self.target.len());
+        let mut scratch = self.scratch_matcher(self.target[..end].to_vec());
+        let found = scratch.find_at(window.start.min(end));
+        self.abandoned = scratch.abandoned;
+        self.last_captures = scratch.last_captures;
+        // Go on from the end of the match found within the whole target
+        self.match_cache.clear();
+        self.seek(scratch.pos);
+        found
+    }
+
+    // Find the next match, like `next`, along with statistics of the search
+    // which services can log to spot slow patterns
+    pub fn find_with_stats(&mut self) -> (Option<Range<usize>>, SearchStats) {
+        let start = Instant::now();
+        self.stats = Some(SearchStats::default());
+        let found = self.next();
+        let mut stats = self.stats.take().unwrap_or_default();
+        stats.elapsed = start.elapsed();
+        (found, stats)
+    }
+
+    // Matches from start of target following `options`
+    // like at most 10 matches, or overlapping matches
+    // They can be taken from the back too, `matches_with(&options).rev()` gives the last first
+    pub fn matches_with(&mut self, options: &SearchOptions) -> Matches<'_> {
+        Matches::new(self, *options)
+    }
+
     // Return true if the whole target fully matches pattern
-    // In other words, there is exactly one match starting from index 0
+    // In other words, there is a match starting from index 0
     // ending at index N where N is target length
     pub fn fullmatch(&mut self) -> bool {
+        self.whole_target_match().is_some()
+    }
+
+    // Does the whole of `target` match pattern? `fullmatch` on `target`
+    pub fn full_match(&mut self, target: &str) -> bool {
+        self.match_full(target).is_some()
+    }
+
+    // Match pattern against the whole of `target`, from index 0 to its length
+    // It is found even if pattern matches a prefix first, like `a|ab` matching "ab"
+    // Ranges captured by groups in this match are given by `captures`
+    // Target of this matcher is left as it is
+    pub fn match_full<'t>(&mut self, target: &'t str) -> Option<Match<'t>> {
+        let mut anchored = self.anchored_matcher(true, true, target.chars().collect());
+        let found = anchored.next();
+        self.abandoned = anchored.abandoned.take();
+        self.last_captures = std::mem::take(&mut anchored.last_captures);
+        Some(Match::with_map(target, found?, anchored.byte_map()))
+    }
+
+    // Does `target` begin with a match of pattern?
+    // Only a match starting at index 0 is attempted
+    // Target of this matcher, and what its groups captured, are left as they are
+    pub fn starts_with(&mut self, target: &str) -> bool {
+        let mut anchored = self.anchored_matcher(true, false, target.chars().collect());
+        // Pattern is anchored at start, thus no other start is attempted
+        let found = anchored.next().is_some();
+        self.abandoned = anchored.abandoned;
+        found
+    }
+
+    // Does `target` end with a match of pattern?
+    // Starts are probed from its end backwards, no further than the longest match can be long
+    // Target of this matcher, and what its groups captured, are left as they are
+    pub fn ends_with(&mut self, target: &str) -> bool {
+        let mut anchored = self.anchored_matcher(false, true, target.chars().collect());
+        let found = anchored.matches_suffix();
+        self.abandoned = anchored.abandoned;
+        found
+    }
+
+    // Last match in `target`, if any
+    // Start positions are probed from end of target backwards, so earlier matches are never
+    // computed, then the first match found grows back as long as earlier starts match up to
+    // the same end, thus `\d+` finds "34" in "12ab34" rather than "4"
+    // It may overlap a match found by iterating, like characters 1..3 of `aa` in "aaa" where
+    // iteration finds 0..2 alone
+    // Target of this matcher is left as it is
+    pub fn rfind<'t>(&mut self, target: &'t str) -> Option<Match<'t>> {
+        let mut scratch = self.scratch_matcher(target.chars().collect());
+        let found = scratch.rfind_target(target);
+        self.abandoned = scratch.abandoned;
+        self.last_captures = scratch.last_captures;
+        found
+    }
+
+    // `rfind` in target of this matcher, which is `target`
+    fn rfind_target<'t>(&mut self, target: &'t str) -> Option<Match<'t>> {
+        self.steps_taken = 0;
+        self.abandoned = None;
+        // Matches are at least that long, and may have to begin at start of target
+        let last_start = if self.is_anchored_start() {
+            0
+        } else {
+            self.target.len().saturating_sub(self.min_match_len())
+        };
+        let mut found: Option<Range<usize>> = None;
+        let mut captures = vec![];
+        for start in (0..=last_start).rev() {
+            self.seek(start);
+            // Groups capture nothing before each attempt
+            self.capture_slots.clear();
+            let attempt = self.compute_match();
+            self.backtrack_table.clear();
+            if self.abandoned.is_some() {
+                // An abandoned search finds nothing, see `set_step_limit`
+                found = None;
+                break;
+            }
+            match attempt {
+                Some(matched) if found.as_ref().is_none_or(|last| last.end == matched.end) => {
+                    captures = std::iter::once(Some(matched.clone()))
+                        .chain(self.capture_slots.slots().iter().cloned())
+                        .collect();
+                    found = Some(matched);
+                }
+                _ if found.is_some() => break,
+                _ => {}
+            }
+        }
+        self.last_captures = if found.is_some() { captures } else { vec![] };
         self.reset();
-        match self.next() {
-            Some(m) => m.start == 0 && m.end == self.target.len(),
-            None => false,
+        found.map(|found| Match::with_map(target, found, self.byte_map()))
+    }
+
+    // Match `target`, telling apart text which cannot match from text which
+    // has run out before a match could be completed, like `\d{4}-\d{2}` with "2024-0"
+    // A complete match is preferred over a partial one, even if it starts later
+    // Partial matches start at the earliest index from which matching ran out of text
+    // and include at least one character, unless target is empty
+    // Useful for validating input as it is typed, or parsing a protocol incrementally
+    // Target of this matcher is left as it is
+    pub fn match_partial<'t>(&mut self, target: &'t str) -> PartialMatch<'t> {
+        let mut scratch = self.scratch_matcher(target.chars().collect());
+        let found = scratch.match_partial_target(target);
+        self.abandoned = scratch.abandoned;
+        self.last_captures = scratch.last_captures;
+        found
+    }
+
+    // `match_partial` in target of this matcher, which is `target`
+    fn match_partial_target<'t>(&mut self, target: &'t str) -> PartialMatch<'t> {
+        self.steps_taken = 0;
+        self.abandoned = None;
+        let last_start = if self.is_anchored_start() {
+            0
+        } else {
+            self.target.len()
+        };
+        let mut partial = None;
+        let mut outcome = None;
+        for start in 0..=last_start {
+            self.seek(start);
+            self.capture_slots.clear();
+            self.hit_end = false;
+            let attempt = self.compute_match();
+            self.backtrack_table.clear();
+            if self.abandoned.is_some() {
+                // An abandoned search finds nothing, see `set_step_limit`
+                partial = None;
+                break;
+            }
+            if let Some(matched) = attempt {
+                self.last_captures = std::iter::once(Some(matched.clone()))
+                    .chain(self.capture_slots.slots().iter().cloned())
+                    .collect();
+                outcome = Some(matched);
+                break;
+            }
+            if self.hit_end
+                && partial.is_none()
+                && (start < self.target.len() || self.target.is_empty())
+            {
+                partial = Some(start..self.target.len());
+            }
+        }
+        self.hit_end = false;
+        if outcome.is_none() {
+            self.last_captures = vec![];
+        }
+        self.reset();
+        let map = self.byte_map();
+        match (outcome, partial) {
+            (Some(matched), _) => PartialMatch::Complete(Match::with_map(target, matched, map)),
+            (None, Some(partial)) => PartialMatch::Partial(Match::with_map(target, partial, map)),
+            (None, None) => PartialMatch::NoMatch,
         }
     }
 
+    // Match of pattern spanning the whole target, if any
+    fn whole_target_match(&mut self) -> Option<Range<usize>> {
+        let mut anchored = self.anchored_matcher(true, true, self.target.clone());
+        let found = anchored.next();
+        self.abandoned = anchored.abandoned;
+        self.last_captures = anchored.last_captures;
+        found
+    }
+
+    // Matcher of a copy of pattern anchored at start of target if `start` and at its end
+    // if `end` against `target`, within the same budget as this one
+    // Each anchored copy is compiled once, when first needed
+    fn anchored_matcher(&self, start: bool, end: bool, target: Vec<char>) -> Matcher {
+        let index = match (start, end) {
+            (true, false) => 0,
+            (false, true) => 1,
+            _ => 2,
+        };
+        let compiled = self.anchored[index].get_or_init(|| {
+            CompiledPattern::new(anchored(&self.pattern.read().unwrap(), start, end))
+        });
+        let mut matcher = Matcher::with_target(compiled, target);
+        matcher.step_limit = self.step_limit;
+        matcher.deadline = self.deadline;
+        matcher
+    }
+
+    // Matcher of pattern against `target`, within the same budget as this one
+    // Methods given a target other than this matcher's search it with one
+    fn scratch_matcher(&self, target: Vec<char>) -> Matcher {
+        let compiled = CompiledPattern {
+            pattern: Arc::clone(&self.pattern),
+            capture_slots: self.capture_slots.clone(),
+            literal_searcher: self.literal_searcher.clone(),
+            anchored: Arc::clone(&self.anchored),
+        };
+        let mut matcher = Matcher::with_target(&compiled, target);
+        // Groups of this matcher may hold what they captured last
+        matcher.capture_slots.clear();
+        matcher.step_limit = self.step_limit;
+        matcher.deadline = self.deadline;
+        matcher
+    }
+
+    // Does pattern, anchored at end of target, match from some start?
+    // Starts are probed from end of target backwards, between the farthest from it
+    // the longest match can begin and the closest the shortest match can
+    fn matches_suffix(&mut self) -> bool {
+        self.steps_taken = 0;
+        self.abandoned = None;
+        let length = self.target.len();
+        let starts = if self.is_anchored_start() {
+            0..=0
+        } else {
+            let first_start = self
+                .max_match_len()
+                .map_or(0, |longest| length.saturating_sub(longest));
+            first_start..=length.saturating_sub(self.min_match_len())
+        };
+        for start in starts.rev() {
+            self.seek(start);
+            self.capture_slots.clear();
+            let attempt = self.compute_match();
+            self.backtrack_table.clear();
+            if self.abandoned.is_some() {
+                // An abandoned search finds nothing, see `set_step_limit`
+                return false;
+            }
+            if attempt.is_some() {
+                return true;
+            }
+        }
+        false
+    }
+
     // Split target `splits_count` times
     // A large splits_count splits the whole target
     pub fn splitn(&mut self, splits_count: usize) -> Vec<String> {
@@ -977,18 +2527,8 @@ impl Matcher {
         }
 
         self.reset();
-        let target = self.target.iter().collect::<String>();
-        let mut splits = vec![];
-        let mut split_start = 0;
-        for m in self.by_ref() {
-            if splits.len() < splits_count {
-                splits.push(target[split_start..m.start].to_string());
-                split_start = m.end;
-            }
-        }
-        splits.push(target[split_start..target.len()].to_string());
-
-        splits
+        let target = self.target.clone();
+        split_around_matches(&target, self.by_ref(), splits_count)
     }
 
     // Split the whole target
@@ -998,31 +2538,168 @@ impl Matcher {
 
     // Return copy of target with `subs_count` substitutions replacing
     // each match with `repl`
-    pub fn subn(&mut self, repl: &str, mut subs_count: usize) -> String {
-        let target = self.target.iter().collect::<String>();
-        if subs_count == 0 {
-            return target;
-        }
+    pub fn subn(&mut self, repl: &str, subs_count: usize) -> String {
+        let target = self.target.clone();
+        substitute_matches(&target, self.by_ref(), repl, subs_count)
+    }
 
-        let mut result = String::with_capacity(self.target.len() + subs_count * repl.len() + 1);
-        let mut split_start = 0;
-        for m in self.by_ref() {
-            if subs_count > 0 {
-                result.push_str(&target[split_start..m.start]);
-                result.push_str(repl);
-                split_start = m.end;
-                subs_count -= 1;
-            } else {
-                break;
+    // Return copy of target with each match replaced with `repl`
+    pub fn sub(&mut self, repl: &str) -> String {
+        self.subn(repl, self.target.len() + 1)
+    }
+
+    // Like `subn` but `template` is expanded for each match, see `expand_replacement`
+    pub fn subn_expand(&mut self, template: &str, subs_count: usize) -> String {
+        let target = self.target.clone();
+        substitute_matches_with(
+            &target,
+            self.by_ref(),
+            |m| expand_replacement(template, &target[m.clone()].iter().collect::<String>()),
+            subs_count,
+        )
+    }
+
+    // Like `sub` but `template` is expanded for each match, see `expand_replacement`
+    pub fn sub_expand(&mut self, template: &str) -> String {
+        self.subn_expand(template, self.target.len() + 1)
+    }
+
+    // Replacements `sub_expand` would make, without making them
+    pub fn preview_sub_expand(&mut self, template: &str) -> Vec<preview::Replacement> {
+        let target = self.target.clone();
+        preview::replacements(&target, self.by_ref(), template)
+    }
+}
+
+// Copy of `pattern` whose matches begin at start of target if `start`
+// and end at end of target if `end`, like `\A(?:a|ab)\Z` for `a|ab`
+fn anchored(pattern: &ParsedRegexp, start: bool, end: bool) -> Arc<RwLock<ParsedRegexp>> {
+    surrounded(
+        pattern,
+        start.then_some(Assertion::StartOfInput),
+        end.then_some(Assertion::EndOfInput),
+    )
+}
+
+// Copy of `pattern` with assertion `before` in front of it and `after` behind it
+// like `\b(?:a|ab)\b` for `a|ab` with word boundaries
+// Assertions are added to each alternative instead, `\ba\b|\bab\b`, because
+// groups do not backtrack into their alternatives
+// Copied expressions keep their patterns and spans, assertions have empty spans at both ends
+pub(crate) fn surrounded(
+    pattern: &ParsedRegexp,
+    before: Option<Assertion>,
+    after: Option<Assertion>,
+) -> Arc<RwLock<ParsedRegexp>> {
+    let end_index = pattern.span.end;
+    let assertion = |kind: Assertion, position: usize| {
+        let mut assertion = ParsedRegexp::new(ExpressionType::Assertion { kind });
+        assertion.pattern = Arc::from(kind.to_string());
+        assertion.span = position..position;
+        Arc::new(RwLock::new(assertion))
+    };
+    let surround = |alternative: Arc<RwLock<ParsedRegexp>>| {
+        let mut concatenation = ParsedRegexp::new(ExpressionType::Concatenation);
+        let items = {
+            let alternative_ref = alternative.read().unwrap();
+            concatenation.pattern = Arc::clone(&alternative_ref.pattern);
+            concatenation.span = alternative_ref.span.clone();
+            match alternative_ref.expression_type {
+                ExpressionType::Concatenation => alternative_ref.children.read().unwrap().clone(),
+                _ => vec![Arc::clone(&alternative)],
             }
+        };
+        let items = before
+            .map(|kind| assertion(kind, 0))
+            .into_iter()
+            .chain(items)
+            .chain(after.map(|kind| assertion(kind, end_index)))
+            .collect();
+        adopt(concatenation, items)
+    };
+
+    let copy = pattern.deep_copy();
+    if pattern.expression_type != ExpressionType::Alternation {
+        return surround(copy);
+    }
+    let alternatives = copy.read().unwrap().children.read().unwrap().clone();
+    let mut alternation = ParsedRegexp::new(ExpressionType::Alternation);
+    alternation.pattern = Arc::clone(&pattern.pattern);
+    alternation.span = pattern.span.clone();
+    adopt(
+        alternation,
+        alternatives.into_iter().map(surround).collect(),
+    )
+}
+
+// `expr` having `children`, it becomes their parent
+fn adopt(
+    expr: ParsedRegexp,
+    children: Vec<Arc<RwLock<ParsedRegexp>>>,
+) -> Arc<RwLock<ParsedRegexp>> {
+    let expr = Arc::new(RwLock::new(expr));
+    for child in &children {
+        child.write().unwrap().parent = Some(Arc::downgrade(&expr));
+    }
+    *expr.read().unwrap().children.write().unwrap() = children;
+    expr
+}
+
+// Split `target` around the first `splits_count` items of `matches`
+// Used by all matchers implementing `Iterator<Item = Range<usize>>`
+pub(crate) fn split_around_matches(
+    target: &[char],
+    matches: impl Iterator<Item = Range<usize>>,
+    splits_count: usize,
+) -> Vec<String> {
+    let mut splits = vec![];
+    let mut split_start = 0;
+    for m in matches {
+        if splits.len() < splits_count {
+            splits.push(target[split_start..m.start].iter().collect());
+            split_start = m.end;
         }
-        result.push_str(&target[split_start..]);
+    }
+    splits.push(target[split_start..].iter().collect());
+
+    splits
+}
 
-        result
+// Return copy of `target` with the first `subs_count` items of `matches` replaced with `repl`
+// Used by all matchers implementing `Iterator<Item = Range<usize>>`
+pub(crate) fn substitute_matches(
+    target: &[char],
+    matches: impl Iterator<Item = Range<usize>>,
+    repl: &str,
+    subs_count: usize,
+) -> String {
+    substitute_matches_with(target, matches, |_| String::from(repl), subs_count)
+}
+
+// Like `substitute_matches` but each match is replaced with what `replacement` gives for it
+pub(crate) fn substitute_matches_with(
+    target: &[char],
+    matches: impl Iterator<Item = Range<usize>>,
+    mut replacement: impl FnMut(&Range<usize>) -> String,
+    mut subs_count: usize,
+) -> String {
+    if subs_count == 0 {
+        return target.iter().collect();
     }
 
-    // Return copy of target with each match replaced with `repl`
-    pub fn sub(&mut self, repl: &str) -> String {
-        self.subn(repl, self.target.len() + 1)
+    let mut result = String::with_capacity(target.len() + 1);
+    let mut split_start = 0;
+    for m in matches {
+        if subs_count > 0 {
+            result.extend(&target[split_start..m.start]);
+            result.push_str(&replacement(&m));
+            split_start = m.end;
+            subs_count -= 1;
+        } else {
+            break;
+        }
     }
+    result.extend(&target[split_start..]);
+
+    result
 }
@@ -0,0 +1,135 @@
+// Replacing matches of many patterns in a single pass over text
+// Each rule pairs a pattern with a template (see `expand_replacement`)
+// Text is scanned left to right and the leftmost match of any rule is replaced,
+// then scanning resumes after it, thus replaced text is never matched again
+// Rules do not interfere like sequential replacements do:
+// replacing `cat` by `dog` and then `dog` by `cat` turns every `dog` and `cat` into `cat`
+// while a single pass with both rules swaps them
+
+use std::ops::Range;
+use std::sync::{Arc, RwLock};
+
+use super::{expand_replacement, Matcher};
+use crate::parser::{syntax_tree::ParsedRegexp, Parser};
+
+// Which match is replaced when matches of several rules start at the same position
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum Conflict {
+    // Longest match, rules added first win among equally long ones
+    #[default]
+    Longest,
+    // Match of the rule added first
+    Priority,
+}
+
+// A match of one rule of a MultiReplacer
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct RuleMatch {
+    // Index of the rule, in the order rules were added
+    pub rule: usize,
+    // Range of character indices in text
+    pub range: Range<usize>,
+}
+
+#[derive(Default)]
+pub struct MultiReplacer {
+    // Pattern and template of each rule
+    rules: Vec<(Arc<RwLock<ParsedRegexp>>, String)>,
+    conflict: Conflict,
+}
+
+impl MultiReplacer {
+    pub fn new() -> MultiReplacer {
+        MultiReplacer::default()
+    }
+
+    // Add a rule replacing matches of `pattern` with expanded `template`
+    pub fn add(&mut self, pattern: &str, template: &str) -> Result<(), String> {
+        self.add_regexp(Parser::parse(pattern)?, template);
+        Ok(())
+    }
+
+    pub fn add_regexp(&mut self, regexp: Arc<RwLock<ParsedRegexp>>, template: &str) {
+        self.rules.push((regexp, template.to_string()));
+    }
+
+    pub fn conflict(&mut self, conflict: Conflict) -> &mut MultiReplacer {
+        self.conflict = conflict;
+        self
+    }
+
+    // Matches replaced by `replace`, in order
+    pub fn find_all(&self, text: &str) -> Vec<RuleMatch> {
+        let mut matchers = self
+            .rules
+            .iter()
+            .map(|(regexp, _)| Matcher::from_regexp(Arc::clone(regexp), text))
+            .collect::<Vec<_>>();
+        let length = matchers.first().map_or(0, |matcher| matcher.target.len());
+        // Next match of each rule starting at or after `pos`, if any
+        let mut next = vec![None; matchers.len()];
+        let mut found = vec![];
+        let mut pos = 0;
+        while pos <= length {
+            for (matcher, next) in matchers.iter_mut().zip(&mut next) {
+                if next
+                    .as_ref()
+                    .is_some_and(|next: &Range<usize>| next.start >= pos)
+                {
+                    continue;
+                }
+                // Matches cached by a matcher follow its own earlier matches
+                // which may have been overtaken by matches of other rules
+                matcher.match_cache.clear();
+                matcher.seek(pos);
+                *next = matcher.next();
+            }
+            let Some(chosen) = self.choose(&next) else {
+                break;
+            };
+            let range = next[chosen].clone().unwrap();
+            // Move past an empty match or it is found again and again
+            pos = range.end.max(range.start + 1);
+            found.push(RuleMatch {
+                rule: chosen,
+                range,
+            });
+        }
+        found
+    }
+
+    // Index of the rule whose match is replaced first among `next` matches of all rules
+    fn choose(&self, next: &[Option<Range<usize>>]) -> Option<usize> {
+        let start = next.iter().flatten().map(|next| next.start).min()?;
+        let mut starting = next
+            .iter()
+            .enumerate()
+            .filter_map(|(rule, next)| Some((rule, next.as_ref()?)))
+            .filter(|(_, next)| next.start == start);
+        match self.conflict {
+            Conflict::Priority => starting.next().map(|(rule, _)| rule),
+            // Earlier rules come first, keep them on ties
+            Conflict::Longest => starting
+                .rev()
+                .max_by_key(|(_, next)| next.len())
+                .map(|(rule, _)| rule),
+        }
+    }
+
+    // Copy of `text` with each match found by `find_all` replaced by its rule's expanded template
+    pub fn replace(&self, text: &str) -> String {
+        let target = text.chars().collect::<Vec<_>>();
+        let mut replaced = String::with_capacity(text.len());
+        let mut copied = 0;
+        for RuleMatch { rule, range } in self.find_all(text) {
+            replaced.extend(&target[copied..range.start]);
+            replaced.push_str(&expand_replacement(
+                &self.rules[rule].1,
+                &target[range.clone()].iter().collect::<String>(),
+            ));
+            copied = range.end;
+        }
+        replaced.extend(&target[copied..]);
+        replaced
+    }
+}
@@ -0,0 +1,31 @@
+// Observers of the matching process
+// Profilers and visualizers register one on a Matcher to be told what it is doing
+// All methods do nothing by default, implement only the ones you need
+
+use std::ops::Range;
+
+use crate::parser::syntax_tree::NodeId;
+
+pub trait MatchObserver: Send {
+    // Matcher attempts to find a match starting at `position` of target
+    fn attempt_start(&mut self, position: usize) {
+        let _ = position;
+    }
+
+    // Attempt which started at `matched.start` found a match
+    fn attempt_success(&mut self, matched: &Range<usize>) {
+        let _ = matched;
+    }
+
+    // Attempt which started at `position` found no match
+    // the next attempt (if any) starts one character later
+    fn attempt_failure(&mut self, position: usize) {
+        let _ = position;
+    }
+
+    // Subexpression `expression` is matched again from `position` to give back a shorter range
+    // because a sibling after it failed to match
+    fn backtrack(&mut self, expression: NodeId, position: usize) {
+        let _ = (expression, position);
+    }
+}
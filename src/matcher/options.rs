@@ -0,0 +1,167 @@
+// Per-search policies: how many matches, whether empty ones count and whether they may overlap
+// see Matcher::matches_with
+
+use std::collections::VecDeque;
+use std::ops::Range;
+
+use super::{CachedMatch, Matcher};
+
+// What to do with empty matches, like those of `a*` in "bab"
+// which are 0..0, 1..2, 2..2 and 3..3 when all are yielded
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum EmptyMatches {
+    // Yield every one of them
+    #[default]
+    Yield,
+    // Never yield them, only 1..2 above
+    Skip,
+    // Yield them unless where the previous match ended, so that at most
+    // one match begins or ends at each position, 0..0, 1..2 and 3..3 above
+    OnePerPosition,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub struct SearchOptions {
+    // Stop after this many matches, None for no limit
+    max_matches: Option<usize>,
+    empty_matches: EmptyMatches,
+    // Look for the next match right after start of previous one, not after its end
+    overlapping: bool,
+}
+
+impl SearchOptions {
+    pub fn new() -> SearchOptions {
+        SearchOptions::default()
+    }
+
+    pub fn max_matches(&mut self, limit: usize) -> &mut SearchOptions {
+        self.max_matches = Some(limit);
+        self
+    }
+
+    pub fn empty_matches(&mut self, policy: EmptyMatches) -> &mut SearchOptions {
+        self.empty_matches = policy;
+        self
+    }
+
+    // With `aa` in `aaaa`, overlapping matches are 0..2, 1..3 and 2..4
+    // while non-overlapping ones are 0..2 and 2..4
+    pub fn overlapping(&mut self, enabled: bool) -> &mut SearchOptions {
+        self.overlapping = enabled;
+        self
+    }
+}
+
+// Matches of a Matcher following some SearchOptions
+pub struct Matches<'m> {
+    matcher: &'m mut Matcher,
+    options: SearchOptions,
+    // Number of matches given back so far
+    yielded: usize,
+    // Where to look for next match in overlapping mode
+    next_start: usize,
+    // End of the match given back last, if any
+    last_end: Option<usize>,
+    // Matches not given back yet along with what groups captured in each
+    // all found at once when matches are first taken from the back
+    rest: Option<VecDeque<CachedMatch>>,
+}
+
+impl<'m> Matches<'m> {
+    pub(super) fn new(matcher: &'m mut Matcher, options: SearchOptions) -> Matches<'m> {
+        matcher.reset();
+        Matches {
+            matcher,
+            options,
+            yielded: 0,
+            next_start: 0,
+            last_end: None,
+            rest: None,
+        }
+    }
+
+    // Next match ignoring limit and empty matches policy
+    fn next_any(&mut self) -> Option<Range<usize>> {
+        if !self.options.overlapping {
+            return self.matcher.next();
+        }
+        if self.next_start > self.matcher.target.len() {
+            return None;
+        }
+        // Cached matches are non-overlapping, one may begin after
+        // a match overlapping previous one
+        // Also drop what this search caches, a cache missing some
+        // non-overlapping matches misleads later searches
+        self.matcher.match_cache.clear();
+        self.matcher.seek(self.next_start);
+        let found = self.matcher.next();
+        self.matcher.match_cache.clear();
+        let found = found?;
+        self.next_start = found.start + 1;
+        Some(found)
+    }
+
+    // Next match following options, found from where previous one ended
+    fn find_next(&mut self) -> Option<Range<usize>> {
+        if self
+            .options
+            .max_matches
+            .is_some_and(|limit| self.yielded >= limit)
+        {
+            return None;
+        }
+        loop {
+            let found = self.next_any()?;
+            let skipped = found.is_empty()
+                && match self.options.empty_matches {
+                    EmptyMatches::Yield => false,
+                    EmptyMatches::Skip => true,
+                    EmptyMatches::OnePerPosition => self.last_end == Some(found.start),
+                };
+            if skipped {
+                continue;
+            }
+            self.yielded += 1;
+            self.last_end = Some(found.end);
+            return Some(found);
+        }
+    }
+
+    // Give back a match taken from those found at once, see field `rest`
+    // Groups captures are those of that match
+    fn give_back(&mut self, taken: Option<CachedMatch>) -> Option<Range<usize>> {
+        let (found, captures) = taken?;
+        self.matcher.last_captures = captures;
+        Some(found)
+    }
+}
+
+impl Iterator for Matches<'_> {
+    type Item = Range<usize>;
+
+    fn next(&mut self) -> Option<Range<usize>> {
+        match &mut self.rest {
+            Some(rest) => {
+                let taken = rest.pop_front();
+                self.give_back(taken)
+            }
+            None => self.find_next(),
+        }
+    }
+}
+
+// Matches are found from start of target, thus taking one from the back
+// finds all of those not given back yet, later ones are taken from them
+impl DoubleEndedIterator for Matches<'_> {
+    fn next_back(&mut self) -> Option<Range<usize>> {
+        if self.rest.is_none() {
+            let mut rest = VecDeque::new();
+            while let Some(found) = self.find_next() {
+                rest.push_back((found, self.matcher.captures().to_vec()));
+            }
+            self.rest = Some(rest);
+        }
+        let taken = self.rest.as_mut().and_then(VecDeque::pop_back);
+        self.give_back(taken)
+    }
+}
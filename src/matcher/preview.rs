@@ -0,0 +1,56 @@
+// Previewing a bulk replace without producing the replaced text
+// Each replacement is described by what it removes and what it inserts
+// so that user interfaces can show a diff before the replace is applied
+
+use std::ops::Range;
+
+use super::{expand_replacement, Matcher};
+
+// One match replaced by an expanded template
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Replacement {
+    // Range of character indices of the match in original text
+    pub removed: Range<usize>,
+    // Text of the match
+    pub removed_text: String,
+    // Range of character indices of the inserted text in replaced text
+    pub inserted: Range<usize>,
+    // Expanded template
+    pub inserted_text: String,
+}
+
+// Replacements `sub_expand` would make with `matches` of `target`
+pub(crate) fn replacements(
+    target: &[char],
+    matches: impl Iterator<Item = Range<usize>>,
+    template: &str,
+) -> Vec<Replacement> {
+    // Difference between lengths of replaced text and original text so far
+    let mut shift = 0_isize;
+    matches
+        .map(|removed| {
+            let removed_text = target[removed.clone()].iter().collect::<String>();
+            let inserted_text = expand_replacement(template, &removed_text);
+            let inserted_length = inserted_text.chars().count();
+            let start = removed.start.saturating_add_signed(shift);
+            shift += inserted_length as isize - removed.len() as isize;
+            Replacement {
+                inserted: start..start + inserted_length,
+                removed,
+                removed_text,
+                inserted_text,
+            }
+        })
+        .collect()
+}
+
+// Replacements made by replacing each match of `pattern` in `text` with expanded `template`
+// (see `expand_replacement`), in order
+pub fn preview_replacements(
+    pattern: &str,
+    template: &str,
+    text: &str,
+) -> Result<Vec<Replacement>, String> {
+    Ok(Matcher::new(pattern, text)?.preview_sub_expand(template))
+}
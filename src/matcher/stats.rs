@@ -0,0 +1,13 @@
+// Statistics of a single search, see Matcher::find_with_stats
+
+use std::time::Duration;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub struct SearchStats {
+    // Number of target positions a match was attempted at
+    pub positions_scanned: usize,
+    // Number of times a subexpression was matched again to give back a shorter range
+    pub backtracks: usize,
+    // Wall-clock time the search took
+    pub elapsed: Duration,
+}
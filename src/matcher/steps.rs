@@ -0,0 +1,116 @@
+// Step logs
+// When enabled, Matcher records each step it takes while searching for a match
+// so one can follow why a pattern did or did not match
+
+use std::fmt::Display;
+use std::ops::Range;
+use std::sync::Arc;
+
+use crate::parser::syntax_tree::NodeId;
+
+// One step of a search
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Step {
+    // Matcher attempts to find a match starting at `position` of target
+    // When an attempt fails, the next one starts one character later
+    Attempt {
+        position: usize,
+    },
+
+    // Subexpression `id` starts matching at `position`
+    Enter {
+        id: NodeId,
+        pattern: Arc<str>,
+        position: usize,
+    },
+
+    // Subexpression `id` finished matching, `matched` is None if it failed
+    Exit {
+        id: NodeId,
+        matched: Option<Range<usize>>,
+    },
+
+    // Child `failed` of concatenation `id` failed to match
+    // thus its preceding sibling `to` is matched again, from `position`, to give back a shorter range
+    Backtrack {
+        id: NodeId,
+        failed: NodeId,
+        to: NodeId,
+        position: usize,
+    },
+
+    // Backtrack table entry of subexpression `id` is updated
+    // it is used to force the subexpression to match a shorter range when backtracking
+    TableUpdate {
+        id: NodeId,
+        last_match_start: usize,
+        last_match_end: usize,
+        backtracked_to_last_match_start: bool,
+    },
+}
+
+impl Display for Step {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Step::Attempt { position } => write!(f, "attempt match at {position}"),
+            Step::Enter {
+                id,
+                pattern,
+                position,
+            } => write!(f, "enter {id} {pattern} at {position}"),
+            Step::Exit {
+                id,
+                matched: Some(matched),
+            } => write!(f, "exit {id} matched {}..{}", matched.start, matched.end),
+            Step::Exit { id, matched: None } => write!(f, "exit {id} failed"),
+            Step::Backtrack {
+                id,
+                failed,
+                to,
+                position,
+            } => write!(
+                f,
+                "backtrack in {id}: {failed} failed, retry {to} from {position}"
+            ),
+            Step::TableUpdate {
+                id,
+                last_match_start,
+                last_match_end,
+                backtracked_to_last_match_start,
+            } => write!(
+                f,
+                "table {id}: last match {last_match_start}..{last_match_end}{}",
+                if *backtracked_to_last_match_start {
+                    ", backtracked to its start"
+                } else {
+                    ""
+                }
+            ),
+        }
+    }
+}
+
+// Steps of a search, in the order Matcher took them
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct StepLog {
+    pub steps: Vec<Step>,
+}
+
+// One line for each step, steps inside a subexpression are indented below it
+impl Display for StepLog {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let mut depth = 0_usize;
+        for step in &self.steps {
+            match step {
+                Step::Attempt { .. } => depth = 0,
+                Step::Exit { .. } => depth = depth.saturating_sub(1),
+                _ => {}
+            }
+            writeln!(f, "{}{step}", "  ".repeat(depth))?;
+            if let Step::Enter { .. } = step {
+                depth += 1;
+            }
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,143 @@
+// Replacing matches in text read from an `io::Read`, writing the result to an `io::Write`
+// Text is read in chunks and only a bounded window of it is kept in memory
+// thus huge files can be rewritten, but the pattern must have a longest match length
+// (see ParsedRegexp::max_match_len)
+// Whether a match starts at some position depends on at most that many characters
+// after it, so text is written out as soon as nothing read later can change it
+
+use std::io::{self, Read, Write};
+use std::sync::{Arc, RwLock};
+
+use super::{expand_replacement, Matcher};
+use crate::parser::{syntax_tree::ParsedRegexp, Parser};
+
+// Bytes read at once by default
+const DEFAULT_CHUNK_SIZE: usize = 64 * 1024;
+
+pub struct StreamReplacer {
+    regexp: Arc<RwLock<ParsedRegexp>>,
+    // Longest match of pattern, in characters
+    max_match_len: usize,
+    // Bytes read at once
+    chunk_size: usize,
+}
+
+impl StreamReplacer {
+    pub fn new(pattern: &str) -> Result<StreamReplacer, String> {
+        StreamReplacer::from_regexp(Parser::parse(pattern)?)
+    }
+
+    // Fails if `regexp` can match arbitrarily long strings, like `a+`
+    // or has assertions, like `^`, because the window never knows where the whole text starts and ends
+    pub fn from_regexp(regexp: Arc<RwLock<ParsedRegexp>>) -> Result<StreamReplacer, String> {
+        let max_match_len = {
+            let parsed = regexp.read().unwrap();
+            if parsed.has_assertions() {
+                return Err(format!(
+                    "Pattern `{}` has assertions, \
+                     streaming replacement only sees part of the text at once",
+                    parsed.pattern
+                ));
+            }
+            parsed.max_match_len().ok_or_else(|| {
+                format!(
+                    "Pattern `{}` can match arbitrarily long strings, \
+                     streaming replacement needs a bound on match length",
+                    parsed.pattern
+                )
+            })?
+        };
+        Ok(StreamReplacer {
+            regexp,
+            max_match_len,
+            chunk_size: DEFAULT_CHUNK_SIZE,
+        })
+    }
+
+    // Number of bytes read at once, at least one
+    pub fn chunk_size(&mut self, bytes: usize) -> &mut StreamReplacer {
+        self.chunk_size = bytes.max(1);
+        self
+    }
+
+    // Copy text from `reader` to `writer` replacing each match with expanded `template`
+    // (see `expand_replacement`) and give back number of replaced matches
+    // Output is the same as `Matcher::sub_expand` on the whole text
+    // Reading fails with `io::ErrorKind::InvalidData` if text is not valid UTF-8
+    pub fn replace<R: Read, W: Write>(
+        &self,
+        mut reader: R,
+        mut writer: W,
+        template: &str,
+    ) -> io::Result<usize> {
+        let mut matcher = Matcher::from_regexp(Arc::clone(&self.regexp), "");
+        let mut replacements = 0;
+        // Text read but not written yet
+        let mut pending = Vec::<char>::new();
+        // Bytes of a character split between chunks
+        let mut partial = Vec::<u8>::new();
+        // True if an empty match at start of `pending` was already replaced
+        let mut replaced_empty_at_start = false;
+        let mut chunk = vec![0; self.chunk_size];
+        loop {
+            let read = reader.read(&mut chunk)?;
+            let finished = read == 0;
+            partial.extend_from_slice(&chunk[..read]);
+            let decoded = decode_utf8(&mut partial, finished)?;
+            pending.extend(decoded.chars());
+
+            // Matches and skipped positions within this many characters
+            // from end of `pending` may change once more text is read
+            let margin = if finished { 0 } else { self.max_match_len };
+            let stable = pending.len().saturating_sub(margin);
+            let mut output = String::new();
+            // Index of first character of `pending` not written to `output` yet
+            let mut written = 0;
+            matcher.assign_match_target(&pending.iter().collect::<String>());
+            for found in matcher.by_ref() {
+                if !finished && found.start + self.max_match_len > pending.len() {
+                    break;
+                }
+                if found.is_empty() && found.start == 0 && replaced_empty_at_start {
+                    continue;
+                }
+                output.extend(&pending[written..found.start]);
+                output.push_str(&expand_replacement(
+                    template,
+                    &pending[found.clone()].iter().collect::<String>(),
+                ));
+                replacements += 1;
+                written = found.end;
+                replaced_empty_at_start = found.is_empty();
+            }
+            // Positions before `stable` without a match never get one
+            if written < stable {
+                output.extend(&pending[written..stable]);
+                written = stable;
+                replaced_empty_at_start = false;
+            }
+            writer.write_all(output.as_bytes())?;
+            pending.drain(..written);
+            if finished {
+                break;
+            }
+        }
+        writer.flush()?;
+        Ok(replacements)
+    }
+}
+
+// Decode and remove complete characters at start of `bytes`
+// leaving bytes of a character whose remaining bytes are not read yet
+// unless there is nothing left to read
+fn decode_utf8(bytes: &mut Vec<u8>, finished: bool) -> io::Result<String> {
+    let valid = match std::str::from_utf8(bytes) {
+        Ok(_) => bytes.len(),
+        Err(error) if error.error_len().is_none() && !finished => error.valid_up_to(),
+        Err(error) => return Err(io::Error::new(io::ErrorKind::InvalidData, error)),
+    };
+    let rest = bytes.split_off(valid);
+    let decoded = String::from_utf8(std::mem::replace(bytes, rest))
+        .expect("bytes before `valid` are valid UTF-8");
+    Ok(decoded)
+}
@@ -0,0 +1,150 @@
+// Sub-match traces
+// When enabled, Matcher records which range of target each subexpression matched
+// and after a successful match it gives back a tree mirroring the syntax tree
+// of the pattern where each node holds the range its subexpression matched
+
+use std::collections::BTreeMap;
+use std::fmt::Display;
+use std::ops::{Range, RangeInclusive};
+use std::sync::{Arc, RwLock};
+
+use crate::parser::syntax_tree::{NodeId, ParsedRegexp};
+
+// Subexpression of a pattern along with what it matched
+#[derive(Debug, Clone)]
+pub struct SubmatchTrace {
+    // Pattern of this subexpression
+    pub pattern: Arc<str>,
+
+    // Range of character indices this subexpression occupies in source pattern
+    pub span: Range<usize>,
+
+    // Identifier of this subexpression in pattern syntax tree
+    pub id: NodeId,
+
+    // Range of target matched by this subexpression
+    // None if this subexpression did not take part in the match
+    // like the unused branch of an alternation
+    // A repeated subexpression, like `a` in `(a|b)+`, holds its last iteration match
+    pub matched: Option<Range<usize>>,
+
+    // Traces of children subexpressions, in the same order as syntax tree children
+    pub children: Vec<SubmatchTrace>,
+}
+
+impl SubmatchTrace {
+    fn fmt_indented(&self, f: &mut std::fmt::Formatter<'_>, depth: usize) -> std::fmt::Result {
+        let indent = "  ".repeat(depth);
+        match &self.matched {
+            Some(matched) => writeln!(
+                f,
+                "{indent}{} => {}..{}",
+                self.pattern, matched.start, matched.end
+            )?,
+            None => writeln!(f, "{indent}{} => (no match)", self.pattern)?,
+        }
+        for child in &self.children {
+            child.fmt_indented(f, depth + 1)?;
+        }
+        Ok(())
+    }
+}
+
+// One line for each subexpression, children are indented below their parent
+// (a|b)+c => 0..3
+//   (a|b)+ => 0..2
+//     a|b => 1..2
+//       a => (no match)
+//       b => 1..2
+//   c => 2..3
+impl Display for SubmatchTrace {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        self.fmt_indented(f, 0)
+    }
+}
+
+// Ranges matched so far by subexpressions of ongoing match
+#[derive(Default)]
+pub(super) struct SubmatchRecorder {
+    // node id => (range matched, time of recording)
+    entries: BTreeMap<NodeId, (Range<usize>, usize)>,
+    // Incremented each time an entry is recorded
+    clock: usize,
+}
+
+// Entries of a subexpression and all of its descendants taken before it attempts to match
+pub(super) struct SubmatchSnapshot {
+    // Identifiers of the subexpression and its descendants
+    nodes: RangeInclusive<NodeId>,
+    taken_at: usize,
+    entries: Vec<(NodeId, (Range<usize>, usize))>,
+}
+
+impl SubmatchRecorder {
+    pub(super) fn clear(&mut self) {
+        self.entries.clear();
+    }
+
+    // Entries of a subexpression and its descendants
+    // Descendants are numbered right after their ancestor (see NodeId)
+    fn subtree(&self, nodes: &RangeInclusive<NodeId>) -> Vec<(NodeId, (Range<usize>, usize))> {
+        self.entries
+            .range(nodes.clone())
+            .map(|(key, value)| (*key, value.clone()))
+            .collect()
+    }
+
+    pub(super) fn snapshot(&self, expr: &ParsedRegexp) -> SubmatchSnapshot {
+        let nodes = expr.id..=expr.last_descendant_id();
+        SubmatchSnapshot {
+            entries: self.subtree(&nodes),
+            nodes,
+            taken_at: self.clock,
+        }
+    }
+
+    // Forget whatever subexpression of `snapshot` and its descendants recorded after
+    // snapshot was taken, like when the subexpression failed to match
+    pub(super) fn restore(&mut self, snapshot: SubmatchSnapshot) {
+        for (key, _) in self.subtree(&snapshot.nodes) {
+            self.entries.remove(&key);
+        }
+        self.entries.extend(snapshot.entries);
+    }
+
+    // Record the range matched by subexpression of `snapshot`
+    // Entries of its descendants recorded before snapshot was taken are stale
+    // because they belong to an earlier attempt, like a different alternation branch
+    pub(super) fn record(&mut self, snapshot: SubmatchSnapshot, matched: Range<usize>) {
+        for (key, (_, recorded_at)) in self.subtree(&snapshot.nodes) {
+            if recorded_at < snapshot.taken_at {
+                self.entries.remove(&key);
+            }
+        }
+        self.clock += 1;
+        self.entries
+            .insert(*snapshot.nodes.start(), (matched, self.clock));
+    }
+
+    // Build a trace tree for `expr` and its descendants
+    pub(super) fn trace(&self, expr: &Arc<RwLock<ParsedRegexp>>) -> SubmatchTrace {
+        let expr = expr.read().unwrap();
+        let children = expr
+            .children
+            .read()
+            .unwrap()
+            .iter()
+            .map(|child| self.trace(child))
+            .collect();
+        SubmatchTrace {
+            pattern: Arc::clone(&expr.pattern),
+            span: expr.span.clone(),
+            id: expr.id,
+            matched: self
+                .entries
+                .get(&expr.id)
+                .map(|(matched, _)| matched.clone()),
+            children,
+        }
+    }
+}
@@ -0,0 +1,44 @@
+// UTF-16 positions of matches
+// Matches are ranges of character indices, but editors and the Language Server Protocol
+// count positions in UTF-16 code units, where characters outside the Basic Multilingual
+// Plane (like most emoji) take two units instead of one
+
+use std::ops::Range;
+
+// Converts character indices of a haystack to UTF-16 code unit offsets
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Utf16Map {
+    // offsets[i] is UTF-16 offset of character i
+    // there is one more offset than characters, for the end of haystack
+    offsets: Vec<usize>,
+}
+
+impl Utf16Map {
+    pub fn new(haystack: &str) -> Utf16Map {
+        let mut offsets = Vec::with_capacity(haystack.len() + 1);
+        let mut offset = 0;
+        offsets.push(offset);
+        for ch in haystack.chars() {
+            offset += ch.len_utf16();
+            offsets.push(offset);
+        }
+        Utf16Map { offsets }
+    }
+
+    // UTF-16 offset of character at `char_index`
+    // Indices past the end of haystack are clamped to its end
+    pub fn offset(&self, char_index: usize) -> usize {
+        self.offsets[char_index.min(self.offsets.len() - 1)]
+    }
+
+    // Range of UTF-16 code units covered by `m`
+    pub fn range(&self, m: &Range<usize>) -> Range<usize> {
+        self.offset(m.start)..self.offset(m.end)
+    }
+}
+
+// UTF-16 code unit ranges of `matches` found in `haystack`
+pub fn utf16_ranges(haystack: &str, matches: &[Range<usize>]) -> Vec<Range<usize>> {
+    let map = Utf16Map::new(haystack);
+    matches.iter().map(|m| map.range(m)).collect()
+}
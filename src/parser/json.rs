@@ -0,0 +1,283 @@
+// Syntax trees as JSON documents
+// Let tools written in other languages consume parsed patterns
+// or supply their own trees to be matched, each node looks like:
+// {
+//   "expression_type": {"Group": {"quantifier": "OneOrMore"}},
+//   "pattern": "(a|b)+",
+//   "span": {"start": 0, "end": 6},
+//   "id": 0,
+//   "children": [...]
+// }
+// When reading a tree only "expression_type" and "children" are required
+// patterns, spans and ids are computed again from tree structure
+
+use std::sync::{Arc, RwLock};
+
+use serde::ser::SerializeStruct;
+use serde::{Deserialize, Serialize, Serializer};
+
+use super::{class_escape, syntax_tree::*, MAX_REPETITION};
+use crate::scanner::{escape_char, is_class_metacharacter, is_metacharacter};
+
+impl Serialize for ParsedRegexp {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let children = self
+            .children
+            .read()
+            .unwrap()
+            .iter()
+            .map(|child| child.read().unwrap().clone())
+            .collect::<Vec<_>>();
+        let mut node = serializer.serialize_struct("ParsedRegexp", 5)?;
+        node.serialize_field("expression_type", &self.expression_type)?;
+        node.serialize_field("pattern", self.pattern.as_ref())?;
+        node.serialize_field("span", &self.span)?;
+        node.serialize_field("id", &self.id)?;
+        node.serialize_field("children", &children)?;
+        node.end()
+    }
+}
+
+// A node as read from JSON, before it is checked and linked to its parent
+#[derive(Deserialize)]
+struct JsonNode {
+    expression_type: ExpressionType,
+    #[serde(default)]
+    children: Vec<JsonNode>,
+}
+
+impl ParsedRegexp {
+    // JSON document describing this expression and all of its subexpressions
+    pub fn to_json(&self) -> String {
+        serde_json::to_string(self).unwrap()
+    }
+
+    // Read a syntax tree from JSON (see `to_json`)
+    pub fn from_json(json: &str) -> Result<Arc<RwLock<ParsedRegexp>>, String> {
+        let root = serde_json::from_str::<JsonNode>(json)
+            .map_err(|error| format!("Invalid syntax tree JSON: {error}"))?;
+        let root = build(root, &mut 0, false)?;
+        ParsedRegexp::assign_ids(&root);
+        // Same check as the parser does, groups are opened in pre-order
+        let mut opened_groups = 0;
+        for visited in ParsedRegexp::iter(&root) {
+            let expr = visited.expression.read().unwrap();
+            match expr.expression_type {
+                expression_type if expression_type.is_capturing_group() => opened_groups += 1,
+                ExpressionType::Backreference { group, .. } if group > opened_groups => {
+                    return Err(format!(
+                        "Invalid syntax tree JSON: {:?} refers to a group not opened before it",
+                        expr.expression_type
+                    ));
+                }
+                _ => {}
+            }
+        }
+        // Like the parser, root pattern begins with inline flags the tree needs
+        // and spans are within that pattern
+        let flags = root.read().unwrap().inline_flags();
+        if !flags.is_empty() {
+            for visited in ParsedRegexp::iter(&root) {
+                let mut expr = visited.expression.write().unwrap();
+                expr.span = expr.span.start + flags.len()..expr.span.end + flags.len();
+            }
+            let mut root = root.write().unwrap();
+            root.pattern = Arc::from(format!("{flags}{}", root.pattern));
+        }
+        Ok(root)
+    }
+}
+
+// Build a syntax tree node from a JSON node
+// `position` is where the pattern of this node begins in the pattern of the whole tree
+// `in_class` tells whether the node is a member of a character class
+fn build(
+    node: JsonNode,
+    position: &mut usize,
+    in_class: bool,
+) -> Result<Arc<RwLock<ParsedRegexp>>, String> {
+    let is_member = matches!(
+        node.expression_type,
+        ExpressionType::CharacterExpression {
+            value: Some(_),
+            quantifier: Quantifier::None,
+            ..
+        } | ExpressionType::ShorthandClass {
+            quantifier: Quantifier::None,
+            ..
+        } | ExpressionType::CharacterClass {
+            quantifier: Quantifier::None
+        } | ExpressionType::CharacterRange { .. }
+    );
+    if in_class && !is_member {
+        return Err(format!(
+            "Invalid syntax tree JSON: {:?} can not be a member of a character class",
+            node.expression_type
+        ));
+    }
+    if let ExpressionType::CharacterRange { first, last } = node.expression_type {
+        if !in_class {
+            return Err(format!(
+                "Invalid syntax tree JSON: {:?} must be a member of a character class",
+                node.expression_type
+            ));
+        }
+        if first > last {
+            return Err(format!(
+                "Invalid syntax tree JSON: {:?} is reversed, {first:?} comes after {last:?}",
+                node.expression_type
+            ));
+        }
+    }
+    if let Quantifier::Counted { min, max } | Quantifier::Lazy { min, max } =
+        node.expression_type.quantifier()
+    {
+        // Same checks as the parser does
+        if max.is_some_and(|max| max < min) || max.unwrap_or(min) > MAX_REPETITION {
+            return Err(format!(
+                "Invalid syntax tree JSON: {:?} has reversed counts or counts above {MAX_REPETITION}",
+                node.expression_type
+            ));
+        }
+    }
+    let children_count = node.children.len();
+    let expected = match node.expression_type {
+        ExpressionType::EmptyExpression
+        | ExpressionType::Assertion { .. }
+        | ExpressionType::CharacterExpression { .. }
+        | ExpressionType::ShorthandClass { .. }
+        | ExpressionType::CharacterRange { .. }
+        | ExpressionType::Backreference { .. } => children_count == 0,
+        ExpressionType::Group { .. } | ExpressionType::Lookbehind { .. } => children_count == 1,
+        ExpressionType::CharacterClass { .. } => children_count >= 1,
+        ExpressionType::Concatenation | ExpressionType::Alternation => children_count >= 2,
+    };
+    if !expected {
+        return Err(format!(
+            "Invalid syntax tree JSON: {:?} can not have {children_count} children",
+            node.expression_type
+        ));
+    }
+
+    let start = *position;
+    let mut children = vec![];
+    let pattern = match node.expression_type {
+        ExpressionType::EmptyExpression => String::new(),
+        ExpressionType::Assertion { kind } => kind.to_string(),
+        ExpressionType::CharacterExpression {
+            value, quantifier, ..
+        } => {
+            // Characters special inside a class differ from those special outside
+            let is_special = if in_class {
+                is_class_metacharacter
+            } else {
+                is_metacharacter
+            };
+            let pattern = match value {
+                Some(ch) => format!("{}{quantifier}", escape_char(ch, is_special)),
+                None => format!(".{quantifier}"),
+            };
+            *position += pattern.chars().count();
+            pattern
+        }
+        ExpressionType::ShorthandClass { class, quantifier } => {
+            let pattern = format!("{class}{quantifier}");
+            *position += pattern.chars().count();
+            pattern
+        }
+        ExpressionType::CharacterRange { first, last } => {
+            let pattern = format!("{}-{}", class_escape(first), class_escape(last));
+            *position += pattern.chars().count();
+            pattern
+        }
+        ExpressionType::Backreference {
+            group, quantifier, ..
+        } => {
+            // Only \1 to \9 can be written
+            if !(1..=9).contains(&group) {
+                return Err(format!(
+                    "Invalid syntax tree JSON: {:?} must refer to a group from 1 to 9",
+                    node.expression_type
+                ));
+            }
+            let pattern = format!("\\{group}{quantifier}");
+            *position += pattern.chars().count();
+            pattern
+        }
+        ExpressionType::Group { .. } | ExpressionType::Lookbehind { .. } => {
+            let quantifier = node.expression_type.quantifier();
+            let opening = node.expression_type.group_opening();
+            // Move past (, (?:, (?>, (?<= or (?<!
+            *position += opening.len();
+            let child = build(node.children.into_iter().next().unwrap(), position, false)?;
+            if let ExpressionType::Lookbehind { .. } = node.expression_type {
+                // Same check as the parser does
+                let child = child.read().unwrap();
+                if child.max_match_len() != Some(child.min_match_len()) {
+                    return Err(format!(
+                        "Invalid syntax tree JSON: {:?} encloses `{}` which matches strings of different lengths",
+                        node.expression_type, child.pattern
+                    ));
+                }
+            }
+            let pattern = format!("{opening}{}){quantifier}", child.read().unwrap().pattern);
+            // Move past ) and quantifier
+            *position += 1 + quantifier.to_string().len();
+            children.push(child);
+            pattern
+        }
+        ExpressionType::CharacterClass { quantifier } => {
+            // Move past [
+            *position += 1;
+            let mut members = String::new();
+            for member in node.children {
+                let member = build(member, position, true)?;
+                members.push_str(&member.read().unwrap().pattern);
+                children.push(member);
+            }
+            // Move past ] and quantifier
+            *position += 1 + quantifier.to_string().len();
+            format!("[{members}]{quantifier}")
+        }
+        ExpressionType::Concatenation | ExpressionType::Alternation => {
+            let separator = match node.expression_type {
+                ExpressionType::Alternation => "|",
+                _ => "",
+            };
+            let mut patterns = vec![];
+            for (index, child) in node.children.into_iter().enumerate() {
+                if index > 0 {
+                    *position += separator.len();
+                }
+                // Alternation has lower precedence than concatenation, `(?:a|b)c` is not `a|bc`
+                let grouped = separator.is_empty()
+                    && matches!(child.expression_type, ExpressionType::Alternation);
+                if grouped {
+                    // Move past (?:
+                    *position += 3;
+                }
+                let child = build(child, position, false)?;
+                let pattern = child.read().unwrap().pattern.to_string();
+                if grouped {
+                    // Move past )
+                    *position += 1;
+                    patterns.push(format!("(?:{pattern})"));
+                } else {
+                    patterns.push(pattern);
+                }
+                children.push(child);
+            }
+            patterns.join(separator)
+        }
+    };
+
+    let mut expr = ParsedRegexp::new(node.expression_type);
+    expr.pattern = Arc::from(pattern);
+    expr.span = start..*position;
+    let expr = Arc::new(RwLock::new(expr));
+    for child in &children {
+        child.write().unwrap().parent = Some(Arc::downgrade(&expr));
+    }
+    *expr.read().unwrap().children.write().unwrap() = children;
+    Ok(expr)
+}
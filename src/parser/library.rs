@@ -0,0 +1,130 @@
+// Named pattern fragments
+// Fragments are defined once and referenced by name inside other patterns
+// For instance, after defining fragment `digit` as `0|1|2|3|4|5|6|7|8|9`
+// pattern `{digit}+-{digit}+` means `(0|1|2|3|4|5|6|7|8|9)+-(0|1|2|3|4|5|6|7|8|9)+`
+// A reference is a name inside braces, a name is a letter or _ followed by letters, digits or _
+// Braces which do not enclose a name, or preceded by a slash, are left as they are
+// References are replaced by their (grouped) fragments before parsing
+// thus spans of parsed subexpressions refer to the expanded pattern
+
+use std::collections::HashMap;
+use std::sync::{Arc, RwLock};
+
+use super::{syntax_tree::ParsedRegexp, Parser};
+use crate::diagnostics::DiagnosticCode;
+use crate::format_error;
+use crate::matcher::Matcher;
+
+#[derive(Debug, Clone, Default)]
+pub struct PatternLibrary {
+    // name => pattern
+    fragments: HashMap<String, String>,
+}
+
+impl PatternLibrary {
+    pub fn new() -> PatternLibrary {
+        PatternLibrary::default()
+    }
+
+    // Define (or redefine) fragment `name`
+    // `pattern` may reference other fragments, even ones defined later
+    pub fn define(&mut self, name: &str, pattern: &str) -> Result<(), String> {
+        if !is_valid_name(name) {
+            return Err(format!(
+                "Invalid fragment name `{name}`\n\
+                A name is a letter or _ followed by letters, digits or _"
+            ));
+        }
+        self.fragments.insert(name.to_string(), pattern.to_string());
+        Ok(())
+    }
+
+    // Pattern of fragment `name`, as it was defined
+    pub fn get(&self, name: &str) -> Option<&str> {
+        self.fragments.get(name).map(String::as_str)
+    }
+
+    // Replace all fragment references in `pattern`
+    pub fn expand(&self, pattern: &str) -> Result<String, String> {
+        self.expand_nested(pattern, &mut vec![])
+    }
+
+    // Expand references in `pattern` then parse it
+    pub fn parse(&self, pattern: &str) -> Result<Arc<RwLock<ParsedRegexp>>, String> {
+        Parser::parse(&self.expand(pattern)?)
+    }
+
+    // Expand references in `pattern` then create a matcher from it
+    pub fn matcher(&self, pattern: &str, target: &str) -> Result<Matcher, String> {
+        Matcher::new(&self.expand(pattern)?, target)
+    }
+
+    // `expanding` holds names of fragments being expanded, from outermost to innermost
+    fn expand_nested(&self, pattern: &str, expanding: &mut Vec<String>) -> Result<String, String> {
+        let source = pattern.chars().collect::<Vec<_>>();
+        let mut expanded = String::with_capacity(pattern.len());
+        let mut current = 0;
+        while current < source.len() {
+            let ch = source[current];
+            if ch == '\\' {
+                // Copy escape sequences verbatim, `\{` is never a reference
+                expanded.extend(&source[current..(current + 2).min(source.len())]);
+                current += 2;
+                continue;
+            }
+            if ch == '{' {
+                let name_end = source[current + 1..]
+                    .iter()
+                    .position(|ch| *ch == '}')
+                    .map(|offset| current + 1 + offset);
+                let name = name_end.map(|end| source[current + 1..end].iter().collect::<String>());
+                if let (Some(end), Some(name)) = (name_end, name.filter(|name| is_valid_name(name)))
+                {
+                    let error = |code: DiagnosticCode, message: &str, hints: &str| {
+                        format_error(
+                            &format!(
+                                "Pattern library error [{code}] in position {current}: {message}"
+                            ),
+                            pattern,
+                            &[(current, (end + 1 - current).min(u8::MAX as usize) as u8)],
+                            hints,
+                        )
+                    };
+                    let Some(fragment) = self.fragments.get(&name) else {
+                        return Err(error(
+                            DiagnosticCode::UndefinedFragment,
+                            &format!("Undefined fragment `{name}`"),
+                            "Define it first using PatternLibrary::define",
+                        ));
+                    };
+                    if expanding.contains(&name) {
+                        return Err(error(
+                            DiagnosticCode::RecursiveFragment,
+                            &format!("Fragment `{name}` references itself"),
+                            &format!("References chain: {} -> {name}", expanding.join(" -> ")),
+                        ));
+                    }
+                    expanding.push(name);
+                    let fragment = self.expand_nested(fragment, expanding)?;
+                    expanding.pop();
+                    // Group fragment so that it is used as a whole
+                    // like `{word}+` repeating the whole fragment `word`
+                    expanded.push('(');
+                    expanded.push_str(&fragment);
+                    expanded.push(')');
+                    current = end + 1;
+                    continue;
+                }
+            }
+            expanded.push(ch);
+            current += 1;
+        }
+        Ok(expanded)
+    }
+}
+
+fn is_valid_name(name: &str) -> bool {
+    let mut chars = name.chars();
+    matches!(chars.next(), Some(first) if first.is_alphabetic() || first == '_')
+        && chars.all(|ch| ch.is_alphanumeric() || ch == '_')
+}
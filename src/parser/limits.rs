@@ -0,0 +1,33 @@
+// Bounds on the size of patterns the parser accepts
+// Patterns crafted to be huge or deeply nested may exhaust memory, or the stack
+// while the parser recurses into groups and classes, these are rejected before that happens
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct ParseLimits {
+    // Longest pattern accepted, in characters
+    pub max_pattern_length: usize,
+    // Most nodes in the syntax tree of a pattern
+    // Case insensitive characters and some escapes are built of more than one node
+    pub max_nodes: usize,
+    // Most groups and classes nested inside each other, `((a))` and `[[a]]` have depth 2
+    pub max_nesting_depth: usize,
+}
+
+impl ParseLimits {
+    // No bounds at all, what `Parser::parse` uses
+    pub const UNLIMITED: ParseLimits = ParseLimits {
+        max_pattern_length: usize::MAX,
+        max_nodes: usize::MAX,
+        max_nesting_depth: usize::MAX,
+    };
+}
+
+impl Default for ParseLimits {
+    fn default() -> ParseLimits {
+        ParseLimits {
+            max_pattern_length: 10_000,
+            max_nodes: 20_000,
+            max_nesting_depth: 128,
+        }
+    }
+}
@@ -4,12 +4,35 @@
 // Syntax tree structs
 pub mod syntax_tree;
 
-use crate::scanner::{tokens::*, Scanner};
-use crate::{format_error, report_fatal_error};
+// Named pattern fragments referenced inside other patterns
+pub mod library;
+
+// Non-fatal findings while parsing
+pub mod warning;
+
+// Restrictions for patterns from untrusted sources
+pub mod untrusted;
+
+// Bounds on pattern size enforced while parsing
+pub mod limits;
+
+// Syntax trees as JSON documents
+#[cfg(feature = "serde")]
+pub mod json;
+
+use crate::diagnostics::{DiagnosticCode, DiagnosticLevels, Level};
+use crate::scanner::{
+    error::ScanError, escape_char, is_class_metacharacter, is_metacharacter, tokens::*, Scanner,
+};
+use crate::{
+    format_error_labeled_with, format_error_with, report_fatal_error, telemetry, ErrorLabel,
+};
+use limits::ParseLimits;
+use std::ops::Range;
 use std::sync::{Arc, RwLock};
 use syntax_tree::*;
+use warning::{ParseWarning, WarningKind};
 
-#[allow(dead_code)]
 // Mark where to a grouping begins
 enum GroupingMark {
     // There is a ( in index `position` in source string
@@ -30,6 +53,30 @@ pub struct Parser {
     // marks stack
     // we need a stack because groups (...) can nest
     grouping_marks: Vec<GroupingMark>,
+
+    // Non-fatal findings so far
+    warnings: Vec<ParseWarning>,
+
+    // Number of capturing groups opened so far
+    // a backreference can only refer to one of them, like `\1` in `(a)\1`
+    opened_groups: usize,
+
+    // What ends lines for dots and for `^` and `$` in multiline mode
+    // unless the pattern turns on CRLF mode (inline flag `R`)
+    line_terminator: LineTerminator,
+
+    // Largest pattern accepted, see `parse_with_limits`
+    limits: ParseLimits,
+
+    // Character classes currently open, `[[a]` has 2 open
+    // they count toward nesting depth like groups do
+    open_classes: usize,
+
+    // Syntax tree nodes built so far, see `count_nodes`
+    nodes: usize,
+
+    // Whether errors are colored for a terminal, see `parse_with_colored_errors`
+    colored_errors: bool,
 }
 
 impl Parser {
@@ -37,20 +84,197 @@ impl Parser {
         let scanner = Scanner::new(source);
         let current = None;
         let grouping_marks = vec![];
+        let warnings = vec![];
+        let opened_groups = 0;
+        let line_terminator = LineTerminator::default();
+        let limits = ParseLimits::UNLIMITED;
+        let open_classes = 0;
+        let nodes = 0;
+        let colored_errors = false;
         Parser {
             scanner,
             current,
             grouping_marks,
+            warnings,
+            opened_groups,
+            line_terminator,
+            limits,
+            open_classes,
+            nodes,
+            colored_errors,
         }
     }
 
     pub fn parse(source: &str) -> Result<Arc<RwLock<ParsedRegexp>>, String> {
         // parse source string into a `ParsedRegexp` object
-        Parser::new(source).parse_source()
+        telemetry::compile("parse", Some(source), || Parser::new(source).parse_source())
+    }
+
+    // Parse source string where lines end with `terminator` instead of \n
+    // The dot does not match it and `^` and `$` in multiline mode hold around it
+    // like `\0` for NUL separated records, CRLF mode (inline flag `R`) takes precedence
+    pub fn parse_with_line_terminator(
+        source: &str,
+        terminator: char,
+    ) -> Result<Arc<RwLock<ParsedRegexp>>, String> {
+        telemetry::compile("parse", Some(source), || {
+            let mut parser = Parser::new(source);
+            parser.line_terminator = LineTerminator::Char(terminator);
+            parser.parse_source()
+        })
+    }
+
+    // Parse source string unless it is larger than `limits` allow
+    // Patterns from services' users should be parsed this way, see also `parse_untrusted`
+    pub fn parse_with_limits(
+        source: &str,
+        limits: &ParseLimits,
+    ) -> Result<Arc<RwLock<ParsedRegexp>>, String> {
+        telemetry::compile("parse", Some(source), || {
+            let mut parser = Parser::new(source);
+            parser.limits = *limits;
+            parser.parse_source()
+        })
+    }
+
+    // Parse source string in case-insensitive mode, as if it began with inline flag `i`
+    pub fn parse_case_insensitive(source: &str) -> Result<Arc<RwLock<ParsedRegexp>>, String> {
+        telemetry::compile("parse", Some(source), || {
+            let mut parser = Parser::new(source);
+            parser.scanner.set_case_insensitive();
+            parser.parse_source()
+        })
+    }
+
+    // Syntax tree matching `text` literally, metacharacters included, `a.b` matches only "a.b"
+    // Nothing is scanned, thus unlike parsing `escape(text)` this is quicker and never fails
+    pub fn parse_literal(text: &str) -> Arc<RwLock<ParsedRegexp>> {
+        Parser::literal(text, false)
+    }
+
+    // Syntax tree matching `text` literally, ignoring case, `ab` matches "aB" too
+    pub fn parse_literal_case_insensitive(text: &str) -> Arc<RwLock<ParsedRegexp>> {
+        Parser::literal(text, true)
+    }
+
+    fn literal(text: &str, case_insensitive: bool) -> Arc<RwLock<ParsedRegexp>> {
+        telemetry::compile("parse", Some(text), || {
+            let mut characters = text
+                .chars()
+                .enumerate()
+                .map(|(position, ch)| {
+                    let others = if case_insensitive {
+                        other_cases(ch)
+                    } else {
+                        vec![]
+                    };
+                    if others.is_empty() {
+                        return literal_character(ch, position);
+                    }
+                    // A class listing all cases of the character, like parsing `(?i)k` gives
+                    let span = position..position + 1;
+                    let members = std::iter::once(ch)
+                        .chain(others)
+                        .map(|ch| class_member(ch, span.clone()))
+                        .collect();
+                    character_class(members, Quantifier::None, span)
+                })
+                .collect::<Vec<_>>();
+            let regexp = match characters.len() {
+                0 => Arc::new(RwLock::new(ParsedRegexp::new(
+                    ExpressionType::EmptyExpression,
+                ))),
+                1 => characters.pop().unwrap(),
+                length => {
+                    let mut concatenation = ParsedRegexp::new(ExpressionType::Concatenation);
+                    concatenation.pattern = Arc::from(
+                        characters
+                            .iter()
+                            .map(|ch| ch.read().unwrap().pattern.to_string())
+                            .collect::<String>(),
+                    );
+                    concatenation.span = 0..length;
+                    let concatenation = Arc::new(RwLock::new(concatenation));
+                    for ch in &characters {
+                        ch.write().unwrap().parent = Some(Arc::downgrade(&concatenation));
+                    }
+                    *concatenation.read().unwrap().children.write().unwrap() = characters;
+                    concatenation
+                }
+            };
+            ParsedRegexp::assign_ids(&regexp);
+            regexp
+        })
+    }
+
+    // Parse source string and also report non-fatal findings (warnings)
+    pub fn parse_with_warnings(
+        source: &str,
+    ) -> Result<(Arc<RwLock<ParsedRegexp>>, Vec<ParseWarning>), String> {
+        telemetry::compile("parse", Some(source), || {
+            let mut parser = Parser::new(source);
+            let regexp = parser.parse_source()?;
+            Ok((regexp, parser.warnings))
+        })
+    }
+
+    // Parse source string like `parse_with_warnings`, coloring errors
+    // for printing them to a terminal when `colored` (see `format_error_with`)
+    pub fn parse_with_colored_errors(
+        source: &str,
+        colored: bool,
+    ) -> Result<(Arc<RwLock<ParsedRegexp>>, Vec<ParseWarning>), String> {
+        telemetry::compile("parse", Some(source), || {
+            let mut parser = Parser::new(source);
+            parser.colored_errors = colored;
+            let regexp = parser.parse_source()?;
+            Ok((regexp, parser.warnings))
+        })
+    }
+
+    // Parse source string treating warnings as errors
+    // The first warning, if any, is reported as an error
+    pub fn parse_strict(source: &str) -> Result<Arc<RwLock<ParsedRegexp>>, String> {
+        let (regexp, _) = Parser::parse_with_levels(source, &DiagnosticLevels::all(Level::Deny))?;
+        Ok(regexp)
+    }
+
+    // Parse source string handling each warning according to its level in `levels`:
+    // allowed warnings are dropped and the first denied one, if any, is reported as an error
+    pub fn parse_with_levels(
+        source: &str,
+        levels: &DiagnosticLevels,
+    ) -> Result<(Arc<RwLock<ParsedRegexp>>, Vec<ParseWarning>), String> {
+        let (regexp, mut warnings) = Parser::parse_with_warnings(source)?;
+        if let Some(denied) = warnings
+            .iter()
+            .find(|warning| levels.level(warning.code()) == Level::Deny)
+        {
+            return Err(denied.render(source));
+        }
+        warnings.retain(|warning| levels.level(warning.code()) != Level::Allow);
+        Ok((regexp, warnings))
+    }
+
+    // Parse source string supplied by an untrusted user, rejecting it
+    // unless it follows `profile` (see `untrusted`)
+    pub fn parse_untrusted(
+        source: &str,
+        profile: &untrusted::UntrustedProfile,
+    ) -> Result<Arc<RwLock<ParsedRegexp>>, String> {
+        profile.parse(source)
     }
 
     // Attempt to parse source string
     fn parse_source(&mut self) -> Result<Arc<RwLock<ParsedRegexp>>, String> {
+        let length = self.scanner.get_source_string().chars().count();
+        if length > self.limits.max_pattern_length {
+            let code = DiagnosticCode::PatternTooLong;
+            return Err(format!(
+                "Rejected pattern [{code}]: Pattern has {length} characters, at most {} are allowed",
+                self.limits.max_pattern_length
+            ));
+        }
         // Grab the first token in stream
         self.advance()?;
         match self.parse_expression() {
@@ -62,6 +286,13 @@ impl Parser {
                 // `option_regexp` has type Option<Arc<RwLock<ParsedRegexp>>>
                 match option_regexp {
                     Some(regexp) => {
+                        ParsedRegexp::assign_ids(&regexp);
+                        // Root pattern stands for the whole pattern, modes included
+                        let flags = regexp.read().unwrap().inline_flags();
+                        if !flags.is_empty() {
+                            let mut root = regexp.write().unwrap();
+                            root.pattern = Arc::from(format!("{flags}{}", root.pattern));
+                        }
                         // Return the Arc itself otherwise it will dropped making direct child of
                         // root expression hold invalid Weak references to their parent (root itself)
                         Ok(regexp)
@@ -94,14 +325,25 @@ impl Parser {
                 match token.type_name {
                     // This token can begin a valid expression
                     TokenType::Empty
-                    | TokenType::Dot { .. }
+                    | TokenType::Dot
                     | TokenType::Character { .. }
-                    | TokenType::LeftParen => {
+                    | TokenType::ShorthandClass { .. }
+                    | TokenType::Backreference { .. }
+                    | TokenType::Linebreak
+                    | TokenType::Caret
+                    | TokenType::Dollar
+                    | TokenType::StartAnchor
+                    | TokenType::EndAnchor
+                    | TokenType::WordBoundary
+                    | TokenType::NonWordBoundary
+                    | TokenType::LeftParen
+                    | TokenType::LeftBracket => {
                         // Attempt to parse an arbitrary expression
                         // But do that attempt to parse an alternation expression
                         // because alternation has the lowest precedence of all regular expressions operations
                         let mut alternation_pattern = String::new();
                         let mut alternation = ParsedRegexp::new(ExpressionType::Alternation);
+                        let start = self.position();
 
                         // First, attempt to parse one concatenation
                         if let Some(concatenation) = self.parse_concatenation()? {
@@ -146,11 +388,25 @@ impl Parser {
                             _ => {
                                 // Remove trailing |
                                 alternation_pattern.pop();
+                                self.count_nodes(1)?;
 
                                 // At least two expressions were parsed
                                 // Composed an alternation expression
                                 // Its children are already inside it, in ParsedRegexp field `children`
                                 alternation.pattern = Arc::from(alternation_pattern);
+                                alternation.span = start..self.position();
+                                for child in alternation.children.read().unwrap().iter() {
+                                    let child = child.read().unwrap();
+                                    if matches!(
+                                        child.expression_type,
+                                        ExpressionType::EmptyExpression
+                                    ) {
+                                        self.warnings.push(ParseWarning {
+                                            kind: WarningKind::EmptyAlternative,
+                                            span: child.span.clone(),
+                                        });
+                                    }
+                                }
                                 let alternation = Arc::new(RwLock::new(alternation));
                                 alternation
                                     .write()
@@ -173,21 +429,17 @@ impl Parser {
                     _ => {
                         // Any token which can not begin a valid expression, like + or *
                         let source = self.scanner.get_source_string();
-                        let error_char = &source[token.position..=token.position];
-                        let error = format!("Expected expression before {error_char}");
-                        let (error_index, error_position) = {
-                            match self.current {
-                                Some(Token { position, .. }) => {
-                                    (position, format!("in position {position}"))
-                                }
-                                None => (source.len(), String::from("at end of pattern")), // in case parser reached end of input
-                            }
-                        };
-                        Err(format_error(
-                            &format!("Syntax error {error_position}: {error}"),
+                        let error_token =
+                            &source[token.byte_position..token.byte_position + token.byte_length];
+                        let error = format!("Expected expression before {error_token}");
+                        let (error_index, carets, error_position) = self.error_site();
+                        let code = DiagnosticCode::ExpectedExpression;
+                        Err(format_error_with(
+                            &format!("Syntax error [{code}] {error_position}: {error}"),
                             &source,
-                            &[(error_index, 1_u8)],
+                            &[(error_index, carets)],
                             "",
+                            self.colored_errors,
                         ))
                     }
                 }
@@ -201,6 +453,7 @@ impl Parser {
 
         let mut concatenation_pattern = String::new();
         let mut concatenation = ParsedRegexp::new(ExpressionType::Concatenation);
+        let start = self.position();
         while let Some(primary_expression) = self.parse_primary()? {
             // Parsed a new expression
             // Append its pattern
@@ -232,7 +485,9 @@ impl Parser {
                 // At least two expressions were parsed
                 // Composed a concatenation expression
                 // Its children are already inside it, in ParsedRegexp field `children`
+                self.count_nodes(1)?;
                 concatenation.pattern = Arc::from(concatenation_pattern);
+                concatenation.span = start..self.position();
                 let concatenation = Arc::new(RwLock::new(concatenation));
                 concatenation
                     .write()
@@ -252,7 +507,8 @@ impl Parser {
         }
     }
 
-    // Primary => Empty | Group | MatchCharacter | MatchAnyCharacter
+    // Primary => Empty | Group | MatchCharacter | MatchAnyCharacter | MatchCharacterClass
+    //          | MatchShorthandClass | Assertion | Backreference | Linebreak
     fn parse_primary(&mut self) -> Result<Option<Arc<RwLock<ParsedRegexp>>>, String> {
         // WHAT DO YOU DO `parse_primary`?
         // I parse primary expressions, which are:
@@ -260,22 +516,56 @@ impl Parser {
         // - The dot expression `.`
         // - Character expressions like `x`
         // - Grouped regular expressions, like `(abc)`
+        // - Character classes, like `[abc]`
+        // - Shorthand classes, like `\d`
+        // - Assertions, like `^`
+        // - Backreferences, like `\1`
+        // - Line breaks, `\R`
 
-        match self.current {
+        // Groups and lookbehinds begin with (
+        let group = self.check(TokenType::LeftParen);
+        let primary = match self.current {
             Some(token) => {
                 match &token.type_name {
                     TokenType::Empty => self.parse_empty_expression(),
                     TokenType::Dot => self.parse_dot_expression(),
                     TokenType::Character { value, .. } => self.parse_character_expression(*value),
                     TokenType::LeftParen => self.parse_group(),
+                    TokenType::LeftBracket => self.parse_class(),
+                    TokenType::ShorthandClass { letter } => self.parse_shorthand_class(*letter),
+                    TokenType::Backreference { group } => self.parse_backreference(*group),
+                    TokenType::Linebreak => self.parse_linebreak(),
+                    TokenType::Caret if self.scanner.is_multiline() => {
+                        self.parse_assertion(Assertion::StartOfLine(self.line_terminator()))
+                    }
+                    TokenType::Dollar if self.scanner.is_multiline() => {
+                        self.parse_assertion(Assertion::EndOfLine(self.line_terminator()))
+                    }
+                    TokenType::Caret => self.parse_assertion(Assertion::Start),
+                    TokenType::Dollar => self.parse_assertion(Assertion::End),
+                    TokenType::StartAnchor => self.parse_assertion(Assertion::StartOfInput),
+                    TokenType::EndAnchor => self.parse_assertion(Assertion::EndOfInput),
+                    TokenType::WordBoundary => self.parse_assertion(Assertion::WordBoundary),
+                    TokenType::NonWordBoundary => self.parse_assertion(Assertion::NonWordBoundary),
                     _ => Ok(None), // Current token can begin a valid expression
                 }
             }
             None => Ok(None), // End of pattern
+        }?;
+        if let Some(primary) = &primary {
+            let nodes = if group {
+                // Expression it encloses was counted while it was parsed
+                1
+            } else {
+                ParsedRegexp::iter(primary).count()
+            };
+            self.count_nodes(nodes)?;
         }
+        Ok(primary)
     }
 
     // Group => "(" ParsedRegexp ")"
+    // Lookbehind => ( "(?<=" | "(?<!" ) ParsedRegexp ")"
     fn parse_group(&mut self) -> Result<Option<Arc<RwLock<ParsedRegexp>>>, String> {
         // Attempt to:
         // First : parse an arbitrary expression
@@ -287,6 +577,25 @@ impl Parser {
         // First : After `(` parser expects a `ParsedRegexp`
         // Second: After `ParsedRegexp` parser expects a `)`
 
+        // Position of opening (
+        let start = self.position();
+        // Opening `(?>` begins an atomic group and `(?:` a non-capturing one
+        // while `(?<=` and `(?<!` begin lookbehinds
+        let opening = self.current.map_or(String::from("("), |token| {
+            let source = self.scanner.get_source_string();
+            source[token.byte_position..token.byte_position + token.byte_length].to_string()
+        });
+        let atomic = opening == "(?>";
+        let capturing = opening == "(";
+        let lookbehind = match opening.as_str() {
+            "(?<=" => Some(false),
+            "(?<!" => Some(true),
+            _ => None,
+        };
+        if capturing {
+            // Groups are numbered in order of their opening parentheses
+            self.opened_groups += 1;
+        }
         // Move past opening (
         self.advance()?;
 
@@ -297,21 +606,48 @@ impl Parser {
 
                 // Advance only when current item has name TokenName::RightParent
                 // or report error `Expected ) after expression` (? operator)
-                self.consume(TokenType::RightParen, "Expected ) after expression")?;
+                self.consume_group_end()?;
                 // field `current` now points to the first character (or Empty token)
                 // after the closing )
 
-                // Consume group quantifier (if any)
-                let quantifier = self.consume_quantifier()?;
+                let expression_type = match lookbehind {
+                    Some(negative) => {
+                        self.check_lookbehind_length(&parsed_expression, &opening, start)?;
+                        // A lookbehind matches no characters, there is nothing to repeat
+                        let pattern =
+                            format!("{opening}{})", parsed_expression.read().unwrap().pattern);
+                        self.reject_quantifier(&format!("lookbehind {pattern}"))?;
+                        ExpressionType::Lookbehind { negative }
+                    }
+                    // Consume group quantifier (if any)
+                    None => ExpressionType::Group {
+                        quantifier: self.consume_quantifier()?,
+                        atomic,
+                        capturing,
+                    },
+                };
                 // Construct parsed grouped expression
-                let mut group = ParsedRegexp::new(ExpressionType::Group { quantifier });
+                let mut group = ParsedRegexp::new(expression_type);
                 // Surround parsed expression pattern with parentheses
                 // to create pattern of this group expression
                 group.pattern = {
                     let parsed_expression_pattern = &parsed_expression.read().unwrap().pattern;
-                    let group_quantifier = quantifier;
-                    Arc::from(format!("({parsed_expression_pattern}){group_quantifier}"))
+                    let group_quantifier = expression_type.quantifier();
+                    let opening = group.expression_type.group_opening();
+                    Arc::from(format!(
+                        "{opening}{parsed_expression_pattern}){group_quantifier}"
+                    ))
                 };
+                group.span = start..self.position();
+                if matches!(
+                    parsed_expression.read().unwrap().expression_type,
+                    ExpressionType::EmptyExpression
+                ) {
+                    self.warnings.push(ParseWarning {
+                        kind: WarningKind::EmptyGroup,
+                        span: group.span.clone(),
+                    });
+                }
                 // let `group` take ownership of the expression it encloses
                 group.children.write().unwrap().push(parsed_expression);
                 // convert `group` to appropriate return type
@@ -337,21 +673,16 @@ impl Parser {
                 // it's a syntax error you made
                 let error = "Expected expression after (";
                 let source = self.scanner.get_source_string();
-                let (error_index, error_position) = {
-                    match self.current {
-                        Some(Token { position, .. }) => {
-                            (position, format!("in position {position}"))
-                        }
-                        None => (source.len(), String::from("at end of pattern")), // in case parser reached end of input
-                    }
-                };
-                Err(format_error(
-                    &format!("Syntax error {error_position}: {error}"),
+                let (error_index, carets, error_position) = self.error_site();
+                let code = DiagnosticCode::ExpectedExpressionAfterLeftParen;
+                Err(format_error_with(
+                    &format!("Syntax error [{code}] {error_position}: {error}"),
                     &source,
-                    // Place one (1_u8) caret `^` below error position
+                    // Place carets `^` below the offending token
                     // in source string as a visual aid
-                    &[(error_index, 1_u8)],
+                    &[(error_index, carets)],
                     "", // Hints
+                    self.colored_errors,
                 ))
             }
         }
@@ -359,6 +690,7 @@ impl Parser {
 
     // Empty => ""
     fn parse_empty_expression(&mut self) -> Result<Option<Arc<RwLock<ParsedRegexp>>>, String> {
+        let start = self.position();
         // Move past Empty token
         self.advance()?;
         // field `current` now points to the first character after
@@ -370,6 +702,8 @@ impl Parser {
         let mut expr = ParsedRegexp::new(ExpressionType::EmptyExpression);
         // Empty string pattern for the empty expression
         expr.pattern = Arc::from("");
+        // Empty expression occupies no characters at all
+        expr.span = start..start;
 
         // Successfully parsed an empty expression
         Ok(Some(Arc::new(RwLock::new(expr))))
@@ -377,61 +711,469 @@ impl Parser {
 
     // MatchAnyCharacter => Dot
     fn parse_dot_expression(&mut self) -> Result<Option<Arc<RwLock<ParsedRegexp>>>, String> {
+        let start = self.position();
         // Move past Dot token
         self.advance()?;
 
         let value = None;
         let quantifier = self.consume_quantifier()?;
-        let mut expr = ParsedRegexp::new(ExpressionType::CharacterExpression { value, quantifier });
+        if self.scanner.is_dot_all() {
+            // A class matching anything, newlines included, `(?s).` is `[\s\S]`
+            let members = [Shorthand::Space, Shorthand::NotSpace]
+                .into_iter()
+                .map(|class| {
+                    let mut member = ParsedRegexp::new(ExpressionType::ShorthandClass {
+                        class,
+                        quantifier: Quantifier::None,
+                    });
+                    member.pattern = Arc::from(class.to_string());
+                    member.span = start..start + 1;
+                    Arc::new(RwLock::new(member))
+                })
+                .collect();
+            return Ok(Some(character_class(
+                members,
+                quantifier,
+                start..self.position(),
+            )));
+        }
+        let mut expr = ParsedRegexp::new(ExpressionType::CharacterExpression {
+            value,
+            quantifier,
+            line_terminator: self.line_terminator(),
+        });
         // A dot for dot expressions succeeded with a quantifier (if any)
         expr.pattern = Arc::from(format!(".{quantifier}").as_str());
+        expr.span = start..self.position();
 
         // Successfully parsed a dot expression
         Ok(Some(Arc::new(RwLock::new(expr))))
     }
 
+    // MatchShorthandClass => "\" ShorthandLetter
+    fn parse_shorthand_class(
+        &mut self,
+        letter: char,
+    ) -> Result<Option<Arc<RwLock<ParsedRegexp>>>, String> {
+        let start = self.position();
+        // Move past `ShorthandClass` token
+        self.advance()?;
+
+        let class = Shorthand::from_letter(letter).expect("scanner checked shorthand letter");
+        let quantifier = self.consume_quantifier()?;
+        let mut expr = ParsedRegexp::new(ExpressionType::ShorthandClass { class, quantifier });
+        expr.pattern = Arc::from(format!("{class}{quantifier}"));
+        expr.span = start..self.position();
+
+        // Successfully parsed a shorthand class
+        Ok(Some(Arc::new(RwLock::new(expr))))
+    }
+
+    // Backreference => "\" [1-9]
+    fn parse_backreference(
+        &mut self,
+        group: usize,
+    ) -> Result<Option<Arc<RwLock<ParsedRegexp>>>, String> {
+        if group > self.opened_groups {
+            // Capturing groups opened later, or never, capture nothing to match again here
+            let source = self.scanner.get_source_string();
+            let (error_index, carets, error_position) = self.error_site();
+            let code = DiagnosticCode::UndefinedBackreference;
+            let opened = match self.opened_groups {
+                0 => String::from("no capturing group is opened before it"),
+                1 => String::from("only 1 capturing group is opened before it"),
+                opened => format!("only {opened} capturing groups are opened before it"),
+            };
+            return Err(format_error_with(
+                &format!(
+                    "Syntax error [{code}] {error_position}: \
+                     Backreference \\{group} refers to group {group} but {opened}"
+                ),
+                &source,
+                &[(error_index, carets)],
+                "\nGroups are numbered from 1 in order of their opening parentheses",
+                self.colored_errors,
+            ));
+        }
+
+        let start = self.position();
+        // Move past `Backreference` token
+        self.advance()?;
+
+        let quantifier = self.consume_quantifier()?;
+        let mut expr = ParsedRegexp::new(ExpressionType::Backreference {
+            group,
+            quantifier,
+            case_insensitive: self.scanner.is_case_insensitive(),
+        });
+        expr.pattern = Arc::from(format!("\\{group}{quantifier}"));
+        expr.span = start..self.position();
+
+        // Successfully parsed a backreference
+        Ok(Some(Arc::new(RwLock::new(expr))))
+    }
+
+    // Linebreak => "\R"
+    // Parsed as the atomic group LINEBREAK, so a quantified \R never splits \r\n
+    // `\R{2}` matches "\n\n" but not "\r\n"
+    fn parse_linebreak(&mut self) -> Result<Option<Arc<RwLock<ParsedRegexp>>>, String> {
+        let start = self.position();
+        // Move past `Linebreak` token
+        self.advance()?;
+
+        let quantifier = self.consume_quantifier()?;
+        let mut parser = Parser::new(&format!("{LINEBREAK}{quantifier}"));
+        parser.advance()?;
+        let linebreak = parser.parse_primary()?.expect("LINEBREAK is a valid group");
+        // All of it stands for `\R` in source pattern
+        let span = start..self.position();
+        for visited in ParsedRegexp::iter(&linebreak) {
+            visited.expression.write().unwrap().span = span.clone();
+        }
+        linebreak.write().unwrap().pattern = Arc::from(format!("\\R{quantifier}"));
+
+        // Successfully parsed a line break
+        Ok(Some(linebreak))
+    }
+
+    // Assertion => "^" | "$" | "\A" | "\Z" | "\b" | "\B"
+    fn parse_assertion(
+        &mut self,
+        kind: Assertion,
+    ) -> Result<Option<Arc<RwLock<ParsedRegexp>>>, String> {
+        let start = self.position();
+        // Move past assertion token
+        self.advance()?;
+        // An assertion matches no characters, there is nothing to repeat
+        self.reject_quantifier(&format!("assertion {kind}"))?;
+
+        let mut expr = ParsedRegexp::new(ExpressionType::Assertion { kind });
+        expr.pattern = Arc::from(kind.to_string());
+        expr.span = start..self.position();
+
+        // Successfully parsed an assertion
+        Ok(Some(Arc::new(RwLock::new(expr))))
+    }
+
+    // Report a quantifier right after `described`, an expression matching no characters
+    fn reject_quantifier(&self, described: &str) -> Result<(), String> {
+        if let Some(token) = self.current.filter(|token| {
+            matches!(
+                token.type_name,
+                TokenType::Mark | TokenType::Star | TokenType::Plus | TokenType::Repetition
+            )
+        }) {
+            let source = self.scanner.get_source_string();
+            let quantifier = &source[token.byte_position..token.byte_position + token.byte_length];
+            let (error_index, carets, error_position) = self.error_site();
+            let code = DiagnosticCode::QuantifiedAssertion;
+            return Err(format_error_with(
+                &format!(
+                    "Syntax error [{code}] {error_position}: \
+                     Quantifier {quantifier} after {described}, which matches no characters"
+                ),
+                &source,
+                &[(error_index, carets)],
+                &format!("\nTo match a literal {quantifier} use \\{quantifier}"),
+                self.colored_errors,
+            ));
+        }
+        Ok(())
+    }
+
+    // A lookbehind beginning at `start` with `opening` must enclose an expression
+    // matching strings of a single length, then where they begin is known when matching
+    fn check_lookbehind_length(
+        &self,
+        expression: &Arc<RwLock<ParsedRegexp>>,
+        opening: &str,
+        start: usize,
+    ) -> Result<(), String> {
+        let expression = expression.read().unwrap();
+        let min = expression.min_match_len();
+        let lengths = match expression.max_match_len() {
+            Some(max) if max == min => return Ok(()),
+            Some(max) => format!("{min} to {max} characters"),
+            None => format!("{min} or more characters"),
+        };
+        let code = DiagnosticCode::VariableLengthLookbehind;
+        Err(format_error_labeled_with(
+            &format!(
+                "Syntax error [{code}] in position {start}: \
+                 Lookbehind {opening}{}) matches strings of different lengths",
+                expression.pattern
+            ),
+            &self.scanner.get_source_string(),
+            &[ErrorLabel::primary(
+                expression.span.clone(),
+                &format!("matches strings of {lengths}"),
+            )],
+            &[match expression.expression_type {
+                ExpressionType::Alternation => {
+                    "only lookbehinds matching strings of a single length are supported, \
+                     split alternatives of different lengths like (?<=a)|(?<=bc)"
+                }
+                _ => "only lookbehinds matching strings of a single length are supported",
+            }],
+            self.colored_errors,
+        ))
+    }
+
     // Character => OrdinaryCharacter | EscapedMetacharacter
     fn parse_character_expression(
         &mut self,
         value: char,
     ) -> Result<Option<Arc<RwLock<ParsedRegexp>>>, String> {
+        let start = self.position();
+        self.warn_literal_escape();
         // Move past `Character` token
         self.advance()?;
 
+        let end = self.position();
         let quantifier = self.consume_quantifier()?;
+        let others = if self.scanner.is_case_insensitive() {
+            other_cases(value)
+        } else {
+            vec![]
+        };
+        if !others.is_empty() {
+            // A class listing all cases of the character, `(?i)k` is `[kK\u{212A}]`
+            let members = std::iter::once(value)
+                .chain(others)
+                .map(|ch| class_member(ch, start..end))
+                .collect();
+            return Ok(Some(character_class(
+                members,
+                quantifier,
+                start..self.position(),
+            )));
+        }
         let mut expr = ParsedRegexp::new(ExpressionType::CharacterExpression {
             value: Some(value),
             quantifier,
+            line_terminator: LineTerminator::default(),
         });
 
         // Use given character for this character expression succeeded with a quantifier (if any)
+        // Metacharacters are escaped again, so pattern of `\*` is `\*` not `*`
+        // and so are control characters, pattern of `\t` is `\t` not a tab
+        let value = escape_char(value, is_metacharacter);
         expr.pattern = Arc::from(format!("{value}{quantifier}").as_str());
+        expr.span = start..self.position();
 
         // Successfully parsed a character expression
         Ok(Some(Arc::new(RwLock::new(expr))))
     }
 
+    // What ends lines for dots and for `^` and `$` in multiline mode parsed from now on
+    fn line_terminator(&self) -> LineTerminator {
+        if self.scanner.is_crlf() {
+            LineTerminator::Crlf
+        } else {
+            self.line_terminator
+        }
+    }
+
+    // Warn about current `Character` token if it is a lone slash, not an escaped one `\\`
+    // which is not followed by a metacharacter either
+    fn warn_literal_escape(&mut self) {
+        // `Character` token, its length is 2 if it is an escaped metacharacter
+        let Some(token) = self.current.filter(|token| {
+            token.type_name == (TokenType::Character { value: '\\' }) && token.length == 1
+        }) else {
+            return;
+        };
+        let source = self.scanner.get_source_string();
+        if let Some(next) = source[token.byte_position + token.byte_length..]
+            .chars()
+            .next()
+        {
+            self.warnings.push(ParseWarning {
+                kind: WarningKind::LiteralEscape(next),
+                span: token.position..token.position + 2,
+            });
+        }
+    }
+
+    // Character of current token inside a character class along with its span, if any
+    // and move past it, a - is the character itself
+    fn class_character(&mut self) -> Result<Option<(char, Range<usize>)>, String> {
+        let Some(token) = self.current else {
+            return Ok(None);
+        };
+        let value = match token.type_name {
+            TokenType::Character { value } => value,
+            TokenType::Hyphen => '-',
+            _ => return Ok(None),
+        };
+        self.warn_literal_escape();
+        self.advance()?;
+        Ok(Some((value, token.position..token.position + token.length)))
+    }
+
+    // MatchCharacterClass => "[" ClassMember+ "]"
+    // ClassMember => MatchCharacterClass | MatchShorthandClass
+    //              | ClassCharacter ( "-" ClassCharacter )?
+    fn parse_class(&mut self) -> Result<Option<Arc<RwLock<ParsedRegexp>>>, String> {
+        // Position of opening [
+        let start = self.position();
+        // Reject deep nesting before recursing into this class
+        self.open_classes += 1;
+        self.check_nesting_depth("Class")?;
+        // Move past opening [
+        self.advance()?;
+
+        // Inside a class the scanner generates only `Character`, `Hyphen`, `ShorthandClass`
+        // and `LeftBracket` tokens, until ]
+        let mut members = vec![];
+        loop {
+            // A class nested inside this one, like `[a-z]` in `[[a-z]_]`
+            // Quantifiers mean nothing inside classes, thus nested classes never have one
+            if self.check(TokenType::LeftBracket) {
+                members.extend(self.parse_class()?);
+                continue;
+            }
+            if let Some(Token {
+                type_name: TokenType::ShorthandClass { letter },
+                position,
+                length,
+                ..
+            }) = self.current
+            {
+                self.advance()?;
+                let class =
+                    Shorthand::from_letter(letter).expect("scanner checked shorthand letter");
+                let mut member = ParsedRegexp::new(ExpressionType::ShorthandClass {
+                    class,
+                    quantifier: Quantifier::None,
+                });
+                member.pattern = Arc::from(class.to_string());
+                member.span = position..position + length;
+                members.push(Arc::new(RwLock::new(member)));
+                continue;
+            }
+            let Some((first, span)) = self.class_character()? else {
+                break;
+            };
+            // A - not between two characters is an ordinary character, like in [-a] or [a-]
+            let Some(hyphen) = self
+                .current
+                .filter(|token| token.type_name == TokenType::Hyphen)
+            else {
+                members.push(class_member(first, span));
+                continue;
+            };
+            self.advance()?;
+            let Some((last, last_span)) = self.class_character()? else {
+                members.push(class_member(first, span));
+                members.push(class_member('-', hyphen.position..hyphen.position + 1));
+                continue;
+            };
+            let span = span.start..last_span.end;
+            if first > last {
+                let code = DiagnosticCode::ReversedRange;
+                let range = format!("{}-{}", class_escape(first), class_escape(last));
+                return Err(format_error_with(
+                    &format!(
+                        "Syntax error [{code}] in position {}: Reversed range {range} in character class\n\
+                        A range matches characters from its first to its last, \
+                        but {first:?} comes after {last:?}",
+                        span.start
+                    ),
+                    &self.scanner.get_source_string(),
+                    &[(span.start, span.len().clamp(1, u8::MAX as usize) as u8)],
+                    &format!(
+                        "\nWrite the range the other way round, like {}-{}",
+                        class_escape(last),
+                        class_escape(first)
+                    ),
+                 self.colored_errors,));
+            }
+            let mut member = ParsedRegexp::new(ExpressionType::CharacterRange { first, last });
+            member.pattern = Arc::from(format!("{}-{}", class_escape(first), class_escape(last)));
+            member.span = span;
+            members.push(Arc::new(RwLock::new(member)));
+        }
+
+        let source = self.scanner.get_source_string();
+        if !self.check(TokenType::RightBracket) {
+            let (error_index, carets, error_position) = self.error_site();
+            let code = DiagnosticCode::ExpectedRightBracket;
+            return Err(format_error_labeled_with(
+                &format!(
+                    "Syntax error [{code}] {error_position}: Expected ] closing character class"
+                ),
+                &source,
+                &[
+                    ErrorLabel::secondary(start..start + 1, "class opened here"),
+                    ErrorLabel::primary(
+                        error_index..error_index + carets as usize,
+                        "expected ] here",
+                    ),
+                ],
+                &["each [ needs a matching ], to match a literal [ use \\["],
+                self.colored_errors,
+            ));
+        }
+        if members.is_empty() {
+            let code = DiagnosticCode::EmptyCharacterClass;
+            return Err(format_error_with(
+                &format!(
+                    "Syntax error [{code}] in position {start}: Empty character class\n\
+                    A character class matches one of the characters it lists, but [] lists none"
+                ),
+                &source,
+                &[(start, 2)],
+                "\nTo match a literal ] inside a character class use \\], like [\\]]",
+                self.colored_errors,
+            ));
+        }
+        self.open_classes -= 1;
+        // Move past closing ]
+        self.advance()?;
+        if self.scanner.is_case_insensitive() {
+            // Nested classes already list other cases of their characters
+            let others = other_case_members(&members, start..self.position());
+            members.extend(others);
+        }
+
+        let quantifier = self.consume_quantifier()?;
+
+        // Successfully parsed a character class
+        Ok(Some(character_class(
+            members,
+            quantifier,
+            start..self.position(),
+        )))
+    }
+
     // Read next token in stream
     fn advance(&mut self) -> Result<(), String> {
         self.current = self.scanner.next();
+        if let Some(position) = self.scanner.unterminated_comment() {
+            let error = ScanError::UnterminatedComment { position };
+            return Err(error.render_with(&self.scanner.get_source_string(), self.colored_errors));
+        }
+        if let Some(error) = self
+            .current
+            .and_then(|token| self.scanner.unknown_escape(&token))
+        {
+            return Err(error.render_with(&self.scanner.get_source_string(), self.colored_errors));
+        }
         if self.check(TokenType::RightParen) && self.grouping_marks.pop().is_none() {
             // There is no group expression currently processed
             // Thus ) was used without its matching (
             // Syntax error!
             let error = "Unbalanced )\n) is used without a matching (";
             let source = self.scanner.get_source_string();
-            let (error_index, error_position) = {
-                match self.current {
-                    Some(Token { position, .. }) => (position, format!("in position {position}")),
-                    None => (source.len(), String::from("at end of pattern")), // in case parser reached end of input
-                }
-            };
-            return Err(format_error(
-                &format!("Syntax error {error_position}: {error}"),
+            let (error_index, carets, error_position) = self.error_site();
+            let code = DiagnosticCode::UnbalancedRightParen;
+            return Err(format_error_with(
+                &format!("Syntax error [{code}] {error_position}: {error}"),
                 &source,
-                // Place one (1_u8) caret `^` below error position
+                // Place carets `^` below the offending token
                 // in source string as a visual aid
-                &[(error_index, 1_u8)],
+                &[(error_index, carets)],
                 // Hints
                 "\nTo match a literal ) use \\)\n\
                 To match a metacharacter, precede it with a slash in your pattern \\\n\
@@ -440,7 +1182,7 @@ impl Parser {
                 Hence to match a single literal slash, you write pattern \\\\\\\\\n\
                 The first pair (one slash, operator) escape the second pair (one slash, operand)\n\
                 Or, you can use a raw string r\"\\\\\"",
-            ));
+             self.colored_errors,));
         }
         if self.check(TokenType::LeftParen) {
             // The parser has found a possibly opening (
@@ -449,12 +1191,80 @@ impl Parser {
             self.grouping_marks.push(GroupingMark::Group {
                 position: self.current.unwrap().position,
             });
+            // Reject deep nesting before recursing into this group
+            self.check_nesting_depth("Group")?;
             return Ok(());
         }
 
         Ok(())
     }
 
+    // Groups and classes currently open, including the one at current token
+    // must not be nested deeper than limits allow, checked before recursing into it
+    fn check_nesting_depth(&self, nested: &str) -> Result<(), String> {
+        let depth = self.grouping_marks.len() + self.open_classes;
+        if depth <= self.limits.max_nesting_depth {
+            return Ok(());
+        }
+        let source = self.scanner.get_source_string();
+        let (error_index, carets, error_position) = self.error_site();
+        let code = DiagnosticCode::NestingTooDeep;
+        Err(format_error_with(
+            &format!(
+                "Rejected pattern [{code}] {error_position}: {nested} is nested {depth} groups and classes deep, at most {} are allowed",
+                self.limits.max_nesting_depth
+            ),
+            &source,
+            &[(error_index, carets)],
+            "Groups enclosing only another group can be dropped, `((a))` matches what `(a)` matches\n\
+            and so can classes enclosing only another class, `[[a]]` matches what `[a]` matches",
+         self.colored_errors,))
+    }
+
+    // Count `nodes` more syntax tree nodes, rejecting the pattern as soon as
+    // there are more than limits allow instead of after building all of it
+    fn count_nodes(&mut self, nodes: usize) -> Result<(), String> {
+        self.nodes += nodes;
+        if self.nodes <= self.limits.max_nodes {
+            return Ok(());
+        }
+        let code = DiagnosticCode::TooManyNodes;
+        Err(format!(
+            "Rejected pattern [{code}]: Pattern is built of more than {} syntax tree nodes",
+            self.limits.max_nodes
+        ))
+    }
+
+    // Index in source string where current token begins
+    // or source string length if parser reached end of input
+    // Tokens are adjacent, thus this is also where the most recently parsed expression ends
+    fn position(&self) -> usize {
+        match self.current {
+            Some(token) => token.position,
+            None => self.scanner.get_source_length(),
+        }
+    }
+
+    // Where to report an error about current token:
+    // (index in source string, number of carets to place below it, position description)
+    fn error_site(&self) -> (usize, u8, String) {
+        match self.current {
+            Some(Token {
+                position, length, ..
+            }) => (
+                position,
+                length.clamp(1, u8::MAX as usize) as u8,
+                format!("in position {position}"),
+            ),
+            // in case parser reached end of input
+            None => (
+                self.scanner.get_source_length(),
+                1,
+                String::from("at end of pattern"),
+            ),
+        }
+    }
+
     // Check if current token (if any) has a given type
     fn check(&self, expected: TokenType) -> bool {
         match self.current {
@@ -466,36 +1276,70 @@ impl Parser {
     // Check if current token (if any) has a given type
     // if true then advance
     // if false report `error`
-    fn consume(&mut self, expected: TokenType, error: &str) -> Result<(), String> {
+    fn consume(
+        &mut self,
+        expected: TokenType,
+        code: DiagnosticCode,
+        error: &str,
+    ) -> Result<(), String> {
         if !self.check(expected) {
             // current token name (type) is not what was expected
             // in other words, grammar requires a specific item to appear here
             // but parser found something else
             // this is a syntax error
             let source = self.scanner.get_source_string();
-            let (error_index, error_position) = {
-                match self.current {
-                    Some(Token { position, .. }) => (position, format!("in position {position}")),
-                    None => (source.len(), String::from("at end of pattern")), // in case parser reached end of input
-                }
-            };
-            return Err(format_error(
-                &format!("Syntax error {error_position}: {error}"),
-                &self.scanner.get_source_string(),
-                // Place one (1_u8) caret `^` below error position
+            let (error_index, carets, error_position) = self.error_site();
+            return Err(format_error_with(
+                &format!("Syntax error [{code}] {error_position}: {error}"),
+                &source,
+                // Place carets `^` below the offending token
                 // in source string as a visual aid
-                &[(error_index, 1_u8)],
+                &[(error_index, carets)],
                 "", // Hints
+                self.colored_errors,
             ));
         }
         self.advance()?;
         Ok(())
     }
 
+    // Consume ) closing the innermost group, or report where that group was opened
+    fn consume_group_end(&mut self) -> Result<(), String> {
+        let opened_at = match self.grouping_marks.last() {
+            Some(GroupingMark::Group { position }) if !self.check(TokenType::RightParen) => {
+                *position
+            }
+            _ => {
+                return self.consume(
+                    TokenType::RightParen,
+                    DiagnosticCode::ExpectedRightParen,
+                    "Expected ) after expression",
+                )
+            }
+        };
+        let source = self.scanner.get_source_string();
+        let (error_index, carets, error_position) = self.error_site();
+        let code = DiagnosticCode::ExpectedRightParen;
+        Err(format_error_labeled_with(
+            &format!("Syntax error [{code}] {error_position}: Expected ) after expression"),
+            &source,
+            &[
+                ErrorLabel::secondary(opened_at..opened_at + 1, "group opened here"),
+                ErrorLabel::primary(
+                    error_index..error_index + carets as usize,
+                    "expected ) here",
+                ),
+            ],
+            &["each ( needs a matching ), to match a literal ( use \\("],
+            self.colored_errors,
+        ))
+    }
+
     fn consume_quantifier(&mut self) -> Result<Quantifier, String> {
         // Check current token, if its name (field `name`) is either one of:
-        // Mark, Star, Plus
+        // Mark, Star, Plus, Repetition
         // Consume each and construct a Quantifier variant
+        // along with a following Mark, if any, which makes it lazy
         let quantifier = {
             match self.current {
                 Some(tok) => {
@@ -504,16 +1348,187 @@ impl Parser {
                         TokenType::Mark => Quantifier::ZeroOrOne,
                         TokenType::Star => Quantifier::ZeroOrMore,
                         TokenType::Plus => Quantifier::OneOrMore,
+                        TokenType::Repetition => self.repetition_counts(tok)?,
                         _ => Quantifier::None,
                     }
                 }
                 None => Quantifier::None,
             }
         };
-        if !matches!(quantifier, Quantifier::None) {
-            // We found a quantifier, consume it
+        if matches!(quantifier, Quantifier::None) {
+            return Ok(quantifier);
+        }
+        // We found a quantifier, consume it
+        self.advance()?;
+        // A ? right after a quantifier makes it lazy, like `*?`
+        if self.check(TokenType::Mark) {
             self.advance()?;
+            return Ok(quantifier.lazy());
         }
         Ok(quantifier)
     }
+
+    // Counts of `Repetition` token `token`, like 2 and 5 in `{2,5}`
+    // Counts are at most MAX_REPETITION and the first one is never greater than the second one
+    fn repetition_counts(&self, token: Token) -> Result<Quantifier, String> {
+        let source = self.scanner.get_source_string();
+        let text = &source[token.byte_position..token.byte_position + token.byte_length];
+        let error = |code: DiagnosticCode, error: &str, hints: &str| {
+            let (error_index, carets, error_position) = self.error_site();
+            Err(format_error_with(
+                &format!("Syntax error [{code}] {error_position}: {error}"),
+                &source,
+                &[(error_index, carets)],
+                hints,
+                self.colored_errors,
+            ))
+        };
+        // Scanner checked there are only digits around the comma, if any
+        let counts = text[1..text.len() - 1]
+            .split(',')
+            .map(|count| match count {
+                "" => Ok(None),
+                _ => count
+                    .parse::<usize>()
+                    .ok()
+                    .filter(|count| *count <= MAX_REPETITION)
+                    .map(Some)
+                    .ok_or(count),
+            })
+            .collect::<Result<Vec<_>, _>>();
+        let counts = match counts {
+            Ok(counts) => counts,
+            Err(count) => {
+                return error(
+                    DiagnosticCode::RepetitionTooLarge,
+                    &format!("Repetition count {count} in {text} is too large"),
+                    &format!("\nRepetition counts are at most {MAX_REPETITION}"),
+                )
+            }
+        };
+        let min = counts[0].expect("scanner checked first count is not empty");
+        // `{n}` is exactly n repetitions and `{n,}` at least n
+        let max = if counts.len() == 1 {
+            Some(min)
+        } else {
+            counts[1]
+        };
+        if let Some(max) = max.filter(|max| *max < min) {
+            return error(
+                DiagnosticCode::ReversedRepetition,
+                &format!("Repetition {text} allows at least {min} but at most {max} repetitions"),
+                &format!("\nDid you mean {{{max},{min}}}?"),
+            );
+        }
+        Ok(Quantifier::Counted { min, max })
+    }
+}
+
+// What `\R` matches: \r\n, or else one of \n, vertical tab, form feed, \r,
+// next line (U+0085), line separator (U+2028) and paragraph separator (U+2029)
+pub const LINEBREAK: &str = "(?>\r\n|[\n\u{B}\u{C}\r\u{85}\u{2028}\u{2029}])";
+
+// Largest count in counted repetitions, like `{2,5}`
+pub const MAX_REPETITION: usize = 1000;
+
+// `ch` as written inside a character class
+fn class_escape(ch: char) -> String {
+    escape_char(ch, is_class_metacharacter)
+}
+
+// Character expression matching only `ch`, found at `position` of a literal pattern
+fn literal_character(ch: char, position: usize) -> Arc<RwLock<ParsedRegexp>> {
+    let mut expr = ParsedRegexp::new(ExpressionType::CharacterExpression {
+        value: Some(ch),
+        quantifier: Quantifier::None,
+        line_terminator: LineTerminator::default(),
+    });
+    expr.pattern = Arc::from(escape_char(ch, is_metacharacter));
+    expr.span = position..position + 1;
+    Arc::new(RwLock::new(expr))
+}
+
+// Member of a character class matching only `ch`, found at `span`
+fn class_member(ch: char, span: Range<usize>) -> Arc<RwLock<ParsedRegexp>> {
+    let mut member = ParsedRegexp::new(ExpressionType::CharacterExpression {
+        value: Some(ch),
+        quantifier: Quantifier::None,
+        line_terminator: LineTerminator::default(),
+    });
+    member.pattern = Arc::from(class_escape(ch));
+    member.span = span;
+    Arc::new(RwLock::new(member))
+}
+
+// Character class of `members` repeated by `quantifier`, found at `span`
+fn character_class(
+    members: Vec<Arc<RwLock<ParsedRegexp>>>,
+    quantifier: Quantifier,
+    span: Range<usize>,
+) -> Arc<RwLock<ParsedRegexp>> {
+    let mut class = ParsedRegexp::new(ExpressionType::CharacterClass { quantifier });
+    class.pattern = {
+        let members = members
+            .iter()
+            .map(|member| member.read().unwrap().pattern.to_string())
+            .collect::<String>();
+        Arc::from(format!("[{members}]{quantifier}"))
+    };
+    class.span = span;
+    let class = Arc::new(RwLock::new(class));
+    for member in &members {
+        member.write().unwrap().parent = Some(Arc::downgrade(&class));
+    }
+    *class.read().unwrap().children.write().unwrap() = members;
+    class
+}
+
+// Members of a class listing `members` for the characters they do not list
+// but which are equal ignoring case to some they list, found at `span`
+// Consecutive characters are listed as a range, `[a-z]` needs `A-Z`, `ſ` and `K` (Kelvin sign)
+fn other_case_members(
+    members: &[Arc<RwLock<ParsedRegexp>>],
+    span: Range<usize>,
+) -> Vec<Arc<RwLock<ParsedRegexp>>> {
+    let listed = members
+        .iter()
+        .filter_map(|member| match member.read().unwrap().expression_type {
+            ExpressionType::CharacterExpression {
+                value: Some(ch), ..
+            } => Some((ch, ch)),
+            ExpressionType::CharacterRange { first, last } => Some((first, last)),
+            _ => None,
+        })
+        .collect::<Vec<_>>();
+    let mut others = listed
+        .iter()
+        .flat_map(|(first, last)| other_cases_of_range(*first, *last))
+        .filter(|ch| {
+            !listed
+                .iter()
+                .any(|(first, last)| (first..=last).contains(&ch))
+        })
+        .collect::<Vec<_>>();
+    others.sort_unstable();
+    others.dedup();
+
+    let mut ranges: Vec<(char, char)> = vec![];
+    for ch in others {
+        match ranges.last_mut() {
+            Some((_, last)) if char::from_u32(*last as u32 + 1) == Some(ch) => *last = ch,
+            _ => ranges.push((ch, ch)),
+        }
+    }
+    ranges
+        .into_iter()
+        .map(|(first, last)| {
+            if first == last {
+                return class_member(first, span.clone());
+            }
+            let mut member = ParsedRegexp::new(ExpressionType::CharacterRange { first, last });
+            member.pattern = Arc::from(format!("{}-{}", class_escape(first), class_escape(last)));
+            member.span = span.clone();
+            Arc::new(RwLock::new(member))
+        })
+        .collect()
 }
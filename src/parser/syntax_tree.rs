@@ -2,14 +2,63 @@
 
 use std::collections::LinkedList;
 use std::fmt::Display;
+use std::hash::{Hash, Hasher};
+use std::ops::Range;
 use std::sync::{Arc, RwLock, Weak};
 
-#[derive(Debug, Clone, Copy)]
+// Arbitrary quantifiers are generated in `fuzzing`, keeping counts small
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum Quantifier {
     None,       // No quantifier
     ZeroOrOne,  // Quantifier ?
     ZeroOrMore, // Quantifier *
     OneOrMore,  // Quantifier +
+    // Quantifier {n}, {n,} or {n,m}, from `min` to `max` repetitions
+    // `max` is None when there is no upper bound, it's never less than `min`
+    Counted { min: usize, max: Option<usize> },
+    // Any of the above followed by ?, like `*?` or `{2,5}?`, with the same bounds
+    // It repeats as few times as possible and more only when backtracking
+    Lazy { min: usize, max: Option<usize> },
+}
+
+impl Quantifier {
+    // Fewest and most repetitions allowed, most is None when unbounded
+    // `x` is repeated once, (1, Some(1)), and `x*` any number of times, (0, None)
+    pub fn bounds(&self) -> (usize, Option<usize>) {
+        match *self {
+            Self::None => (1, Some(1)),
+            Self::ZeroOrOne => (0, Some(1)),
+            Self::ZeroOrMore => (0, None),
+            Self::OneOrMore => (1, None),
+            Self::Counted { min, max } | Self::Lazy { min, max } => (min, max),
+        }
+    }
+
+    // Does this quantifier prefer fewer repetitions?
+    pub fn is_lazy(&self) -> bool {
+        matches!(self, Self::Lazy { .. })
+    }
+
+    // Quantifier with the same bounds repeating as few times as possible
+    pub fn lazy(&self) -> Quantifier {
+        let (min, max) = self.bounds();
+        Self::Lazy { min, max }
+    }
+
+    // Quantifier with the same bounds repeating as many times as possible
+    // `*?` becomes `*` and `{2,5}?` becomes `{2,5}`
+    pub fn greedy(&self) -> Quantifier {
+        match *self {
+            Self::Lazy { min, max } => match (min, max) {
+                (0, Some(1)) => Self::ZeroOrOne,
+                (0, None) => Self::ZeroOrMore,
+                (1, None) => Self::OneOrMore,
+                _ => Self::Counted { min, max },
+            },
+            greedy => greedy,
+        }
+    }
 }
 
 impl Display for Quantifier {
@@ -19,13 +68,394 @@ impl Display for Quantifier {
             Self::ZeroOrOne => "?",
             Self::ZeroOrMore => "*",
             Self::OneOrMore => "+",
+            Self::Counted { min, max: None } => return write!(f, "{{{min},}}"),
+            Self::Counted {
+                min,
+                max: Some(max),
+            } if min == max => return write!(f, "{{{min}}}"),
+            Self::Counted {
+                min,
+                max: Some(max),
+            } => return write!(f, "{{{min},{max}}}"),
+            Self::Lazy { .. } => return write!(f, "{}?", self.greedy()),
         };
         write!(f, "{string_value}")
     }
 }
 
+// Can `ch` be part of a word? This is what `\w` matches and what `\b` looks for
+// Letters, marks, decimal digits and connector punctuation (like `_`) of any script
+// Without feature `unicode` only ASCII letters, digits and `_`, which saves Unicode tables
+#[cfg(feature = "unicode")]
+pub fn is_word_character(ch: char) -> bool {
+    use unicode_properties::{GeneralCategory, GeneralCategoryGroup, UnicodeGeneralCategory};
+    ch.is_alphabetic()
+        || ch.general_category_group() == GeneralCategoryGroup::Mark
+        || matches!(
+            ch.general_category(),
+            GeneralCategory::DecimalNumber | GeneralCategory::ConnectorPunctuation
+        )
+}
+
+#[cfg(not(feature = "unicode"))]
+pub fn is_word_character(ch: char) -> bool {
+    ch.is_ascii_alphanumeric() || ch == '_'
+}
+
+// Characters other than `ch` equal to it ignoring case, what `ch` also matches in
+// case-insensitive mode, like 'K' and the Kelvin sign U+212A for 'k', or 'Σ' and 'ς' for 'σ'
+// Characters are equal ignoring case if they have the same simple case folding, a single
+// character, thus 'ß' is equal to 'ẞ' but not to "ss"
+// Without feature `unicode` only ASCII letters have other cases
+#[cfg(feature = "unicode")]
+pub fn other_cases(ch: char) -> Vec<char> {
+    case_foldings()
+        .get(&simple_case_folding(ch))
+        .into_iter()
+        .flatten()
+        .copied()
+        .filter(|other| *other != ch)
+        .collect()
+}
+
+#[cfg(not(feature = "unicode"))]
+pub fn other_cases(ch: char) -> Vec<char> {
+    match ch {
+        'a'..='z' => vec![ch.to_ascii_uppercase()],
+        'A'..='Z' => vec![ch.to_ascii_lowercase()],
+        _ => vec![],
+    }
+}
+
+// Are `a` and `b` equal ignoring case? Do they have the same simple case folding?
+#[cfg(feature = "unicode")]
+pub fn equal_ignoring_case(a: char, b: char) -> bool {
+    a == b || simple_case_folding(a) == simple_case_folding(b)
+}
+
+#[cfg(not(feature = "unicode"))]
+pub fn equal_ignoring_case(a: char, b: char) -> bool {
+    a.eq_ignore_ascii_case(&b)
+}
+
+// Characters outside `first..=last` equal ignoring case to one inside it (see `other_cases`)
+// like A to Z, 'ſ' (long s) and the Kelvin sign for a to z
+#[cfg(feature = "unicode")]
+pub fn other_cases_of_range(first: char, last: char) -> Vec<char> {
+    let range = first..=last;
+    case_foldings()
+        .values()
+        .filter(|equal| equal.iter().any(|ch| range.contains(ch)))
+        .flatten()
+        .copied()
+        .filter(|ch| !range.contains(ch))
+        .collect()
+}
+
+#[cfg(not(feature = "unicode"))]
+pub fn other_cases_of_range(first: char, last: char) -> Vec<char> {
+    let range = first..=last;
+    range
+        .clone()
+        .filter(char::is_ascii_alphabetic)
+        .flat_map(other_cases)
+        .filter(|ch| !range.contains(ch))
+        .collect()
+}
+
+// Character `ch` folds to when ignoring case, a lowercase letter for most letters
+// Mapping to uppercase first joins all characters with the same uppercase, like 'σ' and 'ς'
+// Dotted capital I and dotless small i fold to themselves, like in Unicode CaseFolding.txt
+// whose simple foldings pair them with nothing
+#[cfg(feature = "unicode")]
+fn simple_case_folding(ch: char) -> char {
+    if matches!(ch, '\u{130}' | '\u{131}') {
+        return ch;
+    }
+    // Mappings to more than one character, like 'ß' to "SS", are not simple
+    let upper = single_char(ch.to_uppercase()).unwrap_or(ch);
+    single_char(upper.to_lowercase()).unwrap_or(upper)
+}
+
+// The only character of `chars`, None if there are none or more than one
+#[cfg(feature = "unicode")]
+fn single_char(mut chars: impl Iterator<Item = char>) -> Option<char> {
+    match (chars.next(), chars.next()) {
+        (Some(ch), None) => Some(ch),
+        _ => None,
+    }
+}
+
+// Characters grouped by their simple case folding, only groups of two characters or more
+// Found by trying every character, thus groups are built once and reused
+#[cfg(feature = "unicode")]
+fn case_foldings() -> &'static std::collections::HashMap<char, Vec<char>> {
+    static FOLDINGS: std::sync::OnceLock<std::collections::HashMap<char, Vec<char>>> =
+        std::sync::OnceLock::new();
+    FOLDINGS.get_or_init(|| {
+        let mut foldings = std::collections::HashMap::<char, Vec<char>>::new();
+        for ch in '\0'..=char::MAX {
+            let folding = simple_case_folding(ch);
+            if folding != ch {
+                foldings
+                    .entry(folding)
+                    .or_insert_with(|| vec![folding])
+                    .push(ch);
+            }
+        }
+        foldings
+    })
+}
+
+// A shorthand for a common class of characters, like `\d` for digits
+// Uppercase letters negate, `\D` matches any character but a digit
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "fuzzing", derive(arbitrary::Arbitrary))]
+pub enum Shorthand {
+    Digit,    // \d, ASCII digits 0 to 9
+    NotDigit, // \D
+    Word,     // \w, word characters, see `is_word_character`
+    NotWord,  // \W
+    Space,    // \s, whitespace, in any script
+    NotSpace, // \S
+    // \h, horizontal whitespace, tab and space separators like U+00A0 no-break space
+    HorizontalSpace,
+    NotHorizontalSpace, // \H
+    // \v, vertical whitespace, \n to \r, U+0085 next line and line and paragraph separators
+    VerticalSpace,
+    NotVerticalSpace, // \V
+}
+
+impl Shorthand {
+    // Shorthand written as escape character followed by `letter`, if any
+    pub fn from_letter(letter: char) -> Option<Shorthand> {
+        match letter {
+            'd' => Some(Shorthand::Digit),
+            'D' => Some(Shorthand::NotDigit),
+            'w' => Some(Shorthand::Word),
+            'W' => Some(Shorthand::NotWord),
+            's' => Some(Shorthand::Space),
+            'S' => Some(Shorthand::NotSpace),
+            'h' => Some(Shorthand::HorizontalSpace),
+            'H' => Some(Shorthand::NotHorizontalSpace),
+            'v' => Some(Shorthand::VerticalSpace),
+            'V' => Some(Shorthand::NotVerticalSpace),
+            _ => None,
+        }
+    }
+
+    pub fn letter(&self) -> char {
+        match self {
+            Shorthand::Digit => 'd',
+            Shorthand::NotDigit => 'D',
+            Shorthand::Word => 'w',
+            Shorthand::NotWord => 'W',
+            Shorthand::Space => 's',
+            Shorthand::NotSpace => 'S',
+            Shorthand::HorizontalSpace => 'h',
+            Shorthand::NotHorizontalSpace => 'H',
+            Shorthand::VerticalSpace => 'v',
+            Shorthand::NotVerticalSpace => 'V',
+        }
+    }
+
+    // Shorthand matching exactly the characters this one does not match
+    pub fn negated(&self) -> Shorthand {
+        match self {
+            Shorthand::Digit => Shorthand::NotDigit,
+            Shorthand::NotDigit => Shorthand::Digit,
+            Shorthand::Word => Shorthand::NotWord,
+            Shorthand::NotWord => Shorthand::Word,
+            Shorthand::Space => Shorthand::NotSpace,
+            Shorthand::NotSpace => Shorthand::Space,
+            Shorthand::HorizontalSpace => Shorthand::NotHorizontalSpace,
+            Shorthand::NotHorizontalSpace => Shorthand::HorizontalSpace,
+            Shorthand::VerticalSpace => Shorthand::NotVerticalSpace,
+            Shorthand::NotVerticalSpace => Shorthand::VerticalSpace,
+        }
+    }
+
+    pub fn is_negated(&self) -> bool {
+        self.letter().is_ascii_uppercase()
+    }
+
+    pub fn matches(&self, ch: char) -> bool {
+        match self {
+            Shorthand::Digit => ch.is_ascii_digit(),
+            Shorthand::Word => is_word_character(ch),
+            Shorthand::Space => ch.is_whitespace(),
+            // Same characters as PCRE
+            Shorthand::HorizontalSpace => matches!(
+                ch,
+                '\t' | ' ' | '\u{A0}' | '\u{1680}' | '\u{180E}' | '\u{2000}'
+                    ..='\u{200A}' | '\u{202F}' | '\u{205F}' | '\u{3000}'
+            ),
+            Shorthand::VerticalSpace => {
+                matches!(ch, '\n'..='\r' | '\u{85}' | '\u{2028}' | '\u{2029}')
+            }
+            negated => !negated.negated().matches(ch),
+        }
+    }
+
+    // What a single character matched by this shorthand is called, like "digit"
+    pub fn name(&self) -> &'static str {
+        match self {
+            Shorthand::Digit => "digit",
+            Shorthand::NotDigit => "non-digit character",
+            Shorthand::Word => "word character",
+            Shorthand::NotWord => "non-word character",
+            Shorthand::Space => "whitespace character",
+            Shorthand::NotSpace => "non-whitespace character",
+            Shorthand::HorizontalSpace => "horizontal whitespace character",
+            Shorthand::NotHorizontalSpace => "non-horizontal-whitespace character",
+            Shorthand::VerticalSpace => "vertical whitespace character",
+            Shorthand::NotVerticalSpace => "non-vertical-whitespace character",
+        }
+    }
+}
+
+impl Display for Shorthand {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "\\{}", self.letter())
+    }
+}
+
+// What ends lines, for the dot and for `^` and `$` in multiline mode
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "fuzzing", derive(arbitrary::Arbitrary))]
+pub enum LineTerminator {
+    // A single character, \n unless set by `Parser::parse_with_line_terminator`
+    Char(char),
+    // \r\n, \r or \n, in CRLF mode (inline flag `R`)
+    // \r\n is a single line boundary, lines never begin or end between \r and \n
+    Crlf,
+}
+
+impl Default for LineTerminator {
+    fn default() -> Self {
+        LineTerminator::Char('\n')
+    }
+}
+
+impl LineTerminator {
+    // Does `ch` end a line? The dot never matches it
+    pub fn matches(&self, ch: char) -> bool {
+        match self {
+            LineTerminator::Char(terminator) => ch == *terminator,
+            LineTerminator::Crlf => ch == '\r' || ch == '\n',
+        }
+    }
+
+    // Does a line begin at `position` (a character index) of `target`?
+    pub fn is_line_start(&self, target: &[char], position: usize) -> bool {
+        let Some(before) = position.checked_sub(1).map(|before| target[before]) else {
+            return true;
+        };
+        match self {
+            LineTerminator::Char(terminator) => before == *terminator,
+            LineTerminator::Crlf => {
+                before == '\n' || (before == '\r' && target.get(position) != Some(&'\n'))
+            }
+        }
+    }
+
+    // Does a line end at `position` (a character index) of `target`?
+    pub fn is_line_end(&self, target: &[char], position: usize) -> bool {
+        let Some(after) = target.get(position) else {
+            return true;
+        };
+        match self {
+            LineTerminator::Char(terminator) => after == terminator,
+            LineTerminator::Crlf => {
+                *after == '\r'
+                    || (*after == '\n' && (position == 0 || target[position - 1] != '\r'))
+            }
+        }
+    }
+}
+
+// A zero-width assertion, matching the empty string only at certain positions of target
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "fuzzing", derive(arbitrary::Arbitrary))]
+pub enum Assertion {
+    Start,           // ^, start of target
+    End,             // $, end of target
+    StartOfInput,    // \A, start of target
+    EndOfInput,      // \Z, end of target
+    WordBoundary,    // \b, between a word character and anything else, see `is_word_character`
+    NonWordBoundary, // \B, anywhere \b does not hold
+    // ^ in multiline mode, start of target or right after a line terminator
+    StartOfLine(LineTerminator),
+    // $ in multiline mode, end of target or right before a line terminator
+    EndOfLine(LineTerminator),
+}
+
+impl Assertion {
+    // Does this assertion hold at `position` (a character index) of `target`?
+    pub fn holds(&self, target: &[char], position: usize) -> bool {
+        match self {
+            Assertion::Start | Assertion::StartOfInput => position == 0,
+            Assertion::End | Assertion::EndOfInput => position == target.len(),
+            Assertion::StartOfLine(terminator) => terminator.is_line_start(target, position),
+            Assertion::EndOfLine(terminator) => terminator.is_line_end(target, position),
+            Assertion::WordBoundary | Assertion::NonWordBoundary => {
+                // Start and end of target are not words
+                let before = position
+                    .checked_sub(1)
+                    .and_then(|before| target.get(before))
+                    .is_some_and(|ch| is_word_character(*ch));
+                let after = target
+                    .get(position)
+                    .is_some_and(|ch| is_word_character(*ch));
+                (before != after) == (*self == Assertion::WordBoundary)
+            }
+        }
+    }
+
+    // Does this assertion only hold at start of target?
+    pub fn is_start(&self) -> bool {
+        matches!(self, Assertion::Start | Assertion::StartOfInput)
+    }
+}
+
+impl Display for Assertion {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let string_value = match self {
+            Self::Start => "^",
+            Self::End => "$",
+            Self::StartOfInput => "\\A",
+            Self::EndOfInput => "\\Z",
+            Self::WordBoundary => "\\b",
+            Self::NonWordBoundary => "\\B",
+            // Written the same, patterns having them begin with `(?m)` (see `inline_flags`)
+            Self::StartOfLine(_) => "^",
+            Self::EndOfLine(_) => "$",
+        };
+        write!(f, "{string_value}")
+    }
+}
+
+// Identifier of a syntax tree node
+// Nodes are numbered in pre-order when parsed, root is node 0
+// thus descendants of a node have consecutive identifiers right after its own
+// For instance, nodes of pattern `(a|b)c` are numbered:
+// (a|b)c => 0, (a|b) => 1, a|b => 2, a => 3, b => 4, c => 5
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct NodeId(pub usize);
+
+impl Display for NodeId {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "#{}", self.0)
+    }
+}
+
 // Expression types
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum ExpressionType {
     // Empty string expression
     // the expression between ( and ) in string `()`
@@ -40,6 +470,9 @@ pub enum ExpressionType {
         // . \ .? \ .* \ .+
         value: Option<char>,
         quantifier: Quantifier,
+        // What a dot expression does not match, unused by other character expressions
+        #[cfg_attr(feature = "serde", serde(default))]
+        line_terminator: LineTerminator,
     },
 
     // Concatenation expression
@@ -54,7 +487,109 @@ pub enum ExpressionType {
     // where `...` is another regular expression
     Group {
         quantifier: Quantifier,
+        // An atomic group (?>...) never gives back what it matched once it matched
+        // later expressions failing can not make it match a shorter (or longer) range
+        #[cfg_attr(feature = "serde", serde(default))]
+        atomic: bool,
+        // A capturing group (...) records the range it matched, see matcher::capture
+        // a non-capturing group (?:...) only groups, just like an atomic group
+        #[cfg_attr(feature = "serde", serde(default = "capturing_by_default"))]
+        capturing: bool,
+    },
+
+    // A character class, like `[abc]`, matching a single character among those it lists
+    // Its children are its members, all without quantifiers: character expressions,
+    // character ranges, shorthand classes and nested character classes, like `[[a-z]_]`
+    CharacterClass {
+        quantifier: Quantifier,
+    },
+
+    // A zero-width assertion, like `^`, matching the empty string where it holds
+    // Assertions are never quantified
+    Assertion {
+        kind: Assertion,
+    },
+
+    // A shorthand class, like `\d`, matching a single character of that class
+    // Found on its own or as a member of a character class, then without a quantifier
+    ShorthandClass {
+        class: Shorthand,
+        quantifier: Quantifier,
+    },
+
+    // A range of characters, like `a-z`, matching characters from `first` to `last`
+    // Only found as a member of a character class
+    CharacterRange {
+        first: char,
+        last: char,
     },
+
+    // A backreference, like `\1`, matching the text most recently captured
+    // by capturing group number `group` (see matcher::capture)
+    // It matches nothing if that group did not match
+    Backreference {
+        group: usize,
+        quantifier: Quantifier,
+        // In case-insensitive mode captured text matches in any case, see `equal_ignoring_case`
+        #[cfg_attr(feature = "serde", serde(default))]
+        case_insensitive: bool,
+    },
+
+    // A lookbehind, like `(?<=a)` or `(?<!a)`, matching the empty string where the text
+    // right before it matches (or, if negative, does not match) its only child
+    // That child matches strings of a single length, so where they begin is known
+    // Like assertions, lookbehinds are never quantified
+    Lookbehind {
+        negative: bool,
+    },
+}
+
+impl ExpressionType {
+    // Quantifier of expressions of this type, Quantifier::None if they can not have one
+    pub fn quantifier(&self) -> Quantifier {
+        match *self {
+            ExpressionType::CharacterExpression { quantifier, .. }
+            | ExpressionType::ShorthandClass { quantifier, .. }
+            | ExpressionType::CharacterClass { quantifier }
+            | ExpressionType::Group { quantifier, .. }
+            | ExpressionType::Backreference { quantifier, .. } => quantifier,
+            _ => Quantifier::None,
+        }
+    }
+
+    // Does an expression of this type record the range it matched? see matcher::capture
+    // Atomic groups never do
+    pub fn is_capturing_group(&self) -> bool {
+        matches!(
+            self,
+            ExpressionType::Group {
+                atomic: false,
+                capturing: true,
+                ..
+            }
+        )
+    }
+
+    // Text opening groups of this type: `(?>`, `(?:` or `(`
+    // and lookbehinds: `(?<=` or `(?<!`, empty for other expressions
+    pub fn group_opening(&self) -> &'static str {
+        match self {
+            ExpressionType::Lookbehind { negative: false } => "(?<=",
+            ExpressionType::Lookbehind { negative: true } => "(?<!",
+            ExpressionType::Group { atomic: true, .. } => "(?>",
+            ExpressionType::Group {
+                capturing: false, ..
+            } => "(?:",
+            ExpressionType::Group { .. } => "(",
+            _ => "",
+        }
+    }
+}
+
+// Groups read without saying whether they capture are plain (...) groups
+#[cfg(feature = "serde")]
+fn capturing_by_default() -> bool {
+    true
 }
 
 // (Wrapper) Expression objects after parsing
@@ -66,6 +601,13 @@ pub struct ParsedRegexp {
     // Pattern of this (sub)expression
     pub pattern: Arc<str>,
 
+    // Range of character indices this (sub)expression occupies in source pattern
+    // for instance, in pattern `x(ab)+` group `(ab)+` has span 1..6
+    pub span: Range<usize>,
+
+    // Identifier of this (sub)expression within its syntax tree, see NodeId
+    pub id: NodeId,
+
     // -- Parent expression of this object
     // * We use a Weak reference to avoid reference cycles
     // because parent points to child and child points to parent
@@ -95,11 +637,39 @@ impl ParsedRegexp {
         ParsedRegexp {
             expression_type: expr_type,
             pattern: Arc::from(""),
+            span: 0..0,
+            id: NodeId::default(),
             parent: None,
             children: RwLock::new(vec![]),
         }
     }
 
+    // Does this expression match `ch` on its own?
+    // Only characters, dots, shorthand classes, character classes and their ranges
+    // match single characters
+    pub fn matches_char(&self, ch: char) -> bool {
+        match self.expression_type {
+            ExpressionType::CharacterExpression {
+                value,
+                line_terminator,
+                ..
+            } => match value {
+                Some(value) => value == ch,
+                // The dot matches anything but a line terminator
+                None => !line_terminator.matches(ch),
+            },
+            ExpressionType::CharacterRange { first, last } => (first..=last).contains(&ch),
+            ExpressionType::ShorthandClass { class, .. } => class.matches(ch),
+            ExpressionType::CharacterClass { .. } => self
+                .children
+                .read()
+                .unwrap()
+                .iter()
+                .any(|member| member.read().unwrap().matches_char(ch)),
+            _ => false,
+        }
+    }
+
     pub fn debug_as_strings(&self) -> String {
         let mut debug = String::new();
         debug.push_str("ParsedRegexp {\n");
@@ -140,6 +710,8 @@ impl ParsedRegexp {
         let deep_copy = Arc::new(RwLock::new(ParsedRegexp {
             expression_type: self.expression_type,
             pattern: Arc::from(self.pattern.as_ref()),
+            span: self.span.clone(),
+            id: self.id,
             parent: None,
             children: RwLock::new(vec![]),
         }));
@@ -166,6 +738,8 @@ impl ParsedRegexp {
                         expression_type: src_kid.expression_type,
                         parent: Some(Arc::downgrade(&dest_child)),
                         pattern: Arc::from(src_kid.pattern.as_ref()),
+                        span: src_kid.span.clone(),
+                        id: src_kid.id,
                         children: RwLock::new(vec![]),
                     }));
 
@@ -179,11 +753,147 @@ impl ParsedRegexp {
     }
 }
 
+// A subexpression visited while traversing a syntax tree
+#[derive(Debug, Clone)]
+pub struct VisitedExpression {
+    // The subexpression itself
+    pub expression: Arc<RwLock<ParsedRegexp>>,
+
+    // Number of ancestors of this subexpression, traversal root has depth 0
+    pub depth: usize,
+
+    // Index of this subexpression in its parent children
+    // traversal root has child index 0
+    pub child_index: usize,
+}
+
+// Pre-order traversal, each expression is visited before its children
+// see `ParsedRegexp::iter`
+pub struct PreOrderIter {
+    // Expressions to visit next, last one is visited first
+    stack: Vec<VisitedExpression>,
+}
+
+impl Iterator for PreOrderIter {
+    type Item = VisitedExpression;
+
+    fn next(&mut self) -> Option<VisitedExpression> {
+        let visited = self.stack.pop()?;
+        let children = visited
+            .expression
+            .read()
+            .unwrap()
+            .children
+            .read()
+            .unwrap()
+            .clone();
+        // Push children in reverse order so that first child is visited first
+        for (child_index, child) in children.into_iter().enumerate().rev() {
+            self.stack.push(VisitedExpression {
+                expression: child,
+                depth: visited.depth + 1,
+                child_index,
+            });
+        }
+        Some(visited)
+    }
+}
+
+// Post-order traversal, each expression is visited after its children
+// see `ParsedRegexp::iter_post`
+pub struct PostOrderIter {
+    // Expressions to visit next, last one is visited first
+    // along with whether their children were already pushed
+    stack: Vec<(VisitedExpression, bool)>,
+}
+
+impl Iterator for PostOrderIter {
+    type Item = VisitedExpression;
+
+    fn next(&mut self) -> Option<VisitedExpression> {
+        loop {
+            let (visited, expanded) = self.stack.pop()?;
+            if expanded {
+                return Some(visited);
+            }
+            let children = visited
+                .expression
+                .read()
+                .unwrap()
+                .children
+                .read()
+                .unwrap()
+                .clone();
+            let depth = visited.depth + 1;
+            // Visit this expression again once all of its children are visited
+            self.stack.push((visited, true));
+            for (child_index, child) in children.into_iter().enumerate().rev() {
+                self.stack.push((
+                    VisitedExpression {
+                        expression: child,
+                        depth,
+                        child_index,
+                    },
+                    false,
+                ));
+            }
+        }
+    }
+}
+
+impl ParsedRegexp {
+    // Visit `root` and all of its subexpressions in pre-order
+    // For pattern `(a|b)c` visited patterns are: (a|b)c, (a|b), a|b, a, b, c
+    pub fn iter(root: &Arc<RwLock<ParsedRegexp>>) -> PreOrderIter {
+        PreOrderIter {
+            stack: vec![VisitedExpression {
+                expression: Arc::clone(root),
+                depth: 0,
+                child_index: 0,
+            }],
+        }
+    }
+
+    // Number `root` and all of its subexpressions in pre-order, root becomes node 0
+    pub fn assign_ids(root: &Arc<RwLock<ParsedRegexp>>) {
+        for (index, visited) in ParsedRegexp::iter(root).enumerate() {
+            visited.expression.write().unwrap().id = NodeId(index);
+        }
+    }
+
+    // Identifier of the last descendant of this expression in pre-order
+    // or its own identifier if it has no children
+    // Descendants of this expression are exactly nodes `self.id..=self.last_descendant_id()`
+    pub fn last_descendant_id(&self) -> NodeId {
+        match self.children.read().unwrap().last() {
+            Some(last_child) => last_child.read().unwrap().last_descendant_id(),
+            None => self.id,
+        }
+    }
+
+    // Visit `root` and all of its subexpressions in post-order
+    // For pattern `(a|b)c` visited patterns are: a, b, a|b, (a|b), c, (a|b)c
+    pub fn iter_post(root: &Arc<RwLock<ParsedRegexp>>) -> PostOrderIter {
+        PostOrderIter {
+            stack: vec![(
+                VisitedExpression {
+                    expression: Arc::clone(root),
+                    depth: 0,
+                    child_index: 0,
+                },
+                false,
+            )],
+        }
+    }
+}
+
 impl Clone for ParsedRegexp {
     fn clone(&self) -> Self {
         ParsedRegexp {
             expression_type: self.expression_type,
             pattern: Arc::from(self.pattern.as_ref()),
+            span: self.span.clone(),
+            id: self.id,
             parent: self.parent.as_ref().map(Weak::clone),
             children: RwLock::new(
                 self.children
@@ -202,3 +912,39 @@ impl Display for ParsedRegexp {
         write!(f, "{}", self.pattern)
     }
 }
+
+// Structural comparison of syntax trees
+// Two expressions are equal when they have the same type and their children
+// are pairwise equal, in order. Where an expression appears (its span, id and parent)
+// does not matter, so `a` in `ab` equals `a` in `ba`
+impl PartialEq for ParsedRegexp {
+    fn eq(&self, other: &Self) -> bool {
+        if self.expression_type != other.expression_type {
+            return false;
+        }
+        let children = self.children.read().unwrap();
+        let other_children = other.children.read().unwrap();
+        children.len() == other_children.len()
+            && children
+                .iter()
+                .zip(other_children.iter())
+                .all(|(child, other_child)| {
+                    Arc::ptr_eq(child, other_child)
+                        || *child.read().unwrap() == *other_child.read().unwrap()
+                })
+    }
+}
+
+impl Eq for ParsedRegexp {}
+
+// Consistent with PartialEq: only expression types and tree shape are hashed
+impl Hash for ParsedRegexp {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.expression_type.hash(state);
+        let children = self.children.read().unwrap();
+        children.len().hash(state);
+        for child in children.iter() {
+            child.read().unwrap().hash(state);
+        }
+    }
+}
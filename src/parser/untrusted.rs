@@ -0,0 +1,135 @@
+// Restrictions for patterns supplied by untrusted users, like visitors of a web service
+// A pattern breaking a restriction is rejected with an error naming the offending construct
+// and searches with an accepted pattern are given a step budget (see Matcher::set_step_limit)
+
+use std::sync::{Arc, RwLock};
+
+use super::limits::ParseLimits;
+use super::syntax_tree::{ExpressionType, ParsedRegexp, Quantifier};
+use super::Parser;
+use crate::diagnostics::DiagnosticCode;
+use crate::format_error;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct UntrustedProfile {
+    // Longest pattern accepted, in characters
+    pub max_pattern_length: usize,
+    // Most groups and classes nested inside each other, `((a))` and `[[a]]` have depth 2
+    pub max_nesting_depth: usize,
+    // Accept repeated groups containing repeated subexpressions, like `(a+)+`
+    // Such patterns may take exponential time to fail
+    pub allow_nested_quantifiers: bool,
+    // Accept backreferences, like `\1` in `(a+)\1`
+    // Matching them is NP-hard, no pattern matcher avoids exponential time for all of them
+    pub allow_backreferences: bool,
+    // Most subexpression match attempts a single search may take
+    pub step_limit: usize,
+}
+
+impl Default for UntrustedProfile {
+    fn default() -> UntrustedProfile {
+        UntrustedProfile {
+            max_pattern_length: 1000,
+            max_nesting_depth: 16,
+            allow_nested_quantifiers: false,
+            allow_backreferences: false,
+            step_limit: 1_000_000,
+        }
+    }
+}
+
+// Can `quantifier` repeat its expression more than once? Like `*`, `+` or `{2,5}`
+fn repeats(quantifier: Quantifier) -> bool {
+    quantifier.bounds().1.is_none_or(|max| max > 1)
+}
+
+impl UntrustedProfile {
+    // Error rejecting construct of `expression`
+    fn reject(
+        source: &str,
+        expression: &ParsedRegexp,
+        code: DiagnosticCode,
+        message: &str,
+    ) -> String {
+        let length = expression.span.len().clamp(1, u8::MAX as usize) as u8;
+        format_error(
+            &format!(
+                "Rejected pattern [{code}] in position {}: {message}",
+                expression.span.start
+            ),
+            source,
+            &[(expression.span.start, length)],
+            "This pattern is not accepted from untrusted sources",
+        )
+    }
+
+    // Check `expression` and its descendants
+    // Length and nesting depth are checked while parsing, see `parse_limits`
+    // `repeated_group` is the innermost enclosing group repeated more than once, if any
+    fn check_expression(
+        &self,
+        source: &str,
+        expression: &Arc<RwLock<ParsedRegexp>>,
+        repeated_group: Option<&Arc<RwLock<ParsedRegexp>>>,
+    ) -> Result<(), String> {
+        let parsed = expression.read().unwrap();
+        // Lookbehinds nest like groups do
+        let is_group = matches!(
+            parsed.expression_type,
+            ExpressionType::Group { .. } | ExpressionType::Lookbehind { .. }
+        );
+
+        if let (false, ExpressionType::Backreference { group, .. }) =
+            (self.allow_backreferences, parsed.expression_type)
+        {
+            return Err(UntrustedProfile::reject(
+                source,
+                &parsed,
+                DiagnosticCode::UntrustedBackreference,
+                &format!("Backreference `{}` to group {group}", parsed.pattern),
+            ));
+        }
+
+        let repeated = repeats(parsed.expression_type.quantifier());
+        if let (false, true, Some(group)) =
+            (self.allow_nested_quantifiers, repeated, repeated_group)
+        {
+            return Err(UntrustedProfile::reject(
+                source,
+                &group.read().unwrap(),
+                DiagnosticCode::NestedQuantifier,
+                &format!(
+                    "Repeated group `{}` contains repeated subexpression `{}`",
+                    group.read().unwrap().pattern,
+                    parsed.pattern
+                ),
+            ));
+        }
+
+        let repeated_group = if is_group && repeated {
+            Some(expression)
+        } else {
+            repeated_group
+        };
+        for child in parsed.children.read().unwrap().iter() {
+            self.check_expression(source, child, repeated_group)?;
+        }
+        Ok(())
+    }
+
+    // Bounds the parser enforces for this profile
+    pub fn parse_limits(&self) -> ParseLimits {
+        ParseLimits {
+            max_pattern_length: self.max_pattern_length,
+            max_nesting_depth: self.max_nesting_depth,
+            ..ParseLimits::UNLIMITED
+        }
+    }
+
+    // Parse `source` if it follows this profile
+    pub fn parse(&self, source: &str) -> Result<Arc<RwLock<ParsedRegexp>>, String> {
+        let regexp = Parser::parse_with_limits(source, &self.parse_limits())?;
+        self.check_expression(source, &regexp, None)?;
+        Ok(regexp)
+    }
+}
@@ -0,0 +1,72 @@
+// Non-fatal findings while parsing
+// A pattern with warnings is still valid, but it probably does not mean
+// what its author intended
+
+use std::fmt::Display;
+use std::ops::Range;
+
+use crate::diagnostics::DiagnosticCode;
+use crate::format_error_with;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum WarningKind {
+    // A slash before a character which is neither a metacharacter nor a letter, like `\@`
+    // The slash itself is matched literally, followed by the character
+    LiteralEscape(char),
+
+    // An alternative matching only the empty string, like the one after | in `a|`
+    EmptyAlternative,
+
+    // A group enclosing nothing, `()`
+    EmptyGroup,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct ParseWarning {
+    pub kind: WarningKind,
+    // Range of character indices in source pattern this warning is about
+    pub span: Range<usize>,
+}
+
+impl ParseWarning {
+    // Stable code of this warning (see `diagnostics`)
+    pub fn code(&self) -> DiagnosticCode {
+        DiagnosticCode::from(self.kind)
+    }
+
+    // Warning message followed by source pattern with carets below `span`
+    pub fn render(&self, source: &str) -> String {
+        self.render_with(source, false)
+    }
+
+    // Render like `render`, colored for a terminal when `colored` (see `format_error_with`)
+    pub fn render_with(&self, source: &str, colored: bool) -> String {
+        let carets = (self.span.end - self.span.start).clamp(1, u8::MAX as usize) as u8;
+        format_error_with(
+            &format!(
+                "Warning [{}] in position {}: {self}",
+                self.code(),
+                self.span.start
+            ),
+            source,
+            &[(self.span.start, carets)],
+            "",
+            colored,
+        )
+    }
+}
+
+impl Display for ParseWarning {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self.kind {
+            WarningKind::LiteralEscape(ch) => write!(
+                f,
+                "{ch} is not a metacharacter, the slash before it is matched literally"
+            ),
+            WarningKind::EmptyAlternative => {
+                write!(f, "Empty alternative matches only the empty string")
+            }
+            WarningKind::EmptyGroup => write!(f, "Empty group matches only the empty string"),
+        }
+    }
+}
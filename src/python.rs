@@ -0,0 +1,154 @@
+// Python bindings
+// A Python module named `regexps`, build it with maturin (`maturin develop --features python`)
+//
+//     import regexps
+//     regexps.findall("[ab]+", "xxabyba")     # ['ab', 'ba']
+//     regexps.findall("(a)(b)?", "ab a")      # [('a', 'b'), ('a', '')]
+//     pattern = regexps.compile("a+")
+//     pattern.search("xaay").span()           # (1, 3)
+//     pattern.sub("[$0]", "xaay")             # 'x[aa]y'
+//
+// Positions are character indices, the same as Python string indices
+
+use pyo3::exceptions::PyValueError;
+use pyo3::prelude::*;
+use pyo3::types::PyTuple;
+
+use crate::matcher::Matcher;
+use crate::regex::Regex;
+
+// A parsed pattern
+#[pyclass(name = "Pattern", module = "regexps", frozen)]
+struct PyPattern {
+    pattern: String,
+    regex: Regex,
+}
+
+// A match found in some string
+#[pyclass(name = "Match", module = "regexps", frozen)]
+struct PyMatch {
+    start: usize,
+    end: usize,
+    // Matched text
+    text: String,
+}
+
+impl PyPattern {
+    fn matcher(&self, string: &str) -> Matcher {
+        self.regex.matcher(string)
+    }
+}
+
+#[pymethods]
+impl PyPattern {
+    #[getter]
+    fn pattern(&self) -> &str {
+        &self.pattern
+    }
+
+    // First match in `string`, or None
+    fn search(&self, string: &str) -> Option<PyMatch> {
+        let m = self.matcher(string).next()?;
+        Some(PyMatch {
+            text: string.chars().skip(m.start).take(m.len()).collect(),
+            start: m.start,
+            end: m.end,
+        })
+    }
+
+    // Like Python's `re.findall`, for each non-overlapping match in `string`
+    // its text if pattern has no groups, text of the group if it has one
+    // or a tuple of texts of all groups, groups not taking part in a match give ''
+    fn findall(&self, py: Python<'_>, string: &str) -> PyResult<Vec<PyObject>> {
+        let groups = self.regex.syntax_tree().read().unwrap().capture_count();
+        self.regex
+            .captures_iter(string)
+            .map(|captures| {
+                let text = |index| captures.get(index).map_or("", |group| group.as_str());
+                Ok(match groups {
+                    0 => text(0).into_pyobject(py)?.into_any().unbind(),
+                    1 => text(1).into_pyobject(py)?.into_any().unbind(),
+                    _ => PyTuple::new(py, (1..groups + 1).map(text))?
+                        .into_any()
+                        .unbind(),
+                })
+            })
+            .collect()
+    }
+
+    // Copy of `string` with matches replaced by `repl`, at most `count` of them (0 for all)
+    // In `repl`, $0 is the matched text and $$ is a literal $
+    #[pyo3(signature = (repl, string, count = 0))]
+    fn sub(&self, repl: &str, string: &str, count: usize) -> String {
+        let count = if count == 0 { usize::MAX } else { count };
+        self.matcher(string).subn_expand(repl, count)
+    }
+
+    fn __repr__(&self) -> String {
+        format!("regexps.compile({:?})", self.pattern)
+    }
+}
+
+#[pymethods]
+impl PyMatch {
+    fn start(&self) -> usize {
+        self.start
+    }
+
+    fn end(&self) -> usize {
+        self.end
+    }
+
+    fn span(&self) -> (usize, usize) {
+        (self.start, self.end)
+    }
+
+    // Matched text
+    fn group(&self) -> &str {
+        &self.text
+    }
+
+    fn __repr__(&self) -> String {
+        format!(
+            "<regexps.Match object; span=({}, {}), match={:?}>",
+            self.start, self.end, self.text
+        )
+    }
+}
+
+// Parse `pattern`, syntax errors are raised as ValueError
+#[pyfunction]
+fn compile(pattern: &str) -> PyResult<PyPattern> {
+    let regex = Regex::new(pattern).map_err(PyValueError::new_err)?;
+    Ok(PyPattern {
+        pattern: pattern.to_string(),
+        regex,
+    })
+}
+
+#[pyfunction]
+fn search(pattern: &str, string: &str) -> PyResult<Option<PyMatch>> {
+    Ok(compile(pattern)?.search(string))
+}
+
+#[pyfunction]
+fn findall(py: Python<'_>, pattern: &str, string: &str) -> PyResult<Vec<PyObject>> {
+    compile(pattern)?.findall(py, string)
+}
+
+#[pyfunction]
+#[pyo3(signature = (pattern, repl, string, count = 0))]
+fn sub(pattern: &str, repl: &str, string: &str, count: usize) -> PyResult<String> {
+    Ok(compile(pattern)?.sub(repl, string, count))
+}
+
+#[pymodule]
+fn regexps(module: &Bound<'_, PyModule>) -> PyResult<()> {
+    module.add_class::<PyPattern>()?;
+    module.add_class::<PyMatch>()?;
+    module.add_function(wrap_pyfunction!(compile, module)?)?;
+    module.add_function(wrap_pyfunction!(search, module)?)?;
+    module.add_function(wrap_pyfunction!(findall, module)?)?;
+    module.add_function(wrap_pyfunction!(sub, module)?)?;
+    Ok(())
+}
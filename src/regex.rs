@@ -0,0 +1,427 @@
+// Compiled patterns and the options they are compiled with
+//
+// For instance:
+//     let regex = RegexBuilder::new("a.b(c)").literal(true).build()?;
+// matches only "a.b(c)", like `Regex::literal("a.b(c)")` does
+//
+// A Regex holds no target, each search creates its own Matcher from the compiled pattern
+// thus one Regex serves any number of targets, and threads, without reparsing:
+//     for line in lines { if regex.is_match(line) { ... } }
+//
+// What groups captured converts into user types, see `FromCaptures`:
+//     let (host, status): (String, u16) = regex.captures(line).ok_or("no match")?.parse()?;
+//
+// Loops going through many matches reuse one CaptureLocations instead:
+//     let mut locations = regex.capture_locations();
+//     let mut matches = regex.captures_iter(text);
+//     while matches.read_next(&mut locations).is_some() { ... locations.get(1) ... }
+
+use std::fmt::Display;
+use std::ops::Range;
+use std::str::FromStr;
+use std::sync::{Arc, RwLock};
+
+use crate::matcher::{surrounded, CompiledPattern, Match, Matcher};
+use crate::parser::{
+    syntax_tree::{Assertion, ExpressionType, LineTerminator, ParsedRegexp},
+    Parser,
+};
+use crate::scanner::Scanner;
+
+// A pattern compiled once, matched against any number of targets
+#[derive(Clone)]
+pub struct Regex {
+    // Pattern as written, before options rewrote it
+    source: Arc<str>,
+    compiled: CompiledPattern,
+}
+
+impl Regex {
+    // Compile `pattern` with default options
+    pub fn new(pattern: &str) -> Result<Regex, String> {
+        RegexBuilder::new(pattern).build()
+    }
+
+    // Compile a pattern matching `text` literally, see `RegexBuilder::literal`
+    pub fn literal(text: &str) -> Regex {
+        Regex::from_regexp(text, Parser::parse_literal(text))
+    }
+
+    pub(crate) fn from_regexp(source: &str, regexp: Arc<RwLock<ParsedRegexp>>) -> Regex {
+        Regex {
+            source: Arc::from(source),
+            compiled: CompiledPattern::new(regexp),
+        }
+    }
+
+    // Pattern as written, modes included, like `(?i)ab`
+    // even though case insensitivity and builder options rewrite the syntax tree
+    pub fn as_str(&self) -> Arc<str> {
+        Arc::clone(&self.source)
+    }
+
+    pub fn syntax_tree(&self) -> &Arc<RwLock<ParsedRegexp>> {
+        self.compiled.pattern()
+    }
+
+    // Matcher of this pattern against `target`, for what searches below do not offer
+    // like step limits, deadlines or search options
+    pub fn matcher(&self, target: &str) -> Matcher {
+        Matcher::from_compiled(&self.compiled, target)
+    }
+
+    // Does some range within `haystack` match pattern?
+    pub fn is_match(&self, haystack: &str) -> bool {
+        self.matcher(haystack).next().is_some()
+    }
+
+    // First match in `haystack`, if any
+    pub fn find<'t>(&self, haystack: &'t str) -> Option<Match<'t>> {
+        self.find_iter(haystack).next()
+    }
+
+    // Successive non-overlapping matches in `haystack`
+    pub fn find_iter<'t>(&self, haystack: &'t str) -> FindMatches<'t> {
+        FindMatches {
+            haystack,
+            matcher: self.matcher(haystack).without_match_cache(),
+        }
+    }
+
+    // Text matched by each capturing group in first match in `haystack`
+    pub fn captures<'t>(&self, haystack: &'t str) -> Option<Captures<'t>> {
+        let mut matcher = self.matcher(haystack);
+        matcher.next()?;
+        Some(Captures::new(haystack, &matcher))
+    }
+
+    // Captures of successive non-overlapping matches in `haystack`
+    pub fn captures_iter<'t>(&self, haystack: &'t str) -> CaptureMatches<'t> {
+        CaptureMatches {
+            haystack,
+            matcher: self.matcher(haystack).without_match_cache(),
+        }
+    }
+
+    // Slots for what groups of this pattern capture, filled by `CaptureMatches::read_next`
+    pub fn capture_locations(&self) -> CaptureLocations {
+        let groups = self.syntax_tree().read().unwrap().capture_count() + 1;
+        CaptureLocations {
+            slots: vec![None; groups],
+        }
+    }
+}
+
+// Byte offsets captured by each group in one match, index 0 holds the whole match
+// Filled over and over by `CaptureMatches::read_next`, reusing the same slots for each match
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CaptureLocations {
+    slots: Vec<Option<Range<usize>>>,
+}
+
+impl CaptureLocations {
+    // Byte offsets matched by group `index`, None if it did not take part in the match
+    // or pattern has no such group
+    pub fn get(&self, index: usize) -> Option<Range<usize>> {
+        self.slots.get(index).cloned().flatten()
+    }
+
+    // Number of slots, whole match included
+    pub fn len(&self) -> usize {
+        self.slots.len()
+    }
+
+    // True only for locations not built by `Regex::capture_locations`, which always
+    // has a slot for the whole match
+    pub fn is_empty(&self) -> bool {
+        self.slots.is_empty()
+    }
+}
+
+// Captures of matches of a Regex in a haystack, see `Regex::captures_iter`
+// Iterating allocates Captures for each match, `read_next` fills caller slots instead
+pub struct CaptureMatches<'t> {
+    haystack: &'t str,
+    matcher: Matcher,
+}
+
+impl<'t> CaptureMatches<'t> {
+    // Find the next match and write what groups captured into `locations`
+    // Slots of groups beyond those of `locations` are dropped, missing ones are cleared
+    // Matches are not cached either, memory use stays flat however many are read
+    pub fn read_next(&mut self, locations: &mut CaptureLocations) -> Option<Match<'t>> {
+        let found = self.matcher.next();
+        let map = self.matcher.byte_map();
+        let captures = self.matcher.captures();
+        for (index, slot) in locations.slots.iter_mut().enumerate() {
+            *slot = captures
+                .get(index)
+                .cloned()
+                .flatten()
+                .map(|capture| map.range(&capture));
+        }
+        Some(Match::with_map(self.haystack, found?, map))
+    }
+}
+
+impl<'t> Iterator for CaptureMatches<'t> {
+    type Item = Captures<'t>;
+
+    fn next(&mut self) -> Option<Captures<'t>> {
+        self.matcher.next()?;
+        Some(Captures::new(self.haystack, &self.matcher))
+    }
+}
+
+// Text matched by each capturing group in one match, see `Regex::captures`
+// index 0 holds the whole match and index i holds group i, see `Matcher::captures`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Captures<'t> {
+    groups: Vec<Option<Match<'t>>>,
+}
+
+impl<'t> Captures<'t> {
+    // Captures of the most recent match `matcher` found in `haystack`
+    fn new(haystack: &'t str, matcher: &Matcher) -> Captures<'t> {
+        let map = matcher.byte_map();
+        let groups = matcher
+            .captures()
+            .iter()
+            .map(|capture| {
+                let capture = capture.clone()?;
+                Some(Match::with_map(haystack, capture, map))
+            })
+            .collect();
+        Captures { groups }
+    }
+
+    // Text matched by group `index`, None if it did not take part in the match
+    // or pattern has no such group
+    pub fn get(&self, index: usize) -> Option<Match<'t>> {
+        self.groups.get(index).copied().flatten()
+    }
+
+    // Text matched by each group, whole match first
+    pub fn iter(&self) -> impl ExactSizeIterator<Item = Option<Match<'t>>> + '_ {
+        self.groups.iter().copied()
+    }
+
+    // Text matched by group `index` converted with `FromStr`, like `captures.parse_group::<u16>(2)`
+    // Errors tell which group is missing or what text failed to convert
+    pub fn parse_group<T>(&self, index: usize) -> Result<T, String>
+    where
+        T: FromStr,
+        T::Err: Display,
+    {
+        if index >= self.groups.len() {
+            return Err(format!("Pattern has no group {index}"));
+        }
+        let Some(group) = self.get(index) else {
+            return Err(format!("Group {index} did not take part in the match"));
+        };
+        group.as_str().parse().map_err(|error| {
+            format!(
+                "Group {index} matched {:?} which is not a valid {}: {error}",
+                group.as_str(),
+                std::any::type_name::<T>()
+            )
+        })
+    }
+
+    // Convert these captures into a `T`, like a struct holding fields of a log line
+    pub fn parse<T: FromCaptures>(&self) -> Result<T, String> {
+        T::from_captures(self)
+    }
+}
+
+// Types built from what groups of a match captured, see `Captures::parse`
+// Groups have no names, a struct converts each of its fields from a group by number:
+//     impl FromCaptures for LogLine {
+//         fn from_captures(captures: &Captures) -> Result<LogLine, String> {
+//             Ok(LogLine {
+//                 host: captures.parse_group(1)?,
+//                 status: captures.parse_group(2)?,
+//             })
+//         }
+//     }
+// Tuples of up to 6 `FromStr` types take groups 1, 2, ... in order
+pub trait FromCaptures: Sized {
+    fn from_captures(captures: &Captures) -> Result<Self, String>;
+}
+
+macro_rules! tuple_from_captures {
+    ($($element:ident $index:tt),+) => {
+        impl<$($element),+> FromCaptures for ($($element,)+)
+        where
+            $($element: FromStr, $element::Err: Display,)+
+        {
+            fn from_captures(captures: &Captures) -> Result<Self, String> {
+                Ok(($(captures.parse_group::<$element>($index)?,)+))
+            }
+        }
+    };
+}
+
+tuple_from_captures!(A 1);
+tuple_from_captures!(A 1, B 2);
+tuple_from_captures!(A 1, B 2, C 3);
+tuple_from_captures!(A 1, B 2, C 3, D 4);
+tuple_from_captures!(A 1, B 2, C 3, D 4, E 5);
+tuple_from_captures!(A 1, B 2, C 3, D 4, E 5, F 6);
+
+// Matches of a Regex in a haystack, see `Regex::find_iter`
+pub struct FindMatches<'t> {
+    haystack: &'t str,
+    matcher: Matcher,
+}
+
+impl<'t> Iterator for FindMatches<'t> {
+    type Item = Match<'t>;
+
+    fn next(&mut self) -> Option<Match<'t>> {
+        let found = self.matcher.next()?;
+        Some(Match::with_map(
+            self.haystack,
+            found,
+            self.matcher.byte_map(),
+        ))
+    }
+}
+
+impl std::fmt::Debug for Regex {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_tuple("Regex").field(&self.as_str()).finish()
+    }
+}
+
+impl std::fmt::Display for Regex {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.as_str())
+    }
+}
+
+// Options for compiling a pattern into a Regex
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct RegexBuilder {
+    pattern: String,
+    literal: bool,
+    smart_case: bool,
+    word: bool,
+    line: bool,
+    colored_errors: bool,
+}
+
+impl RegexBuilder {
+    pub fn new(pattern: &str) -> RegexBuilder {
+        RegexBuilder {
+            pattern: pattern.to_string(),
+            literal: false,
+            smart_case: false,
+            word: false,
+            line: false,
+            colored_errors: false,
+        }
+    }
+
+    // Treat the whole pattern as text to match, metacharacters included
+    // Quicker and safer than escaping it then parsing it, see `Parser::parse_literal`
+    pub fn literal(&mut self, enabled: bool) -> &mut RegexBuilder {
+        self.literal = enabled;
+        self
+    }
+
+    // Ignore case unless the pattern has an uppercase letter, like grep tools do
+    // `foo` matches "Foo" while `Foo` does not match "foo"
+    // Escapes are not letters of the pattern, `\W` is all lowercase
+    pub fn smart_case(&mut self, enabled: bool) -> &mut RegexBuilder {
+        self.smart_case = enabled;
+        self
+    }
+
+    // Match only whole words, as if the pattern was enclosed by word boundaries `\b`
+    // `cat` then matches "a cat" but not "concatenate"
+    // Boundaries are added to the syntax tree only, `as_str` still gives the pattern without them
+    pub fn word(&mut self, enabled: bool) -> &mut RegexBuilder {
+        self.word = enabled;
+        self
+    }
+
+    // Match only whole lines, as if the pattern was `^(?:...)$` in multiline mode, like grep -x
+    // Lines end with \n, or with \r\n, \r or \n if the pattern turns on CRLF mode (inline flag `R`)
+    // Anchors are added to the syntax tree only, `as_str` still gives the pattern without them
+    pub fn line(&mut self, enabled: bool) -> &mut RegexBuilder {
+        self.line = enabled;
+        self
+    }
+
+    // Color pattern errors for printing them to a terminal, see `format_error_with`
+    pub fn colored_errors(&mut self, enabled: bool) -> &mut RegexBuilder {
+        self.colored_errors = enabled;
+        self
+    }
+
+    pub fn build(&self) -> Result<Regex, String> {
+        let mut regexp = if self.literal {
+            if self.smart_case && !self.pattern.chars().any(is_uppercase_letter) {
+                Parser::parse_literal_case_insensitive(&self.pattern)
+            } else {
+                Parser::parse_literal(&self.pattern)
+            }
+        } else {
+            let (regexp, _) =
+                Parser::parse_with_colored_errors(&self.pattern, self.colored_errors)?;
+            if self.smart_case && !has_uppercase(&self.pattern, &regexp) {
+                Parser::parse_case_insensitive(&self.pattern)?
+            } else {
+                regexp
+            }
+        };
+        if self.word {
+            let boundary = Some(Assertion::WordBoundary);
+            let wrapped = surrounded(&regexp.read().unwrap(), boundary, boundary);
+            regexp = wrapped;
+        }
+        if self.line {
+            // Inline flags are read as soon as the pattern is scanned
+            let terminator = if !self.literal && Scanner::new(&self.pattern).is_crlf() {
+                LineTerminator::Crlf
+            } else {
+                LineTerminator::default()
+            };
+            let wrapped = surrounded(
+                &regexp.read().unwrap(),
+                Some(Assertion::StartOfLine(terminator)),
+                Some(Assertion::EndOfLine(terminator)),
+            );
+            regexp = wrapped;
+        }
+        Ok(Regex::from_regexp(&self.pattern, regexp))
+    }
+}
+
+// Does `regexp` match an uppercase letter written as itself? Like `A` in `A+` or `[A-Z]`
+// Escapes are not letters of the pattern, `\0101` matches 'A' but has no letter written
+// thus only characters spelled out as themselves in `source` count
+fn has_uppercase(source: &str, regexp: &Arc<RwLock<ParsedRegexp>>) -> bool {
+    let source: Vec<char> = source.chars().collect();
+    ParsedRegexp::iter(regexp).any(|visited| {
+        let expression = visited.expression.read().unwrap();
+        let written = source.get(expression.span.clone()).unwrap_or_default();
+        match expression.expression_type {
+            ExpressionType::CharacterExpression {
+                value: Some(value), ..
+            } => written.first() == Some(&value) && is_uppercase_letter(value),
+            ExpressionType::CharacterRange { first, last } => {
+                (written.first() == Some(&first) && is_uppercase_letter(first))
+                    || (written.last() == Some(&last) && is_uppercase_letter(last))
+            }
+            _ => false,
+        }
+    })
+}
+
+// Uppercase letters and titlecase letters, like 'ǅ' which begins words written as "ǅemal"
+// Both have a lowercase form, which is what smart case looks for
+fn is_uppercase_letter(ch: char) -> bool {
+    ch.is_uppercase() || ch.to_lowercase().ne([ch])
+}
@@ -0,0 +1,83 @@
+// Places where the scanner generates Empty tokens
+// An Empty token stands for an expression matching only the empty string, like the
+// alternative after | in `a|`, lint tools can warn about ones which are probably unintended
+
+use std::fmt::Display;
+
+use super::{tokens::TokenType, Scanner};
+
+// Why an empty expression is found at some position
+// Each variant is one of the cases Scanner generates an Empty token in
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum EmptyReason {
+    // "" the whole pattern is empty
+    EmptyPattern,
+    // "|..." before the leading |
+    LeadingPipe,
+    // "...|" after the trailing |
+    TrailingPipe,
+    // "...||..." between | and |
+    AdjacentPipes,
+    // "...(|...)..." between ( and |
+    AfterLeftParen,
+    // "...(...|)..." between | and )
+    BeforeRightParen,
+    // "...()..." between ( and )
+    EmptyGroup,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct EmptyExpression {
+    // Index of character in pattern the empty expression is right before
+    // or pattern length if it is at the end
+    pub position: usize,
+    pub reason: EmptyReason,
+}
+
+impl Display for EmptyReason {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let description = match self {
+            EmptyReason::EmptyPattern => "pattern is empty",
+            EmptyReason::LeadingPipe => "empty alternative before leading |",
+            EmptyReason::TrailingPipe => "empty alternative after trailing |",
+            EmptyReason::AdjacentPipes => "empty alternative between adjacent |'s",
+            EmptyReason::AfterLeftParen => "empty alternative between ( and |",
+            EmptyReason::BeforeRightParen => "empty alternative between | and )",
+            EmptyReason::EmptyGroup => "group encloses nothing",
+        };
+        write!(f, "{description}")
+    }
+}
+
+// Empty expressions in `pattern`, in order of position
+pub fn empty_expressions(pattern: &str) -> Vec<EmptyExpression> {
+    use TokenType::*;
+
+    let tokens = Scanner::new(pattern).collect::<Vec<_>>();
+    let mut empty_expressions = Vec::new();
+    // Type of most recent token other than Empty
+    let mut previous = None;
+    for (index, token) in tokens.iter().enumerate() {
+        if token.type_name != Empty {
+            previous = Some(token.type_name);
+            continue;
+        }
+        // An Empty token is generated depending on tokens around it
+        // thus they tell which case it is
+        let next = tokens.get(index + 1).map(|token| token.type_name);
+        let reason = match (previous, next) {
+            (None, None) => EmptyReason::EmptyPattern,
+            (None, _) => EmptyReason::LeadingPipe,
+            (Some(Pipe), None) => EmptyReason::TrailingPipe,
+            (Some(Pipe), Some(Pipe)) => EmptyReason::AdjacentPipes,
+            (Some(LeftParen), Some(Pipe)) => EmptyReason::AfterLeftParen,
+            (Some(Pipe), _) => EmptyReason::BeforeRightParen,
+            _ => EmptyReason::EmptyGroup,
+        };
+        empty_expressions.push(EmptyExpression {
+            position: token.position,
+            reason,
+        });
+    }
+    empty_expressions
+}
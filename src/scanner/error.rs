@@ -0,0 +1,91 @@
+// Errors found while scanning a pattern
+// Scanner never terminates the process, it hands these back to the caller
+// see Scanner::try_next
+
+use std::fmt::Display;
+
+use crate::diagnostics::DiagnosticCode;
+use crate::format_error_with;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ScanError {
+    // Pattern ends with an escape character which escapes nothing, like `a\`
+    // `position` is the index of the escape character in source pattern
+    TrailingEscape { position: usize },
+    // Comment without a closing ), like `a(?#b`
+    // `position` is the index of ( beginning the comment
+    UnterminatedComment { position: usize },
+    // Escape character before a letter which means nothing escaped, like `\q`
+    // `position` is the index of the escape character in source pattern
+    UnknownEscape { position: usize, letter: char },
+}
+
+impl ScanError {
+    // Index of the offending character in source pattern
+    pub fn position(&self) -> usize {
+        match self {
+            ScanError::TrailingEscape { position }
+            | ScanError::UnterminatedComment { position }
+            | ScanError::UnknownEscape { position, .. } => *position,
+        }
+    }
+
+    // Number of characters of the offending text starting at `position`
+    fn length(&self) -> u8 {
+        match self {
+            ScanError::TrailingEscape { .. } => 1,
+            // Opening `(?#`
+            ScanError::UnterminatedComment { .. } => 3,
+            // Escape character and the letter after it
+            ScanError::UnknownEscape { .. } => 2,
+        }
+    }
+
+    // Stable code of this error (see `diagnostics`)
+    pub fn code(&self) -> DiagnosticCode {
+        match self {
+            ScanError::TrailingEscape { .. } => DiagnosticCode::TrailingEscape,
+            ScanError::UnterminatedComment { .. } => DiagnosticCode::UnterminatedComment,
+            ScanError::UnknownEscape { .. } => DiagnosticCode::UnknownEscape,
+        }
+    }
+
+    // Error message followed by source pattern with carets below the offending characters
+    pub fn render(&self, source: &str) -> String {
+        self.render_with(source, false)
+    }
+
+    // Render like `render`, colored for a terminal when `colored` (see `format_error_with`)
+    pub fn render_with(&self, source: &str, colored: bool) -> String {
+        format_error_with(
+            &format!(
+                "Syntax error [{}] in position {}: {self}",
+                self.code(),
+                self.position()
+            ),
+            source,
+            &[(self.position(), self.length())],
+            "",
+            colored,
+        )
+    }
+}
+
+impl Display for ScanError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ScanError::TrailingEscape { .. } => {
+                write!(f, "Pattern ends with an escape character escaping nothing")
+            }
+            ScanError::UnterminatedComment { .. } => {
+                write!(f, "Comment (?# is never closed by )")
+            }
+            ScanError::UnknownEscape { letter, .. } => write!(
+                f,
+                "Unknown escape \\{letter}, write {letter} alone to match it literally"
+            ),
+        }
+    }
+}
+
+impl std::error::Error for ScanError {}
@@ -0,0 +1,211 @@
+// Highlight categories for syntax highlighting patterns
+// Editors and terminal tools color each span of a pattern by its category
+
+use std::ops::Range;
+
+use super::{error::ScanError, tokens::TokenType, Scanner};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum HighlightCategory {
+    // An ordinary character, like `a`
+    Literal,
+    // A metacharacter other than quantifiers and parentheses, like `.`, `|` or `[`
+    Metacharacter,
+    // `*`, `+`, `?` or a counted repetition, like `{2,5}`
+    Quantifier,
+    // `(` or `)`
+    GroupDelimiter,
+    // An escape sequence, like `\*`, `\t`, `\b` or `\1`
+    Escape,
+    // Something the parser rejects, like an unbalanced `)`
+    Error,
+    // A comment, like `(?#note)`, or whitespace and `#` comments in free-spacing mode
+    Comment,
+    // Inline flags at start of pattern, like `(?x)`
+    Flags,
+}
+
+// A span of pattern along with its category
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Highlight {
+    // Range of character indices in pattern
+    pub span: Range<usize>,
+    pub category: HighlightCategory,
+}
+
+// Split `pattern` into highlighted spans, in order
+// Spans cover all of pattern characters, none is empty
+// Errors are found without parsing: unbalanced parentheses, unclosed or empty character classes,
+// quantifiers with nothing to repeat, a trailing escape character, unknown escapes like `\q`
+// and a comment never closed
+pub fn highlight(pattern: &str) -> Vec<Highlight> {
+    use HighlightCategory::*;
+
+    let mut scanner = Scanner::new(pattern);
+    let mut highlights: Vec<Highlight> = Vec::new();
+    // Indices (in `highlights`) of ( not closed yet
+    let mut open_groups = Vec::new();
+    // Indices (in `highlights`) of [ of character classes not closed yet, innermost last
+    let mut open_classes = Vec::new();
+    // Whether last token of the open character class is a character which can begin a range
+    let mut can_start_range = false;
+    // Index (in `highlights`) of `-` which forms a range if a character follows it, if any
+    let mut range_hyphen: Option<usize> = None;
+    // Type of most recent token other than Empty
+    let mut previous = None;
+    // Number of spans skipped by the scanner already highlighted
+    let mut skipped = 0;
+    let flags = scanner.flags_span();
+    if !flags.is_empty() {
+        highlights.push(Highlight {
+            span: flags,
+            category: Flags,
+        });
+    }
+    loop {
+        let next = scanner.try_next();
+        // Comments skipped before this token
+        highlights.extend(
+            scanner.skipped_spans()[skipped..]
+                .iter()
+                .map(|span| Highlight {
+                    span: span.clone(),
+                    category: Comment,
+                }),
+        );
+        skipped = scanner.skipped_spans().len();
+        let token = match next {
+            Ok(Some(token)) => token,
+            Ok(None) => break,
+            Err(error @ ScanError::TrailingEscape { .. }) => {
+                highlights.push(Highlight {
+                    span: error.position()..error.position() + 1,
+                    category: Error,
+                });
+                break;
+            }
+            // The comment runs until end of pattern
+            Err(error @ ScanError::UnterminatedComment { .. }) => {
+                highlights.push(Highlight {
+                    span: error.position()..scanner.get_source_length(),
+                    category: Error,
+                });
+                break;
+            }
+            // Highlight the escaped letter along with the slash and move on after it
+            Err(error @ ScanError::UnknownEscape { .. }) => {
+                highlights.push(Highlight {
+                    span: error.position()..error.position() + 2,
+                    category: Error,
+                });
+                previous = scanner.next().map(|letter| letter.type_name);
+                continue;
+            }
+        };
+        let mut category = match token.type_name {
+            TokenType::Empty => continue,
+            TokenType::Character { .. } if token.length == 1 => {
+                if token.type_name == (TokenType::Character { value: '\\' }) {
+                    // A slash escaping nothing, see WarningKind::LiteralEscape
+                    Escape
+                } else {
+                    Literal
+                }
+            }
+            TokenType::Character { .. }
+            | TokenType::ShorthandClass { .. }
+            | TokenType::Backreference { .. }
+            | TokenType::Linebreak
+            | TokenType::StartAnchor
+            | TokenType::EndAnchor
+            | TokenType::WordBoundary
+            | TokenType::NonWordBoundary => Escape,
+            TokenType::Dot | TokenType::Pipe | TokenType::Caret | TokenType::Dollar => {
+                Metacharacter
+            }
+            TokenType::LeftBracket => {
+                // Nested classes never end ranges, `[a-[b]]` lists a, - and b
+                if let Some(index) = range_hyphen.take() {
+                    highlights[index].category = Literal;
+                }
+                open_classes.push(highlights.len());
+                can_start_range = false;
+                Metacharacter
+            }
+            TokenType::RightBracket => {
+                open_classes.pop();
+                // Nested classes never begin ranges either
+                can_start_range = false;
+                match previous {
+                    // `[]` lists no characters
+                    Some(TokenType::LeftBracket) => Error,
+                    _ => {
+                        // `-` right before ] is an ordinary character, like in `[a-]`
+                        if let Some(index) = range_hyphen.take() {
+                            highlights[index].category = Literal;
+                        }
+                        Metacharacter
+                    }
+                }
+            }
+            // Unless it forms a range, see below
+            TokenType::Hyphen => Literal,
+            TokenType::Mark | TokenType::Star | TokenType::Plus | TokenType::Repetition => {
+                match previous {
+                    // Nothing before this quantifier to repeat
+                    None | Some(TokenType::LeftParen) | Some(TokenType::Pipe) => Error,
+                    // Assertions match no characters to repeat
+                    Some(
+                        TokenType::Caret
+                        | TokenType::Dollar
+                        | TokenType::StartAnchor
+                        | TokenType::EndAnchor
+                        | TokenType::WordBoundary
+                        | TokenType::NonWordBoundary,
+                    ) => Error,
+                    _ => Quantifier,
+                }
+            }
+            TokenType::LeftParen => {
+                open_groups.push(highlights.len());
+                GroupDelimiter
+            }
+            TokenType::RightParen => match open_groups.pop() {
+                Some(_) => GroupDelimiter,
+                None => Error,
+            },
+        };
+        // A `-` after a character which does not end a range forms a range
+        // if another character follows it, like in `[a-z]` but not in `[a-c-]`
+        if !open_classes.is_empty() {
+            if let TokenType::ShorthandClass { .. } = token.type_name {
+                // Shorthand classes never end ranges, `[a-\d]` lists a, - and digits
+                if let Some(index) = range_hyphen.take() {
+                    highlights[index].category = Literal;
+                }
+                can_start_range = false;
+            }
+            if let TokenType::Character { .. } | TokenType::Hyphen = token.type_name {
+                if range_hyphen.take().is_some() {
+                    can_start_range = false;
+                } else if token.type_name == TokenType::Hyphen && can_start_range {
+                    category = Metacharacter;
+                    range_hyphen = Some(highlights.len());
+                    can_start_range = false;
+                } else {
+                    can_start_range = true;
+                }
+            }
+        }
+        previous = Some(token.type_name);
+        highlights.push(Highlight {
+            span: token.position..token.position + token.length,
+            category,
+        });
+    }
+    // Groups and classes never closed
+    for index in open_groups.into_iter().chain(open_classes) {
+        highlights[index].category = HighlightCategory::Error;
+    }
+    highlights
+}
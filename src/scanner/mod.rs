@@ -4,9 +4,19 @@
 #[allow(dead_code)]
 pub mod tokens;
 
-use tokens::{Token, TokenType::*};
+// Errors found while scanning
+pub mod error;
 
-use crate::report_fatal_error;
+// Highlight categories of pattern spans, for syntax highlighting
+pub mod highlight;
+
+// Where empty expressions occur in a pattern, and why
+pub mod empty;
+
+use error::ScanError;
+use std::collections::VecDeque;
+use std::ops::Range;
+use tokens::{Token, TokenType, TokenType::*};
 
 pub const ANCHORS: [char; 4] = ['A', 'Z', 'b', 'B'];
 
@@ -14,36 +24,325 @@ pub fn is_anchor_char(ch: char) -> bool {
     ANCHORS.contains(&ch)
 }
 
+// Letters naming classes of characters when escaped, like `d` in `\d`
+pub const SHORTHAND_CLASSES: [char; 10] = ['d', 'D', 'w', 'W', 's', 'S', 'h', 'H', 'v', 'V'];
+
+pub fn is_shorthand_class_char(ch: char) -> bool {
+    SHORTHAND_CLASSES.contains(&ch)
+}
+
+// Letters naming control characters when escaped, paired with those characters
+// like `t` in `\t` which matches a tab
+// \0 is an octal escape instead (see Scanner::octal_escape)
+pub const CONTROL_ESCAPES: [(char, char); 4] =
+    [('n', '\n'), ('t', '\t'), ('r', '\r'), ('f', '\x0C')];
+
+// Largest number of octal digits after \0 in an octal escape, like `\0101` for A
+pub const MAX_OCTAL_DIGITS: usize = 3;
+
+// Control character named by `letter` when escaped, like a tab for `t`
+pub fn control_escape(letter: char) -> Option<char> {
+    CONTROL_ESCAPES
+        .iter()
+        .find(|(name, _)| *name == letter)
+        .map(|(_, ch)| *ch)
+}
+
+// `ch` as written in a pattern, escaped when `is_special` says so, like \*
+// and control characters written as their escapes, like \t for a tab
+pub fn escape_char(ch: char, is_special: fn(char) -> bool) -> String {
+    match CONTROL_ESCAPES.iter().find(|(_, control)| *control == ch) {
+        Some((letter, _)) => format!("\\{letter}"),
+        // With all octal digits, so digits after it are never read as part of it
+        None if ch == '\0' => String::from("\\0000"),
+        None if is_special(ch) => format!("\\{ch}"),
+        None => ch.to_string(),
+    }
+}
+
+// Characters with special meaning in patterns
+// To match one of them literally, precede it with a slash, like \*
+// Braces are special only around repetition counts, like `{2,3}`, elsewhere `{` and `}` are
+// ordinary characters, still escaping them is always allowed
+pub const METACHARACTERS: [char; 13] = [
+    '(', ')', '\\', '|', '*', '+', '.', '?', '[', '^', '$', '{', '}',
+];
+
+pub fn is_metacharacter(ch: char) -> bool {
+    METACHARACTERS.contains(&ch)
+}
+
+// Characters with special meaning inside character classes, like `]` in `[ab]`
+// Other metacharacters are ordinary characters there, `[*+]` matches either * or +
+pub const CLASS_METACHARACTERS: [char; 4] = ['\\', '[', ']', '-'];
+
+pub fn is_class_metacharacter(ch: char) -> bool {
+    CLASS_METACHARACTERS.contains(&ch)
+}
+
+// Letters of inline flags, written at start of a pattern like `(?x)` or `(?ix)`
+// i turns on case-insensitive mode, where characters also match their other cases
+// (see syntax_tree::other_cases) and backreferences match captured text in any case
+// (see syntax_tree::equal_ignoring_case), `(?i)(a)\1` matches "aA"
+// m turns on multiline mode, where ^ and $ match at start and end of each line
+// s turns on dot-all mode, where the dot matches a newline `\n` too
+// x turns on free-spacing mode (see ScannerConfig::free_spacing)
+// R turns on CRLF mode, where lines end with \r\n, \r or \n for the dot, ^ and $
+pub const INLINE_FLAGS: [char; 5] = ['i', 'm', 's', 'x', 'R'];
+
+// Syntax the scanner recognizes
+// Dialects differ in which characters are special, for instance in POSIX basic
+// regular expressions `(` is an ordinary character and `\(` starts a group
+// Whatever the configuration, escape character followed by A, Z, b or B is an anchor
+// and followed by d, D, w, W, s or S is a shorthand class
+#[derive(Debug, Clone, PartialEq)]
+pub struct ScannerConfig {
+    // Character starting escape sequences
+    // It's an ordinary character when escaped itself or not followed by a metacharacter
+    pub escape: char,
+    // Characters with special meaning when written on their own, like `*`
+    // Escaping one of them makes it an ordinary character
+    pub metacharacters: Vec<char>,
+    // Characters with special meaning only when escaped, like `(` in `\(`
+    // On their own they are ordinary characters
+    pub escaped_metacharacters: Vec<char>,
+    // Free-spacing mode, whitespace is insignificant and `#` starts a comment running
+    // until end of line, both are skipped (escape them to match them literally, like `\ `)
+    // Patterns beginning with `(?x)` are scanned in this mode whatever the configuration
+    pub free_spacing: bool,
+}
+
+impl Default for ScannerConfig {
+    // Syntax of this library, see METACHARACTERS
+    fn default() -> Self {
+        ScannerConfig {
+            escape: '\\',
+            metacharacters: METACHARACTERS
+                .iter()
+                .copied()
+                .filter(|&ch| ch != '\\')
+                .collect(),
+            escaped_metacharacters: Vec::new(),
+            free_spacing: false,
+        }
+    }
+}
+
+impl ScannerConfig {
+    // POSIX basic regular expressions (with GNU extensions)
+    // Only `*`, `.`, `[`, `^` and `$` are special on their own
+    // groups, alternation, `+` and `?` are written escaped like `\(a\|b\)\+`
+    pub fn posix_basic() -> Self {
+        ScannerConfig {
+            escape: '\\',
+            metacharacters: vec!['*', '.', '[', '^', '$'],
+            escaped_metacharacters: vec!['(', ')', '|', '+', '?'],
+            free_spacing: false,
+        }
+    }
+
+    // Syntax of this library in free-spacing mode
+    pub fn free_spacing() -> Self {
+        ScannerConfig {
+            free_spacing: true,
+            ..ScannerConfig::default()
+        }
+    }
+}
+
+// Token type a metacharacter stands for, if `ch` can be a metacharacter
+fn metacharacter_token(ch: char) -> Option<TokenType> {
+    match ch {
+        '(' => Some(LeftParen),
+        ')' => Some(RightParen),
+        '|' => Some(Pipe),
+        '?' => Some(Mark),
+        '*' => Some(Star),
+        '+' => Some(Plus),
+        '.' => Some(Dot),
+        '[' => Some(LeftBracket),
+        '^' => Some(Caret),
+        '$' => Some(Dollar),
+        _ => None,
+    }
+}
+
+// A token along with the range of character indices it occupies in source pattern
+// For instance, in pattern `a\*` token Character { value: '*' } has span 1..3
+// Empty tokens occupy no characters, their spans are empty
+#[derive(Debug, Clone, PartialEq)]
+pub struct SpannedToken {
+    pub token_type: TokenType,
+    pub span: Range<usize>,
+    // Range of byte indices this token occupies, `&pattern[byte_span]` is its text
+    pub byte_span: Range<usize>,
+}
+
+// Split `pattern` into tokens, the same way the parser sees it
+// Syntax highlighters and linters can use this instead of scanning patterns themselves
+pub fn tokenize(pattern: &str) -> Tokens {
+    Tokens {
+        scanner: Scanner::new(pattern),
+    }
+}
+
+// Iterator over tokens of a pattern, see `tokenize`
+pub struct Tokens {
+    scanner: Scanner,
+}
+
+impl Iterator for Tokens {
+    type Item = SpannedToken;
+
+    fn next(&mut self) -> Option<SpannedToken> {
+        let token = self.scanner.next()?;
+        Some(SpannedToken {
+            token_type: token.type_name,
+            span: token.position..token.position + token.length,
+            byte_span: token.byte_position..token.byte_position + token.byte_length,
+        })
+    }
+}
+
 pub struct Scanner {
     // source string characters vector to allow fast access
     source: Vec<char>,
     // index of character in field (source) that's currenlty processed
     current: usize,
+    // index of first byte of current character in source string
+    current_byte: usize,
     // `found_empty_string` flag indicates whether we found the empty string token
     // in current position
     // when it's false it means we can attempt to generate Empty token
     // when it's true it means we already generated EmtpyString token or we could not do so
     // rather we should attempt to generate another token (if any remaining)
     found_empty_string: bool,
+    // type of most recently generated token other than Empty, if any
+    // Empty tokens are generated depending on what came before current position
+    // looking at previous token rather than previous character
+    // tells apart `(` (LeftParen) from `\(` (escaped, a Character)
+    previous_token: Option<TokenType>,
+    // tokens already generated by `peek`/`peek_n` but not yet consumed by `next`
+    // in order, front is the next token returned by `next`
+    lookahead: VecDeque<Token>,
+    // which characters are special
+    config: ScannerConfig,
+    // Ranges of character indices skipped so far
+    // one for each comment `(?#...)` and, in free-spacing mode, for each run of whitespace
+    // and each `#` comment
+    skipped: Vec<Range<usize>>,
+    // Range of character indices of inline flags at start of pattern, like `(?x)`
+    // empty if there are none
+    flags: Range<usize>,
+    // Free-spacing mode turned on by inline flag `x`
+    flag_free_spacing: bool,
+    // Case-insensitive mode turned on by inline flag `i`
+    flag_case_insensitive: bool,
+    // Multiline mode turned on by inline flag `m`
+    flag_multiline: bool,
+    // Dot-all mode turned on by inline flag `s`
+    flag_dot_all: bool,
+    // CRLF mode turned on by inline flag `R`
+    flag_crlf: bool,
+    // Index of `(?#` beginning a comment which is never closed, if any
+    unterminated_comment: Option<usize>,
+    // Number of character classes open at current character, they nest like `[[a-z]_]`
+    // Inside classes characters are scanned differently, see `classify_in_class`
+    class_depth: usize,
 }
 
 // an Iterator transforming source string into a tokens stream
 // each toekn is generated on request
 impl Scanner {
     pub fn new(source: &str) -> Scanner {
+        Scanner::with_config(source, ScannerConfig::default())
+    }
+
+    // Scanner for a pattern written in another syntax, see ScannerConfig
+    pub fn with_config(source: &str, config: ScannerConfig) -> Scanner {
         // source characters as a vector for fast access
         let source = source.chars().collect::<Vec<_>>();
         // current (`processed` or `to be processed`) character
         let current = 0;
+        let current_byte = 0;
         // flag (found_empty_string) is false on start
         // because the empty string can occur anywhere with an abitrary string
         // even within the empty string (which is itself)
         let found_empty_string = false;
-        Scanner {
+        let previous_token = None;
+        let lookahead = VecDeque::new();
+        let mut scanner = Scanner {
             source,
             current,
+            current_byte,
             found_empty_string,
+            previous_token,
+            lookahead,
+            config,
+            skipped: Vec::new(),
+            flags: 0..0,
+            flag_free_spacing: false,
+            flag_case_insensitive: false,
+            flag_multiline: false,
+            flag_dot_all: false,
+            flag_crlf: false,
+            unterminated_comment: None,
+            class_depth: 0,
+        };
+        scanner.read_flags();
+        scanner
+    }
+
+    // Start over scanning `source`, as if this Scanner was just created by `Scanner::new`
+    // The characters buffer keeps its capacity, so scanning many patterns one after another
+    // with the same Scanner does not allocate a new buffer for each one
+    pub fn reset(&mut self, source: &str) {
+        self.source.clear();
+        self.source.extend(source.chars());
+        self.current = 0;
+        self.current_byte = 0;
+        self.found_empty_string = false;
+        self.previous_token = None;
+        self.lookahead.clear();
+        self.skipped.clear();
+        self.flags = 0..0;
+        self.flag_free_spacing = false;
+        self.flag_case_insensitive = false;
+        self.flag_multiline = false;
+        self.flag_dot_all = false;
+        self.flag_crlf = false;
+        self.unterminated_comment = None;
+        self.class_depth = 0;
+        self.read_flags();
+    }
+
+    // Read inline flags at start of pattern, like `(?x)`, and move past them
+    // Letters other than those of INLINE_FLAGS make `(?` begin something else
+    fn read_flags(&mut self) {
+        if !self.config.metacharacters.contains(&'?') || !self.source.starts_with(&['(', '?']) {
+            return;
+        }
+        let letters = self.source[2..]
+            .iter()
+            .take_while(|ch| INLINE_FLAGS.contains(ch))
+            .count();
+        if letters == 0 || self.get_char_at(2 + letters, 0) != ')' {
+            return;
+        }
+        for letter in &self.source[2..2 + letters] {
+            match letter {
+                'i' => self.flag_case_insensitive = true,
+                'm' => self.flag_multiline = true,
+                's' => self.flag_dot_all = true,
+                'R' => self.flag_crlf = true,
+                'x' => self.flag_free_spacing = true,
+                _ => unreachable!("letters are among INLINE_FLAGS"),
+            }
         }
+        // Flags are ASCII, one byte each
+        self.current = 3 + letters;
+        self.current_byte = self.current;
+        self.flags = 0..self.current;
     }
 
     // construct source string from field (self.source)
@@ -53,6 +352,61 @@ impl Scanner {
         self.source.iter().collect::<String>()
     }
 
+    pub fn config(&self) -> &ScannerConfig {
+        &self.config
+    }
+
+    // Comments, and whitespace runs in free-spacing mode, skipped so far, in order
+    pub fn skipped_spans(&self) -> &[Range<usize>] {
+        &self.skipped
+    }
+
+    // Range of inline flags at start of pattern, like `(?x)`, empty if there are none
+    pub fn flags_span(&self) -> Range<usize> {
+        self.flags.clone()
+    }
+
+    // Is the pattern scanned in free-spacing mode? by configuration or inline flag `x`
+    pub fn is_free_spacing(&self) -> bool {
+        self.config.free_spacing || self.flag_free_spacing
+    }
+
+    // Is the pattern in case-insensitive mode? by inline flag `i`
+    pub fn is_case_insensitive(&self) -> bool {
+        self.flag_case_insensitive
+    }
+
+    // Turn case-insensitive mode on, as if the pattern began with inline flag `i`
+    pub fn set_case_insensitive(&mut self) {
+        self.flag_case_insensitive = true;
+    }
+
+    // Is the pattern in multiline mode? by inline flag `m`
+    pub fn is_multiline(&self) -> bool {
+        self.flag_multiline
+    }
+
+    // Does the dot match a newline `\n` too? by inline flag `s`
+    pub fn is_dot_all(&self) -> bool {
+        self.flag_dot_all
+    }
+
+    // Do lines end with \r\n, \r or \n? by inline flag `R`
+    pub fn is_crlf(&self) -> bool {
+        self.flag_crlf
+    }
+
+    // Index of `(?#` beginning a comment without a closing ), once the scanner reached it
+    // The comment runs until end of pattern
+    pub fn unterminated_comment(&self) -> Option<usize> {
+        self.unterminated_comment
+    }
+
+    // number of characters in source string
+    pub fn get_source_length(&self) -> usize {
+        self.source.len()
+    }
+
     // get character at (index + offset) if this position exists
     // otherwise return \0
     fn get_char_at(&self, index: usize, offset: isize) -> char {
@@ -77,13 +431,6 @@ impl Scanner {
         *self.source.get(index + offset).unwrap_or(&'\0')
     }
 
-    // advance the current character marker one step forward
-    fn advance(&mut self) {
-        if self.current < self.source.len() {
-            self.current += 1;
-        }
-    }
-
     // check if we reached end of input
     // if current character marker index is a valid index
     // then we still have characters to process
@@ -92,11 +439,6 @@ impl Scanner {
         self.current < self.source.len()
     }
 
-    // get character right before currently processed character
-    fn get_previous_char(&self) -> char {
-        self.get_char_at(self.current, -1)
-    }
-
     // get the currenlty processed character
     fn get_peek_char(&self) -> char {
         self.get_char_at(self.current, 0)
@@ -108,25 +450,84 @@ impl Scanner {
     }
 }
 
-impl Iterator for Scanner {
-    type Item = Token;
+impl Scanner {
+    // the token `next` returns when called, without consuming it
+    pub fn peek(&mut self) -> Option<Token> {
+        self.peek_n(0)
+    }
+
+    // the token `next` returns after `k` other calls, without consuming anything
+    // peek_n(0) is the same as peek()
+    pub fn peek_n(&mut self, k: usize) -> Option<Token> {
+        while self.lookahead.len() <= k {
+            let token = self.scan_token()?;
+            self.lookahead.push_back(token);
+        }
+        self.lookahead.get(k).copied()
+    }
+
+    // Like `next`, but a pattern ending with an escape character escaping nothing
+    // (like `a\`) is an error instead of a literal escape character
+    // and so is one escaping a letter with no meaning escaped (see `unknown_escape`)
+    pub fn try_next(&mut self) -> Result<Option<Token>, ScanError> {
+        let token = self.next();
+        if let Some(Token {
+            type_name: Character { value },
+            position,
+            length: 1,
+            ..
+        }) = token
+        {
+            if value == self.config.escape && position + 1 == self.source.len() {
+                return Err(ScanError::TrailingEscape { position });
+            }
+        }
+        if let Some(error) = token.and_then(|token| self.unknown_escape(&token)) {
+            return Err(error);
+        }
+        if let Some(position) = self.unterminated_comment {
+            return Err(ScanError::UnterminatedComment { position });
+        }
+        Ok(token)
+    }
+
+    // Error for `token` if it is a lone escape character before a letter
+    // Escaped letters which are not anchors, shorthand classes or control escapes
+    // are reserved, so `\q` is an error instead of matching a slash followed by q
+    pub fn unknown_escape(&self, token: &Token) -> Option<ScanError> {
+        if token.type_name
+            != (Character {
+                value: self.config.escape,
+            })
+            || token.length != 1
+        {
+            return None;
+        }
+        let letter = self.get_char_at(token.position, 1);
+        (token.position + 1 < self.source.len() && letter.is_ascii_alphabetic()).then_some(
+            ScanError::UnknownEscape {
+                position: token.position,
+                letter,
+            },
+        )
+    }
 
     // (Attempt to) generate a token for the current character
     // or an Empty token
-    fn next(&mut self) -> Option<Token> {
+    fn scan_token(&mut self) -> Option<Token> {
+        // Insignificant characters are not part of any token
+        // skip them before deciding whether an Empty token is here
+        self.skip_insignificant();
+
         // First, try to generate an Empty token because
         // the empty string can appear anywhere within a string
         // even within the empty string (which is itself)
-        let peek_char = self.get_peek_char();
-        let next_char = self.get_next_char();
-        let previous_car = self.get_previous_char();
-        // if certain characters "( | )" are adjacent with the former not escaped
+        // if certain metacharacters "( | )" are adjacent
         // we can generate an Empty token
-        // self.source[self.current - 2] (if exists) is second to current character
-        // it's where to look to check whether previous character is preceeded by a slash
-        // which means it's escaped
-        let is_prev_escaped = self.get_char_at(self.current, -2) == '\\';
-        if !is_prev_escaped && !self.found_empty_string {
+        // Tokens are compared rather than characters
+        // so an escaped ( or | (a Character token) does not count
+        let previous_token = self.previous_token;
+        if !self.found_empty_string {
             // Set flag (self.found_empty_string) to not attempt to generate Empty token
             // if previous iteration did
             self.found_empty_string = true;
@@ -138,38 +539,39 @@ impl Iterator for Scanner {
                 // "" (empty string)
                 // source string is empty, emit `Empty` because it's the only token
                 // which can appear in an emtpy string since it contains no characters at all
-                self.source.is_empty() ||
+                // (or, in free-spacing mode, it has nothing but whitespace and comments)
+                (previous_token.is_none() && !self.has_next()) ||
 
                 // CASE 2
                 // "|..."
                 // source string begins with |, emit `Empty` BEFORE the leading |
-                (self.current == 0 && peek_char == '|') ||
+                (previous_token.is_none() && self.current_is(Pipe)) ||
 
                 // CASE 3
                 // "...|"
                 // source string ends with |, emit `Empty` AFTER the trailing |
-                (self.current == self.source.len() && previous_car == '|' && peek_char == '\0') ||
+                (!self.has_next() && previous_token == Some(Pipe)) ||
 
                 // CASE 4
                 // "...||..."
                 // emit `Empty` AFTER | and BEFORE following |
                 // in other words, emit `Empty` between two adjacent |'s
-                (previous_car == '|' && peek_char == '|') ||
+                (previous_token == Some(Pipe) && self.current_is(Pipe)) ||
 
                 // CASE 5
                 // "...(|...)..."
                 // emit `Empty` AFTER ( and BEFORE |
-                (previous_car == '(' && peek_char == '|') ||
+                (previous_token == Some(LeftParen) && self.current_is(Pipe)) ||
 
                 // CASE 6
                 // "...(...|)..."
                 // emit `Empty` AFTER | and BEFORE )
-                (previous_car == '|' && peek_char == ')') ||
+                (previous_token == Some(Pipe) && self.current_is(RightParen)) ||
 
                 // CASE 7
                 // "...()..."
                 // emit `Empty` AFTER ( and BEFORE )
-                (previous_car == '(' && peek_char == ')')
+                (previous_token == Some(LeftParen) && self.current_is(RightParen))
             ) {
                 // Note that we do not call advance()
                 // because Empty contains no characters at all
@@ -179,6 +581,9 @@ impl Iterator for Scanner {
                 return Some(Token {
                     type_name: Empty,
                     position: self.current,
+                    length: 0,
+                    byte_position: self.current_byte,
+                    byte_length: 0,
                 });
             }
             // we did not generate an Empty token at current position
@@ -208,65 +613,270 @@ impl Iterator for Scanner {
             // another token, not even Empty
             // All characters are consumed and we can not generate an Empty token
             // this iterator has no more elements, return None
+            // Keep flag (found_empty_string) set so that calling `next` again
+            // (like `peek_n` does) does not generate the trailing Empty token again
+            self.found_empty_string = true;
             return None;
         }
 
-        // By default assume the current character is an ordinary character
-        // (not a metacharacter and not an escaped metacharacter)
-        let mut next = Some(Token {
-            type_name: Character { value: peek_char },
+        let (type_name, length) = self.classify_current();
+        let byte_length = self.source[self.current..self.current + length]
+            .iter()
+            .map(|ch| ch.len_utf8())
+            .sum();
+        let next = Some(Token {
+            type_name,
             position: self.current,
+            length,
+            byte_position: self.current_byte,
+            byte_length,
         });
+        self.previous_token = Some(type_name);
+        match type_name {
+            LeftBracket => self.class_depth += 1,
+            RightBracket => self.class_depth -= 1,
+            _ => {}
+        }
+        // move current character marker past the token characters
+        self.current += length;
+        self.current_byte += byte_length;
+        next
+    }
 
-        // a mutable (&mut) reference to Token object inside local variable `next`
-        // we use this &mut reference to modify Token::name field in case current character
-        // is not an ordinary character (metacharacter or an escaped metacharacter)
-        let next_token = next.as_mut().unwrap(); //&mut Token
+    // Whether `ch` is skipped, or starts a skipped comment, in free-spacing mode
+    fn is_insignificant(&self, ch: char) -> bool {
+        self.is_free_spacing() && (ch.is_whitespace() || ch == '#')
+    }
 
-        match peek_char {
-            '(' => {
-                next_token.type_name = LeftParen;
+    // Does a comment `(?#...)` begin at current character?
+    fn at_comment(&self) -> bool {
+        self.config.metacharacters.contains(&'?')
+            && self.get_peek_char() == '('
+            && self.get_next_char() == '?'
+            && self.get_char_at(self.current, 2) == '#'
+    }
+
+    // Move past comments `(?#...)`, and whitespace and `#` comments in free-spacing mode
+    // recording their spans
+    // A comment `(?#...)` ends at the first ), escaped or not, like in `(?#a\)`
+    // Whitespace inside a character class is significant, `[ ]` matches a space
+    fn skip_insignificant(&mut self) {
+        while self.class_depth == 0
+            && self.has_next()
+            && (self.at_comment() || self.is_insignificant(self.get_peek_char()))
+        {
+            let start = self.current;
+            if self.at_comment() {
+                let Some(length) = self.source[start..].iter().position(|ch| *ch == ')') else {
+                    // Nothing closes this comment, it's an error (see `try_next`)
+                    self.unterminated_comment = Some(start);
+                    while self.has_next() {
+                        self.skip_char();
+                    }
+                    return;
+                };
+                for _ in 0..=length {
+                    self.skip_char();
+                }
+            } else if self.get_peek_char() == '#' {
+                // A comment runs until end of line, the newline itself is whitespace
+                while self.has_next() && self.get_peek_char() != '\n' {
+                    self.skip_char();
+                }
+            } else {
+                while self.has_next() && self.get_peek_char().is_whitespace() {
+                    self.skip_char();
+                }
             }
-            ')' => {
-                next_token.type_name = RightParen;
+            self.skipped.push(start..self.current);
+        }
+    }
+
+    fn skip_char(&mut self) {
+        self.current_byte += self.get_peek_char().len_utf8();
+        self.current += 1;
+    }
+
+    // Type and length (in characters) of the token starting at current character
+    // without generating it
+    fn classify_current(&self) -> (TokenType, usize) {
+        if self.class_depth > 0 {
+            return self.classify_in_class();
+        }
+        let peek_char = self.get_peek_char();
+        let next_char = self.get_next_char();
+        let config = &self.config;
+        if peek_char == config.escape && self.current + 1 < self.source.len() {
+            if is_anchor_char(next_char) {
+                let type_name = match next_char {
+                    'A' => StartAnchor,
+                    'Z' => EndAnchor,
+                    'b' => WordBoundary,
+                    _ => NonWordBoundary,
+                };
+                return (type_name, 2);
+            }
+            if is_shorthand_class_char(next_char) {
+                return (ShorthandClass { letter: next_char }, 2);
+            }
+            if next_char == 'R' {
+                return (Linebreak, 2);
+            }
+            if let Some(group) = next_char.to_digit(10).filter(|digit| *digit > 0) {
+                return (
+                    Backreference {
+                        group: group as usize,
+                    },
+                    2,
+                );
+            }
+            if config.escaped_metacharacters.contains(&next_char) {
+                // A metacharacter which must be escaped to have its special meaning
+                // like \( in POSIX basic regular expressions
+                if let Some(type_name) = metacharacter_token(next_char) {
+                    return (type_name, 2);
+                }
             }
-            '|' => {
-                next_token.type_name = Pipe;
+            if next_char == config.escape
+                || config.metacharacters.contains(&next_char)
+                || self.is_insignificant(next_char)
+            {
+                // An escaped metacharacter, like \* or \\
+                // is the metacharacter itself as an ordinary character
+                return (Character { value: next_char }, 2);
             }
-            '?' => {
-                next_token.type_name = Mark;
+            if let Some(control) = control_escape(next_char) {
+                return (Character { value: control }, 2);
             }
-            '*' => {
-                next_token.type_name = Star;
+            if let Some((value, length)) = self.octal_escape() {
+                return (Character { value }, length);
             }
-            '+' => {
-                next_token.type_name = Plus;
+        }
+        if config.metacharacters.contains(&peek_char) {
+            if peek_char == '(' {
+                return (LeftParen, self.group_opening_length());
             }
-            '.' => {
-                next_token.type_name = Dot;
+            if let Some(type_name) = metacharacter_token(peek_char) {
+                return (type_name, 1);
             }
-            '\\' if is_anchor_char(next_char) => {
-                self.current += 2;
-                if next_char == 'A' {
-                    next_token.type_name = StartAnchor;
-                } else if next_char == 'Z' {
-                    next_token.type_name = EndAnchor;
-                } else if next_char == 'b' {
-                    next_token.type_name = WordBoundary;
-                } else {
-                    next_token.type_name = NonWordBoundary;
+            if peek_char == '{' {
+                if let Some(length) = self.repetition_length() {
+                    return (Repetition, length);
                 }
-                return next;
             }
-            _ => {
-                // Any other ordinary character.
-                // that's, not a metacharacter and an escaped metacharacter
-                // Nothing to be handled because by default
-                // token name is TokenName::Character
+        }
+        // Any other ordinary character
+        // that's, not a metacharacter and not an escaped metacharacter
+        (Character { value: peek_char }, 1)
+    }
+
+    // Number of characters of a counted repetition starting at current character
+    // that's `{`, digits, optionally a comma and more digits, then `}`
+    // like `{3}`, `{3,}` or `{3,5}`, the parser reads the counts
+    fn repetition_length(&self) -> Option<usize> {
+        let digits = |from: usize| {
+            self.source[from..]
+                .iter()
+                .take_while(|ch| ch.is_ascii_digit())
+                .count()
+        };
+        let min_digits = digits(self.current + 1);
+        if min_digits == 0 {
+            return None;
+        }
+        let mut end = self.current + 1 + min_digits;
+        if self.get_char_at(end, 0) == ',' {
+            end += 1 + digits(end + 1);
+        }
+        (self.get_char_at(end, 0) == '}').then_some(end + 1 - self.current)
+    }
+
+    // Number of characters of the ( starting a group at current character
+    // 3 for `(?>` which starts an atomic group and `(?:` which starts a non-capturing one
+    // 4 for `(?<=` and `(?<!` which start lookbehinds
+    // otherwise 1, the parser tells group kinds apart by the text of their LeftParen tokens
+    fn group_opening_length(&self) -> usize {
+        if !self.config.metacharacters.contains(&'?') || self.get_next_char() != '?' {
+            return 1;
+        }
+        match self.get_char_at(self.current, 2) {
+            '>' | ':' => 3,
+            '<' if matches!(self.get_char_at(self.current, 3), '=' | '!') => 4,
+            _ => 1,
+        }
+    }
+
+    // Like `classify_current`, but inside a character class
+    // There, all metacharacters but class metacharacters are ordinary characters
+    // and escaping any metacharacter gives the character itself, like \] or \*
+    // Shorthand classes, control and octal escapes keep their meaning, `[\d_]` matches a digit or _
+    // and [ begins a class nested inside this one, `[[a-z]_]` matches a lowercase letter or _
+    fn classify_in_class(&self) -> (TokenType, usize) {
+        let peek_char = self.get_peek_char();
+        let next_char = self.get_next_char();
+        let config = &self.config;
+        if peek_char == config.escape
+            && self.current + 1 < self.source.len()
+            && is_shorthand_class_char(next_char)
+        {
+            return (ShorthandClass { letter: next_char }, 2);
+        }
+        if peek_char == config.escape
+            && self.current + 1 < self.source.len()
+            && (next_char == config.escape
+                || is_class_metacharacter(next_char)
+                || config.metacharacters.contains(&next_char)
+                || config.escaped_metacharacters.contains(&next_char))
+        {
+            return (Character { value: next_char }, 2);
+        }
+        if peek_char == config.escape && self.current + 1 < self.source.len() {
+            if let Some(control) = control_escape(next_char) {
+                return (Character { value: control }, 2);
+            }
+            if let Some((value, length)) = self.octal_escape() {
+                return (Character { value }, length);
             }
         }
-        // move current character marker one step forward
-        self.advance();
-        next
+        match peek_char {
+            '[' => (LeftBracket, 1),
+            ']' => (RightBracket, 1),
+            '-' => (Hyphen, 1),
+            _ => (Character { value: peek_char }, 1),
+        }
+    }
+
+    // Character of the octal escape starting at current character along with its length
+    // that's \0 followed by up to MAX_OCTAL_DIGITS octal digits, like `\0`, `\012` or `\0101`
+    // \0 is required, `\12` is a backreference and `\0123` is S, not a newline followed by 3
+    fn octal_escape(&self) -> Option<(char, usize)> {
+        if self.get_peek_char() != self.config.escape
+            || self.current + 1 >= self.source.len()
+            || self.get_next_char() != '0'
+        {
+            return None;
+        }
+        let digits = self.source[self.current + 2..]
+            .iter()
+            .take_while(|ch| ch.is_digit(8))
+            .take(MAX_OCTAL_DIGITS)
+            .collect::<String>();
+        let value = u32::from_str_radix(&digits, 8).unwrap_or(0);
+        let value = char::from_u32(value).expect("octal escapes are below surrogates");
+        Some((value, 2 + digits.len()))
+    }
+
+    // Whether the token starting at current character is of type `type_name`
+    fn current_is(&self, type_name: TokenType) -> bool {
+        self.has_next() && self.classify_current().0 == type_name
+    }
+}
+
+impl Iterator for Scanner {
+    type Item = Token;
+
+    // Next token, either one already peeked or a newly generated one
+    fn next(&mut self) -> Option<Token> {
+        self.lookahead.pop_front().or_else(|| self.scan_token())
     }
 }
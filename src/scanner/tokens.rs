@@ -10,6 +10,8 @@ pub enum TokenType {
     EndAnchor,       // \Z
     WordBoundary,    // \b
     NonWordBoundary, // \B
+    Caret,           // ^
+    Dollar,          // $
 
     // SPECIAL
     // indicator of places like:
@@ -22,22 +24,37 @@ pub enum TokenType {
     // "...()..." between ( and )
     Empty,
     Character { value: char },
+    // Escape character followed by a letter naming a class of characters, like \d
+    ShorthandClass { letter: char },
+    // Escape character followed by a digit from 1 to 9 numbering a capturing group, like \1
+    Backreference { group: usize },
+    // \R, a line break, either \r\n or a single line terminator
+    Linebreak,
 
     // METACHARACTERS
-    LeftParen,  // (
+    LeftParen,  // (, (?>, (?:, (?<= or (?<! beginning groups and lookbehinds
     RightParen, // )
     Pipe,       // |, alternation operator (E1|E2|...|E_n)
     Mark,       // ?, match zero or one occurrence of previous expression
     Star,       // *, match zero or more occurrences of previous expression
     Plus,       // +, match zero or more occurrences of previous expression
-    Dot,        // ., match any single character even newline `\n`
+    Dot,        // ., match any single character but newline `\n`
+    Repetition, // {n}, {n,} or {n,m}, match n to m occurrences of previous expression
+
+    // CHARACTER CLASSES
+    LeftBracket,  // [, begins a character class like [abc]
+    RightBracket, // ], ends a character class, only inside one
+    Hyphen,       // -, between two characters of a class forms a range like a-z, only inside one
 }
 
 // Scanner generates `Tokens` which are a atoms of regular expressions
-// Token is identified by two properties:
-// name    : a variant of TokenName
-// position: usize integer indicating where this Token begins inside source string given to the
+// Token is identified by these properties:
+// name         : a variant of TokenName
+// position     : usize integer indicating where this Token begins inside source string given to the
 // scanner
+// length       : number of source characters this Token occupies
+// byte_position: like position, but counting bytes of source string instead of characters
+// byte_length  : like length, but counting bytes instead of characters
 // The scanner just splits the pattern string for the parser
 
 // enable pretty-printing if needed
@@ -47,4 +64,12 @@ pub struct Token {
     pub type_name: TokenType,
     // index in source string
     pub position: usize,
+    // number of characters in source string, like 2 for `\*`
+    // and 0 for Empty tokens
+    pub length: usize,
+    // index of first byte in source string
+    // `&source[byte_position..byte_position + byte_length]` is this token text
+    pub byte_position: usize,
+    // number of bytes in source string
+    pub byte_length: usize,
 }
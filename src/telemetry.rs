@@ -0,0 +1,83 @@
+// Observing this crate with `tracing` (feature `tracing`)
+// Compilation phases and searches run inside spans, each reports its duration in an event
+// Patterns are identified by their hashes so that telemetry never holds pattern text
+// Without the feature these helpers just run what they are given
+
+#[cfg(feature = "tracing")]
+use std::collections::hash_map::DefaultHasher;
+#[cfg(feature = "tracing")]
+use std::hash::{Hash, Hasher};
+use std::ops::Range;
+#[cfg(feature = "tracing")]
+use std::time::Instant;
+
+// Hash identifying `pattern` in spans
+#[cfg(feature = "tracing")]
+fn pattern_hash(pattern: &str) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    pattern.hash(&mut hasher);
+    hasher.finish()
+}
+
+// Run compilation `phase` (like "parse" or "dfa") of `pattern`, if it is known
+pub(crate) fn compile<T>(phase: &'static str, pattern: Option<&str>, run: impl FnOnce() -> T) -> T {
+    #[cfg(feature = "tracing")]
+    {
+        let span = tracing::debug_span!(
+            "regexps::compile",
+            phase,
+            pattern_hash = tracing::field::Empty
+        );
+        if let Some(pattern) = pattern {
+            span.record("pattern_hash", pattern_hash(pattern));
+        }
+        let _entered = span.entered();
+        let start = Instant::now();
+        let output = run();
+        tracing::debug!(duration_us = start.elapsed().as_micros() as u64, "compiled");
+        output
+    }
+    #[cfg(not(feature = "tracing"))]
+    {
+        let _ = (phase, pattern);
+        run()
+    }
+}
+
+// Run a search by `engine` (like "backtracker") of `pattern`, if it is known,
+// for the next match in a haystack of `haystack_length` characters
+pub(crate) fn search(
+    engine: &'static str,
+    pattern: Option<&str>,
+    haystack_length: usize,
+    run: impl FnOnce() -> Option<Range<usize>>,
+) -> Option<Range<usize>> {
+    #[cfg(feature = "tracing")]
+    {
+        let span = tracing::debug_span!(
+            "regexps::search",
+            engine,
+            pattern_hash = tracing::field::Empty,
+            haystack_length
+        );
+        if let Some(pattern) = pattern {
+            span.record("pattern_hash", pattern_hash(pattern));
+        }
+        let _entered = span.entered();
+        let start = Instant::now();
+        let found = run();
+        tracing::debug!(
+            duration_us = start.elapsed().as_micros() as u64,
+            found = found.is_some(),
+            start = found.as_ref().map(|found| found.start),
+            end = found.as_ref().map(|found| found.end),
+            "searched"
+        );
+        found
+    }
+    #[cfg(not(feature = "tracing"))]
+    {
+        let _ = (engine, pattern, haystack_length);
+        run()
+    }
+}